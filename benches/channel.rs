@@ -0,0 +1,131 @@
+// Throughput of channel::bounded against std's sync_channel and a Mutex<Vec> (the way Server.clients used to be shared)
+// Run with 'cargo bench --bench channel'. Plain main(), timed with Instant, so it needs nothing beyond std
+
+use std::hint::black_box;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use rs_basics::channel;
+
+const MESSAGES: usize = 200_000;
+const CAPACITY: usize = 1024;
+const RUNS: usize = 5;
+
+// Every producer sends MESSAGES / producers values, every consumer takes whatever it gets
+type Setup = fn( producers: usize, consumers: usize ) -> Duration;
+
+fn bounded_channel( producers: usize, consumers: usize ) -> Duration {
+	let ( tx, rx ) = channel::bounded::<usize>( CAPACITY );
+	let start = Instant::now();
+
+	let senders: Vec<_> = ( 0..producers ).map( |_| {
+		let tx = tx.clone();
+		thread::spawn( move || for i in 0..MESSAGES / producers { tx.send( i ).unwrap(); } )
+	}).collect();
+
+	let receivers: Vec<_> = ( 0..consumers ).map( |_| {
+		let rx = rx.clone();
+		thread::spawn( move || for value in rx.iter() { black_box( value ); } )
+	}).collect();
+
+	drop( ( tx, rx ) ); // Closes once the producers are done
+
+	senders.into_iter().chain( receivers ).for_each( |t| t.join().unwrap() );
+
+	return start.elapsed();
+}
+
+// The Receiver can't be cloned, so several consumers have to share it behind a Mutex
+fn std_sync_channel( producers: usize, consumers: usize ) -> Duration {
+	let ( tx, rx ) = mpsc::sync_channel::<usize>( CAPACITY );
+	let rx = Arc::new( Mutex::new( rx ) );
+	let start = Instant::now();
+
+	let senders: Vec<_> = ( 0..producers ).map( |_| {
+		let tx = tx.clone();
+		thread::spawn( move || for i in 0..MESSAGES / producers { tx.send( i ).unwrap(); } )
+	}).collect();
+
+	let receivers: Vec<_> = ( 0..consumers ).map( |_| {
+		let rx = Arc::clone( &rx );
+		thread::spawn( move || loop {
+			let next = rx.lock().unwrap().recv();
+
+			match next {
+				Ok( value ) => { black_box( value ); },
+				Err( _ ) => break,
+			}
+		})
+	}).collect();
+
+	drop( tx );
+
+	senders.into_iter().chain( receivers ).for_each( |t| t.join().unwrap() );
+
+	return start.elapsed();
+}
+
+// No blocking and no bound: consumers poll, and the Vec grows as much as the producers get ahead
+fn mutex_vec( producers: usize, consumers: usize ) -> Duration {
+	let shared: Arc<Mutex<Vec<usize>>> = Arc::new( Mutex::new( Vec::new() ) );
+	let taken = Arc::new( AtomicUsize::new( 0 ) );
+	let total = MESSAGES / producers * producers;
+	let start = Instant::now();
+
+	let senders: Vec<_> = ( 0..producers ).map( |_| {
+		let shared = Arc::clone( &shared );
+		thread::spawn( move || for i in 0..MESSAGES / producers { shared.lock().unwrap().push( i ); } )
+	}).collect();
+
+	let receivers: Vec<_> = ( 0..consumers ).map( |_| {
+		let shared = Arc::clone( &shared );
+		let taken = Arc::clone( &taken );
+
+		thread::spawn( move || while taken.load( Ordering::Relaxed ) < total {
+			let next = shared.lock().unwrap().pop();
+
+			match next {
+				Some( value ) => {
+					black_box( value );
+					taken.fetch_add( 1, Ordering::Relaxed );
+				},
+				None => thread::yield_now(),
+			}
+		})
+	}).collect();
+
+	senders.into_iter().chain( receivers ).for_each( |t| t.join().unwrap() );
+
+	return start.elapsed();
+}
+
+fn median( setup: Setup, producers: usize, consumers: usize ) -> Duration {
+	let mut times: Vec<Duration> = ( 0..RUNS ).map( |_| setup( producers, consumers ) ).collect();
+	times.sort();
+
+	return times[RUNS / 2];
+}
+
+fn main() {
+	let cases: [( &str, Setup ); 3] = [
+		( "channel::bounded", bounded_channel ),
+		( "mpsc::sync_channel", std_sync_channel ),
+		( "Mutex<Vec>", mutex_vec ),
+	];
+
+	println! ( "{} messages, capacity {}, median of {} runs", MESSAGES, CAPACITY, RUNS );
+
+	for ( producers, consumers ) in [( 1, 1 ), ( 4, 1 ), ( 4, 4 )] {
+		println! ( "\n{} producer(s), {} consumer(s)", producers, consumers );
+
+		for ( name, setup ) in cases {
+			let time = median( setup, producers, consumers );
+			let rate = MESSAGES as f64 / time.as_secs_f64() / 1e6;
+
+			println! ( "  {:<20} {:>10.2?} {:>8.2} M msg/s", name, time, rate );
+		}
+	}
+}
@@ -0,0 +1,145 @@
+// Throughput of the lock-free ring::spsc and ring::mpsc against a Mutex<VecDeque> with the same bound
+// Run with 'cargo bench --bench ring'. Plain main(), timed with Instant, like benches/channel.rs
+
+use std::collections::VecDeque;
+use std::hint::black_box;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use rs_basics::ring;
+
+const MESSAGES: usize = 1_000_000;
+const CAPACITY: usize = 1024;
+const RUNS: usize = 5;
+
+// Every producer pushes MESSAGES / producers values, and one consumer pops them all. Both sides spin (with a yield) on full/empty
+type Setup = fn( producers: usize ) -> Duration;
+
+fn spsc( producers: usize ) -> Duration {
+	assert_eq! ( producers, 1 );
+
+	let ( mut tx, mut rx ) = ring::spsc::<usize>( CAPACITY );
+	let start = Instant::now();
+
+	let sender = thread::spawn( move || for i in 0..MESSAGES {
+		let mut value = i;
+
+		while let Err( back ) = tx.push( value ) {
+			value = back;
+			thread::yield_now();
+		}
+	});
+
+	let mut taken = 0;
+
+	while taken < MESSAGES {
+		match rx.pop() {
+			Some( value ) => { black_box( value ); taken += 1; },
+			None => thread::yield_now(),
+		}
+	}
+
+	sender.join().unwrap();
+
+	return start.elapsed();
+}
+
+fn mpsc( producers: usize ) -> Duration {
+	let ( tx, mut rx ) = ring::mpsc::<usize>( CAPACITY );
+	let total = MESSAGES / producers * producers;
+	let start = Instant::now();
+
+	let senders: Vec<_> = ( 0..producers ).map( |_| {
+		let tx = tx.clone();
+
+		thread::spawn( move || for i in 0..MESSAGES / producers {
+			let mut value = i;
+
+			while let Err( back ) = tx.push( value ) {
+				value = back;
+				thread::yield_now();
+			}
+		})
+	}).collect();
+
+	let mut taken = 0;
+
+	while taken < total {
+		match rx.pop() {
+			Some( value ) => { black_box( value ); taken += 1; },
+			None => thread::yield_now(),
+		}
+	}
+
+	senders.into_iter().for_each( |t| t.join().unwrap() );
+
+	return start.elapsed();
+}
+
+fn mutex_vecdeque( producers: usize ) -> Duration {
+	let shared: Arc<Mutex<VecDeque<usize>>> = Arc::new( Mutex::new( VecDeque::with_capacity( CAPACITY ) ) );
+	let total = MESSAGES / producers * producers;
+	let start = Instant::now();
+
+	let senders: Vec<_> = ( 0..producers ).map( |_| {
+		let shared = Arc::clone( &shared );
+
+		thread::spawn( move || for i in 0..MESSAGES / producers {
+			loop {
+				let mut queue = shared.lock().unwrap();
+
+				if queue.len() < CAPACITY {
+					queue.push_back( i );
+					break;
+				}
+
+				drop( queue );
+				thread::yield_now();
+			}
+		})
+	}).collect();
+
+	let mut taken = 0;
+
+	while taken < total {
+		let next = shared.lock().unwrap().pop_front();
+
+		match next {
+			Some( value ) => { black_box( value ); taken += 1; },
+			None => thread::yield_now(),
+		}
+	}
+
+	senders.into_iter().for_each( |t| t.join().unwrap() );
+
+	return start.elapsed();
+}
+
+fn median( setup: Setup, producers: usize ) -> Duration {
+	let mut times: Vec<Duration> = ( 0..RUNS ).map( |_| setup( producers ) ).collect();
+	times.sort();
+
+	return times[RUNS / 2];
+}
+
+fn main() {
+	println! ( "{} messages, capacity {}, median of {} runs", MESSAGES, CAPACITY, RUNS );
+
+	for producers in [1, 4] {
+		println! ( "\n{} producer(s), 1 consumer", producers );
+
+		let mut cases: Vec<( &str, Setup )> = vec![( "ring::mpsc", mpsc ), ( "Mutex<VecDeque>", mutex_vecdeque )];
+
+		if producers == 1 {
+			cases.insert( 0, ( "ring::spsc", spsc ) );
+		}
+
+		for ( name, setup ) in cases {
+			let time = median( setup, producers );
+			let rate = MESSAGES as f64 / time.as_secs_f64() / 1e6;
+
+			println! ( "  {:<20} {:>10.2?} {:>8.2} M msg/s", name, time, rate );
+		}
+	}
+}
@@ -12,6 +12,12 @@ Exercises for Rust [01]
 	08. Importing modules and running functions
 */
 
+// The localization module is shared with the main crate (strings come from locales/*.msg, run from the repo root)
+#[macro_use]
+#[allow( dead_code )]
+#[path = "../src/i18n.rs"]
+mod i18n;
+
 fn conv_p( a: u16, b: f64 ) -> u16 {
 	let ret = a + b as u16;
 
@@ -45,11 +51,11 @@ fn calc_res(  a: u16, b: u16 ) -> u16 {
 
 fn test_if_int( input: i16 ) {
 	if input > 16 {
-		println! ( "User can drive a car!\n" );
+		println! ( "{}\n", t! ( "ex.drive.can" ) );
 	} else if input == 16 {
-		println! ( "User is close to driving a car!\n" );
+		println! ( "{}\n", t! ( "ex.drive.close" ) );
 	} else {
-		println! ( "User cannot drive a car!\n" );
+		println! ( "{}\n", t! ( "ex.drive.cannot" ) );
 	}
 }
 
@@ -14,6 +14,12 @@ Exercises for Rust [02]
 	08. Importing enums, traits, and structs from modules
 */
 
+// The localization module is shared with the main crate (strings come from locales/*.msg, run from the repo root)
+#[macro_use]
+#[allow( dead_code )]
+#[path = "../src/i18n.rs"]
+mod i18n;
+
 #[allow( dead_code, unused_assignments, non_snake_case )]
 fn check_option_1( Name: String ) -> Option<String> {
 	let mut this_str: Option<String> = None; // Init the var as a null object
@@ -98,7 +104,7 @@ impl Person {
 		let mut info: String = String::new();
 
 		// String concatenation with some formatting
-		info = format! ( "Person {0} is {1} years old!", self.Name, self.Age );
+		info = t! ( "ex.person.info", name = self.Name, age = self.Age );
 		return info;
 	}
 }
@@ -106,10 +112,10 @@ impl Person {
 impl PersonT for Person {
 	fn check_age( &self ) -> String {
 		match self.Age { // Match it to the struct's Age attribute
-			1..15 => return String::from( "Person cannot drive!" ), // 1 to 14 cannot drive
-			15 | 16 => return String::from( "Person is almost ready to drive!" ), // 15 or 16 is an almost
-			16..80 => return String::from( "Person can drive!" ), // 16 to 79 can driver
-			_ => return String::from( "Person is prolly dead!" ) // Rest is R.I.P
+			1..15 => return t! ( "ex.person.cannot_drive" ), // 1 to 14 cannot drive
+			15 | 16 => return t! ( "ex.person.almost" ), // 15 or 16 is an almost
+			16..80 => return t! ( "ex.person.can_drive" ), // 16 to 79 can driver
+			_ => return t! ( "ex.person.dead" ) // Rest is R.I.P
 		}
 	}
 }
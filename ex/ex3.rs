@@ -4,6 +4,12 @@ Exercises for Rust [03]
 	00. Write a full Rust program that defines enums and structs with traits, calls them from a child-module* inside a module and constructs instances -> Designed w/ Pseudo-code.
 */
 
+// The localization module is shared with the main crate (strings come from locales/*.msg, run from the repo root)
+#[macro_use]
+#[allow( dead_code )]
+#[path = "../src/i18n.rs"]
+mod i18n;
+
 pub mod helpers;
 
 #[allow( unused_imports )]
@@ -30,7 +36,7 @@ fn with_sp_use() {
 	};
 	let age_check = PersonTraits::check_age( &person1 );
 
-	println! ( "{:?}\nCan person drive? {:?}", person1, age_check );	
+	println! ( "{:?}\n{}", person1, t! ( "ex.person.can_drive_question", answer = age_check ) );
 }
 
 fn main() {
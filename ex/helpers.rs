@@ -41,7 +41,7 @@ pub mod PersonModule {
 			let PersonName: &String = &self.Name;
 			let PersonAge: &u8 = &self.Age;
 
-			let PersonString: String = format! ( "Person {0} is {1} years old!", PersonName, PersonAge );
+			let PersonString: String = t! ( "ex.person.info", name = PersonName, age = PersonAge );
 
 			return PersonString;
 		}
@@ -2,6 +2,9 @@
 Advanced concepts in Rust. Expanded definitions...
 */
 
+use rs_basics::table;
+use rs_basics::history::{ edits, History };
+
 /*
 Iterator mapping and collection.
 
@@ -260,6 +263,17 @@ struct Car {
 	model: String
 }
 
+// Printing a whole Vec<Car> with '{:?}' is unreadable, so Car implements the library's Row trait and gets printed as a table instead
+impl table::Row for Car {
+	fn headers() -> Vec<String> {
+		return vec!["Name".to_string(), "Model".to_string()];
+	}
+
+	fn cells( &self ) -> Vec<String> {
+		return vec![self.name.clone(), self.model.clone()];
+	}
+}
+
 // Vectors can also contain custom types (like structs, enums, etc...)
 #[allow( dead_code, unused_variables )]
 pub fn test_vec_custom() -> () {
@@ -276,8 +290,8 @@ pub fn test_vec_custom() -> () {
 		car_lot2.push( Car{ name: "Hyundai".to_string(), model: "Sanata".to_string() });
 	}
 
-	println! ( "Car lot 1 has: {:?}\n", car_lot1 );
-	println! ( "Car lot 2 has: {:?}\n", car_lot2 );
+	println! ( "Car lot 1 has:\n{}", table::render( &car_lot1 ) );
+	println! ( "Car lot 2 has:\n{}", table::render( &car_lot2 ) );
 
 	// Appending: Moves values from a vector to another: 'vecX.append( &mut vecY );'
 	car_lot3.append( &mut car_lot1 ); // Now car_lot1 is empty
@@ -288,9 +302,9 @@ pub fn test_vec_custom() -> () {
 		model: "Aventador".to_string()
 	}); // This will shuffle everything accordingly
 
-	println! ( "Car lot 1 has: {:?}\n", car_lot1 );
-	println! ( "Car lot 2 has: {:?}\n", car_lot2 );
-	println! ( "Car lot 3 has: {:?}\n", car_lot3 );
+	println! ( "Car lot 1 has:\n{}", table::render( &car_lot1 ) );
+	println! ( "Car lot 2 has:\n{}", table::render( &car_lot2 ) );
+	println! ( "Car lot 3 has:\n{}", table::render( &car_lot3 ) );
 
 	// Removing elements from the merged vector
 	// car_lot3.remove(0);
@@ -298,12 +312,37 @@ pub fn test_vec_custom() -> () {
 	// Retains only the elements that meet the conditionals
 	car_lot3.retain( |e: &Car| { if e.name == "Lamborghini" { return true; } else { return false; } } );
 
-	println! ( "Car lot 3 has: {:?}\n", car_lot3 );
+	println! ( "Car lot 3 has:\n{}", table::render( &car_lot3 ) );
 
 	// Pre-allocating mem to our vec
 	car_lot3.reserve( 50 ); // Reserves an extra 50 beyond the current len of the vec
 }
 
+// The same edits, but undoable: a History owns the vec and records each edit as a command (see src/history.rs)
+pub fn test_vec_history() -> () {
+	let porsche = || Car{ name: "Porsche".to_string(), model: "Panamera".to_string() };
+	let mut car_lot = History::new( Vec::new() ).with_memory_limit( 4096 ); // Old undo steps are forgotten past ~4 KB
+
+	car_lot.execute( edits::append( vec![porsche(), porsche(), porsche()] ) );
+	car_lot.execute( edits::insert( 0, Car{ name: "Lamborghini".to_string(), model: "Aventador".to_string() } ) );
+
+	// Several edits as one undo step
+	car_lot.begin( "trade in" );
+	car_lot.execute( edits::remove( 1 ) );
+	car_lot.execute( edits::push( Car{ name: "Hyundai".to_string(), model: "Sonata".to_string() } ) );
+	car_lot.commit();
+
+	car_lot.execute( edits::retain( |e: &Car| e.name == "Lamborghini" ) );
+	println! ( "After retain:\n{}", table::render( car_lot.target() ) );
+
+	car_lot.undo(); // Brings back every car retain removed, in place
+	car_lot.undo(); // Undoes the whole "trade in"
+	println! ( "After two undos:\n{}", table::render( car_lot.target() ) );
+
+	car_lot.redo();
+	println! ( "Undo: {:?}, redo: {:?}", car_lot.undo_labels(), car_lot.redo_labels() );
+}
+
 /*
 Linting.
 */
@@ -0,0 +1,105 @@
+/*
+Chat client.
+
+- Connects to a chat server (see src/chat.rs for the protocol), prints everything the server sends, and sends every line typed on stdin.
+- Usage: 'cargo run --bin chat_client -- [addr] [nickname]'. The address defaults to 127.0.0.1:7878, and a nickname, if given, is sent with NICK right away.
+- A server can be started with 'rs_basics::chat::serve_chat( addr )' (commented out in main.rs).
+- The server's heartbeats ("PING") are answered automatically, so an idle client isn't dropped as unresponsive.
+- Lines that don't start with a command are sent to the last room joined, so typing "hello" after "JOIN #rust" works like "MSG #rust hello". Ctrl+D sends QUIT.
+*/
+
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::TcpStream;
+use std::process::ExitCode;
+use std::thread;
+
+use rs_basics::chat::{ChatError, Command};
+
+const DEFAULT_ADDR: &str = "127.0.0.1:7878";
+
+// Prints the server's lines until it closes the connection. Heartbeats are answered here, without showing them
+fn print_incoming( stream: TcpStream ) {
+	let mut pong = stream.try_clone().ok();
+
+	for line in BufReader::new( stream ).lines() {
+		let Ok( line ) = line else { break };
+
+		if let Some( seq ) = line.strip_prefix( "PING" ) {
+			if let Some( writer ) = &mut pong {
+				let _ = writer.write_all( format! ( "PONG{seq}\n" ).as_bytes() );
+			}
+
+			continue;
+		}
+
+		// Answers to our own commands are dimmed, messages from others stand out
+		match line.strip_prefix( "OK " ) {
+			Some( ok ) => println! ( "\x1b[2m{}\x1b[0m", ok ),
+			None => println! ( "{}", line ),
+		}
+	}
+
+	println! ( "Disconnected" );
+	std::process::exit( 0 );
+}
+
+fn main() -> ExitCode {
+	let mut args = std::env::args().skip( 1 );
+	let addr = args.next().unwrap_or( DEFAULT_ADDR.to_string() );
+	let nick = args.next();
+
+	let stream = match TcpStream::connect( &addr ) {
+		Ok( stream ) => stream,
+		Err( e ) => {
+			eprintln! ( "Cannot connect to {}: {}", addr, e );
+			return ExitCode::FAILURE;
+		}
+	};
+
+	let reader = match stream.try_clone() {
+		Ok( reader ) => reader,
+		Err( e ) => {
+			eprintln! ( "{}", e );
+			return ExitCode::FAILURE;
+		}
+	};
+
+	thread::spawn( move || print_incoming( reader ) );
+
+	let mut writer = stream;
+	let mut room: Option<String> = None;
+	let mut send = |line: &str| writer.write_all( format! ( "{line}\n" ).as_bytes() );
+
+	if let Some( nick ) = nick && send( &format! ( "NICK {nick}" ) ).is_err() {
+		return ExitCode::FAILURE;
+	}
+
+	for line in io::stdin().lock().lines() {
+		let Ok( line ) = line else { break };
+
+		if line.trim().is_empty() {
+			continue;
+		}
+
+		let line = match ( line.parse::<Command>(), &room ) {
+			( Ok( Command::Join( joined ) ), _ ) => {
+				room = Some( joined );
+				line
+			},
+			( Ok( _ ), _ ) => line,
+			( Err( ChatError::UnknownCommand( _ ) ), Some( room ) ) => format! ( "MSG {} {}", room, line ),
+			( Err( _ ), _ ) => line, // The server explains what's wrong with it
+		};
+
+		if send( &line ).is_err() {
+			break;
+		}
+	}
+
+	let _ = send( "QUIT" );
+
+	// The reader thread exits the process once the server closes the connection
+	thread::park();
+
+	return ExitCode::SUCCESS;
+}
@@ -0,0 +1,440 @@
+/*
+Naming-convention linter.
+
+- Scans .rs files (src/ and ex/ by default) and reports identifiers that break Rust's naming conventions, with a suggested rename:
+	* snake_case: functions, modules, macros, variables ('let'), parameters and struct fields
+	* PascalCase: structs, enums, enum variants, traits and type aliases
+	* SCREAMING_SNAKE_CASE: consts and statics
+- Usage: 'cargo run --bin naming_lint -- [paths...]'. Exits with code 1 if anything was reported.
+- This is a token scanner, not a full Rust parser: comments, strings and char literals are skipped, then declarations are recognized from the keyword in front of them and the brace they sit in.
+*/
+
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use rs_basics::text::{convert, is_case, Case};
+
+#[derive( Debug, Clone, PartialEq )]
+enum Tok {
+	Ident( String ),
+	Punct( char ),
+	Other, // Literals and lifetimes, we only need to know something is there
+}
+
+#[derive( Debug, Clone )]
+struct Token {
+	tok: Tok,
+	line: usize,
+	col: usize,
+}
+
+fn tokenize( src: &str ) -> Vec<Token> {
+	let chars: Vec<char> = src.chars().collect();
+	let mut tokens: Vec<Token> = Vec::new();
+	let ( mut i, mut line, mut col ) = ( 0usize, 1usize, 1usize );
+
+	// Advances over 'n' chars, keeping line/column in sync
+	let advance = |i: &mut usize, line: &mut usize, col: &mut usize, n: usize| {
+		for _ in 0..n {
+			if chars.get( *i ) == Some( &'\n' ) {
+				*line += 1;
+				*col = 1;
+			} else {
+				*col += 1;
+			}
+			*i += 1;
+		}
+	};
+
+	while i < chars.len() {
+		let c = chars[i];
+		let next = chars.get( i + 1 ).copied();
+		let ( start_line, start_col ) = ( line, col );
+
+		if c.is_whitespace() {
+			advance( &mut i, &mut line, &mut col, 1 );
+		} else if c == '/' && next == Some( '/' ) {
+			while i < chars.len() && chars[i] != '\n' {
+				advance( &mut i, &mut line, &mut col, 1 );
+			}
+		} else if c == '/' && next == Some( '*' ) {
+			// Block comments nest in Rust
+			let mut depth = 0;
+
+			while i < chars.len() {
+				if chars[i] == '/' && chars.get( i + 1 ) == Some( &'*' ) {
+					depth += 1;
+					advance( &mut i, &mut line, &mut col, 2 );
+				} else if chars[i] == '*' && chars.get( i + 1 ) == Some( &'/' ) {
+					depth -= 1;
+					advance( &mut i, &mut line, &mut col, 2 );
+
+					if depth == 0 {
+						break;
+					}
+				} else {
+					advance( &mut i, &mut line, &mut col, 1 );
+				}
+			}
+		} else if ( c == 'r' || c == 'b' ) && raw_string_hashes( &chars, i ).is_some() {
+			// r"...", r#"..."#, br"..."
+			let ( prefix, hashes ) = raw_string_hashes( &chars, i ).unwrap();
+			let closing: Vec<char> = std::iter::once( '"' ).chain( std::iter::repeat_n( '#', hashes ) ).collect();
+
+			advance( &mut i, &mut line, &mut col, prefix + hashes + 1 );
+
+			while i < chars.len() && !chars[i..].starts_with( &closing ) {
+				advance( &mut i, &mut line, &mut col, 1 );
+			}
+
+			advance( &mut i, &mut line, &mut col, closing.len() );
+			tokens.push( Token { tok: Tok::Other, line: start_line, col: start_col } );
+		} else if c == '"' || ( c == 'b' && next == Some( '"' ) ) {
+			advance( &mut i, &mut line, &mut col, if c == 'b' { 2 } else { 1 } );
+
+			while i < chars.len() && chars[i] != '"' {
+				let n = if chars[i] == '\\' { 2 } else { 1 };
+				advance( &mut i, &mut line, &mut col, n );
+			}
+
+			advance( &mut i, &mut line, &mut col, 1 );
+			tokens.push( Token { tok: Tok::Other, line: start_line, col: start_col } );
+		} else if c == '\'' {
+			// Either a char literal ('a', '\n', '\'') or a lifetime ('a, 'static)
+			if next == Some( '\\' ) {
+				// The quote, the backslash and the escaped char, so '\'' works
+				advance( &mut i, &mut line, &mut col, 3 );
+
+				while i < chars.len() && chars[i] != '\'' {
+					advance( &mut i, &mut line, &mut col, 1 );
+				}
+
+				advance( &mut i, &mut line, &mut col, 1 );
+			} else if chars.get( i + 2 ) == Some( &'\'' ) {
+				advance( &mut i, &mut line, &mut col, 3 );
+			} else {
+				advance( &mut i, &mut line, &mut col, 1 );
+
+				while i < chars.len() && ( chars[i].is_alphanumeric() || chars[i] == '_' ) {
+					advance( &mut i, &mut line, &mut col, 1 );
+				}
+			}
+
+			tokens.push( Token { tok: Tok::Other, line: start_line, col: start_col } );
+		} else if c.is_alphabetic() || c == '_' {
+			let mut ident = String::new();
+
+			// Raw identifiers (r#type) are written that way on purpose
+			if c == 'r' && next == Some( '#' ) {
+				advance( &mut i, &mut line, &mut col, 2 );
+			}
+
+			while i < chars.len() && ( chars[i].is_alphanumeric() || chars[i] == '_' ) {
+				ident.push( chars[i] );
+				advance( &mut i, &mut line, &mut col, 1 );
+			}
+
+			tokens.push( Token { tok: Tok::Ident( ident ), line: start_line, col: start_col } );
+		} else if c.is_ascii_digit() {
+			while i < chars.len() && ( chars[i].is_alphanumeric() || chars[i] == '_' || ( chars[i] == '.' && chars.get( i + 1 ).is_some_and( |n| n.is_ascii_digit() ) ) ) {
+				advance( &mut i, &mut line, &mut col, 1 );
+			}
+
+			tokens.push( Token { tok: Tok::Other, line: start_line, col: start_col } );
+		} else {
+			tokens.push( Token { tok: Tok::Punct( c ), line: start_line, col: start_col } );
+			advance( &mut i, &mut line, &mut col, 1 );
+		}
+	}
+
+	return tokens;
+}
+
+// If a raw string starts at 'i', returns the prefix length ('r' or 'br') and the number of '#'
+fn raw_string_hashes( chars: &[char], i: usize ) -> Option<( usize, usize )> {
+	let prefix = if chars[i] == 'b' && chars.get( i + 1 ) == Some( &'r' ) { 2 } else if chars[i] == 'r' { 1 } else { return None };
+	let mut j = i + prefix;
+	let mut hashes = 0;
+
+	while chars.get( j ) == Some( &'#' ) {
+		hashes += 1;
+		j += 1;
+	}
+
+	return if chars.get( j ) == Some( &'"' ) { Some( ( prefix, hashes ) ) } else { None };
+}
+
+#[derive( Debug, Clone, Copy, PartialEq )]
+enum Scope {
+	StructBody, // Named fields
+	EnumBody, // Variants
+	Params, // fn parameters
+	Other,
+}
+
+const KEYWORDS: [&str; 38] = [
+	"as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern", "false", "fn", "for", "if", "impl", "in",
+	"let", "loop", "match", "mod", "move", "mut", "pub", "ref", "return", "self", "static", "struct", "super", "trait", "true", "type",
+	"unsafe", "use", "where", "while", "gen"
+];
+
+// The suggested rename, with a trailing underscore if it would be a keyword ('Struct' -> 'struct_')
+fn suggest( name: &str, case: Case ) -> String {
+	let renamed = convert( name, case );
+
+	return if KEYWORDS.contains( &renamed.as_str() ) { format! ( "{}_", renamed ) } else { renamed };
+}
+
+#[derive( Debug )]
+struct Finding {
+	line: usize,
+	col: usize,
+	kind: &'static str,
+	name: String,
+	expected: Case,
+}
+
+fn check( findings: &mut Vec<Finding>, token: Option<&Token>, kind: &'static str, expected: Case ) {
+	if let Some( token ) = token && let Tok::Ident( name ) = &token.tok && !is_case( name, expected ) {
+		findings.push( Finding { line: token.line, col: token.col, kind, name: name.clone(), expected } );
+	}
+}
+
+fn ident_at( tokens: &[Token], i: usize ) -> Option<&str> {
+	match tokens.get( i ).map( |t| &t.tok ) {
+		Some( Tok::Ident( s ) ) => Some( s.as_str() ),
+		_ => None
+	}
+}
+
+fn punct_at( tokens: &[Token], i: usize ) -> Option<char> {
+	match tokens.get( i ).map( |t| &t.tok ) {
+		Some( Tok::Punct( c ) ) => Some( *c ),
+		_ => None
+	}
+}
+
+// Skips an attribute ('#[...]' or '#![...]') starting at 'i', returning the index after it
+fn skip_attribute( tokens: &[Token], mut i: usize ) -> usize {
+	i += 1;
+
+	if punct_at( tokens, i ) == Some( '!' ) {
+		i += 1;
+	}
+
+	let mut depth = 0;
+
+	while i < tokens.len() {
+		match punct_at( tokens, i ) {
+			Some( '[' ) => depth += 1,
+			Some( ']' ) => {
+				depth -= 1;
+
+				if depth == 0 {
+					return i + 1;
+				}
+			},
+			_ => {}
+		}
+		i += 1;
+	}
+
+	return i;
+}
+
+// Skips 'pub', 'pub(crate)', 'pub(in path)' etc.
+fn skip_visibility( tokens: &[Token], mut i: usize ) -> usize {
+	if ident_at( tokens, i ) == Some( "pub" ) {
+		i += 1;
+
+		if punct_at( tokens, i ) == Some( '(' ) {
+			while i < tokens.len() && punct_at( tokens, i ) != Some( ')' ) {
+				i += 1;
+			}
+			i += 1;
+		}
+	}
+
+	return i;
+}
+
+fn lint_tokens( tokens: &[Token] ) -> Vec<Finding> {
+	let mut findings: Vec<Finding> = Vec::new();
+	let mut stack: Vec<Scope> = Vec::new();
+	let mut pending: Option<Scope> = None; // The scope the next opening '{' (or '(' for fn) starts
+	let mut item_start = true; // At the start of a field/variant/parameter
+	let mut i = 0;
+
+	while i < tokens.len() {
+		let scope = stack.last().copied().unwrap_or( Scope::Other );
+
+		if punct_at( tokens, i ) == Some( '#' ) && matches! ( punct_at( tokens, i + 1 ), Some( '[' ) | Some( '!' ) ) {
+			i = skip_attribute( tokens, i );
+			continue;
+		}
+
+		// Fields, variants and parameters are recognized at the start of each comma-separated item
+		if item_start && scope != Scope::Other {
+			let j = skip_visibility( tokens, i );
+			let is_mut = ident_at( tokens, j ) == Some( "mut" );
+			let j = if is_mut { j + 1 } else { j };
+
+			if ident_at( tokens, j ).is_some() && ident_at( tokens, j ) != Some( "self" ) {
+				let followed_by_colon = punct_at( tokens, j + 1 ) == Some( ':' ) && punct_at( tokens, j + 2 ) != Some( ':' );
+
+				match scope {
+					Scope::StructBody if followed_by_colon => check( &mut findings, tokens.get( j ), "field", Case::Snake ),
+					Scope::Params if followed_by_colon => check( &mut findings, tokens.get( j ), "parameter", Case::Snake ),
+					Scope::EnumBody => {
+						check( &mut findings, tokens.get( j ), "enum variant", Case::Pascal );
+						pending = Some( Scope::StructBody ); // Struct-like variants: 'Variant { field: T }'
+					},
+					_ => {}
+				}
+			}
+
+			item_start = false;
+		}
+
+		match &tokens[i].tok {
+			Tok::Punct( '{' ) => {
+				// A fn body, or a block we don't care about
+				stack.push( match pending.take() { Some( Scope::Params ) | None => Scope::Other, Some( s ) => s } );
+				item_start = true;
+			},
+			Tok::Punct( '(' ) => {
+				if pending == Some( Scope::Params ) {
+					pending = None;
+					stack.push( Scope::Params );
+				} else {
+					stack.push( Scope::Other );
+				}
+				item_start = true;
+			},
+			Tok::Punct( '}' ) | Tok::Punct( ')' ) => {
+				stack.pop();
+				item_start = false;
+			},
+			Tok::Punct( ',' ) => {
+				item_start = true;
+
+				if scope == Scope::EnumBody {
+					pending = None;
+				}
+			},
+			Tok::Punct( ';' ) => {
+				pending = None;
+				item_start = true;
+			},
+			Tok::Ident( kw ) => {
+				let next = i + 1;
+
+				match kw.as_str() {
+					"fn" => {
+						check( &mut findings, tokens.get( next ), "function", Case::Snake );
+						pending = Some( Scope::Params );
+					},
+					"mod" => check( &mut findings, tokens.get( next ), "module", Case::Snake ),
+					"struct" | "union" if ident_at( tokens, next ).is_some() => {
+						check( &mut findings, tokens.get( next ), "struct", Case::Pascal );
+						pending = Some( Scope::StructBody );
+					},
+					"enum" => {
+						check( &mut findings, tokens.get( next ), "enum", Case::Pascal );
+						pending = Some( Scope::EnumBody );
+					},
+					"trait" => check( &mut findings, tokens.get( next ), "trait", Case::Pascal ),
+					// 'type' inside a trait impl ('type Err = String;') is checked the same way
+					"type" => check( &mut findings, tokens.get( next ), "type alias", Case::Pascal ),
+					"const" | "static" => {
+						let mut j = next;
+
+						if ident_at( tokens, j ) == Some( "mut" ) {
+							j += 1;
+						}
+
+						// Skip 'const fn', and '_' placeholders
+						if ident_at( tokens, j ).is_some_and( |n| n != "fn" && n != "unsafe" && n != "_" ) && punct_at( tokens, j + 1 ) == Some( ':' ) {
+							check( &mut findings, tokens.get( j ), "constant", Case::ScreamingSnake );
+						}
+					},
+					"let" => {
+						let j = if ident_at( tokens, next ) == Some( "mut" ) { next + 1 } else { next };
+
+						// Only simple bindings: 'let x', 'let mut x: T' (not patterns like 'let Some( x )'). Skip '_' and '_unused', which are meant to be ignored
+						let path_follows = punct_at( tokens, j + 1 ) == Some( ':' ) && punct_at( tokens, j + 2 ) == Some( ':' ); // 'let Enum::Variant( x ) = ...'
+
+						if ident_at( tokens, j ).is_some_and( |n| !n.starts_with( '_' ) ) && !path_follows && matches! ( punct_at( tokens, j + 1 ), Some( ':' ) | Some( '=' ) | Some( ';' ) ) {
+							check( &mut findings, tokens.get( j ), "variable", Case::Snake );
+						}
+					},
+					"macro_rules" if punct_at( tokens, next ) == Some( '!' ) => check( &mut findings, tokens.get( next + 1 ), "macro", Case::Snake ),
+					_ => {}
+				}
+			},
+			_ => {}
+		}
+
+		i += 1;
+	}
+
+	return findings;
+}
+
+fn collect_files( path: &Path, files: &mut Vec<PathBuf> ) {
+	if path.is_dir() {
+		let mut entries: Vec<PathBuf> = match std::fs::read_dir( path ) {
+			Ok( rd ) => rd.filter_map( |e| e.ok().map( |e| e.path() ) ).collect(),
+			Err( e ) => {
+				eprintln! ( "Cannot read {}: {}", path.display(), e );
+				return;
+			}
+		};
+
+		entries.sort();
+
+		for entry in entries {
+			collect_files( &entry, files );
+		}
+	} else if path.extension().is_some_and( |e| e == "rs" ) {
+		files.push( path.to_path_buf() );
+	}
+}
+
+fn main() -> ExitCode {
+	let mut args: Vec<String> = std::env::args().skip( 1 ).collect();
+
+	if args.is_empty() {
+		args = vec!["src".to_string(), "ex".to_string()];
+	}
+
+	let mut files: Vec<PathBuf> = Vec::new();
+
+	for arg in &args {
+		collect_files( Path::new( arg ), &mut files );
+	}
+
+	let mut total = 0;
+
+	for file in &files {
+		let src = match std::fs::read_to_string( file ) {
+			Ok( s ) => s,
+			Err( e ) => {
+				eprintln! ( "Cannot read {}: {}", file.display(), e );
+				continue;
+			}
+		};
+
+		for f in lint_tokens( &tokenize( &src ) ) {
+			println! (
+				"{}:{}:{}: {} `{}` should be {}, e.g. `{}`",
+				file.display(), f.line, f.col, f.kind, f.name, f.expected, suggest( &f.name, f.expected )
+			);
+			total += 1;
+		}
+	}
+
+	println! ( "{} naming issue(s) in {} file(s)", total, files.len() );
+
+	return if total == 0 { ExitCode::SUCCESS } else { ExitCode::FAILURE };
+}
@@ -0,0 +1,408 @@
+// Calendar module (months, weekdays, ISO weeks, month grids, holidays and business days)
+
+/*
+Calendar.
+
+- Builds on 'crate::date::Date' (the Weekday enum lives there and is re-exported here).
+- Weeks start on Monday by default, following ISO 8601 (the 'cal' command starts on Sunday, which 'month_grid' also supports).
+- Holidays are read from a local rules file, one rule per line, in the form '<rule> | <name>':
+
+	# Comments start with '#'
+	01-01           | New Year's Day      (fixed date, MM-DD)
+	last Mon May    | Memorial Day        (nth weekday of a month: 1st, 2nd, 3rd, 4th, 5th or last)
+	4th Thu Nov     | Thanksgiving
+	easter-2        | Good Friday         (relative to Western Easter Sunday)
+	easter+1        | Easter Monday
+
+- See 'holidays.rules' at the root of the repo for an example.
+*/
+
+use std::fmt;
+
+use crate::date::{days_in_month, Date};
+pub use crate::date::Weekday;
+
+#[derive( Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash )]
+pub enum Month {
+	January = 1,
+	February,
+	March,
+	April,
+	May,
+	June,
+	July,
+	August,
+	September,
+	October,
+	November,
+	December
+}
+
+impl Month {
+	pub const ALL: [Month; 12] = [
+		Month::January, Month::February, Month::March, Month::April, Month::May, Month::June,
+		Month::July, Month::August, Month::September, Month::October, Month::November, Month::December
+	];
+
+	pub fn number( &self ) -> u8 {
+		return *self as u8;
+	}
+
+	pub fn from_number( n: u8 ) -> Option<Month> {
+		match n {
+			1..=12 => Some( Month::ALL[( n - 1 ) as usize] ),
+			_ => None
+		}
+	}
+
+	pub fn from_name( name: &str ) -> Option<Month> {
+		return crate::date::month_from_name( name ).and_then( Month::from_number );
+	}
+
+	pub fn name( &self ) -> &'static str {
+		match self {
+			Month::January => "January",
+			Month::February => "February",
+			Month::March => "March",
+			Month::April => "April",
+			Month::May => "May",
+			Month::June => "June",
+			Month::July => "July",
+			Month::August => "August",
+			Month::September => "September",
+			Month::October => "October",
+			Month::November => "November",
+			Month::December => "December",
+		}
+	}
+
+	pub fn days( &self, year: i32 ) -> u8 {
+		return days_in_month( year, self.number() );
+	}
+
+	pub fn next( &self ) -> Month {
+		return Month::ALL[( self.number() % 12 ) as usize];
+	}
+
+	pub fn prev( &self ) -> Month {
+		return Month::ALL[( ( self.number() + 10 ) % 12 ) as usize];
+	}
+}
+
+impl fmt::Display for Month {
+	fn fmt( &self, f: &mut fmt::Formatter<'_> ) -> fmt::Result {
+		f.write_str( self.name() )
+	}
+}
+
+fn weekday_from_name( name: &str ) -> Option<Weekday> {
+	let lower = name.to_ascii_lowercase();
+
+	if lower.len() < 3 {
+		return None;
+	}
+
+	return Weekday::ALL.iter().copied().find( |w| w.name().to_ascii_lowercase().starts_with( &lower ) );
+}
+
+/*
+ISO 8601 week numbers.
+- Weeks start on Monday, and week 1 is the week containing the year's first Thursday.
+- Returns the ISO "week-year" too, since Dec 29-31 can be in week 1 of the next year, and Jan 1-3 in week 52/53 of the previous one.
+*/
+pub fn iso_week( date: &Date ) -> ( i32, u8 ) {
+	let thursday = date.add_days( 4 - date.weekday().number_from_monday() as i64 );
+	let week = ( thursday.ordinal() - 1 ) / 7 + 1;
+
+	return ( thursday.year(), week as u8 );
+}
+
+// Western (Gregorian) Easter Sunday, using the anonymous "Meeus/Jones/Butcher" algorithm. None for years before 1 AD, where its integer divisions stop making sense
+pub fn easter_sunday( year: i32 ) -> Option<Date> {
+	if year < 1 {
+		return None;
+	}
+
+	let a = year % 19;
+	let b = year / 100;
+	let c = year % 100;
+	let d = b / 4;
+	let e = b % 4;
+	let f = ( b + 8 ) / 25;
+	let g = ( b - f + 1 ) / 3;
+	let h = ( 19 * a + b - d - g + 15 ) % 30;
+	let i = c / 4;
+	let k = c % 4;
+	let l = ( 32 + 2 * e + 2 * i - h - k ) % 7;
+	let m = ( a + 11 * h + 22 * l ) / 451;
+	let month = ( h + l - 7 * m + 114 ) / 31;
+	let day = ( h + l - 7 * m + 114 ) % 31 + 1;
+
+	return Date::new( year, month as u8, day as u8 ).ok(); // Always March or April for positive years
+}
+
+// The nth (1-based) 'weekday' of a month, or the last one if 'n' is negative. None if the month has no such day (e.g. a 5th Monday)
+pub fn nth_weekday( year: i32, month: Month, weekday: Weekday, n: i8 ) -> Option<Date> {
+	if n > 0 {
+		let first = Date::new( year, month.number(), 1 ).ok()?;
+		let offset = ( weekday as i64 - first.weekday() as i64 ).rem_euclid( 7 );
+		let date = first.add_days( offset + 7 * ( n as i64 - 1 ) );
+
+		return if date.month() == month.number() { Some( date ) } else { None };
+	}
+
+	let last = Date::new( year, month.number(), month.days( year ) ).ok()?;
+	let offset = ( last.weekday() as i64 - weekday as i64 ).rem_euclid( 7 );
+
+	return Some( last.add_days( -offset ) );
+}
+
+/*
+Month grids.
+- 'month_weeks' returns the raw rows (one per week, None for padding cells), and 'month_grid' formats them like 'cal':
+
+	   August 2003
+	Mo Tu We Th Fr Sa Su
+	             1  2  3
+	 4  5  6  7  8  9 10
+	...
+*/
+pub fn month_weeks( year: i32, month: Month, first_day: Weekday ) -> Vec<[Option<u8>; 7]> {
+	let mut weeks: Vec<[Option<u8>; 7]> = Vec::new();
+	let mut row: [Option<u8>; 7] = [None; 7];
+
+	let first = Date::new( year, month.number(), 1 ).unwrap();
+	let mut col = ( first.weekday() as i64 - first_day as i64 ).rem_euclid( 7 ) as usize;
+
+	for day in 1..=month.days( year ) {
+		row[col] = Some( day );
+		col += 1;
+
+		if col == 7 {
+			weeks.push( row );
+			row = [None; 7];
+			col = 0;
+		}
+	}
+
+	if col != 0 {
+		weeks.push( row );
+	}
+
+	return weeks;
+}
+
+pub fn month_grid( year: i32, month: Month, first_day: Weekday ) -> String {
+	let title = format! ( "{} {}", month.name(), year );
+	let width: usize = 20; // 7 columns of 2 chars + 6 separators
+	let mut out = format! ( "{:^width$}\n", title, width = width ).trim_end().to_string();
+	out.push( '\n' );
+
+	let header: Vec<&str> = ( 0..7 )
+		.map( |i| &Weekday::ALL[( first_day as usize + i ) % 7].name()[..2] )
+		.collect();
+	out.push_str( &header.join( " " ) );
+	out.push( '\n' );
+
+	for week in month_weeks( year, month, first_day ) {
+		let cells: Vec<String> = week
+			.iter()
+			.map( |d| match d { Some( n ) => format! ( "{:>2}", n ), None => "  ".to_string() } )
+			.collect();
+
+		out.push_str( cells.join( " " ).trim_end() );
+		out.push( '\n' );
+	}
+
+	return out;
+}
+
+// Holiday rules
+
+#[derive( Debug, Clone, PartialEq, Eq )]
+pub enum HolidayRule {
+	Fixed { month: Month, day: u8 },
+	NthWeekday { n: i8, weekday: Weekday, month: Month }, // n = -1 means "last"
+	EasterRelative { offset: i32 },
+}
+
+impl HolidayRule {
+	pub fn date_in( &self, year: i32 ) -> Option<Date> {
+		match self {
+			HolidayRule::Fixed { month, day } => Date::new( year, month.number(), *day ).ok(), // Feb 29 simply doesn't occur in common years
+			HolidayRule::NthWeekday { n, weekday, month } => nth_weekday( year, *month, *weekday, *n ),
+			HolidayRule::EasterRelative { offset } => easter_sunday( year ).map( |easter| easter.add_days( *offset as i64 ) ),
+		}
+	}
+
+	pub fn parse( rule: &str ) -> Result<HolidayRule, String> {
+		let rule = rule.trim();
+		let lower = rule.to_ascii_lowercase();
+
+		// easter, easter+1, easter-2
+		if let Some( rest ) = lower.strip_prefix( "easter" ) {
+			let rest = rest.trim();
+			let offset = if rest.is_empty() {
+				0
+			} else {
+				rest.replace( ' ', "" ).parse::<i32>().map_err( |_| format! ( "Invalid Easter offset in rule '{}'", rule ) )?
+			};
+
+			return Ok( HolidayRule::EasterRelative { offset } );
+		}
+
+		// MM-DD
+		if let Some( ( m, d ) ) = rule.split_once( '-' ) {
+			let month = m.trim().parse::<u8>().ok().and_then( Month::from_number ).ok_or( format! ( "Invalid month in rule '{}'", rule ) )?;
+			let day = d.trim().parse::<u8>().map_err( |_| format! ( "Invalid day in rule '{}'", rule ) )?;
+
+			// Validate against a leap year, so "02-29" is accepted
+			if day == 0 || day > month.days( 2000 ) {
+				return Err( format! ( "Invalid day in rule '{}'", rule ) );
+			}
+
+			return Ok( HolidayRule::Fixed { month, day } );
+		}
+
+		// <nth> <weekday> <month>
+		let parts: Vec<&str> = lower.split_whitespace().collect();
+
+		if parts.len() == 3 {
+			let n: i8 = match parts[0] {
+				"1st" | "first" => 1,
+				"2nd" | "second" => 2,
+				"3rd" | "third" => 3,
+				"4th" | "fourth" => 4,
+				"5th" | "fifth" => 5,
+				"last" => -1,
+				_ => return Err( format! ( "Invalid ordinal '{}' in rule '{}'", parts[0], rule ) )
+			};
+			let weekday = weekday_from_name( parts[1] ).ok_or( format! ( "Invalid weekday '{}' in rule '{}'", parts[1], rule ) )?;
+			let month = Month::from_name( parts[2] ).ok_or( format! ( "Invalid month '{}' in rule '{}'", parts[2], rule ) )?;
+
+			return Ok( HolidayRule::NthWeekday { n, weekday, month } );
+		}
+
+		return Err( format! ( "Unrecognized holiday rule: '{}'", rule ) );
+	}
+}
+
+#[derive( Debug, Clone, PartialEq, Eq )]
+pub struct Holiday {
+	pub name: String,
+	pub rule: HolidayRule,
+}
+
+#[derive( Debug, Clone )]
+pub struct Calendar {
+	pub holidays: Vec<Holiday>,
+	pub weekend: Vec<Weekday>,
+}
+
+impl Default for Calendar {
+	fn default() -> Self {
+		Self { holidays: Vec::new(), weekend: vec![Weekday::Saturday, Weekday::Sunday] }
+	}
+}
+
+impl Calendar {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	// Parses the rules file format described at the top of this module. Errors carry the line number
+	pub fn parse_rules( text: &str ) -> Result<Calendar, String> {
+		let mut calendar = Calendar::new();
+
+		for ( i, line ) in text.lines().enumerate() {
+			let line = line.split( '#' ).next().unwrap_or( "" ).trim();
+
+			if line.is_empty() {
+				continue;
+			}
+
+			let ( rule, name ) = match line.split_once( '|' ) {
+				Some( ( r, n ) ) if !n.trim().is_empty() => ( r, n.trim() ),
+				_ => return Err( format! ( "Line {}: expected '<rule> | <name>'", i + 1 ) )
+			};
+			let rule = HolidayRule::parse( rule ).map_err( |e| format! ( "Line {}: {}", i + 1, e ) )?;
+
+			calendar.holidays.push( Holiday { name: name.to_string(), rule } );
+		}
+
+		return Ok( calendar );
+	}
+
+	pub fn from_rules_file( path: impl AsRef<std::path::Path> ) -> Result<Calendar, String> {
+		let path = path.as_ref();
+		let text = std::fs::read_to_string( path ).map_err( |e| format! ( "Cannot read {}: {}", path.display(), e ) )?;
+
+		return Calendar::parse_rules( &text );
+	}
+
+	// All holidays of a year, sorted by date
+	pub fn holidays_in( &self, year: i32 ) -> Vec<( Date, &str )> {
+		let mut days: Vec<( Date, &str )> = self.holidays
+			.iter()
+			.filter_map( |h| h.rule.date_in( year ).map( |d| ( d, h.name.as_str() ) ) )
+			.filter( |( d, _ )| d.year() == year ) // Easter offsets could, in theory, spill into another year
+			.collect();
+
+		days.sort();
+
+		return days;
+	}
+
+	pub fn holiday_on( &self, date: &Date ) -> Option<&str> {
+		return self.holidays
+			.iter()
+			.find( |h| h.rule.date_in( date.year() ).as_ref() == Some( date ) )
+			.map( |h| h.name.as_str() );
+	}
+
+	pub fn is_business_day( &self, date: &Date ) -> bool {
+		return !self.weekend.contains( &date.weekday() ) && self.holiday_on( date ).is_none();
+	}
+
+	// Moves 'n' business days forward (or backward if negative). Adding 0 returns the date itself, even on a weekend
+	pub fn add_business_days( &self, date: &Date, n: i64 ) -> Date {
+		if self.weekend.len() >= 7 {
+			return *date; // No business days at all, avoid looping forever
+		}
+
+		let step: i64 = if n < 0 { -1 } else { 1 };
+		let mut remaining = n.abs();
+		let mut current = *date;
+
+		while remaining > 0 {
+			current = current.add_days( step );
+
+			if self.is_business_day( &current ) {
+				remaining -= 1;
+			}
+		}
+
+		return current;
+	}
+
+	// Business days in the half-open range [from, to), negative if 'to' is before 'from'
+	pub fn business_days_between( &self, from: &Date, to: &Date ) -> i64 {
+		let ( start, end, sign ) = if from <= to { ( *from, *to, 1 ) } else { ( *to, *from, -1 ) };
+		let mut count: i64 = 0;
+		let mut current = start;
+
+		while current < end {
+			if self.is_business_day( &current ) {
+				count += 1;
+			}
+
+			current = current.add_days( 1 );
+		}
+
+		return count * sign;
+	}
+
+	pub fn next_business_day( &self, date: &Date ) -> Date {
+		return self.add_business_days( date, 1 );
+	}
+}
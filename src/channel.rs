@@ -0,0 +1,474 @@
+// Bounded multi-producer, multi-consumer channel
+
+/*
+Channels.
+
+- "Do not communicate by sharing memory; share memory by communicating." Instead of several threads locking the same Vec, one side sends values and the other receives them, and the channel does the locking.
+- std::sync::mpsc is multi-producer, single-consumer: the Receiver can't be cloned. This one is MPMC: Senders and Receivers can both be cloned, and each value goes to exactly one receiver (e.g. several workers sharing one job queue).
+- It's bounded: at most 'capacity' values wait in the channel. When it's full, 'send' blocks until a receiver makes room. This is backpressure: a fast producer gets slowed down to the consumers' pace, instead of filling up memory.
+- Every blocking call has a non-blocking ('try_') and a timeout ('_timeout') version.
+- Closing:
+	* 'close()' (from either side) closes it right away: sends fail, receivers still get what's left, then Err( Closed ).
+	* Dropping every Sender closes it the same way. Dropping every Receiver makes sends fail (nobody would ever receive).
+- 'select( &[&rx1, &rx2] )' waits on several receivers at once, and returns whichever gets a value first (with its index).
+
+	producer --send--> [ v | v | v | _ | _ ] --recv--> consumer
+	producer --send-->    capacity = 5       --recv--> consumer
+*/
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const SPINS: u32 = 8;
+
+#[derive( Debug, Clone, Copy, PartialEq, Eq )]
+pub enum SendError<T> {
+	Full( T ),    // Only from try_send
+	Timeout( T ), // Only from send_timeout
+	Closed( T ),  // Closed, or every Receiver is gone
+}
+
+impl<T> SendError<T> {
+	// The value that couldn't be sent
+	pub fn into_inner( self ) -> T {
+		match self {
+			SendError::Full( v ) | SendError::Timeout( v ) | SendError::Closed( v ) => v,
+		}
+	}
+}
+
+impl<T> fmt::Display for SendError<T> {
+	fn fmt( &self, f: &mut fmt::Formatter ) -> fmt::Result {
+		match self {
+			SendError::Full( _ ) => write! ( f, "Channel is full" ),
+			SendError::Timeout( _ ) => write! ( f, "Timed out waiting for room in the channel" ),
+			SendError::Closed( _ ) => write! ( f, "Channel is closed" ),
+		}
+	}
+}
+
+impl<T: fmt::Debug> std::error::Error for SendError<T> {}
+
+#[derive( Debug, Clone, Copy, PartialEq, Eq )]
+pub enum RecvError {
+	Empty,   // Only from try_recv
+	Timeout, // Only from the timeout versions
+	Closed,  // Closed, and nothing left to receive
+}
+
+impl fmt::Display for RecvError {
+	fn fmt( &self, f: &mut fmt::Formatter ) -> fmt::Result {
+		match self {
+			RecvError::Empty => write! ( f, "Channel is empty" ),
+			RecvError::Timeout => write! ( f, "Timed out waiting for a value" ),
+			RecvError::Closed => write! ( f, "Channel is closed" ),
+		}
+	}
+}
+
+impl std::error::Error for RecvError {}
+
+// Wakes up a thread blocked in select()
+#[derive( Default )]
+struct Signal {
+	ready: Mutex<bool>,
+	cond: Condvar,
+}
+
+impl Signal {
+	fn notify( &self ) {
+		*self.ready.lock().unwrap() = true;
+		self.cond.notify_one();
+	}
+}
+
+struct State<T> {
+	queue: VecDeque<T>,
+	senders: usize,
+	receivers: usize,
+	closed: bool,
+	selectors: Vec<Arc<Signal>>,
+	// Threads blocked in send/recv. Notifying a Condvar costs a syscall even when nobody waits, so it's skipped then
+	blocked_senders: usize,
+	blocked_receivers: usize,
+}
+
+impl<T> State<T> {
+	fn is_closed( &self ) -> bool {
+		return self.closed || self.senders == 0;
+	}
+}
+
+struct Shared<T> {
+	state: Mutex<State<T>>,
+	capacity: usize,
+	not_empty: Condvar,
+	not_full: Condvar,
+}
+
+impl<T> Shared<T> {
+	// Gives the other side a moment before really blocking. Most waits are short, and a sleep + wake up costs two syscalls
+	fn yield_now<'a>( &'a self, state: MutexGuard<'a, State<T>> ) -> MutexGuard<'a, State<T>> {
+		drop( state );
+		thread::yield_now();
+
+		return self.state.lock().unwrap();
+	}
+
+	fn close( &self ) {
+		let mut state = self.state.lock().unwrap();
+		state.closed = true;
+
+		for signal in &state.selectors {
+			signal.notify();
+		}
+
+		drop( state );
+		self.not_empty.notify_all();
+		self.not_full.notify_all();
+	}
+}
+
+fn wait<'a, T>( cond: &Condvar, state: MutexGuard<'a, State<T>>, timeout: Option<Duration> ) -> MutexGuard<'a, State<T>> {
+	match timeout {
+		None => cond.wait( state ).unwrap(),
+		Some( timeout ) => cond.wait_timeout( state, timeout ).unwrap().0,
+	}
+}
+
+pub struct Sender<T> {
+	shared: Arc<Shared<T>>,
+}
+
+pub struct Receiver<T> {
+	shared: Arc<Shared<T>>,
+}
+
+// Panics if capacity is 0
+pub fn bounded<T>( capacity: usize ) -> ( Sender<T>, Receiver<T> ) {
+	assert! ( capacity > 0, "Channel capacity must be at least 1" );
+
+	let state = State { queue: VecDeque::with_capacity( capacity ), senders: 1, receivers: 1, closed: false, selectors: Vec::new(), blocked_senders: 0, blocked_receivers: 0 };
+	let shared = Arc::new( Shared { state: Mutex::new( state ), capacity, not_empty: Condvar::new(), not_full: Condvar::new() } );
+
+	return ( Sender { shared: Arc::clone( &shared ) }, Receiver { shared } );
+}
+
+impl<T> Sender<T> {
+	pub fn send( &self, value: T ) -> Result<(), SendError<T>> {
+		return self.send_until( value, None );
+	}
+
+	pub fn try_send( &self, value: T ) -> Result<(), SendError<T>> {
+		let state = self.shared.state.lock().unwrap();
+
+		if state.is_closed() || state.receivers == 0 {
+			return Err( SendError::Closed( value ) );
+		}
+
+		if state.queue.len() >= self.shared.capacity {
+			return Err( SendError::Full( value ) );
+		}
+
+		self.push( state, value );
+
+		return Ok( () );
+	}
+
+	pub fn send_timeout( &self, value: T, timeout: Duration ) -> Result<(), SendError<T>> {
+		return self.send_until( value, Some( Instant::now() + timeout ) );
+	}
+
+	fn send_until( &self, value: T, deadline: Option<Instant> ) -> Result<(), SendError<T>> {
+		let mut state = self.shared.state.lock().unwrap();
+		let mut spins = 0;
+
+		loop {
+			if state.is_closed() || state.receivers == 0 {
+				return Err( SendError::Closed( value ) );
+			}
+
+			if state.queue.len() < self.shared.capacity {
+				self.push( state, value );
+				return Ok( () );
+			}
+
+			// Full: wait for a receiver to make room (backpressure)
+			let timeout = match deadline {
+				None => None,
+				Some( deadline ) => match deadline.checked_duration_since( Instant::now() ) {
+					Some( left ) if !left.is_zero() => Some( left ),
+					_ => return Err( SendError::Timeout( value ) ),
+				}
+			};
+
+			if spins < SPINS {
+				spins += 1;
+				state = self.shared.yield_now( state );
+				continue;
+			}
+
+			state.blocked_senders += 1;
+			state = wait( &self.shared.not_full, state, timeout );
+			state.blocked_senders -= 1;
+		}
+	}
+
+	fn push( &self, mut state: MutexGuard<State<T>>, value: T ) {
+		state.queue.push_back( value );
+
+		for signal in &state.selectors {
+			signal.notify();
+		}
+
+		let wake = state.blocked_receivers > 0;
+		drop( state );
+
+		if wake {
+			self.shared.not_empty.notify_one();
+		}
+	}
+
+	pub fn close( &self ) {
+		self.shared.close();
+	}
+
+	pub fn is_closed( &self ) -> bool {
+		let state = self.shared.state.lock().unwrap();
+
+		return state.is_closed() || state.receivers == 0;
+	}
+
+	pub fn len( &self ) -> usize {
+		return self.shared.state.lock().unwrap().queue.len();
+	}
+
+	pub fn is_empty( &self ) -> bool {
+		return self.len() == 0;
+	}
+
+	pub fn is_full( &self ) -> bool {
+		return self.len() >= self.shared.capacity;
+	}
+
+	pub fn capacity( &self ) -> usize {
+		return self.shared.capacity;
+	}
+}
+
+impl<T> Clone for Sender<T> {
+	fn clone( &self ) -> Self {
+		self.shared.state.lock().unwrap().senders += 1;
+
+		Self { shared: Arc::clone( &self.shared ) }
+	}
+}
+
+impl<T> Drop for Sender<T> {
+	fn drop( &mut self ) {
+		let mut state = self.shared.state.lock().unwrap();
+		state.senders -= 1;
+
+		if state.senders == 0 {
+			drop( state );
+			self.shared.close();
+		}
+	}
+}
+
+impl<T> Receiver<T> {
+	pub fn recv( &self ) -> Result<T, RecvError> {
+		return self.recv_until( None );
+	}
+
+	pub fn try_recv( &self ) -> Result<T, RecvError> {
+		let state = self.shared.state.lock().unwrap();
+
+		match self.pop( state ) {
+			Ok( value ) => Ok( value ),
+			Err( state ) if state.is_closed() => Err( RecvError::Closed ),
+			Err( _ ) => Err( RecvError::Empty ),
+		}
+	}
+
+	pub fn recv_timeout( &self, timeout: Duration ) -> Result<T, RecvError> {
+		return self.recv_until( Some( Instant::now() + timeout ) );
+	}
+
+	fn recv_until( &self, deadline: Option<Instant> ) -> Result<T, RecvError> {
+		let mut state = self.shared.state.lock().unwrap();
+		let mut spins = 0;
+
+		loop {
+			state = match self.pop( state ) {
+				Ok( value ) => return Ok( value ),
+				Err( state ) => state,
+			};
+
+			if state.is_closed() {
+				return Err( RecvError::Closed );
+			}
+
+			let timeout = match deadline {
+				None => None,
+				Some( deadline ) => match deadline.checked_duration_since( Instant::now() ) {
+					Some( left ) if !left.is_zero() => Some( left ),
+					_ => return Err( RecvError::Timeout ),
+				}
+			};
+
+			if spins < SPINS {
+				spins += 1;
+				state = self.shared.yield_now( state );
+				continue;
+			}
+
+			state.blocked_receivers += 1;
+			state = wait( &self.shared.not_empty, state, timeout );
+			state.blocked_receivers -= 1;
+		}
+	}
+
+	// Gives the lock back if there was nothing to take
+	fn pop<'a>( &self, mut state: MutexGuard<'a, State<T>> ) -> Result<T, MutexGuard<'a, State<T>>> {
+		let Some( value ) = state.queue.pop_front() else { return Err( state ) };
+
+		let wake = state.blocked_senders > 0;
+		drop( state );
+
+		if wake {
+			self.shared.not_full.notify_one();
+		}
+
+		return Ok( value );
+	}
+
+	// Blocks for each value, ends once the channel is closed and drained
+	pub fn iter( &self ) -> impl Iterator<Item = T> + '_ {
+		std::iter::from_fn( move || self.recv().ok() )
+	}
+
+	pub fn close( &self ) {
+		self.shared.close();
+	}
+
+	pub fn is_closed( &self ) -> bool {
+		return self.shared.state.lock().unwrap().is_closed();
+	}
+
+	pub fn len( &self ) -> usize {
+		return self.shared.state.lock().unwrap().queue.len();
+	}
+
+	pub fn is_empty( &self ) -> bool {
+		return self.len() == 0;
+	}
+
+	pub fn capacity( &self ) -> usize {
+		return self.shared.capacity;
+	}
+
+	fn watch( &self, signal: &Arc<Signal> ) {
+		self.shared.state.lock().unwrap().selectors.push( Arc::clone( signal ) );
+	}
+
+	fn unwatch( &self, signal: &Arc<Signal> ) {
+		self.shared.state.lock().unwrap().selectors.retain( |s| !Arc::ptr_eq( s, signal ) );
+	}
+}
+
+impl<T> Clone for Receiver<T> {
+	fn clone( &self ) -> Self {
+		self.shared.state.lock().unwrap().receivers += 1;
+
+		Self { shared: Arc::clone( &self.shared ) }
+	}
+}
+
+impl<T> Drop for Receiver<T> {
+	fn drop( &mut self ) {
+		let mut state = self.shared.state.lock().unwrap();
+		state.receivers -= 1;
+
+		if state.receivers == 0 {
+			drop( state );
+			self.shared.not_full.notify_all(); // Blocked senders find out nobody is listening
+		}
+	}
+}
+
+// Select
+
+// Waits until one of the receivers has a value. Err( Closed ) once every one of them is closed and drained
+pub fn select<T>( receivers: &[&Receiver<T>] ) -> Result<( usize, T ), RecvError> {
+	return select_until( receivers, None );
+}
+
+pub fn select_timeout<T>( receivers: &[&Receiver<T>], timeout: Duration ) -> Result<( usize, T ), RecvError> {
+	return select_until( receivers, Some( Instant::now() + timeout ) );
+}
+
+fn select_until<T>( receivers: &[&Receiver<T>], deadline: Option<Instant> ) -> Result<( usize, T ), RecvError> {
+	let signal = Arc::new( Signal::default() );
+
+	// Watch before checking, so a value sent in between still wakes us up
+	for rx in receivers {
+		rx.watch( &signal );
+	}
+
+	let result = loop {
+		*signal.ready.lock().unwrap() = false;
+
+		let mut closed = 0;
+		let mut found = None;
+
+		for ( i, rx ) in receivers.iter().enumerate() {
+			match rx.try_recv() {
+				Ok( value ) => {
+					found = Some( ( i, value ) );
+					break;
+				},
+				Err( RecvError::Closed ) => closed += 1,
+				Err( _ ) => ()
+			}
+		}
+
+		if let Some( found ) = found {
+			break Ok( found );
+		}
+
+		if closed == receivers.len() {
+			break Err( RecvError::Closed );
+		}
+
+		let mut ready = signal.ready.lock().unwrap();
+
+		match deadline {
+			None => {
+				while !*ready {
+					ready = signal.cond.wait( ready ).unwrap();
+				}
+			},
+			Some( deadline ) => {
+				let now = Instant::now();
+
+				if now >= deadline {
+					break Err( RecvError::Timeout );
+				}
+
+				if !*ready {
+					let _ = signal.cond.wait_timeout( ready, deadline - now ).unwrap();
+				}
+			}
+		}
+	};
+
+	for rx in receivers {
+		rx.unwatch( &signal );
+	}
+
+	return result;
+}
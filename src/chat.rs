@@ -0,0 +1,411 @@
+// Line-based chat protocol on top of threading::Server
+
+/*
+Chat protocol.
+
+- One command per line, like IRC (simplified):
+
+	NICK <name>          Pick (or change) a nickname. Needed before anything but LIST and QUIT
+	JOIN #room           Join a room, creating it if needed
+	PART #room           Leave a room (empty rooms are removed)
+	MSG <target> <text>  Send to a room you're in (#room), or privately to a nickname
+	LIST [#room]         Rooms with how many are in each, or the nicknames in one room
+	QUIT [reason]        Leave every room and disconnect
+
+- Every command gets exactly one answer line: "OK <command> ..." or "ERR <code> <message>", e.g. "ERR 433 Nickname is already in use: bob". The codes are borrowed from IRC.
+- Everything else a client receives starts with ':' and the sender's nickname, and is pushed whenever it happens (through the Server's Outbox):
+
+	:alice JOIN #rust
+	:alice MSG #rust hello everyone
+	:alice MSG bob hi, privately
+	:alice NICK alicia
+	:alicia PART #rust
+	:alicia QUIT gone home
+
+- Commands are case-insensitive, nicknames and room names aren't.
+- If the server runs with heartbeats (Server::with_heartbeat), it also sends "PING <n>" lines, which must be answered with "PONG <n>".
+- Clients that disconnect without QUIT (or are removed, timed out...) leave their rooms the same way, through the server's "disconnect" event, with its reason as the QUIT message.
+*/
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+use std::io;
+use std::net::SocketAddr;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crate::shutdown::ShutdownSignal;
+use crate::threading::{ClientId, Outbox, Request, Response, Server, ServerEvent};
+
+pub const MAX_NAME_LEN: usize = 16;
+
+#[derive( Debug, Clone, PartialEq, Eq )]
+pub enum ChatError {
+	NoSuchNick( String ),
+	NoSuchRoom( String ),
+	CannotSendToRoom( String ),
+	NoRecipient,
+	NoTextToSend,
+	UnknownCommand( String ),
+	ErroneousNickname( String ),
+	NicknameInUse( String ),
+	NotInRoom( String ),
+	NotRegistered,
+	NeedMoreParams( String ),
+}
+
+impl ChatError {
+	pub fn code( &self ) -> u16 {
+		match self {
+			ChatError::NoSuchNick( _ ) => 401,
+			ChatError::NoSuchRoom( _ ) => 403,
+			ChatError::CannotSendToRoom( _ ) => 404,
+			ChatError::NoRecipient => 411,
+			ChatError::NoTextToSend => 412,
+			ChatError::UnknownCommand( _ ) => 421,
+			ChatError::ErroneousNickname( _ ) => 432,
+			ChatError::NicknameInUse( _ ) => 433,
+			ChatError::NotInRoom( _ ) => 442,
+			ChatError::NotRegistered => 451,
+			ChatError::NeedMoreParams( _ ) => 461,
+		}
+	}
+}
+
+// "<code> <message>", which is what goes after "ERR " on the wire
+impl fmt::Display for ChatError {
+	fn fmt( &self, f: &mut fmt::Formatter ) -> fmt::Result {
+		write! ( f, "{} ", self.code() )?;
+
+		match self {
+			ChatError::NoSuchNick( nick ) => write! ( f, "No such nickname: {nick}" ),
+			ChatError::NoSuchRoom( room ) => write! ( f, "No such room: {room}" ),
+			ChatError::CannotSendToRoom( room ) => write! ( f, "Cannot send to a room you're not in: {room}" ),
+			ChatError::NoRecipient => write! ( f, "No recipient given" ),
+			ChatError::NoTextToSend => write! ( f, "No text to send" ),
+			ChatError::UnknownCommand( cmd ) => write! ( f, "Unknown command: {cmd}" ),
+			ChatError::ErroneousNickname( nick ) => write! ( f, "Erroneous nickname: {nick}" ),
+			ChatError::NicknameInUse( nick ) => write! ( f, "Nickname is already in use: {nick}" ),
+			ChatError::NotInRoom( room ) => write! ( f, "You're not in that room: {room}" ),
+			ChatError::NotRegistered => write! ( f, "Pick a nickname first (NICK <name>)" ),
+			ChatError::NeedMoreParams( cmd ) => write! ( f, "Not enough parameters: {cmd}" ),
+		}
+	}
+}
+
+impl std::error::Error for ChatError {}
+
+#[derive( Debug, Clone, PartialEq, Eq )]
+pub enum Command {
+	Nick( String ),
+	Join( String ),
+	Part( String ),
+	Msg { target: String, text: String },
+	List( Option<String> ),
+	Quit( Option<String> ),
+}
+
+impl FromStr for Command {
+	type Err = ChatError;
+
+	fn from_str( line: &str ) -> Result<Self, Self::Err> {
+		let line = line.trim();
+		let ( cmd, rest ) = line.split_once( ' ' ).unwrap_or( ( line, "" ) );
+		let rest = rest.trim();
+		let cmd = cmd.to_uppercase();
+
+		// The one parameter of NICK/JOIN/PART
+		let param = || match rest.split_whitespace().next() {
+			Some( param ) => Ok( param.to_string() ),
+			None => Err( ChatError::NeedMoreParams( cmd.clone() ) ),
+		};
+
+		let optional = || Some( rest.to_string() ).filter( |s| !s.is_empty() );
+
+		match cmd.as_str() {
+			"NICK" => Ok( Command::Nick( param()? ) ),
+			"JOIN" => Ok( Command::Join( param()? ) ),
+			"PART" => Ok( Command::Part( param()? ) ),
+			"MSG" => {
+				let ( target, text ) = rest.split_once( ' ' ).unwrap_or( ( rest, "" ) );
+
+				match ( target, text.trim() ) {
+					( "", _ ) => Err( ChatError::NoRecipient ),
+					( _, "" ) => Err( ChatError::NoTextToSend ),
+					( target, text ) => Ok( Command::Msg { target: target.to_string(), text: text.to_string() } ),
+				}
+			},
+			"LIST" => Ok( Command::List( optional() ) ),
+			"QUIT" => Ok( Command::Quit( optional() ) ),
+			"" => Err( ChatError::UnknownCommand( "(empty line)".to_string() ) ),
+			_ => Err( ChatError::UnknownCommand( cmd ) ),
+		}
+	}
+}
+
+// Letters, digits, '_' and '-', starting with a letter
+fn is_valid_name( name: &str ) -> bool {
+	let mut chars = name.chars();
+
+	return matches! ( chars.next(), Some( c ) if c.is_ascii_alphabetic() )
+		&& name.len() <= MAX_NAME_LEN
+		&& chars.all( |c| c.is_ascii_alphanumeric() || c == '_' || c == '-' );
+}
+
+fn is_valid_room( room: &str ) -> bool {
+	return room.strip_prefix( '#' ).is_some_and( is_valid_name );
+}
+
+#[derive( Default )]
+struct State {
+	nicks: HashMap<ClientId, String>,
+	by_nick: HashMap<String, ClientId>,
+	rooms: BTreeMap<String, BTreeSet<ClientId>>,
+}
+
+impl State {
+	fn nick( &self, id: ClientId ) -> Result<String, ChatError> {
+		return self.nicks.get( &id ).cloned().ok_or( ChatError::NotRegistered );
+	}
+
+	// Everyone who shares a room with the client, once each, not including the client
+	fn neighbours( &self, id: ClientId ) -> BTreeSet<ClientId> {
+		return self.rooms.values()
+			.filter( |members| members.contains( &id ) )
+			.flat_map( |members| members.iter().copied() )
+			.filter( |&other| other != id )
+			.collect();
+	}
+
+	fn leave_room( &mut self, room: &str, id: ClientId ) -> bool {
+		let Some( members ) = self.rooms.get_mut( room ) else { return false };
+		let removed = members.remove( &id );
+
+		if members.is_empty() {
+			self.rooms.remove( room );
+		}
+
+		return removed;
+	}
+}
+
+// Lines to push once the state lock is released: the recipients, and the line
+type Outgoing = Vec<( Vec<ClientId>, String )>;
+
+// What a command answers, once its lines went out
+enum Reply {
+	Done( String ),
+	Sent { target: String, private: bool }, // MSG: the answer says how many got it
+}
+
+// The protocol's state, shared by every client thread. Its lock is never held while sending, so one slow client can't stall everyone (lines from different senders may reach two clients in a different order)
+pub struct Chat {
+	outbox: Outbox,
+	state: Mutex<State>,
+}
+
+impl Chat {
+	pub fn new( outbox: Outbox ) -> Self {
+		Self { outbox, state: Mutex::new( State::default() ) }
+	}
+
+	pub fn handle( &self, req: &Request ) -> Response {
+		let result = req.body.parse::<Command>().and_then( |cmd| self.run( req.id, cmd ) );
+
+		match result {
+			Ok( body ) => Response { ok: true, body: format! ( "OK {body}" ) },
+			Err( e ) => Response { ok: false, body: e.to_string() },
+		}
+	}
+
+	pub fn nick( &self, id: ClientId ) -> Option<String> {
+		return self.state.lock().unwrap().nicks.get( &id ).cloned();
+	}
+
+	// Room names, with how many are in each
+	pub fn rooms( &self ) -> Vec<( String, usize )> {
+		return self.state.lock().unwrap().rooms.iter().map( |( room, members )| ( room.clone(), members.len() ) ).collect();
+	}
+
+	fn run( &self, id: ClientId, cmd: Command ) -> Result<String, ChatError> {
+		let mut outgoing: Outgoing = Vec::new();
+		let reply = self.update( &mut self.state.lock().unwrap(), id, cmd, &mut outgoing )?; // The lock is released here
+		let delivered = self.deliver( outgoing );
+
+		match reply {
+			Reply::Done( body ) => Ok( body ),
+			Reply::Sent { target, private: true } if delivered == 0 => Err( ChatError::NoSuchNick( target ) ), // Disconnected in the meantime
+			Reply::Sent { target, .. } => Ok( format! ( "MSG {target} {delivered}" ) ),
+		}
+	}
+
+	// Sends every line, returning how many were delivered. Clients whose write failed or timed out are disconnected
+	fn deliver( &self, outgoing: Outgoing ) -> usize {
+		let mut delivered = 0;
+
+		for ( recipients, line ) in outgoing {
+			for to in recipients {
+				match self.outbox.send( to, &line ) {
+					true => delivered += 1,
+					false => { self.outbox.disconnect( to ); }
+				}
+			}
+		}
+
+		return delivered;
+	}
+
+	// Applies the command to the state, queueing the lines it sends
+	fn update( &self, state: &mut State, id: ClientId, cmd: Command, outgoing: &mut Outgoing ) -> Result<Reply, ChatError> {
+		match cmd {
+			Command::Nick( nick ) => {
+				if !is_valid_name( &nick ) {
+					return Err( ChatError::ErroneousNickname( nick ) );
+				}
+
+				match state.by_nick.get( &nick ) {
+					Some( &owner ) if owner == id => return Ok( Reply::Done( format! ( "NICK {nick}" ) ) ),
+					Some( _ ) => return Err( ChatError::NicknameInUse( nick ) ),
+					None => ()
+				}
+
+				if let Some( old ) = state.nicks.insert( id, nick.clone() ) {
+					state.by_nick.remove( &old );
+					outgoing.push( ( state.neighbours( id ).into_iter().collect(), format! ( ":{old} NICK {nick}" ) ) );
+				}
+
+				state.by_nick.insert( nick.clone(), id );
+
+				Ok( Reply::Done( format! ( "NICK {nick}" ) ) )
+			},
+			Command::Join( room ) => {
+				let nick = state.nick( id )?;
+
+				if !is_valid_room( &room ) {
+					return Err( ChatError::NoSuchRoom( room ) );
+				}
+
+				let members = state.rooms.entry( room.clone() ).or_default();
+
+				if members.insert( id ) {
+					outgoing.push( ( members.iter().copied().filter( |&m| m != id ).collect(), format! ( ":{nick} JOIN {room}" ) ) );
+				}
+
+				Ok( Reply::Done( format! ( "JOIN {room}" ) ) )
+			},
+			Command::Part( room ) => {
+				let nick = state.nick( id )?;
+
+				if !state.leave_room( &room, id ) {
+					return Err( ChatError::NotInRoom( room ) );
+				}
+
+				if let Some( members ) = state.rooms.get( &room ) {
+					outgoing.push( ( members.iter().copied().collect(), format! ( ":{nick} PART {room}" ) ) );
+				}
+
+				Ok( Reply::Done( format! ( "PART {room}" ) ) )
+			},
+			Command::Msg { target, text } => {
+				let nick = state.nick( id )?;
+				let line = format! ( ":{nick} MSG {target} {text}" );
+
+				if target.starts_with( '#' ) {
+					let members = state.rooms.get( &target ).filter( |m| m.contains( &id ) ).ok_or( ChatError::CannotSendToRoom( target.clone() ) )?;
+					outgoing.push( ( members.iter().copied().filter( |&m| m != id ).collect(), line ) );
+
+					return Ok( Reply::Sent { target, private: false } );
+				}
+
+				let to = *state.by_nick.get( &target ).ok_or( ChatError::NoSuchNick( target.clone() ) )?;
+				outgoing.push( ( vec![to], line ) );
+
+				Ok( Reply::Sent { target, private: true } )
+			},
+			Command::List( None ) => {
+				let rooms: Vec<String> = state.rooms.iter().map( |( room, members )| format! ( "{room}:{}", members.len() ) ).collect();
+
+				Ok( Reply::Done( format! ( "LIST {}", rooms.join( " " ) ).trim_end().to_string() ) )
+			},
+			Command::List( Some( room ) ) => {
+				let members = state.rooms.get( &room ).ok_or( ChatError::NoSuchRoom( room.clone() ) )?;
+				let mut nicks: Vec<&str> = members.iter().filter_map( |m| state.nicks.get( m ) ).map( String::as_str ).collect();
+				nicks.sort();
+
+				Ok( Reply::Done( format! ( "LIST {room} {}", nicks.join( " " ) ) ) )
+			},
+			Command::Quit( reason ) => {
+				leave_locked( state, id, reason.as_deref().unwrap_or( "Quit" ), outgoing );
+				self.outbox.disconnect( id );
+
+				Ok( Reply::Done( "QUIT Bye".to_string() ) )
+			}
+		}
+	}
+
+	// For clients that went away without QUIT. Does nothing if they already quit
+	pub fn leave( &self, id: ClientId, reason: &str ) {
+		let mut outgoing: Outgoing = Vec::new();
+		leave_locked( &mut self.state.lock().unwrap(), id, reason, &mut outgoing );
+
+		self.deliver( outgoing );
+	}
+}
+
+fn leave_locked( state: &mut State, id: ClientId, reason: &str, outgoing: &mut Outgoing ) {
+	let Some( nick ) = state.nicks.remove( &id ) else { return };
+	state.by_nick.remove( &nick );
+
+	let neighbours = state.neighbours( id );
+	let rooms: Vec<String> = state.rooms.keys().cloned().collect();
+
+	for room in rooms {
+		state.leave_room( &room, id );
+	}
+
+	outgoing.push( ( neighbours.into_iter().collect(), format! ( ":{nick} QUIT {reason}" ) ) );
+}
+
+// A Server speaking the chat protocol. Not started yet, so heartbeats, timeouts etc. can still be configured
+pub fn server( addr: SocketAddr ) -> ( Server, Arc<Chat> ) {
+	let server = Server::new( addr );
+	let chat = Arc::new( Chat::new( server.outbox() ) );
+
+	let on_leave = Arc::clone( &chat );
+	server.events().on( "disconnect", move |e: &ServerEvent| {
+		if let ServerEvent::Disconnected( client, reason ) = e {
+			on_leave.leave( client.id, &reason.to_string() );
+		}
+	});
+
+	let handler = Arc::clone( &chat );
+	let server = server.with_handler( move |r| handler.handle( &r ) );
+
+	return ( server, chat );
+}
+
+// Runs a chat server until Enter, Ctrl+C or SIGTERM. Connect with 'cargo run --bin chat_client -- <addr>'
+pub fn serve_chat( addr: SocketAddr ) -> io::Result<()> {
+	let signal = ShutdownSignal::new();
+	let _ = signal.listen_for_os_signals(); // Not on every OS, Enter still works there
+
+	let ( server, _chat ) = server( addr );
+	let mut server = server.with_shutdown( &signal );
+	let bound = server.start()?;
+
+	println! ( "Chat server on {bound}, press Enter or Ctrl+C to stop" );
+
+	let enter = signal.clone();
+	thread::spawn( move || {
+		let _ = io::stdin().read_line( &mut String::new() );
+		enter.trigger();
+	});
+
+	signal.wait();
+	println! ( "{}", signal.drain( Duration::from_secs( 5 ) ) ); // Clients get up to 5s to finish their current request
+	server.stop();
+
+	return Ok( () );
+}
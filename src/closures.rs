@@ -1,5 +1,9 @@
 // Closures (anonymous functions/lambdas)
 
+use rs_basics::events::{ Event, EventBus };
+use rs_basics::pipeline::{ and_then, compose, map_err, pipe, tap };
+use rs_basics::memo::{ self, Policy };
+
 pub fn test_closures() {
 	let ret_name = || println! ( "Hello Rust!" ); // This marks the variable as a closure function
 
@@ -20,3 +24,58 @@ pub fn test_closures_3() { // A multi-line closure
 
 	ret_name( "Hello", "World" );
 }
+
+// Closures can also be stored and called later, which is how event handlers work (see src/events.rs)
+pub fn test_closures_4() {
+	struct Login( String );
+
+	impl Event for Login {
+		fn topic( &self ) -> &str {
+			"login"
+		}
+	}
+
+	let mut bus: EventBus<Login> = EventBus::new();
+	let mut count = 0;
+
+	let greet = bus.on( "login", |e| println! ( "Welcome, {}!", e.0 ) ); // Fn: only reads its input
+	bus.on_mut( "login", move |_| { count += 1; println! ( "Logins so far: {}", count ) } ); // FnMut: mutates the 'count' it captured (moved in)
+	bus.once( "login", |e| println! ( "First login was {}", e.0 ) ); // FnOnce: runs a single time, then it's removed
+
+	bus.publish( &Login( "Alice".to_string() ) );
+	bus.unsubscribe( &greet );
+	bus.publish( &Login( "Bob".to_string() ) );
+}
+
+// Closures can be combined into new closures (see src/pipeline.rs)
+pub fn test_closures_5() {
+	let double_then_inc = pipe( |x: i32| x * 2, |x| x + 1 ); // (x * 2) + 1
+	let inc_then_double = compose( |x: i32| x * 2, |x: i32| x + 1 ); // (x + 1) * 2
+	let traced = pipe( tap( |x: &i32| println! ( "Got {}", x ) ), double_then_inc );
+
+	println! ( "{} {}", inc_then_double( 5 ), traced( 5 ) );
+
+	// Result pipelines stop at the first error
+	let parse = map_err( |s: &str| s.trim().parse::<u32>(), |e| format! ( "Not a number: {}", e ) );
+	let check = |age: u32| if age < 150 { Ok( age ) } else { Err( format! ( "{} is too old", age ) ) };
+	let parse_age = and_then( parse, check );
+
+	println! ( "{:?} {:?} {:?}", parse_age( " 21 " ), parse_age( "abc" ), parse_age( "200" ) );
+}
+
+// Closures can also be wrapped in a cache, so repeated calls are free (see src/memo.rs)
+pub fn test_closures_6() {
+	// Without the cache this takes ~2^n calls, with it ~n
+	let fib = memo::memoize_rec( Policy::Unbounded, |fib, n: u64| if n < 2 { n } else { fib( n - 1 ) + fib( n - 2 ) } );
+
+	println! ( "fib( 90 ) = {} [{}]", fib.call( 90 ), fib.stats() );
+
+	// Two arguments become one tuple key, and only the 2 most recent results are kept
+	let area = memo::memoize( Policy::Lru( 2 ), |( w, h ): ( u32, u32 )| w * h );
+
+	for size in [( 2, 3 ), ( 4, 5 ), ( 2, 3 ), ( 6, 7 ), ( 4, 5 )] {
+		print! ( "{} ", area.call( size ) );
+	}
+
+	println! ( "[{}]", area.stats() );
+}
@@ -0,0 +1,444 @@
+// Contact details: validated Email and Phone newtypes
+
+/*
+Newtypes.
+
+- A newtype is a tuple struct wrapping a single value ('struct Email( String )'). It costs nothing at runtime, but the compiler now treats it as a different type.
+- If the only way to build one is a constructor that validates, then every 'Email' in the program is known to be valid, and functions taking an 'Email' don't have to check again (unlike a 'Person.Email: String').
+
+Emails (RFC 5322 "addr-spec", with the RFC 6531 internationalized extensions):
+- local-part: a dot-atom ('john.doe+tag') or a quoted string ('"john doe"'), at most 64 bytes.
+- domain: dot-separated labels of letters, digits and hyphens (1-63 chars each, no leading/trailing hyphen), at most 253 chars, or an address literal ('[192.168.0.1]').
+- IDN awareness: labels may contain Unicode letters ('bücher.de'), and 'Email::ascii_domain' returns the Punycode form ('xn--bcher-kva.de') that DNS actually uses. Existing 'xn--' labels are accepted as-is.
+- Comments and folding whitespace (also allowed by RFC 5322) are rejected, nobody types those in a form.
+
+Phones (E.164):
+- Normalized to '+<country code><national number>', digits only, at most 15 digits.
+- Accepts '+44 20 7946 0958', '0044 20 7946 0958', '(020) 7946-0958' (with a default country) and so on.
+- Which country codes are accepted, their trunk prefix ('0' in the UK) and national number lengths come from a 'PhoneConfig'.
+*/
+
+use std::fmt;
+use std::str::FromStr;
+
+const MAX_LOCAL_LEN: usize = 64;
+const MAX_DOMAIN_LEN: usize = 253;
+const MAX_LABEL_LEN: usize = 63;
+const MAX_E164_DIGITS: usize = 15;
+
+// Email
+
+#[derive( Debug, Clone, PartialEq, Eq, Hash )]
+pub struct Email( String );
+
+fn is_atext( c: char ) -> bool {
+	// RFC 5322 'atext', plus any non-ASCII char (RFC 6531)
+	return c.is_ascii_alphanumeric() || "!#$%&'*+-/=?^_`{|}~".contains( c ) || !c.is_ascii();
+}
+
+fn validate_local_part( local: &str ) -> Result<(), String> {
+	if local.is_empty() {
+		return Err( "Email local part is empty".to_string() );
+	}
+
+	if local.len() > MAX_LOCAL_LEN {
+		return Err( format! ( "Email local part is longer than {} bytes", MAX_LOCAL_LEN ) );
+	}
+
+	// Quoted string: any printable char, with '\' escaping '"' and '\'
+	if local.starts_with( '"' ) {
+		if local.len() < 2 || !local.ends_with( '"' ) {
+			return Err( "Unterminated quoted local part".to_string() );
+		}
+
+		let mut chars = local[1..local.len() - 1].chars();
+
+		while let Some( c ) = chars.next() {
+			match c {
+				// The guard also consumes the escaped char
+				'\\' if chars.next().is_none() => return Err( "Dangling escape in quoted local part".to_string() ),
+				'"' => return Err( "Unescaped quote in quoted local part".to_string() ),
+				c if c.is_control() => return Err( "Control character in local part".to_string() ),
+				_ => {}
+			}
+		}
+
+		return Ok( () );
+	}
+
+	// Dot-atom: atoms separated by single dots, no leading/trailing dot
+	for atom in local.split( '.' ) {
+		if atom.is_empty() {
+			return Err( format! ( "Misplaced dot in local part '{}'", local ) );
+		}
+
+		if let Some( c ) = atom.chars().find( |c| !is_atext( *c ) ) {
+			return Err( format! ( "Invalid character '{}' in local part", c ) );
+		}
+	}
+
+	return Ok( () );
+}
+
+fn validate_label( label: &str ) -> Result<(), String> {
+	if label.is_empty() {
+		return Err( "Empty domain label".to_string() );
+	}
+
+	if label.starts_with( '-' ) || label.ends_with( '-' ) {
+		return Err( format! ( "Domain label '{}' starts or ends with a hyphen", label ) );
+	}
+
+	if let Some( c ) = label.chars().find( |c| !( c.is_alphanumeric() || *c == '-' ) ) {
+		return Err( format! ( "Invalid character '{}' in domain label '{}'", c, label ) );
+	}
+
+	// Encoding never makes a label shorter, so anything already too long is rejected before spending time on it
+	if label.chars().count() > MAX_LABEL_LEN {
+		return Err( format! ( "Domain label '{}' is longer than {} chars", label, MAX_LABEL_LEN ) );
+	}
+
+	// The length limit applies to the encoded (ASCII) label
+	if to_ascii_label( label )?.len() > MAX_LABEL_LEN {
+		return Err( format! ( "Domain label '{}' is longer than {} chars", label, MAX_LABEL_LEN ) );
+	}
+
+	return Ok( () );
+}
+
+fn validate_domain( domain: &str ) -> Result<(), String> {
+	if domain.is_empty() {
+		return Err( "Email domain is empty".to_string() );
+	}
+
+	// Address literal, e.g. [192.168.0.1] (only IPv4 and IPv6 literals make sense here)
+	if domain.starts_with( '[' ) {
+		let inner = domain.strip_prefix( '[' ).and_then( |d| d.strip_suffix( ']' ) ).ok_or( "Unterminated domain literal".to_string() )?;
+		let valid = match inner.strip_prefix( "IPv6:" ) {
+			Some( v6 ) => v6.parse::<std::net::Ipv6Addr>().is_ok(),
+			None => inner.parse::<std::net::Ipv4Addr>().is_ok()
+		};
+
+		return if valid { Ok( () ) } else { Err( format! ( "Invalid domain literal '{}'", domain ) ) };
+	}
+
+	for label in domain.split( '.' ) {
+		validate_label( label )?;
+	}
+
+	if ascii_domain( domain )?.len() > MAX_DOMAIN_LEN {
+		return Err( format! ( "Email domain is longer than {} chars", MAX_DOMAIN_LEN ) );
+	}
+
+	// Addresses need a dot in the domain ('user@localhost' is valid RFC 5322, but not deliverable on the internet)
+	if !domain.contains( '.' ) {
+		return Err( format! ( "Email domain '{}' has no top-level domain", domain ) );
+	}
+
+	return Ok( () );
+}
+
+impl Email {
+	pub fn new( input: &str ) -> Result<Email, String> {
+		let input = input.trim();
+
+		// The local part may contain a quoted '@', so split at the last one
+		let ( local, domain ) = input.rsplit_once( '@' ).ok_or( format! ( "Email '{}' has no '@'", input ) )?;
+
+		validate_local_part( local )?;
+		validate_domain( domain )?;
+
+		// Domains are case-insensitive, so we store them lower-cased. Local parts are not (in theory)
+		return Ok( Email( format! ( "{}@{}", local, domain.to_lowercase() ) ) );
+	}
+
+	pub fn local_part( &self ) -> &str {
+		return self.0.rsplit_once( '@' ).unwrap().0;
+	}
+
+	pub fn domain( &self ) -> &str {
+		return self.0.rsplit_once( '@' ).unwrap().1;
+	}
+
+	// The domain as sent over DNS, with Unicode labels Punycode-encoded
+	pub fn ascii_domain( &self ) -> String {
+		return ascii_domain( self.domain() ).expect( "Validated in Email::new" );
+	}
+
+	pub fn is_internationalized( &self ) -> bool {
+		return !self.0.is_ascii();
+	}
+
+	pub fn as_str( &self ) -> &str {
+		return &self.0;
+	}
+}
+
+impl FromStr for Email {
+	type Err = String;
+
+	fn from_str( s: &str ) -> Result<Email, String> {
+		return Email::new( s );
+	}
+}
+
+impl fmt::Display for Email {
+	fn fmt( &self, f: &mut fmt::Formatter<'_> ) -> fmt::Result {
+		f.write_str( &self.0 )
+	}
+}
+
+impl AsRef<str> for Email {
+	fn as_ref( &self ) -> &str {
+		return &self.0;
+	}
+}
+
+/*
+Punycode (RFC 3492).
+- Encodes a Unicode label into ASCII: the basic (ASCII) chars are copied first, followed by a '-' and the non-ASCII chars as variable-length base-36 "deltas".
+- 'bücher' -> 'bcher-kva', and the DNS label becomes 'xn--bcher-kva'.
+- The deltas grow with the label's length and its code points, so very long input can overflow a u32. That's an error, like RFC 3492 section 6.4 says, instead of a panic (or a wrong encoding in release builds).
+*/
+const BASE: u32 = 36;
+const T_MIN: u32 = 1;
+const T_MAX: u32 = 26;
+const SKEW: u32 = 38;
+const DAMP: u32 = 700;
+const INITIAL_BIAS: u32 = 72;
+const INITIAL_N: u32 = 128;
+
+fn adapt( mut delta: u32, num_points: u32, first_time: bool ) -> u32 {
+	delta = if first_time { delta / DAMP } else { delta / 2 };
+	delta += delta / num_points;
+
+	let mut k = 0;
+
+	while delta > ( ( BASE - T_MIN ) * T_MAX ) / 2 {
+		delta /= BASE - T_MIN;
+		k += BASE;
+	}
+
+	return k + ( ( BASE - T_MIN + 1 ) * delta ) / ( delta + SKEW );
+}
+
+fn encode_digit( d: u32 ) -> char {
+	// 0-25 -> 'a'-'z', 26-35 -> '0'-'9'
+	return if d < 26 { ( b'a' + d as u8 ) as char } else { ( b'0' + ( d - 26 ) as u8 ) as char };
+}
+
+pub fn punycode_encode( input: &str ) -> Result<String, String> {
+	let overflow = || format! ( "Label '{}' is too long to encode as Punycode", input );
+	let code_points: Vec<u32> = input.chars().map( |c| c as u32 ).collect();
+	let mut output: String = input.chars().filter( |c| c.is_ascii() ).collect();
+	let basic_len = output.len() as u32;
+	let mut handled = basic_len;
+
+	if basic_len > 0 {
+		output.push( '-' );
+	}
+
+	let ( mut n, mut delta, mut bias ) = ( INITIAL_N, 0u32, INITIAL_BIAS );
+
+	while ( handled as usize ) < code_points.len() {
+		let m = code_points.iter().copied().filter( |&c| c >= n ).min().unwrap();
+		delta = ( m - n ).checked_mul( handled + 1 ).and_then( |d| d.checked_add( delta ) ).ok_or_else( overflow )?;
+		n = m;
+
+		for &c in &code_points {
+			if c < n {
+				delta = delta.checked_add( 1 ).ok_or_else( overflow )?;
+			}
+
+			if c == n {
+				let mut q = delta;
+				let mut k = BASE;
+
+				loop {
+					let t = if k <= bias { T_MIN } else if k >= bias + T_MAX { T_MAX } else { k - bias };
+
+					if q < t {
+						break;
+					}
+
+					output.push( encode_digit( t + ( q - t ) % ( BASE - t ) ) );
+					q = ( q - t ) / ( BASE - t );
+					k += BASE;
+				}
+
+				output.push( encode_digit( q ) );
+				bias = adapt( delta, handled + 1, handled == basic_len );
+				delta = 0;
+				handled += 1;
+			}
+		}
+
+		delta = delta.checked_add( 1 ).ok_or_else( overflow )?;
+		n += 1;
+	}
+
+	return Ok( output );
+}
+
+fn to_ascii_label( label: &str ) -> Result<String, String> {
+	if label.is_ascii() {
+		return Ok( label.to_ascii_lowercase() );
+	}
+
+	return Ok( format! ( "xn--{}", punycode_encode( &label.to_lowercase() )? ) );
+}
+
+pub fn ascii_domain( domain: &str ) -> Result<String, String> {
+	return Ok( domain.split( '.' ).map( to_ascii_label ).collect::<Result<Vec<_>, _>>()?.join( "." ) );
+}
+
+// Phone
+
+#[derive( Debug, Clone, PartialEq, Eq )]
+pub struct CountryRule {
+	pub calling_code: String, // "44"
+	pub trunk_prefix: Option<String>, // "0" in "020 7946 0958"
+	pub min_digits: usize, // National significant number length, without the trunk prefix
+	pub max_digits: usize,
+}
+
+impl CountryRule {
+	pub fn new( calling_code: &str, trunk_prefix: Option<&str>, min_digits: usize, max_digits: usize ) -> Self {
+		Self {
+			calling_code: calling_code.to_string(),
+			trunk_prefix: trunk_prefix.map( |p| p.to_string() ),
+			min_digits,
+			max_digits
+		}
+	}
+}
+
+#[derive( Debug, Clone )]
+pub struct PhoneConfig {
+	pub countries: Vec<CountryRule>,
+	pub default_country: Option<String>, // Calling code used for numbers written without one
+}
+
+impl Default for PhoneConfig {
+	// A few common countries, no default country (so national numbers are rejected)
+	fn default() -> Self {
+		Self {
+			countries: vec![
+				CountryRule::new( "1", Some( "1" ), 10, 10 ), // US, Canada (NANP)
+				CountryRule::new( "44", Some( "0" ), 9, 10 ), // UK
+				CountryRule::new( "49", Some( "0" ), 6, 13 ), // Germany
+				CountryRule::new( "33", Some( "0" ), 9, 9 ), // France
+				CountryRule::new( "91", Some( "0" ), 10, 10 ), // India
+				CountryRule::new( "61", Some( "0" ), 9, 9 ), // Australia
+			],
+			default_country: None,
+		}
+	}
+}
+
+impl PhoneConfig {
+	pub fn with_country( mut self, rule: CountryRule ) -> Self {
+		self.countries.retain( |c| c.calling_code != rule.calling_code );
+		self.countries.push( rule );
+		self
+	}
+
+	pub fn with_default_country( mut self, calling_code: &str ) -> Self {
+		self.default_country = Some( calling_code.to_string() );
+		self
+	}
+
+	fn rule( &self, calling_code: &str ) -> Option<&CountryRule> {
+		return self.countries.iter().find( |c| c.calling_code == calling_code );
+	}
+}
+
+#[derive( Debug, Clone, PartialEq, Eq, Hash )]
+pub struct Phone( String );
+
+impl Phone {
+	pub fn new( input: &str, config: &PhoneConfig ) -> Result<Phone, String> {
+		let trimmed = input.trim();
+		let mut digits = String::new();
+
+		for ( i, c ) in trimmed.chars().enumerate() {
+			match c {
+				'0'..='9' => digits.push( c ),
+				'+' if i == 0 => {},
+				' ' | '-' | '.' | '(' | ')' | '/' => {},
+				_ => return Err( format! ( "Invalid character '{}' in phone number '{}'", c, trimmed ) )
+			}
+		}
+
+		let international = if trimmed.starts_with( '+' ) {
+			Some( digits.as_str() )
+		} else {
+			digits.strip_prefix( "00" ) // The international call prefix used in most of the world
+		};
+
+		let ( rule, national ) = match international {
+			Some( rest ) => {
+				// Calling codes are prefix-free (1 to 3 digits), so at most one of them matches
+				let rule = ( 1..=3 )
+					.filter_map( |len| rest.get( ..len ).and_then( |code| config.rule( code ) ) )
+					.next()
+					.ok_or( format! ( "Unsupported country code in '{}'", trimmed ) )?;
+
+				// "+44 (0)20 7946 0958": the trunk prefix shouldn't be dialled from abroad, but people write it anyway
+				let national = &rest[rule.calling_code.len()..];
+				let national = match &rule.trunk_prefix {
+					Some( prefix ) if national.len() > rule.max_digits => national.strip_prefix( prefix.as_str() ).unwrap_or( national ),
+					_ => national
+				};
+
+				( rule, national.to_string() )
+			},
+			None => {
+				let code = config.default_country.as_deref().ok_or( format! ( "Phone number '{}' has no country code", trimmed ) )?;
+				let rule = config.rule( code ).ok_or( format! ( "Default country code +{} is not configured", code ) )?;
+				let national = match &rule.trunk_prefix {
+					Some( prefix ) => digits.strip_prefix( prefix.as_str() ).filter( |rest| rest.len() >= rule.min_digits ).unwrap_or( &digits ),
+					None => &digits
+				};
+
+				( rule, national.to_string() )
+			}
+		};
+
+		if national.len() < rule.min_digits || national.len() > rule.max_digits {
+			return Err( format! ( "Phone number '{}' should have {}-{} digits after +{}", trimmed, rule.min_digits, rule.max_digits, rule.calling_code ) );
+		}
+
+		if rule.calling_code.len() + national.len() > MAX_E164_DIGITS {
+			return Err( format! ( "Phone number '{}' is longer than {} digits", trimmed, MAX_E164_DIGITS ) );
+		}
+
+		return Ok( Phone( format! ( "+{}{}", rule.calling_code, national ) ) );
+	}
+
+	pub fn as_str( &self ) -> &str {
+		return &self.0;
+	}
+
+	// The E.164 form is "+<country code><national number>", so we need the config to know where the code ends
+	pub fn country_code<'a>( &self, config: &'a PhoneConfig ) -> Option<&'a str> {
+		let digits = &self.0[1..];
+
+		return ( 1..=3 )
+			.filter_map( |len| digits.get( ..len ).and_then( |code| config.rule( code ) ) )
+			.map( |r| r.calling_code.as_str() )
+			.next();
+	}
+}
+
+impl fmt::Display for Phone {
+	fn fmt( &self, f: &mut fmt::Formatter<'_> ) -> fmt::Result {
+		f.write_str( &self.0 )
+	}
+}
+
+impl AsRef<str> for Phone {
+	fn as_ref( &self ) -> &str {
+		return &self.0;
+	}
+}
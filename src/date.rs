@@ -0,0 +1,357 @@
+// A small, dependency-free date module
+
+/*
+Dates.
+
+- A 'Date' is a plain proleptic Gregorian calendar date (year, month, day), without any time or timezone.
+- Internally, dates are converted to "days since 1970-01-01" for differences, additions and weekdays (the same epoch Unix time uses).
+- Parsing accepts the formats we actually store around the codebase:
+	* ISO: "2003-08-03" or "2003/08/03"
+	* European dotted: "03.08.2003"
+	* Month names: "August 03 2003", "Aug 3, 2003", "3 August 2003", "03-Aug-2003"
+- Errors are returned as Strings, like the rest of the crate's Result examples.
+- Years are i32. 'add_months'/'add_years' panic if the result would leave that range (like integer overflow), 'checked_add_months'/'checked_add_years' return None instead.
+*/
+
+use std::fmt;
+use std::str::FromStr;
+
+const MONTH_NAMES: [&str; 12] = [
+	"January", "February", "March", "April", "May", "June",
+	"July", "August", "September", "October", "November", "December"
+];
+
+#[derive( Debug, Clone, Copy, PartialEq, Eq, Hash )]
+pub enum Weekday {
+	Monday,
+	Tuesday,
+	Wednesday,
+	Thursday,
+	Friday,
+	Saturday,
+	Sunday
+}
+
+impl Weekday {
+	pub const ALL: [Weekday; 7] = [
+		Weekday::Monday, Weekday::Tuesday, Weekday::Wednesday, Weekday::Thursday,
+		Weekday::Friday, Weekday::Saturday, Weekday::Sunday
+	];
+
+	// Monday is 1 and Sunday is 7 (ISO 8601 numbering)
+	pub fn number_from_monday( &self ) -> u8 {
+		return *self as u8 + 1;
+	}
+
+	pub fn from_number_from_monday( n: u8 ) -> Option<Weekday> {
+		match n {
+			1..=7 => Some( Weekday::ALL[( n - 1 ) as usize] ),
+			_ => None
+		}
+	}
+
+	pub fn name( &self ) -> &'static str {
+		match self {
+			Weekday::Monday => "Monday",
+			Weekday::Tuesday => "Tuesday",
+			Weekday::Wednesday => "Wednesday",
+			Weekday::Thursday => "Thursday",
+			Weekday::Friday => "Friday",
+			Weekday::Saturday => "Saturday",
+			Weekday::Sunday => "Sunday",
+		}
+	}
+
+	pub fn is_weekend( &self ) -> bool {
+		return matches! ( self, Weekday::Saturday | Weekday::Sunday );
+	}
+}
+
+impl fmt::Display for Weekday {
+	fn fmt( &self, f: &mut fmt::Formatter<'_> ) -> fmt::Result {
+		f.write_str( self.name() )
+	}
+}
+
+#[derive( Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash )]
+pub struct Date {
+	// Field order matters: the derived Ord compares year, then month, then day
+	year: i32,
+	month: u8,
+	day: u8,
+}
+
+pub fn is_leap_year( year: i32 ) -> bool {
+	return ( year % 4 == 0 && year % 100 != 0 ) || year % 400 == 0;
+}
+
+pub fn days_in_month( year: i32, month: u8 ) -> u8 {
+	match month {
+		1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+		4 | 6 | 9 | 11 => 30,
+		2 if is_leap_year( year ) => 29,
+		2 => 28,
+		_ => 0
+	}
+}
+
+// Parses a month name ("August") or its 3-letter abbreviation ("aug"), case-insensitive
+pub fn month_from_name( name: &str ) -> Option<u8> {
+	let lower = name.to_ascii_lowercase();
+
+	if lower.len() < 3 {
+		return None;
+	}
+
+	for ( i, full ) in MONTH_NAMES.iter().enumerate() {
+		let full_lower = full.to_ascii_lowercase();
+
+		if full_lower == lower || ( lower.len() == 3 && full_lower.starts_with( &lower ) ) {
+			return Some( i as u8 + 1 );
+		}
+	}
+
+	return None;
+}
+
+impl Date {
+	pub fn new( year: i32, month: u8, day: u8 ) -> Result<Date, String> {
+		if !( 1..=12 ).contains( &month ) {
+			return Err( format! ( "Invalid month: {}", month ) );
+		}
+
+		if day == 0 || day > days_in_month( year, month ) {
+			return Err( format! ( "Invalid day {} for {}-{:02}", day, year, month ) );
+		}
+
+		return Ok( Date { year, month, day } );
+	}
+
+	pub fn year( &self ) -> i32 {
+		return self.year;
+	}
+
+	pub fn month( &self ) -> u8 {
+		return self.month;
+	}
+
+	pub fn day( &self ) -> u8 {
+		return self.day;
+	}
+
+	pub fn month_name( &self ) -> &'static str {
+		return MONTH_NAMES[( self.month - 1 ) as usize];
+	}
+
+	pub fn is_leap_year( &self ) -> bool {
+		return is_leap_year( self.year );
+	}
+
+	// Day of the year, 1 for January 1st
+	pub fn ordinal( &self ) -> u16 {
+		let mut total: u16 = self.day as u16;
+
+		for m in 1..self.month {
+			total += days_in_month( self.year, m ) as u16;
+		}
+
+		return total;
+	}
+
+	/*
+	Days since 1970-01-01 (negative before it).
+	- This is Howard Hinnant's "days_from_civil" algorithm: it shifts the year to start in March so the leap day is the last day of the year, then counts whole 400-year eras.
+	*/
+	pub fn days_since_epoch( &self ) -> i64 {
+		let y: i64 = self.year as i64 - if self.month <= 2 { 1 } else { 0 };
+		let m: i64 = self.month as i64;
+		let d: i64 = self.day as i64;
+
+		let era = y.div_euclid( 400 );
+		let yoe = y - era * 400; // [0, 399]
+		let doy = ( 153 * ( if m > 2 { m - 3 } else { m + 9 } ) + 2 ) / 5 + d - 1; // [0, 365]
+		let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy; // [0, 146096]
+
+		return era * 146097 + doe - 719468;
+	}
+
+	// The inverse of 'days_since_epoch'
+	pub fn from_days_since_epoch( days: i64 ) -> Date {
+		let z = days + 719468;
+		let era = z.div_euclid( 146097 );
+		let doe = z - era * 146097;
+		let yoe = ( doe - doe / 1460 + doe / 36524 - doe / 146096 ) / 365;
+		let doy = doe - ( 365 * yoe + yoe / 4 - yoe / 100 );
+		let mp = ( 5 * doy + 2 ) / 153;
+		let day = ( doy - ( 153 * mp + 2 ) / 5 + 1 ) as u8;
+		let month = if mp < 10 { mp + 3 } else { mp - 9 } as u8;
+		let year = ( yoe + era * 400 + if month <= 2 { 1 } else { 0 } ) as i32;
+
+		return Date { year, month, day };
+	}
+
+	// Today's date in UTC, read from the system clock
+	pub fn today() -> Date {
+		let secs = match std::time::SystemTime::now().duration_since( std::time::UNIX_EPOCH ) {
+			Ok( d ) => d.as_secs() as i64,
+			Err( e ) => -( e.duration().as_secs() as i64 )
+		};
+
+		return Date::from_days_since_epoch( secs.div_euclid( 86_400 ) );
+	}
+
+	pub fn weekday( &self ) -> Weekday {
+		// 1970-01-01 was a Thursday (index 3 when Monday is 0)
+		let idx = ( self.days_since_epoch() + 3 ).rem_euclid( 7 );
+
+		return Weekday::ALL[idx as usize];
+	}
+
+	pub fn add_days( &self, days: i64 ) -> Date {
+		return Date::from_days_since_epoch( self.days_since_epoch() + days );
+	}
+
+	// Adds whole months, clamping the day to the end of shorter months (Jan 31 + 1 month = Feb 28/29)
+	pub fn add_months( &self, months: i32 ) -> Date {
+		return self.checked_add_months( months ).expect( "Date out of range" );
+	}
+
+	pub fn add_years( &self, years: i32 ) -> Date {
+		return self.checked_add_years( years ).expect( "Date out of range" );
+	}
+
+	// None if the year would overflow i32. Counted in i64 months, which can't overflow
+	pub fn checked_add_months( &self, months: i32 ) -> Option<Date> {
+		let total = self.year as i64 * 12 + ( self.month as i64 - 1 ) + months as i64;
+		let year = i32::try_from( total.div_euclid( 12 ) ).ok()?;
+		let month = ( total.rem_euclid( 12 ) + 1 ) as u8;
+		let day = self.day.min( days_in_month( year, month ) );
+
+		return Some( Date { year, month, day } );
+	}
+
+	pub fn checked_add_years( &self, years: i32 ) -> Option<Date> {
+		return self.checked_add_months( years.checked_mul( 12 )? );
+	}
+
+	// Signed number of days from 'self' to 'other' (positive if 'other' is later)
+	pub fn days_until( &self, other: &Date ) -> i64 {
+		return other.days_since_epoch() - self.days_since_epoch();
+	}
+
+	/*
+	Age in whole years of someone born on 'self', on the given date.
+	- Returns None if 'date' is before the birth date.
+	- People born on February 29th turn a year older on March 1st in non-leap years.
+	*/
+	pub fn age_on( &self, date: &Date ) -> Option<u32> {
+		if date < self {
+			return None;
+		}
+
+		let mut years = ( date.year - self.year ) as u32;
+
+		if ( date.month, date.day ) < ( self.month, self.day ) {
+			years -= 1;
+		}
+
+		return Some( years );
+	}
+
+	pub fn parse( input: &str ) -> Result<Date, String> {
+		let s = input.trim();
+
+		if s.is_empty() {
+			return Err( "Empty date string".to_string() );
+		}
+
+		if s.chars().any( |c| c.is_ascii_alphabetic() ) {
+			return parse_with_month_name( s );
+		}
+
+		return parse_numeric( s );
+	}
+}
+
+fn parse_number<T: FromStr>( part: &str, what: &str, input: &str ) -> Result<T, String> {
+	return part.parse::<T>().map_err( |_| format! ( "Invalid {} '{}' in date '{}'", what, part, input ) );
+}
+
+fn parse_numeric( s: &str ) -> Result<Date, String> {
+	let sep = match s.chars().find( |c| !c.is_ascii_digit() ) {
+		Some( c ) => c,
+		None => return Err( format! ( "Unrecognized date format: '{}'", s ) )
+	};
+	let parts: Vec<&str> = s.split( sep ).collect();
+
+	if parts.len() != 3 {
+		return Err( format! ( "Unrecognized date format: '{}'", s ) );
+	}
+
+	match sep {
+		// YYYY-MM-DD or YYYY/MM/DD
+		'-' | '/' if parts[0].len() == 4 => Date::new(
+			parse_number( parts[0], "year", s )?,
+			parse_number( parts[1], "month", s )?,
+			parse_number( parts[2], "day", s )?
+		),
+		// DD.MM.YYYY
+		'.' if parts[2].len() == 4 => Date::new(
+			parse_number( parts[2], "year", s )?,
+			parse_number( parts[1], "month", s )?,
+			parse_number( parts[0], "day", s )?
+		),
+		// "03/08/2003" is ambiguous (US vs. European order), so we don't guess
+		_ => Err( format! ( "Unrecognized date format: '{}'", s ) )
+	}
+}
+
+fn parse_with_month_name( s: &str ) -> Result<Date, String> {
+	let tokens: Vec<&str> = s
+		.split( |c: char| c.is_whitespace() || c == ',' || c == '-' )
+		.filter( |t| !t.is_empty() )
+		.collect();
+
+	if tokens.len() != 3 {
+		return Err( format! ( "Unrecognized date format: '{}'", s ) );
+	}
+
+	let mut month: Option<u8> = None;
+	let mut numbers: Vec<&str> = Vec::new();
+
+	for token in &tokens {
+		if token.chars().all( |c| c.is_ascii_digit() ) {
+			numbers.push( token );
+		} else {
+			match month_from_name( token.trim_end_matches( '.' ) ) {
+				Some( m ) if month.is_none() => month = Some( m ),
+				_ => return Err( format! ( "Unknown month '{}' in date '{}'", token, s ) )
+			}
+		}
+	}
+
+	let month = match month {
+		Some( m ) if numbers.len() == 2 => m,
+		_ => return Err( format! ( "Unrecognized date format: '{}'", s ) )
+	};
+
+	// Whichever number has 4 digits is the year ("August 03 2003" and "2003 Aug 03" both work)
+	let ( day, year ) = if numbers[0].len() == 4 { ( numbers[1], numbers[0] ) } else { ( numbers[0], numbers[1] ) };
+
+	return Date::new( parse_number( year, "year", s )?, month, parse_number( day, "day", s )? );
+}
+
+impl FromStr for Date {
+	type Err = String;
+
+	fn from_str( s: &str ) -> Result<Date, String> {
+		return Date::parse( s );
+	}
+}
+
+// Dates are displayed in ISO format, i.e. "2003-08-03"
+impl fmt::Display for Date {
+	fn fmt( &self, f: &mut fmt::Formatter<'_> ) -> fmt::Result {
+		write! ( f, "{:04}-{:02}-{:02}", self.year, self.month, self.day )
+	}
+}
@@ -0,0 +1,269 @@
+// Typed event bus built on stored closures
+
+/*
+Event bus.
+
+- Closures don't have to be called right away: they can be boxed and stored, then called later when something happens. That's all an event bus is, a map of topic -> list of stored closures.
+- Closures are stored as trait objects, since every closure has its own anonymous type:
+	* Box<dyn Fn( &E )>      Called through a shared reference, can be called any number of times.
+	* Box<dyn FnMut( &E )>   Can mutate what it captured (e.g. a counter), so calling it needs '&mut'.
+	* Box<dyn FnOnce( &E )>  Consumes what it captured, so it can only be called once (perfect for "once" handlers).
+- Handlers run in priority order (highest first, then in registration order). The "*" topic receives every event.
+- Subscribing returns a 'Subscription' handle, which is how handlers get removed again.
+
+- 'EventBus' is single-threaded. 'SyncEventBus' is the thread-safe variant: handlers are Arc<dyn Fn( &E ) + Send + Sync>, stored behind a RwLock, and the bus itself is cheap to clone (all clones share the same handlers).
+- Its "once" handlers are FnOnce too, in a Mutex<Option<...>>: whichever thread takes the closure out of the Option runs it, everyone else finds None.
+*/
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+
+pub const ALL_TOPICS: &str = "*";
+
+// Events say which topic they belong to, e.g. an enum returning one topic per variant
+pub trait Event {
+	fn topic( &self ) -> &str;
+}
+
+#[derive( Debug, Clone, PartialEq, Eq, Hash )]
+pub struct Subscription {
+	id: u64,
+	topic: String,
+}
+
+impl Subscription {
+	pub fn topic( &self ) -> &str {
+		return &self.topic;
+	}
+}
+
+type OnceHandler<E> = Box<dyn FnOnce( &E )>;
+
+pub enum Handler<E> {
+	Fn( Box<dyn Fn( &E )> ),
+	FnMut( Box<dyn FnMut( &E )> ),
+	Once( Option<OnceHandler<E>> ), // Taken (set to None) when called
+}
+
+struct Entry<E> {
+	id: u64,
+	priority: i32,
+	handler: Handler<E>,
+}
+
+pub struct EventBus<E> {
+	topics: HashMap<String, Vec<Entry<E>>>,
+	next_id: u64,
+}
+
+impl<E: Event> Default for EventBus<E> {
+	fn default() -> Self {
+		Self { topics: HashMap::new(), next_id: 0 }
+	}
+}
+
+impl<E: Event> EventBus<E> {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn subscribe( &mut self, topic: &str, priority: i32, handler: Handler<E> ) -> Subscription {
+		let id = self.next_id;
+		self.next_id += 1;
+
+		let entries = self.topics.entry( topic.to_string() ).or_default();
+		// Insert after every handler with a higher or equal priority, so equal priorities keep registration order
+		let pos = entries.iter().position( |e| e.priority < priority ).unwrap_or( entries.len() );
+		entries.insert( pos, Entry { id, priority, handler } );
+
+		return Subscription { id, topic: topic.to_string() };
+	}
+
+	pub fn on( &mut self, topic: &str, handler: impl Fn( &E ) + 'static ) -> Subscription {
+		return self.subscribe( topic, 0, Handler::Fn( Box::new( handler ) ) );
+	}
+
+	pub fn on_mut( &mut self, topic: &str, handler: impl FnMut( &E ) + 'static ) -> Subscription {
+		return self.subscribe( topic, 0, Handler::FnMut( Box::new( handler ) ) );
+	}
+
+	pub fn once( &mut self, topic: &str, handler: impl FnOnce( &E ) + 'static ) -> Subscription {
+		return self.subscribe( topic, 0, Handler::Once( Some( Box::new( handler ) ) ) );
+	}
+
+	// Returns false if the handler was already removed (or was a "once" handler that already ran)
+	pub fn unsubscribe( &mut self, subscription: &Subscription ) -> bool {
+		let Some( entries ) = self.topics.get_mut( &subscription.topic ) else { return false };
+		let before = entries.len();
+
+		entries.retain( |e| e.id != subscription.id );
+
+		return entries.len() != before;
+	}
+
+	pub fn handler_count( &self, topic: &str ) -> usize {
+		return self.topics.get( topic ).map( |e| e.len() ).unwrap_or( 0 );
+	}
+
+	// Calls the handlers of the event's topic, then the "*" handlers. Returns how many handlers ran
+	pub fn publish( &mut self, event: &E ) -> usize {
+		let mut called = 0;
+		let topics = [event.topic().to_string(), ALL_TOPICS.to_string()];
+
+		for topic in &topics {
+			let Some( entries ) = self.topics.get_mut( topic ) else { continue };
+
+			for entry in entries.iter_mut() {
+				match &mut entry.handler {
+					Handler::Fn( f ) => f( event ),
+					Handler::FnMut( f ) => f( event ),
+					Handler::Once( f ) => {
+						if let Some( f ) = f.take() {
+							f( event );
+						}
+					}
+				}
+				called += 1;
+			}
+
+			// Drop the "once" handlers that just ran
+			entries.retain( |e| !matches! ( e.handler, Handler::Once( None ) ) );
+		}
+
+		return called;
+	}
+}
+
+// Thread-safe variant
+
+type SyncOnceHandler<E> = Mutex<Option<Box<dyn FnOnce( &E ) + Send>>>;
+
+enum SyncHandler<E> {
+	Fn( Arc<dyn Fn( &E ) + Send + Sync> ),
+	Once( Arc<SyncOnceHandler<E>> ), // Taken (set to None) by the one thread that gets to run it
+}
+
+struct SyncEntry<E> {
+	id: u64,
+	priority: i32,
+	handler: SyncHandler<E>,
+}
+
+// Entries are cloned out of the lock before calling them, so handlers can (un)subscribe without deadlocking
+impl<E> Clone for SyncEntry<E> {
+	fn clone( &self ) -> Self {
+		let handler = match &self.handler {
+			SyncHandler::Fn( f ) => SyncHandler::Fn( Arc::clone( f ) ),
+			SyncHandler::Once( f ) => SyncHandler::Once( Arc::clone( f ) ),
+		};
+
+		return Self { id: self.id, priority: self.priority, handler };
+	}
+}
+
+type SyncTopics<E> = Arc<RwLock<HashMap<String, Vec<SyncEntry<E>>>>>;
+
+pub struct SyncEventBus<E> {
+	topics: SyncTopics<E>,
+	next_id: Arc<AtomicU64>,
+}
+
+impl<E> Clone for SyncEventBus<E> {
+	fn clone( &self ) -> Self {
+		Self { topics: Arc::clone( &self.topics ), next_id: Arc::clone( &self.next_id ) }
+	}
+}
+
+impl<E: Event> Default for SyncEventBus<E> {
+	fn default() -> Self {
+		Self { topics: Arc::new( RwLock::new( HashMap::new() ) ), next_id: Arc::new( AtomicU64::new( 0 ) ) }
+	}
+}
+
+impl<E: Event> SyncEventBus<E> {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	fn insert( &self, topic: &str, priority: i32, handler: SyncHandler<E> ) -> Subscription {
+		let id = self.next_id.fetch_add( 1, Ordering::Relaxed );
+		let mut topics = self.topics.write().unwrap();
+		let entries = topics.entry( topic.to_string() ).or_default();
+		let pos = entries.iter().position( |e| e.priority < priority ).unwrap_or( entries.len() );
+
+		entries.insert( pos, SyncEntry { id, priority, handler } );
+
+		return Subscription { id, topic: topic.to_string() };
+	}
+
+	pub fn subscribe( &self, topic: &str, priority: i32, handler: impl Fn( &E ) + Send + Sync + 'static ) -> Subscription {
+		return self.insert( topic, priority, SyncHandler::Fn( Arc::new( handler ) ) );
+	}
+
+	pub fn on( &self, topic: &str, handler: impl Fn( &E ) + Send + Sync + 'static ) -> Subscription {
+		return self.insert( topic, 0, SyncHandler::Fn( Arc::new( handler ) ) );
+	}
+
+	pub fn once( &self, topic: &str, handler: impl FnOnce( &E ) + Send + 'static ) -> Subscription {
+		return self.insert( topic, 0, SyncHandler::Once( Arc::new( Mutex::new( Some( Box::new( handler ) ) ) ) ) );
+	}
+
+	pub fn unsubscribe( &self, subscription: &Subscription ) -> bool {
+		let mut topics = self.topics.write().unwrap();
+		let Some( entries ) = topics.get_mut( &subscription.topic ) else { return false };
+		let before = entries.len();
+
+		entries.retain( |e| e.id != subscription.id );
+
+		return entries.len() != before;
+	}
+
+	pub fn handler_count( &self, topic: &str ) -> usize {
+		return self.topics.read().unwrap().get( topic ).map( |e| e.len() ).unwrap_or( 0 );
+	}
+
+	pub fn publish( &self, event: &E ) -> usize {
+		let snapshot: Vec<SyncEntry<E>> = {
+			let topics = self.topics.read().unwrap();
+			let mut entries: Vec<SyncEntry<E>> = Vec::new();
+
+			for topic in [event.topic(), ALL_TOPICS] {
+				if let Some( list ) = topics.get( topic ) {
+					entries.extend( list.iter().cloned() );
+				}
+			}
+
+			entries
+		};
+
+		let mut called = 0;
+		let mut spent: Vec<u64> = Vec::new();
+
+		for entry in &snapshot {
+			match &entry.handler {
+				SyncHandler::Fn( f ) => f( event ),
+				SyncHandler::Once( f ) => {
+					// Taken in its own statement, so the lock is released before the handler runs
+					let taken = f.lock().unwrap().take();
+					let Some( f ) = taken else { continue }; // Another thread got it first
+
+					spent.push( entry.id );
+					f( event );
+				}
+			}
+
+			called += 1;
+		}
+
+		if !spent.is_empty() {
+			let mut topics = self.topics.write().unwrap();
+
+			for entries in topics.values_mut() {
+				entries.retain( |e| !spent.contains( &e.id ) );
+			}
+		}
+
+		return called;
+	}
+}
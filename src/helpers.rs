@@ -27,6 +27,13 @@ pub mod agehelpers {
 
 		return age + 5;
 	}
+
+	// Computes an age from a stored date of birth (e.g. "August 03 2003") instead of trusting a stored age
+	pub fn age_from_dob( dob: &str, today: &rs_basics::date::Date ) -> Result<u32, String> {
+		let birth = rs_basics::date::Date::parse( dob )?;
+
+		return birth.age_on( today ).ok_or( format! ( "Date of birth {} is after {}", birth, today ) );
+	}
 }
 
 #[doc = "
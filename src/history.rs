@@ -0,0 +1,415 @@
+// Undo/redo history built on reversible commands
+
+/*
+Command pattern.
+
+- Instead of editing a value directly ('car_lot.insert( 0, car )'), the edit is wrapped in a 'Command' that knows how to apply itself and how to undo itself. A 'History' owns the value, runs the commands and keeps them on an undo stack:
+
+	let mut lot = History::new( Vec::new() );
+	lot.execute( edits::push( car ) );
+	lot.undo(); // lot is empty again
+	lot.redo(); // and back
+
+- Built-in commands (in 'edits'): push, insert, remove, append and retain for Vec<T>, plus 'assign' for reassigning a field (e.g. 'person2.Name'), given a closure that picks the field.
+- Transactions group several commands into one undo step ('begin' ... 'commit', or 'rollback' to undo them right away). 'transaction( label, |h| ... )' commits if the closure returns Ok and rolls back on Err.
+- Running a new command clears the redo stack (the "future" we undid is gone).
+- Memory cap: commands report roughly how many bytes they hold on to (e.g. the items 'retain' removed). When the total goes over the cap, the oldest undo steps are forgotten. The sizes are shallow (size_of::<T>()), heap data inside T isn't counted.
+*/
+
+use std::collections::VecDeque;
+
+pub trait Command<T> {
+	fn apply( &mut self, target: &mut T );
+	fn undo( &mut self, target: &mut T );
+
+	fn label( &self ) -> String;
+
+	// Bytes held for undoing, roughly
+	fn size( &self ) -> usize {
+		return std::mem::size_of_val( self );
+	}
+}
+
+struct Step<T> {
+	label: String,
+	commands: Vec<Box<dyn Command<T>>>,
+	size: usize,
+}
+
+impl<T> Step<T> {
+	fn undo( &mut self, target: &mut T ) {
+		for command in self.commands.iter_mut().rev() {
+			command.undo( target );
+		}
+	}
+
+	fn redo( &mut self, target: &mut T ) {
+		for command in self.commands.iter_mut() {
+			command.apply( target );
+		}
+	}
+}
+
+pub struct History<T> {
+	target: T,
+	undo: VecDeque<Step<T>>, // Oldest first, so the oldest can be dropped when over the cap
+	redo: Vec<Step<T>>,      // Next redo last
+	open: Option<Step<T>>,   // Transaction in progress
+	memory_limit: Option<usize>,
+	used: usize,
+}
+
+impl<T> History<T> {
+	pub fn new( target: T ) -> Self {
+		Self { target, undo: VecDeque::new(), redo: Vec::new(), open: None, memory_limit: None, used: 0 }
+	}
+
+	pub fn with_memory_limit( mut self, bytes: usize ) -> Self {
+		self.memory_limit = Some( bytes );
+		self.enforce_limit();
+		self
+	}
+
+	pub fn target( &self ) -> &T {
+		return &self.target;
+	}
+
+	pub fn into_inner( self ) -> T {
+		return self.target;
+	}
+
+	pub fn memory_used( &self ) -> usize {
+		return self.used;
+	}
+
+	pub fn can_undo( &self ) -> bool {
+		return !self.undo.is_empty();
+	}
+
+	pub fn can_redo( &self ) -> bool {
+		return !self.redo.is_empty();
+	}
+
+	// Most recent last
+	pub fn undo_labels( &self ) -> Vec<&str> {
+		return self.undo.iter().map( |s| s.label.as_str() ).collect();
+	}
+
+	// Next redo last
+	pub fn redo_labels( &self ) -> Vec<&str> {
+		return self.redo.iter().map( |s| s.label.as_str() ).collect();
+	}
+
+	pub fn execute( &mut self, mut command: impl Command<T> + 'static ) {
+		command.apply( &mut self.target );
+
+		let size = command.size();
+		self.used += size;
+
+		// Nothing to redo after a new edit
+		for step in self.redo.drain( .. ) {
+			self.used -= step.size;
+		}
+
+		match &mut self.open {
+			Some( step ) => {
+				step.size += size;
+				step.commands.push( Box::new( command ) );
+			},
+			None => {
+				self.undo.push_back( Step { label: command.label(), commands: vec![Box::new( command )], size } );
+			}
+		}
+
+		self.enforce_limit();
+	}
+
+	// Returns false if there was nothing to undo. Not allowed while a transaction is open
+	pub fn undo( &mut self ) -> bool {
+		if self.open.is_some() {
+			return false;
+		}
+
+		let Some( mut step ) = self.undo.pop_back() else { return false };
+		step.undo( &mut self.target );
+		self.redo.push( step );
+
+		return true;
+	}
+
+	pub fn redo( &mut self ) -> bool {
+		if self.open.is_some() {
+			return false;
+		}
+
+		let Some( mut step ) = self.redo.pop() else { return false };
+		step.redo( &mut self.target );
+		self.undo.push_back( step );
+
+		return true;
+	}
+
+	// Returns false if a transaction is already open (they don't nest)
+	pub fn begin( &mut self, label: &str ) -> bool {
+		if self.open.is_some() {
+			return false;
+		}
+
+		self.open = Some( Step { label: label.to_string(), commands: Vec::new(), size: 0 } );
+
+		return true;
+	}
+
+	pub fn commit( &mut self ) -> bool {
+		let Some( step ) = self.open.take() else { return false };
+
+		if !step.commands.is_empty() {
+			self.undo.push_back( step );
+			self.enforce_limit();
+		}
+
+		return true;
+	}
+
+	// Undoes everything done since 'begin', and forgets it
+	pub fn rollback( &mut self ) -> bool {
+		let Some( mut step ) = self.open.take() else { return false };
+
+		step.undo( &mut self.target );
+		self.used -= step.size;
+
+		return true;
+	}
+
+	pub fn transaction<E>( &mut self, label: &str, f: impl FnOnce( &mut Self ) -> Result<(), E> ) -> Result<(), E> {
+		if !self.begin( label ) {
+			return f( self ); // Already inside one: becomes part of it
+		}
+
+		match f( self ) {
+			Ok( () ) => {
+				self.commit();
+				Ok( () )
+			},
+			Err( e ) => {
+				self.rollback();
+				Err( e )
+			}
+		}
+	}
+
+	pub fn clear( &mut self ) {
+		self.undo.clear();
+		self.redo.clear();
+		self.used = self.open.as_ref().map( |s| s.size ).unwrap_or( 0 );
+	}
+
+	fn enforce_limit( &mut self ) {
+		let Some( limit ) = self.memory_limit else { return };
+
+		// Oldest undo steps first, then the redo steps furthest away
+		while self.used > limit {
+			if let Some( step ) = self.undo.pop_front() {
+				self.used -= step.size;
+			} else if !self.redo.is_empty() {
+				self.used -= self.redo.remove( 0 ).size;
+			} else {
+				break; // Only the open transaction is left, it's kept until commit
+			}
+		}
+	}
+}
+
+// Commands for common edits
+
+pub mod edits {
+	use super::Command;
+	use std::mem::size_of;
+
+	pub struct Push<T> {
+		value: Option<T>, // Held here while undone
+	}
+
+	pub fn push<T>( value: T ) -> Push<T> {
+		Push { value: Some( value ) }
+	}
+
+	impl<T> Command<Vec<T>> for Push<T> {
+		fn apply( &mut self, target: &mut Vec<T> ) {
+			target.extend( self.value.take() );
+		}
+
+		fn undo( &mut self, target: &mut Vec<T> ) {
+			self.value = target.pop();
+		}
+
+		fn label( &self ) -> String {
+			return "push".to_string();
+		}
+
+		fn size( &self ) -> usize {
+			return size_of::<Self>();
+		}
+	}
+
+	pub struct Insert<T> {
+		index: usize,
+		value: Option<T>,
+	}
+
+	// Panics (like Vec::insert) if the index is past the end
+	pub fn insert<T>( index: usize, value: T ) -> Insert<T> {
+		Insert { index, value: Some( value ) }
+	}
+
+	impl<T> Command<Vec<T>> for Insert<T> {
+		fn apply( &mut self, target: &mut Vec<T> ) {
+			if let Some( value ) = self.value.take() {
+				target.insert( self.index, value );
+			}
+		}
+
+		fn undo( &mut self, target: &mut Vec<T> ) {
+			self.value = Some( target.remove( self.index ) );
+		}
+
+		fn label( &self ) -> String {
+			return format! ( "insert at {}", self.index );
+		}
+
+		fn size( &self ) -> usize {
+			return size_of::<Self>();
+		}
+	}
+
+	pub struct Remove<T> {
+		index: usize,
+		value: Option<T>, // The removed item, while applied
+	}
+
+	pub fn remove<T>( index: usize ) -> Remove<T> {
+		Remove { index, value: None }
+	}
+
+	impl<T> Command<Vec<T>> for Remove<T> {
+		fn apply( &mut self, target: &mut Vec<T> ) {
+			self.value = Some( target.remove( self.index ) );
+		}
+
+		fn undo( &mut self, target: &mut Vec<T> ) {
+			if let Some( value ) = self.value.take() {
+				target.insert( self.index, value );
+			}
+		}
+
+		fn label( &self ) -> String {
+			return format! ( "remove at {}", self.index );
+		}
+
+		fn size( &self ) -> usize {
+			return size_of::<Self>();
+		}
+	}
+
+	pub struct Append<T> {
+		items: Vec<T>, // Held here while undone
+		count: usize,
+	}
+
+	// Like 'target.append( &mut items )'
+	pub fn append<T>( items: Vec<T> ) -> Append<T> {
+		let count = items.len();
+
+		Append { items, count }
+	}
+
+	impl<T> Command<Vec<T>> for Append<T> {
+		fn apply( &mut self, target: &mut Vec<T> ) {
+			target.append( &mut self.items );
+		}
+
+		fn undo( &mut self, target: &mut Vec<T> ) {
+			self.items = target.split_off( target.len() - self.count );
+		}
+
+		fn label( &self ) -> String {
+			return format! ( "append {}", self.count );
+		}
+
+		fn size( &self ) -> usize {
+			return size_of::<Self>() + self.count * size_of::<T>();
+		}
+	}
+
+	type Predicate<T> = Box<dyn FnMut( &T ) -> bool>;
+
+	pub struct Retain<T> {
+		keep: Predicate<T>,
+		removed: Vec<( usize, T )>, // Original positions, to put them back in place
+	}
+
+	pub fn retain<T>( keep: impl FnMut( &T ) -> bool + 'static ) -> Retain<T> {
+		Retain { keep: Box::new( keep ), removed: Vec::new() }
+	}
+
+	impl<T> Command<Vec<T>> for Retain<T> {
+		fn apply( &mut self, target: &mut Vec<T> ) {
+			let mut kept = Vec::with_capacity( target.len() );
+
+			for ( i, item ) in target.drain( .. ).enumerate() {
+				if ( self.keep )( &item ) {
+					kept.push( item );
+				} else {
+					self.removed.push( ( i, item ) );
+				}
+			}
+
+			*target = kept;
+		}
+
+		fn undo( &mut self, target: &mut Vec<T> ) {
+			// In increasing order, each item lands exactly where it was
+			for ( i, item ) in self.removed.drain( .. ) {
+				target.insert( i, item );
+			}
+		}
+
+		fn label( &self ) -> String {
+			return format! ( "retain (removed {})", self.removed.len() );
+		}
+
+		fn size( &self ) -> usize {
+			return size_of::<Self>() + self.removed.len() * size_of::<( usize, T )>();
+		}
+	}
+
+	pub struct Assign<T, V> {
+		field: fn( &mut T ) -> &mut V,
+		value: V, // The new value before applying, the old one after
+		name: String,
+	}
+
+	// e.g. 'assign( "Name", |p: &mut Person| &mut p.Name, "Doe".to_string() )'
+	pub fn assign<T, V>( name: &str, field: fn( &mut T ) -> &mut V, value: V ) -> Assign<T, V> {
+		Assign { field, value, name: name.to_string() }
+	}
+
+	// Applying and undoing are the same thing: swap the stored value with the field's
+	impl<T, V> Command<T> for Assign<T, V> {
+		fn apply( &mut self, target: &mut T ) {
+			std::mem::swap( ( self.field )( target ), &mut self.value );
+		}
+
+		fn undo( &mut self, target: &mut T ) {
+			std::mem::swap( ( self.field )( target ), &mut self.value );
+		}
+
+		fn label( &self ) -> String {
+			return format! ( "set {}", self.name );
+		}
+
+		fn size( &self ) -> usize {
+			return size_of::<Self>();
+		}
+	}
+}
@@ -0,0 +1,397 @@
+// Localization: message catalogs, placeholders, plural rules and locale fallback
+
+/*
+Localization (i18n, "i" + 18 letters + "n").
+
+- User-facing strings live in message catalogs (locales/<locale>.msg) instead of the code, one file per locale:
+
+	# Comments start with '#'
+	drive.ok = User is old enough to drive!
+	greeting = Hello, {name}!
+	years.left[one] = {count} year to go
+	years.left[other] = {count} years to go
+
+- '{name}' placeholders are filled from named arguments. '{{' and '}}' are literal braces, and '\n' is a newline.
+- 'key[category]' lines make a plural message. The category is picked from the 'count' argument using the CLDR plural rules of the catalog's language (zero, one, two, few, many, other), falling back to 'other'.
+- Lookups walk a fallback chain: "de-AT" -> "de" -> "en". The English catalog is compiled into the binary, so there's always a last resort.
+- The 't!' macro checks at COMPILE TIME that the key exists in the English catalog (a const fn scans the included file), so typos in keys don't build:
+
+	let msg = t! ( "greeting", name = "Rust" );
+
+- This file only uses std, so the exercises in ex/ can include it with '#[path = "../src/i18n.rs"] mod i18n;'.
+*/
+
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+use std::sync::OnceLock;
+
+pub const DEFAULT_LOCALE: &str = "en";
+pub const DEFAULT_CATALOG: &str = include_str! ( "../locales/en.msg" );
+
+#[derive( Debug, Clone, Copy, PartialEq, Eq, Hash )]
+pub enum PluralCategory {
+	Zero,
+	One,
+	Two,
+	Few,
+	Many,
+	Other,
+}
+
+impl PluralCategory {
+	fn from_name( name: &str ) -> Option<PluralCategory> {
+		match name {
+			"zero" => Some( PluralCategory::Zero ),
+			"one" => Some( PluralCategory::One ),
+			"two" => Some( PluralCategory::Two ),
+			"few" => Some( PluralCategory::Few ),
+			"many" => Some( PluralCategory::Many ),
+			"other" => Some( PluralCategory::Other ),
+			_ => None
+		}
+	}
+}
+
+/*
+CLDR plural rules for integers (https://www.unicode.org/cldr/charts/latest/supplemental/language_plural_rules.html).
+- Only the language part of the locale matters ("pt-BR" -> "pt").
+- Languages we don't know get the English rule, which is the most common one.
+*/
+pub fn plural_category( locale: &str, n: i64 ) -> PluralCategory {
+	let lang = locale.split( ['-', '_'] ).next().unwrap_or( "" ).to_ascii_lowercase();
+	let n = n.unsigned_abs();
+	let ( n10, n100 ) = ( n % 10, n % 100 );
+
+	match lang.as_str() {
+		"ja" | "zh" | "ko" | "vi" | "th" | "id" => PluralCategory::Other,
+		"fr" | "pt" => if n <= 1 { PluralCategory::One } else { PluralCategory::Other },
+		"ru" | "uk" | "be" => {
+			if n10 == 1 && n100 != 11 {
+				PluralCategory::One
+			} else if ( 2..=4 ).contains( &n10 ) && !( 12..=14 ).contains( &n100 ) {
+				PluralCategory::Few
+			} else {
+				PluralCategory::Many
+			}
+		},
+		"pl" => {
+			if n == 1 {
+				PluralCategory::One
+			} else if ( 2..=4 ).contains( &n10 ) && !( 12..=14 ).contains( &n100 ) {
+				PluralCategory::Few
+			} else {
+				PluralCategory::Many
+			}
+		},
+		"cs" | "sk" => match n {
+			1 => PluralCategory::One,
+			2..=4 => PluralCategory::Few,
+			_ => PluralCategory::Other
+		},
+		"ar" => match ( n, n100 ) {
+			( 0, _ ) => PluralCategory::Zero,
+			( 1, _ ) => PluralCategory::One,
+			( 2, _ ) => PluralCategory::Two,
+			( _, 3..=10 ) => PluralCategory::Few,
+			( _, 11..=99 ) => PluralCategory::Many,
+			_ => PluralCategory::Other
+		},
+		_ => if n == 1 { PluralCategory::One } else { PluralCategory::Other }
+	}
+}
+
+#[derive( Debug, Clone, PartialEq )]
+pub enum Message {
+	Simple( String ),
+	Plural( HashMap<PluralCategory, String> ),
+}
+
+#[derive( Debug, Clone )]
+pub struct Catalog {
+	pub locale: String,
+	messages: HashMap<String, Message>,
+}
+
+fn unescape( value: &str ) -> String {
+	return value.replace( "\\n", "\n" ).replace( "\\t", "\t" );
+}
+
+impl Catalog {
+	pub fn parse( locale: &str, text: &str ) -> Result<Catalog, String> {
+		let mut messages: HashMap<String, Message> = HashMap::new();
+
+		for ( i, line ) in text.lines().enumerate() {
+			let line = line.trim();
+
+			if line.is_empty() || line.starts_with( '#' ) {
+				continue;
+			}
+
+			let ( key, value ) = line.split_once( '=' ).ok_or( format! ( "{}.msg line {}: expected 'key = message'", locale, i + 1 ) )?;
+			let ( key, value ) = ( key.trim(), unescape( value.trim() ) );
+
+			match key.split_once( '[' ) {
+				Some( ( base, category ) ) => {
+					let category = category
+						.strip_suffix( ']' )
+						.and_then( PluralCategory::from_name )
+						.ok_or( format! ( "{}.msg line {}: invalid plural category in '{}'", locale, i + 1, key ) )?;
+
+					match messages.entry( base.trim().to_string() ).or_insert_with( || Message::Plural( HashMap::new() ) ) {
+						Message::Plural( forms ) => {
+							if forms.insert( category, value ).is_some() {
+								return Err( format! ( "{}.msg line {}: duplicate key '{}'", locale, i + 1, key ) );
+							}
+						},
+						Message::Simple( _ ) => return Err( format! ( "{}.msg line {}: '{}' is both a plain and a plural message", locale, i + 1, base.trim() ) )
+					}
+				},
+				None => {
+					if messages.insert( key.to_string(), Message::Simple( value ) ).is_some() {
+						return Err( format! ( "{}.msg line {}: duplicate key '{}'", locale, i + 1, key ) );
+					}
+				}
+			}
+		}
+
+		return Ok( Catalog { locale: locale.to_string(), messages } );
+	}
+
+	pub fn load( path: impl AsRef<Path> ) -> Result<Catalog, String> {
+		let path = path.as_ref();
+		let locale = path.file_stem().and_then( |s| s.to_str() ).ok_or( format! ( "Invalid catalog path: {}", path.display() ) )?;
+		let text = std::fs::read_to_string( path ).map_err( |e| format! ( "Cannot read {}: {}", path.display(), e ) )?;
+
+		return Catalog::parse( locale, &text );
+	}
+
+	pub fn get( &self, key: &str ) -> Option<&Message> {
+		return self.messages.get( key );
+	}
+
+	pub fn keys( &self ) -> impl Iterator<Item = &String> {
+		return self.messages.keys();
+	}
+}
+
+// Normalizes "de_DE.UTF-8" (POSIX style) to "de-DE" (BCP 47 style)
+pub fn normalize_locale( locale: &str ) -> String {
+	let base = locale.split( ['.', '@'] ).next().unwrap_or( "" );
+
+	return base.replace( '_', "-" );
+}
+
+// "de-AT" -> ["de-AT", "de", "en"]
+pub fn fallback_chain( locale: &str ) -> Vec<String> {
+	let mut chain: Vec<String> = Vec::new();
+	let mut current = normalize_locale( locale );
+
+	while !current.is_empty() {
+		if !chain.contains( &current ) {
+			chain.push( current.clone() );
+		}
+
+		current = match current.rfind( '-' ) {
+			Some( i ) => current[..i].to_string(),
+			None => String::new()
+		};
+	}
+
+	if !chain.iter().any( |l| l == DEFAULT_LOCALE ) {
+		chain.push( DEFAULT_LOCALE.to_string() );
+	}
+
+	return chain;
+}
+
+#[derive( Debug, Clone )]
+pub struct Localizer {
+	chain: Vec<String>,
+	catalogs: HashMap<String, Catalog>,
+}
+
+impl Localizer {
+	// Only the built-in English catalog
+	pub fn new( locale: &str ) -> Self {
+		let mut catalogs = HashMap::new();
+		catalogs.insert( DEFAULT_LOCALE.to_string(), Catalog::parse( DEFAULT_LOCALE, DEFAULT_CATALOG ).expect( "The built-in catalog is valid" ) );
+
+		Self { chain: fallback_chain( locale ), catalogs }
+	}
+
+	// Loads every '<locale>.msg' in 'dir' (a catalog on disk replaces the built-in one for its locale)
+	pub fn from_dir( dir: impl AsRef<Path>, locale: &str ) -> Result<Self, String> {
+		let dir = dir.as_ref();
+		let mut localizer = Localizer::new( locale );
+		let entries = std::fs::read_dir( dir ).map_err( |e| format! ( "Cannot read {}: {}", dir.display(), e ) )?;
+
+		for entry in entries.flatten() {
+			let path = entry.path();
+
+			if path.extension().is_some_and( |e| e == "msg" ) {
+				let catalog = Catalog::load( &path )?;
+				localizer.catalogs.insert( catalog.locale.clone(), catalog );
+			}
+		}
+
+		return Ok( localizer );
+	}
+
+	pub fn add_catalog( &mut self, catalog: Catalog ) {
+		self.catalogs.insert( catalog.locale.clone(), catalog );
+	}
+
+	pub fn locale( &self ) -> &str {
+		return &self.chain[0];
+	}
+
+	pub fn chain( &self ) -> &[String] {
+		return &self.chain;
+	}
+
+	// The first catalog in the fallback chain that has the key, with its locale (needed for plural rules)
+	fn find( &self, key: &str ) -> Option<( &str, &Message )> {
+		return self.chain
+			.iter()
+			.filter_map( |l| self.catalogs.get( l ) )
+			.find_map( |c| c.get( key ).map( |m| ( c.locale.as_str(), m ) ) );
+	}
+
+	pub fn has( &self, key: &str ) -> bool {
+		return self.find( key ).is_some();
+	}
+
+	// Missing keys render as the key itself, which is easy to spot in the output
+	pub fn format( &self, key: &str, args: &[( &str, &dyn fmt::Display )] ) -> String {
+		let ( locale, message ) = match self.find( key ) {
+			Some( found ) => found,
+			None => return key.to_string()
+		};
+
+		let pattern = match message {
+			Message::Simple( s ) => s,
+			Message::Plural( forms ) => {
+				// Counts that aren't integers ("1.5") use 'other', like in English
+				let count = args.iter().find( |( name, _ )| *name == "count" ).and_then( |( _, v )| v.to_string().parse::<i64>().ok() );
+				let category = count.map( |n| plural_category( locale, n ) ).unwrap_or( PluralCategory::Other );
+
+				match forms.get( &category ).or( forms.get( &PluralCategory::Other ) ) {
+					Some( s ) => s,
+					None => return key.to_string()
+				}
+			}
+		};
+
+		return substitute( pattern, args );
+	}
+}
+
+fn substitute( pattern: &str, args: &[( &str, &dyn fmt::Display )] ) -> String {
+	let mut out = String::with_capacity( pattern.len() );
+	let mut rest = pattern;
+
+	while let Some( i ) = rest.find( ['{', '}'] ) {
+		out.push_str( &rest[..i] );
+		let tail = &rest[i..];
+
+		if tail.starts_with( "{{" ) || tail.starts_with( "}}" ) {
+			out.push_str( &tail[..1] );
+			rest = &tail[2..];
+			continue;
+		}
+
+		match ( tail.starts_with( '{' ), tail.find( '}' ) ) {
+			( true, Some( end ) ) => {
+				let name = tail[1..end].trim();
+
+				match args.iter().find( |( n, _ )| *n == name ) {
+					Some( ( _, value ) ) => out.push_str( &value.to_string() ),
+					None => out.push_str( &tail[..=end] ) // Unknown placeholders are left as they are
+				}
+
+				rest = &tail[end + 1..];
+			},
+			_ => {
+				out.push_str( &tail[..1] );
+				rest = &tail[1..];
+			}
+		}
+	}
+
+	out.push_str( rest );
+
+	return out;
+}
+
+/*
+The process-wide localizer used by 't!'.
+- The locale comes from RS_BASICS_LANG, then the usual POSIX variables (LC_ALL, LC_MESSAGES, LANG).
+- Catalogs are loaded from RS_BASICS_LOCALES, or ./locales (i.e. running from the repo root), falling back to the built-in English one.
+*/
+pub fn global() -> &'static Localizer {
+	static GLOBAL: OnceLock<Localizer> = OnceLock::new();
+
+	return GLOBAL.get_or_init( || {
+		let locale = [ "RS_BASICS_LANG", "LC_ALL", "LC_MESSAGES", "LANG" ]
+			.iter()
+			.filter_map( |v| std::env::var( v ).ok() )
+			.find( |v| !v.is_empty() && v != "C" && v != "POSIX" )
+			.unwrap_or( DEFAULT_LOCALE.to_string() );
+		let dir = std::env::var( "RS_BASICS_LOCALES" ).unwrap_or( "locales".to_string() );
+
+		Localizer::from_dir( &dir, &locale ).unwrap_or_else( |_| Localizer::new( &locale ) )
+	});
+}
+
+/*
+Compile-time key check.
+- A 'const fn' can run during compilation, so 't!' calls this on the included English catalog inside a 'const _: () = assert!( ... )'.
+- Const fns can't use iterators or most str methods (yet), hence the manual byte loops.
+*/
+pub const fn has_key( catalog: &str, key: &str ) -> bool {
+	let text = catalog.as_bytes();
+	let key = key.as_bytes();
+	let mut i = 0;
+
+	while i < text.len() {
+		// Skip leading whitespace
+		while i < text.len() && ( text[i] == b' ' || text[i] == b'\t' ) {
+			i += 1;
+		}
+
+		// Compare the key, then expect whitespace, '=' or '[' right after it
+		let mut k = 0;
+
+		while k < key.len() && i + k < text.len() && text[i + k] == key[k] {
+			k += 1;
+		}
+
+		if k == key.len() && k > 0 && i + k < text.len() {
+			let next = text[i + k];
+
+			if next == b'=' || next == b'[' || next == b' ' || next == b'\t' {
+				return true;
+			}
+		}
+
+		// Next line
+		while i < text.len() && text[i] != b'\n' {
+			i += 1;
+		}
+
+		i += 1;
+	}
+
+	return false;
+}
+
+// Looks a message up in the global localizer, failing to compile if the key isn't in locales/en.msg
+#[macro_export]
+macro_rules! t {
+	( $key:literal $(, $name:ident = $value:expr )* $(,)? ) => {{
+		const _: () = assert! ( $crate::i18n::has_key( $crate::i18n::DEFAULT_CATALOG, $key ), concat! ( "Unknown message key: ", $key ) );
+
+		$crate::i18n::global().format( $key, &[ $( ( stringify! ( $name ), &$value as &dyn ::std::fmt::Display ) ),* ] )
+	}};
+}
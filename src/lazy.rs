@@ -0,0 +1,290 @@
+// Lazy values: computed on first use, then cached
+
+/*
+Lazy initialization.
+
+- 'Lazy::new( || expensive() )' stores the closure instead of calling it. The first time the value is used (through Deref, or 'force'), the closure runs and its result is kept; every use after that just reads it.
+- 'Lazy' is for one thread (built on Cell/OnceCell, so it is not Sync). 'SyncLazy' can be shared between threads and used in statics: if several threads ask at once, one runs the initializer and the others wait for it.
+- Lazy statics, the values computed at runtime that a plain 'static' can't hold (a HashMap, a parsed file...):
+
+	static PRIMES: SyncLazy<Vec<u32>> = SyncLazy::new( || sieve( 1000 ) );
+
+	or with the macro:
+
+	lazy_static! {
+		static ref PRIMES: Vec<u32> = sieve( 1000 );
+	}
+
+Poisoning.
+
+- If the initializer panics, the panic goes on to the caller and the lazy value is "poisoned": there is no value, and the closure is gone (it was FnOnce), so it can't be retried.
+- From then on, using it (Deref/'force') panics with "Lazy instance has been poisoned", and 'try_force' returns Err( Poisoned ). Threads that were waiting for the initializer get the same.
+- Using a lazy value from inside its own initializer would wait forever (or recurse forever), so it panics instead.
+
+Thunks.
+
+- A 'Thunk<T>' is a computation that hasn't run yet. They chain without running anything ('map', 'and_then', 'zip'), and nothing happens until 'force' (consumes the thunk) or until the 'Lazy' from 'memo()' is first used.
+*/
+
+use std::cell::{Cell, OnceCell};
+use std::fmt;
+use std::ops::Deref;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Condvar, Mutex, OnceLock};
+use std::thread::{self, ThreadId};
+
+#[derive( Debug, Clone, Copy, PartialEq, Eq )]
+pub struct Poisoned;
+
+impl fmt::Display for Poisoned {
+	fn fmt( &self, f: &mut fmt::Formatter ) -> fmt::Result {
+		write! ( f, "Lazy instance has been poisoned" )
+	}
+}
+
+impl std::error::Error for Poisoned {}
+
+enum Init<F> {
+	Pending( F ),
+	Running,
+	Poisoned,
+	Done,
+}
+
+// Single-threaded
+
+pub struct Lazy<T, F = fn() -> T> {
+	value: OnceCell<T>,
+	init: Cell<Init<F>>,
+}
+
+impl<T, F: FnOnce() -> T> Lazy<T, F> {
+	pub const fn new( init: F ) -> Self {
+		Self { value: OnceCell::new(), init: Cell::new( Init::Pending( init ) ) }
+	}
+
+	pub fn force( this: &Self ) -> &T {
+		match Self::try_force( this ) {
+			Ok( value ) => value,
+			Err( e ) => panic! ( "{}", e ),
+		}
+	}
+
+	// Only an Err if an earlier initializer panicked. A panic in this call's initializer is passed on as a panic
+	pub fn try_force( this: &Self ) -> Result<&T, Poisoned> {
+		if let Some( value ) = this.value.get() {
+			return Ok( value );
+		}
+
+		let f = match this.init.replace( Init::Running ) {
+			Init::Pending( f ) => f,
+			Init::Running => panic! ( "Lazy value used during its own initialization" ),
+			Init::Poisoned | Init::Done => {
+				this.init.set( Init::Poisoned );
+				return Err( Poisoned );
+			}
+		};
+
+		// Stays 'Running' if the initializer panics, so it's set to 'Poisoned' on the way out
+		match panic::catch_unwind( AssertUnwindSafe( f ) ) {
+			Ok( value ) => {
+				this.init.set( Init::Done );
+				let _ = this.value.set( value );
+
+				return Ok( this.value.get().unwrap() );
+			},
+			Err( payload ) => {
+				this.init.set( Init::Poisoned );
+				panic::resume_unwind( payload );
+			}
+		}
+	}
+
+	// The value if it was already computed, without computing it
+	pub fn get( this: &Self ) -> Option<&T> {
+		return this.value.get();
+	}
+
+	pub fn is_poisoned( this: &Self ) -> bool {
+		let state = this.init.replace( Init::Running );
+		let poisoned = matches! ( state, Init::Poisoned );
+		this.init.set( state );
+
+		return poisoned;
+	}
+}
+
+impl<T, F: FnOnce() -> T> Deref for Lazy<T, F> {
+	type Target = T;
+
+	fn deref( &self ) -> &T {
+		return Lazy::force( self );
+	}
+}
+
+impl<T: fmt::Debug, F> fmt::Debug for Lazy<T, F> {
+	fn fmt( &self, f: &mut fmt::Formatter ) -> fmt::Result {
+		match self.value.get() {
+			Some( value ) => f.debug_tuple( "Lazy" ).field( value ).finish(),
+			None => write! ( f, "Lazy(<uninit>)" ),
+		}
+	}
+}
+
+// Thread-safe
+
+pub struct SyncLazy<T, F = fn() -> T> {
+	value: OnceLock<T>,
+	state: Mutex<SyncInit<F>>,
+	done: Condvar,
+}
+
+enum SyncInit<F> {
+	Pending( F ),
+	Running( ThreadId ), // Which thread, to catch re-entrant use instead of deadlocking
+	Poisoned,
+	Done,
+}
+
+impl<T, F: FnOnce() -> T> SyncLazy<T, F> {
+	pub const fn new( init: F ) -> Self {
+		Self { value: OnceLock::new(), state: Mutex::new( SyncInit::Pending( init ) ), done: Condvar::new() }
+	}
+
+	pub fn force( this: &Self ) -> &T {
+		match Self::try_force( this ) {
+			Ok( value ) => value,
+			Err( e ) => panic! ( "{}", e ),
+		}
+	}
+
+	pub fn try_force( this: &Self ) -> Result<&T, Poisoned> {
+		if let Some( value ) = this.value.get() {
+			return Ok( value );
+		}
+
+		// The lock is never held while the initializer runs, so this Mutex can't get poisoned itself
+		let mut state = this.state.lock().unwrap();
+
+		let f = loop {
+			match std::mem::replace( &mut *state, SyncInit::Running( thread::current().id() ) ) {
+				SyncInit::Pending( f ) => break f,
+				SyncInit::Running( id ) if id == thread::current().id() => {
+					*state = SyncInit::Running( id );
+					drop( state );
+					panic! ( "Lazy value used during its own initialization" );
+				},
+				SyncInit::Running( id ) => {
+					// Someone else is on it
+					*state = SyncInit::Running( id );
+					state = this.done.wait( state ).unwrap();
+				},
+				SyncInit::Done => {
+					*state = SyncInit::Done;
+					return Ok( this.value.get().unwrap() );
+				},
+				SyncInit::Poisoned => {
+					*state = SyncInit::Poisoned;
+					return Err( Poisoned );
+				}
+			}
+		};
+
+		drop( state );
+
+		let result = panic::catch_unwind( AssertUnwindSafe( f ) );
+		let mut state = this.state.lock().unwrap();
+
+		match result {
+			Ok( value ) => {
+				let _ = this.value.set( value );
+				*state = SyncInit::Done;
+				this.done.notify_all();
+
+				return Ok( this.value.get().unwrap() );
+			},
+			Err( payload ) => {
+				*state = SyncInit::Poisoned;
+				this.done.notify_all();
+				drop( state );
+
+				panic::resume_unwind( payload );
+			}
+		}
+	}
+
+	pub fn get( this: &Self ) -> Option<&T> {
+		return this.value.get();
+	}
+
+	pub fn is_poisoned( this: &Self ) -> bool {
+		return matches! ( *this.state.lock().unwrap(), SyncInit::Poisoned );
+	}
+}
+
+impl<T, F: FnOnce() -> T> Deref for SyncLazy<T, F> {
+	type Target = T;
+
+	fn deref( &self ) -> &T {
+		return SyncLazy::force( self );
+	}
+}
+
+impl<T: fmt::Debug, F> fmt::Debug for SyncLazy<T, F> {
+	fn fmt( &self, f: &mut fmt::Formatter ) -> fmt::Result {
+		match self.value.get() {
+			Some( value ) => f.debug_tuple( "SyncLazy" ).field( value ).finish(),
+			None => write! ( f, "SyncLazy(<uninit>)" ),
+		}
+	}
+}
+
+// 'lazy_static! { static ref NAME: Type = expr; }' becomes 'static NAME: SyncLazy<Type> = SyncLazy::new( || expr );'
+#[macro_export]
+macro_rules! lazy_static {
+	( $( $( #[$attr:meta] )* $vis:vis static ref $name:ident : $ty:ty = $init:expr ; )* ) => {
+		$(
+			$( #[$attr] )*
+			$vis static $name: $crate::lazy::SyncLazy<$ty> = $crate::lazy::SyncLazy::new( || $init );
+		)*
+	};
+}
+
+// Thunks
+
+pub struct Thunk<T> {
+	run: Box<dyn FnOnce() -> T>,
+}
+
+impl<T: 'static> Thunk<T> {
+	pub fn new( f: impl FnOnce() -> T + 'static ) -> Self {
+		Self { run: Box::new( f ) }
+	}
+
+	// Already computed, wrapped up as a thunk
+	pub fn value( value: T ) -> Self {
+		Self::new( move || value )
+	}
+
+	pub fn force( self ) -> T {
+		return ( self.run )();
+	}
+
+	pub fn map<U: 'static>( self, f: impl FnOnce( T ) -> U + 'static ) -> Thunk<U> {
+		Thunk::new( move || f( self.force() ) )
+	}
+
+	// For steps that are deferred themselves
+	pub fn and_then<U: 'static>( self, f: impl FnOnce( T ) -> Thunk<U> + 'static ) -> Thunk<U> {
+		Thunk::new( move || f( self.force() ).force() )
+	}
+
+	pub fn zip<U: 'static>( self, other: Thunk<U> ) -> Thunk<( T, U )> {
+		Thunk::new( move || ( self.force(), other.force() ) )
+	}
+
+	// Runs at most once, on first use, and can then be read any number of times
+	pub fn memo( self ) -> Lazy<T, Box<dyn FnOnce() -> T>> {
+		Lazy::new( self.run )
+	}
+}
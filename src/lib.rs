@@ -0,0 +1,30 @@
+/*
+Library half of the crate.
+
+- main.rs holds the lessons, and this is where reusable modules go, so that binaries in src/bin/ can use them too ('use rs_basics::module;').
+- A package can have one library crate and many binary crates. The library is named after the package, with '-' replaced by '_'.
+*/
+
+pub mod date;
+pub mod calendar;
+pub mod platform;
+pub mod text;
+pub mod contact;
+pub mod template;
+pub mod table;
+pub mod i18n;
+pub mod events;
+pub mod pipeline;
+pub mod memo;
+pub mod resilience;
+pub mod scheduler;
+pub mod history;
+pub mod lazy;
+pub mod threading;
+pub mod pool;
+pub mod channel;
+pub mod chat;
+pub mod shutdown;
+pub mod ratelimit;
+pub mod metrics;
+pub mod ring;
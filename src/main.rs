@@ -14,6 +14,8 @@
 
 use std::io;
 
+use rs_basics::{ date, history, t, template }; // Modules (and macros) from our own library crate (src/lib.rs)
+
 #[allow( dead_code )] // Prevents Rust warnings if the function isn't used
 /*
 We can also enforce global lints in the Cargo.toml of the project file:
@@ -193,7 +195,7 @@ fn vars_and_data_types() {
 	Differences between new() and from(): new is used to initialize an empty string, while from is used to initialize and assign a default, immutable value to the string (both are different from &str).
 	*/
 
-	println! ( "Enter your name:" );
+	println! ( "{}", t! ( "lesson.name.prompt" ) ); // User-facing strings come from locales/*.msg (see src/i18n.rs)
 
 	io::stdin()
 		.read_line( &mut name1 ) // The string type must be converted to a string pointer type (since String::new() or String::from("") is used)
@@ -203,10 +205,10 @@ fn vars_and_data_types() {
 	let name1 = name1.trim(); // Remove the extra '\n' that is concatenated when receiving inputs with 'io::stdin()'
 	// or by adding 'replace( "\n", "" )' to 'io::stdin()' (recommended)
 
-	println! ( "Hello, {}!", name1 );
+	println! ( "{}", t! ( "lesson.greeting", name = name1 ) );
 
 	// Alternate 01
-	println! ( "Enter your name:" );
+	println! ( "{}", t! ( "lesson.name.prompt" ) );
 
 	let name2 = &mut String::new();
 	io::stdin()
@@ -215,7 +217,7 @@ fn vars_and_data_types() {
 
 	let name2 = name2.trim();
 
-	println! ( "Hello {}!", name2 );
+	println! ( "{}", t! ( "lesson.greeting", name = name2 ) );
 
 	// Or with 'unwrap()': 'io::stdin().read_line( var ).unwrap();'
 
@@ -298,6 +300,9 @@ fn functions_and_modules() {
 	let age = helpers::agehelpers::add_5_to_age( 42 );
 	println! ( "New age is: {} (from child module public function)!", age );
 
+	let real_age = helpers::agehelpers::age_from_dob( "August 03 2003", &date::Date::today() );
+	println! ( "Real age is: {:?} (from a parsed date of birth)!", real_age );
+
 	// Calling functions from another module
 	println! ();
 	let sum = calc::calc_sum( 25.0, 32.0 );
@@ -317,23 +322,23 @@ fn test_if() {
 
 	let age_input = &mut String::new();
 
-	println! ( "How old are you?" );
+	println! ( "{}", t! ( "lesson.age.prompt" ) );
 	io::stdin().read_line( age_input ).unwrap();
 
 	let age_c = age_input.replace( "\n", "" ).parse::<u8>().unwrap(); // Parse is used to convert strings to other data types (and we need to remove the '\n' before parsing)
 
 	if age_c > age {
-		println! ( "User is old enough to drive!" );
+		println! ( "{}", t! ( "lesson.drive.ok" ) );
 	} else if age_c == 16 || age_c == 15 {
-		println! ( "User is almost old enough to drive!" );
+		println! ( "{}", t! ( "lesson.drive.almost" ) );
 	} else {
-		println! ( "User is not old enough to drive!" );
+		println! ( "{}", t! ( "lesson.drive.no" ) );
 	}
 
 	// Simple conditionals
 	let drivers_license: bool = if age_c > 16 { true } else { false };
 
-	println! ( "User has driver license? {}", drivers_license );
+	println! ( "{}", t! ( "lesson.drive.license", answer = drivers_license ) );
 }
 
 #[allow( dead_code )]
@@ -342,7 +347,7 @@ fn test_while() {
 	let mut c_age: u8 = 0;
 
 	while c_age < age {
-		println! ( "Waiting..." );
+		println! ( "{}", t! ( "lesson.waiting" ) );
 		c_age += 1;
 
 		if c_age == 6 { break; }
@@ -354,7 +359,7 @@ fn test_loop() { // Kind of useless
 	let mut x: u8 = 1;
 
 	loop {
-		println! ( "Waiting..." );
+		println! ( "{}", t! ( "lesson.loop.waiting" ) );
 
 		if x > 5 { break; }
 
@@ -369,9 +374,9 @@ fn test_for() {
 	// Iterating through the array with 'for' and running an 'if' loop for case matching* (pretty simple)
 	for x in arr {
 		if x > 16 {
-			println! ( "User is eligible to drive!: '{}'", x );
+			println! ( "{}", t! ( "lesson.eligible.yes", age = x ) );
 		} else {
-			println! ( "User is not eligible to drive!: '{}'", x );
+			println! ( "{}", t! ( "lesson.eligible.no", age = x ) );
 		}
 	}
 }
@@ -467,10 +472,10 @@ enum Direction {
 #[allow( dead_code )]
 fn test_op_directions( direction: Direction ) { // i.e. the arg can only be a variant from 'Direction'
 	match direction { // Used with a simple match case
-		Direction::North => println! ( "This is the north direction" ),
-		Direction::South => println! ( "This is the south direction" ),
-		Direction::East => println! ( "This is the east direction" ),
-		Direction::West => println! ( "This is the west direction" )
+		Direction::North => println! ( "{}", t! ( "lesson.direction.north" ) ),
+		Direction::South => println! ( "{}", t! ( "lesson.direction.south" ) ),
+		Direction::East => println! ( "{}", t! ( "lesson.direction.east" ) ),
+		Direction::West => println! ( "{}", t! ( "lesson.direction.west" ) )
 		// No default match case ('_ => {...}'), since all match patterns are defined ...
 	}
 }
@@ -603,7 +608,19 @@ struct  Person {
 // Apply implementations to the struct
 impl Person {
 	fn get_full_details( &self ) {
-		println! ( "User's name is {} and is {} years old!", self.Name, self.Age );
+		// The format lives in a template instead of the println! (see src/template.rs)
+		println! ( "{}", template::render( "User's name is {{Name}} and is {{Age}} years old!", self ).unwrap() );
+	}
+}
+
+// Implementing the library's Context trait lets templates read the struct's fields by name
+impl template::Context for Person {
+	fn get( &self, key: &str ) -> Option<template::Value> {
+		match key {
+			"Name" | "name" => Some( self.Name.as_str().into() ),
+			"Age" | "age" => Some( self.Age.into() ),
+			_ => None
+		}
 	}
 }
 
@@ -636,6 +653,17 @@ struct Vehicle {
 	color: VehicleColor,
 }
 
+impl template::Context for Vehicle {
+	fn get( &self, key: &str ) -> Option<template::Value> {
+		match key {
+			"manufacturer" => Some( format! ( "{:?}", self.manufacturer ).into() ),
+			"model" => Some( self.model.as_str().into() ),
+			"color" => Some( format! ( "{:?}", self.color ).into() ),
+			_ => None
+		}
+	}
+}
+
 #[allow( dead_code )]
 fn new_vehicle(
 	man: VehicleMan,
@@ -674,6 +702,14 @@ fn test_structs() {
 	let det_p2 = Person::get_full_details( &person2 );
 	let det_p3 = Person::get_full_details( &person3 );
 
+	// Reassigning through a History can be undone (see src/history.rs)
+	let mut person2 = history::History::new( person2 );
+	person2.execute( history::edits::assign( "Name", |p: &mut Person| &mut p.Name, "Smith".to_string() ) );
+	person2.undo(); // Back to "Doe"
+
+	let vehicle = new_vehicle( VehicleMan::Toyota, "Corolla".to_string(), VehicleColor::Silver );
+	println! ( "{}", template::render( "{{color | lower}} {{manufacturer}} {{model | upper}}", &vehicle ).unwrap() );
+
 	// println! (
 	// 	"{:?}\n{:?}\n{:?}", det_p1, det_p2, det_p3
 	// );
@@ -784,7 +820,7 @@ fn let_conditionals() {
 pub mod advanced_concepts;
 
 pub mod smart_pointers;
-pub mod threading;
+// threading.rs lives in the library now (src/lib.rs), so the integration tests in tests/ can start a Server
 
 // Rust requires a main entry function!
 fn main() {
@@ -797,6 +833,9 @@ fn main() {
 	// closures::test_closures();
 	// closures::test_closures_2();
 	// closures::test_closures_3();
+	// closures::test_closures_4();
+	// closures::test_closures_5();
+	// closures::test_closures_6();
 	// test_match_int();
 	// test_op_shapes_check();
 	// test_direct_impl();
@@ -805,6 +844,7 @@ fn main() {
 	// test_tup_struct();
 	// helpers::a_doc_fn();
 	// helpers::fn_for_Linux();
+	// println! ( "{}", rs_basics::platform::HostInfo::collect() );
 	// let res = test_Result(22, 4);
 	// println! ( "Res is: {:?}", res.unwrap() );
 	// test_Result_2();
@@ -816,6 +856,10 @@ fn main() {
 	// advanced_concepts::test_vec_int();
 	// advanced_concepts::test_vec_str();
 	// advanced_concepts::test_vec_custom();
+	// advanced_concepts::test_vec_history();
+	// smart_pointers::test_lazy();
+	// rs_basics::threading::serve_echo( "127.0.0.1:7878".parse().unwrap() ); // Then 'nc 127.0.0.1 7878' in another terminal
+	// rs_basics::chat::serve_chat( "127.0.0.1:7878".parse().unwrap() ).unwrap(); // Then 'cargo run --bin chat_client -- 127.0.0.1:7878 alice' in other terminals
 	// ...
 	let log = advanced_concepts::test_dec_macros();
 
@@ -0,0 +1,249 @@
+// Memoization: caching the results of closures
+
+/*
+Memoization.
+
+- A pure function always returns the same output for the same input, so its results can be cached: the first call computes, the following ones just look the answer up.
+- 'memoize( policy, f )' wraps a closure 'Fn( A ) -> R' into a 'Memo', called with 'memo.call( a )'.
+- Cache policies:
+	* Unbounded   Keeps everything (fine for small input sets, e.g. fib( 0..90 )).
+	* Lru( n )    Keeps the n most recently used results, dropping the least recently used one when full.
+	* Ttl( d )    Results expire d after being computed (e.g. a slow lookup that can go stale).
+- Several arguments: use a tuple, 'memoize( policy, |( a, b ): ( u32, u32 )| ... )', or derive the key yourself with 'memoize_by' (e.g. case-insensitive keys, or skipping an argument that doesn't change the result).
+- Recursion: a closure can't name itself, so 'memoize_rec' passes it a '&dyn Fn( A ) -> R' to recurse through the cache:
+
+	let fib = memoize_rec( Policy::Unbounded, |fib, n: u64| if n < 2 { n } else { fib( n - 1 ) + fib( n - 2 ) } );
+
+- 'SyncMemo' is the thread-safe variant (Mutex inside, closure must be Send + Sync). The lock isn't held while computing, so two threads missing the same key at the same time both compute it (the results are the same anyway).
+- LRU eviction scans for the oldest entry, O(n) in the cache size. Fine for the cache sizes used here.
+- Expired TTL entries are dropped when they're read, and by a sweep on insert once the cache has doubled in size since the last one, so keys that are never read again don't pile up forever.
+*/
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+#[derive( Debug, Clone, Copy, PartialEq, Eq )]
+pub enum Policy {
+	Unbounded,
+	Lru( usize ),
+	Ttl( Duration ),
+}
+
+#[derive( Debug, Clone, Copy, Default, PartialEq, Eq )]
+pub struct Stats {
+	pub hits: u64,
+	pub misses: u64,
+	pub evictions: u64, // Includes expired entries
+}
+
+impl Stats {
+	pub fn hit_rate( &self ) -> f64 {
+		let total = self.hits + self.misses;
+
+		if total == 0 {
+			return 0.0;
+		}
+
+		return self.hits as f64 / total as f64;
+	}
+}
+
+impl std::fmt::Display for Stats {
+	fn fmt( &self, f: &mut std::fmt::Formatter ) -> std::fmt::Result {
+		write! ( f, "{} hits, {} misses, {} evictions ({:.1}% hit rate)", self.hits, self.misses, self.evictions, self.hit_rate() * 100.0 )
+	}
+}
+
+struct Slot<V> {
+	value: V,
+	inserted: Instant,
+	used: u64, // Tick of the last access, for LRU
+}
+
+struct Cache<K, V> {
+	policy: Policy,
+	map: HashMap<K, Slot<V>>,
+	tick: u64,
+	stats: Stats,
+	sweep_at: usize, // TTL only: size at which the next insert drops every expired entry
+}
+
+const MIN_SWEEP: usize = 16;
+
+impl<K: Hash + Eq + Clone, V: Clone> Cache<K, V> {
+	fn new( policy: Policy ) -> Self {
+		Self { policy, map: HashMap::new(), tick: 0, stats: Stats::default(), sweep_at: MIN_SWEEP }
+	}
+
+	fn get( &mut self, key: &K ) -> Option<V> {
+		self.tick += 1;
+
+		if let Policy::Ttl( ttl ) = self.policy
+			&& self.map.get( key ).is_some_and( |slot| slot.inserted.elapsed() >= ttl ) {
+			self.map.remove( key );
+			self.stats.evictions += 1;
+		}
+
+		match self.map.get_mut( key ) {
+			Some( slot ) => {
+				slot.used = self.tick;
+				self.stats.hits += 1;
+
+				Some( slot.value.clone() )
+			},
+			None => {
+				self.stats.misses += 1;
+
+				None
+			}
+		}
+	}
+
+	fn insert( &mut self, key: K, value: V ) {
+		if let Policy::Lru( capacity ) = self.policy {
+			if capacity == 0 {
+				return;
+			}
+
+			while self.map.len() >= capacity && !self.map.contains_key( &key ) {
+				let Some( oldest ) = self.map.iter().min_by_key( |( _, slot )| slot.used ).map( |( k, _ )| k.clone() ) else { break };
+
+				self.map.remove( &oldest );
+				self.stats.evictions += 1;
+			}
+		}
+
+		if let Policy::Ttl( ttl ) = self.policy
+			&& self.map.len() >= self.sweep_at {
+			self.sweep( ttl );
+		}
+
+		self.tick += 1;
+		self.map.insert( key, Slot { value, inserted: Instant::now(), used: self.tick } );
+	}
+
+	// O(n), but only after the size doubled since the last sweep, so it's O(1) per insert on average
+	fn sweep( &mut self, ttl: Duration ) {
+		let before = self.map.len();
+		self.map.retain( |_, slot| slot.inserted.elapsed() < ttl );
+
+		self.stats.evictions += ( before - self.map.len() ) as u64;
+		self.sweep_at = ( self.map.len() * 2 ).max( MIN_SWEEP );
+	}
+
+	fn clear( &mut self ) {
+		self.map.clear();
+		self.sweep_at = MIN_SWEEP;
+	}
+}
+
+type KeyFn<A, K> = Box<dyn Fn( &A ) -> K>;
+type RecFn<A, R> = Box<dyn Fn( &dyn Fn( A ) -> R, A ) -> R>;
+
+pub struct Memo<A, K, R> {
+	key: KeyFn<A, K>,
+	f: RecFn<A, R>,
+	cache: RefCell<Cache<K, R>>,
+}
+
+impl<A, K: Hash + Eq + Clone, R: Clone> Memo<A, K, R> {
+	pub fn call( &self, arg: A ) -> R {
+		let key = ( self.key )( &arg );
+
+		// The borrow ends before computing, since recursive calls borrow the cache again
+		if let Some( value ) = self.cache.borrow_mut().get( &key ) {
+			return value;
+		}
+
+		let value = ( self.f )( &|a| self.call( a ), arg );
+		self.cache.borrow_mut().insert( key, value.clone() );
+
+		return value;
+	}
+
+	pub fn stats( &self ) -> Stats {
+		return self.cache.borrow().stats;
+	}
+
+	pub fn len( &self ) -> usize {
+		return self.cache.borrow().map.len();
+	}
+
+	pub fn is_empty( &self ) -> bool {
+		return self.len() == 0;
+	}
+
+	// Drops the cached results, the stats are kept
+	pub fn clear( &self ) {
+		self.cache.borrow_mut().clear();
+	}
+}
+
+pub fn memoize<A: Hash + Eq + Clone + 'static, R: Clone>( policy: Policy, f: impl Fn( A ) -> R + 'static ) -> Memo<A, A, R> {
+	return Memo { key: Box::new( |a: &A| a.clone() ), f: Box::new( move |_, a| f( a ) ), cache: RefCell::new( Cache::new( policy ) ) };
+}
+
+pub fn memoize_by<A, K: Hash + Eq + Clone, R: Clone>( policy: Policy, key: impl Fn( &A ) -> K + 'static, f: impl Fn( A ) -> R + 'static ) -> Memo<A, K, R> {
+	return Memo { key: Box::new( key ), f: Box::new( move |_, a| f( a ) ), cache: RefCell::new( Cache::new( policy ) ) };
+}
+
+pub fn memoize_rec<A: Hash + Eq + Clone + 'static, R: Clone>( policy: Policy, f: impl Fn( &dyn Fn( A ) -> R, A ) -> R + 'static ) -> Memo<A, A, R> {
+	return Memo { key: Box::new( |a: &A| a.clone() ), f: Box::new( f ), cache: RefCell::new( Cache::new( policy ) ) };
+}
+
+// Thread-safe variant
+
+type SyncKeyFn<A, K> = Box<dyn Fn( &A ) -> K + Send + Sync>;
+type SyncRecFn<A, R> = Box<dyn Fn( &dyn Fn( A ) -> R, A ) -> R + Send + Sync>;
+
+pub struct SyncMemo<A, K, R> {
+	key: SyncKeyFn<A, K>,
+	f: SyncRecFn<A, R>,
+	cache: Mutex<Cache<K, R>>,
+}
+
+impl<A, K: Hash + Eq + Clone, R: Clone> SyncMemo<A, K, R> {
+	pub fn call( &self, arg: A ) -> R {
+		let key = ( self.key )( &arg );
+
+		if let Some( value ) = self.cache.lock().unwrap().get( &key ) {
+			return value;
+		}
+
+		let value = ( self.f )( &|a| self.call( a ), arg );
+		self.cache.lock().unwrap().insert( key, value.clone() );
+
+		return value;
+	}
+
+	pub fn stats( &self ) -> Stats {
+		return self.cache.lock().unwrap().stats;
+	}
+
+	pub fn len( &self ) -> usize {
+		return self.cache.lock().unwrap().map.len();
+	}
+
+	pub fn is_empty( &self ) -> bool {
+		return self.len() == 0;
+	}
+
+	pub fn clear( &self ) {
+		self.cache.lock().unwrap().clear();
+	}
+}
+
+pub fn sync_memoize<A: Hash + Eq + Clone + 'static, R: Clone>( policy: Policy, f: impl Fn( A ) -> R + Send + Sync + 'static ) -> SyncMemo<A, A, R> {
+	return SyncMemo { key: Box::new( |a: &A| a.clone() ), f: Box::new( move |_, a| f( a ) ), cache: Mutex::new( Cache::new( policy ) ) };
+}
+
+pub fn sync_memoize_by<A, K: Hash + Eq + Clone, R: Clone>( policy: Policy, key: impl Fn( &A ) -> K + Send + Sync + 'static, f: impl Fn( A ) -> R + Send + Sync + 'static ) -> SyncMemo<A, K, R> {
+	return SyncMemo { key: Box::new( key ), f: Box::new( move |_, a| f( a ) ), cache: Mutex::new( Cache::new( policy ) ) };
+}
+
+pub fn sync_memoize_rec<A: Hash + Eq + Clone + 'static, R: Clone>( policy: Policy, f: impl Fn( &dyn Fn( A ) -> R, A ) -> R + Send + Sync + 'static ) -> SyncMemo<A, A, R> {
+	return SyncMemo { key: Box::new( |a: &A| a.clone() ), f: Box::new( f ), cache: Mutex::new( Cache::new( policy ) ) };
+}
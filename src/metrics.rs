@@ -0,0 +1,409 @@
+// Counters, gauges and histograms, in the Prometheus text format
+
+/*
+Metrics.
+
+- A metric is a number the program keeps up to date while it runs, so something outside (Prometheus, Grafana, a curl) can watch it. Three kinds:
+	* Counter: only goes up (requests handled, bytes sent). Watchers look at how fast it grows, e.g. 'rate( server_messages_total[1m] )' is messages per second.
+	* Gauge: goes up and down (connected clients, queue length).
+	* Histogram: counts observations into buckets (e.g. "how many requests took at most 5ms, at most 10ms, ..."), plus their sum and count. Percentiles are estimated from the buckets.
+- Each is a family ('name' and 'help') of series told apart by labels, e.g. 'server_messages_total{direction="in"}' and '{direction="out"}'.
+- The hot path is one atomic operation, no lock: the handles ('Counter', 'Gauge', 'Histogram') are Arcs around atomics, cloned wherever they're updated. The registry's Mutex is only taken to register a series and to render them all.
+- Relaxed ordering is enough: each number is updated on its own, nothing else is published through it, and a scrape seeing a value that is a few microseconds old doesn't matter.
+- A histogram's sum is an f64, and there's no atomic float: it's kept as the f64's bits in an AtomicU64, updated with a compare-and-swap loop.
+- 'Registry::render()' writes everything in the Prometheus text format, and 'serve( addr, registry )' answers 'GET /metrics' with it over HTTP.
+
+The text format:
+
+	# HELP server_connections Clients connected right now
+	# TYPE server_connections gauge
+	server_connections 3
+	# HELP server_request_duration_seconds Time to handle a request
+	# TYPE server_request_duration_seconds histogram
+	server_request_duration_seconds_bucket{le="0.005"} 12    <- Cumulative: everything up to 5ms
+	server_request_duration_seconds_bucket{le="+Inf"} 14
+	server_request_duration_seconds_sum 0.041
+	server_request_duration_seconds_count 14
+*/
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::io::{ self, BufRead, BufReader, Write };
+use std::net::{ SocketAddr, TcpListener, TcpStream };
+use std::sync::atomic::{ AtomicI64, AtomicU64, Ordering };
+use std::sync::{ Arc, Mutex, MutexGuard };
+use std::thread::{ self, JoinHandle };
+use std::time::{ Duration, Instant };
+
+use crate::shutdown::CancellationToken;
+
+// The usual Prometheus buckets, in seconds: good for request durations
+pub const DEFAULT_BUCKETS: [f64; 11] = [0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+// 'count' buckets, each 'factor' times the one before, e.g. exponential_buckets( 0.000001, 4.0, 10 ) goes from 1µs to ~0.26s
+pub fn exponential_buckets( start: f64, factor: f64, count: usize ) -> Vec<f64> {
+	return ( 0..count ).map( |i| start * factor.powi( i as i32 ) ).collect();
+}
+
+// Handles. Clones update the same number, and a Default one isn't in any registry (handy when metrics are optional)
+
+#[derive( Clone, Default, Debug )]
+pub struct Counter( Arc<AtomicU64> );
+
+impl Counter {
+	pub fn inc( &self ) {
+		self.add( 1 );
+	}
+
+	pub fn add( &self, n: u64 ) {
+		self.0.fetch_add( n, Ordering::Relaxed );
+	}
+
+	pub fn get( &self ) -> u64 {
+		return self.0.load( Ordering::Relaxed );
+	}
+}
+
+#[derive( Clone, Default, Debug )]
+pub struct Gauge( Arc<AtomicI64> );
+
+impl Gauge {
+	pub fn set( &self, value: i64 ) {
+		self.0.store( value, Ordering::Relaxed );
+	}
+
+	pub fn inc( &self ) {
+		self.add( 1 );
+	}
+
+	pub fn dec( &self ) {
+		self.add( -1 );
+	}
+
+	pub fn add( &self, n: i64 ) {
+		self.0.fetch_add( n, Ordering::Relaxed );
+	}
+
+	pub fn get( &self ) -> i64 {
+		return self.0.load( Ordering::Relaxed );
+	}
+}
+
+#[derive( Debug )]
+struct Buckets {
+	bounds: Vec<f64>,     // Upper bounds, ascending. The +Inf bucket is implied
+	counts: Vec<AtomicU64>, // Per bucket, not cumulative (one more than 'bounds', for +Inf)
+	sum: AtomicU64,       // f64 bits
+}
+
+#[derive( Clone, Debug )]
+pub struct Histogram( Arc<Buckets> );
+
+impl Default for Histogram {
+	fn default() -> Self {
+		return Histogram::new( &DEFAULT_BUCKETS );
+	}
+}
+
+impl Histogram {
+	// Panics if the bounds aren't ascending
+	fn new( bounds: &[f64] ) -> Self {
+		assert! ( bounds.windows( 2 ).all( |w| w[0] < w[1] ), "Histogram buckets must be in ascending order" );
+
+		let counts = ( 0..=bounds.len() ).map( |_| AtomicU64::new( 0 ) ).collect();
+
+		Self( Arc::new( Buckets { bounds: bounds.to_vec(), counts, sum: AtomicU64::new( 0f64.to_bits() ) } ) )
+	}
+
+	pub fn observe( &self, value: f64 ) {
+		let b = &self.0;
+		let index = b.bounds.iter().position( |&bound| value <= bound ).unwrap_or( b.bounds.len() );
+
+		b.counts[index].fetch_add( 1, Ordering::Relaxed );
+		let _ = b.sum.fetch_update( Ordering::Relaxed, Ordering::Relaxed, |bits| Some( ( f64::from_bits( bits ) + value ).to_bits() ) );
+	}
+
+	// In seconds, the Prometheus unit for time
+	pub fn observe_duration( &self, took: Duration ) {
+		self.observe( took.as_secs_f64() );
+	}
+
+	pub fn count( &self ) -> u64 {
+		return self.0.counts.iter().map( |c| c.load( Ordering::Relaxed ) ).sum();
+	}
+
+	pub fn sum( &self ) -> f64 {
+		return f64::from_bits( self.0.sum.load( Ordering::Relaxed ) );
+	}
+}
+
+// Locks the mutex, recording how long that took (contention shows up as a long tail)
+pub fn timed_lock<'a, T>( mutex: &'a Mutex<T>, wait: &Histogram ) -> MutexGuard<'a, T> {
+	let start = Instant::now();
+	let guard = mutex.lock().unwrap();
+	wait.observe_duration( start.elapsed() );
+
+	return guard;
+}
+
+// Registry
+
+#[derive( Debug, Clone, Copy, PartialEq, Eq )]
+enum Kind {
+	Counter,
+	Gauge,
+	Histogram,
+}
+
+impl Kind {
+	fn name( &self ) -> &'static str {
+		match self {
+			Kind::Counter => "counter",
+			Kind::Gauge => "gauge",
+			Kind::Histogram => "histogram",
+		}
+	}
+}
+
+enum Metric {
+	Counter( Counter ),
+	Gauge( Gauge ),
+	Histogram( Histogram ),
+}
+
+type Labels = Vec<( String, String )>;
+
+struct Family {
+	help: String,
+	kind: Kind,
+	series: BTreeMap<Labels, Metric>,
+}
+
+// Cheap to clone: clones share the same metrics
+#[derive( Clone, Default )]
+pub struct Registry {
+	families: Arc<Mutex<BTreeMap<String, Family>>>,
+}
+
+impl Registry {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn counter( &self, name: &str, help: &str ) -> Counter {
+		return self.counter_with( name, help, &[] );
+	}
+
+	// Asking again for the same name and labels returns the same counter
+	pub fn counter_with( &self, name: &str, help: &str, labels: &[( &str, &str )] ) -> Counter {
+		match self.register( name, help, Kind::Counter, labels, || Metric::Counter( Counter::default() ) ) {
+			Metric::Counter( c ) => return c,
+			_ => unreachable! (),
+		}
+	}
+
+	pub fn gauge( &self, name: &str, help: &str ) -> Gauge {
+		return self.gauge_with( name, help, &[] );
+	}
+
+	pub fn gauge_with( &self, name: &str, help: &str, labels: &[( &str, &str )] ) -> Gauge {
+		match self.register( name, help, Kind::Gauge, labels, || Metric::Gauge( Gauge::default() ) ) {
+			Metric::Gauge( g ) => return g,
+			_ => unreachable! (),
+		}
+	}
+
+	pub fn histogram( &self, name: &str, help: &str, buckets: &[f64] ) -> Histogram {
+		return self.histogram_with( name, help, buckets, &[] );
+	}
+
+	// The buckets only count the first time a series is registered
+	pub fn histogram_with( &self, name: &str, help: &str, buckets: &[f64], labels: &[( &str, &str )] ) -> Histogram {
+		match self.register( name, help, Kind::Histogram, labels, || Metric::Histogram( Histogram::new( buckets ) ) ) {
+			Metric::Histogram( h ) => return h,
+			_ => unreachable! (),
+		}
+	}
+
+	// Panics on names Prometheus wouldn't accept, or a name already registered as another kind: both are bugs, not runtime conditions
+	fn register( &self, name: &str, help: &str, kind: Kind, labels: &[( &str, &str )], make: impl FnOnce() -> Metric ) -> Metric {
+		assert! ( is_valid_name( name, true ), "Invalid metric name {name:?}" );
+
+		for ( label, _ ) in labels {
+			assert! ( is_valid_name( label, false ) && !label.starts_with( "__" ), "Invalid label name {label:?}" );
+		}
+
+		let mut families = self.families.lock().unwrap();
+		let family = families.entry( name.to_string() ).or_insert_with( || Family { help: help.to_string(), kind, series: BTreeMap::new() } );
+
+		assert! ( family.kind == kind, "{name} is already registered as a {}", family.kind.name() );
+
+		let key: Labels = labels.iter().map( |( k, v )| ( k.to_string(), v.to_string() ) ).collect();
+
+		return match family.series.entry( key ).or_insert_with( make ) {
+			Metric::Counter( c ) => Metric::Counter( c.clone() ),
+			Metric::Gauge( g ) => Metric::Gauge( g.clone() ),
+			Metric::Histogram( h ) => Metric::Histogram( h.clone() ),
+		};
+	}
+
+	// Everything, in the Prometheus text format (version 0.0.4), families sorted by name
+	pub fn render( &self ) -> String {
+		let families = self.families.lock().unwrap();
+		let mut out = String::new();
+
+		for ( name, family ) in families.iter() {
+			let _ = writeln! ( out, "# HELP {name} {}", escape( &family.help, false ) );
+			let _ = writeln! ( out, "# TYPE {name} {}", family.kind.name() );
+
+			for ( labels, metric ) in &family.series {
+				match metric {
+					Metric::Counter( c ) => { let _ = writeln! ( out, "{name}{} {}", format_labels( labels, None ), c.get() ); },
+					Metric::Gauge( g ) => { let _ = writeln! ( out, "{name}{} {}", format_labels( labels, None ), g.get() ); },
+					Metric::Histogram( h ) => {
+						// Buckets are read once, so the cumulative counts and _count agree even while observations come in
+						let counts: Vec<u64> = h.0.counts.iter().map( |c| c.load( Ordering::Relaxed ) ).collect();
+						let mut cumulative = 0;
+
+						for ( i, count ) in counts.iter().enumerate() {
+							cumulative += count;
+
+							let le = h.0.bounds.get( i ).map_or( "+Inf".to_string(), |b| b.to_string() );
+							let _ = writeln! ( out, "{name}_bucket{} {cumulative}", format_labels( labels, Some( &le ) ) );
+						}
+
+						let _ = writeln! ( out, "{name}_sum{} {}", format_labels( labels, None ), h.sum() );
+						let _ = writeln! ( out, "{name}_count{} {cumulative}", format_labels( labels, None ) );
+					},
+				}
+			}
+		}
+
+		return out;
+	}
+}
+
+// Metric names may contain ':', label names may not
+fn is_valid_name( name: &str, colons: bool ) -> bool {
+	let valid = |c: char, first: bool| c.is_ascii_alphabetic() || c == '_' || ( colons && c == ':' ) || ( !first && c.is_ascii_digit() );
+	let mut chars = name.chars();
+
+	return chars.next().is_some_and( |c| valid( c, true ) ) && chars.all( |c| valid( c, false ) );
+}
+
+// Backslashes and newlines always, double quotes only inside label values
+fn escape( text: &str, quotes: bool ) -> String {
+	let mut out = String::with_capacity( text.len() );
+
+	for c in text.chars() {
+		match c {
+			'\\' => out.push_str( "\\\\" ),
+			'\n' => out.push_str( "\\n" ),
+			'"' if quotes => out.push_str( "\\\"" ),
+			c => out.push( c ),
+		}
+	}
+
+	return out;
+}
+
+fn format_labels( labels: &Labels, le: Option<&str> ) -> String {
+	let mut pairs: Vec<String> = labels.iter().map( |( k, v )| format! ( "{k}=\"{}\"", escape( v, true ) ) ).collect();
+
+	if let Some( le ) = le {
+		pairs.push( format! ( "le=\"{le}\"" ) );
+	}
+
+	return match pairs.is_empty() {
+		true => String::new(),
+		false => format! ( "{{{}}}", pairs.join( "," ) ),
+	};
+}
+
+/*
+The HTTP endpoint.
+
+- Prometheus "scrapes" metrics: every few seconds it sends 'GET /metrics' and reads the text back. That's all the HTTP needed, so it's written by hand: read the request line, skip the headers, answer, close.
+- One request at a time on one thread. A scrape is a few KB, and anything slower (a client that connects and says nothing) is cut off by a read timeout.
+*/
+
+pub struct Endpoint {
+	local_addr: SocketAddr,
+	stop: CancellationToken,
+	thread: Option<JoinHandle<()>>,
+}
+
+// Port 0 picks a free port, see local_addr()
+pub fn serve( addr: SocketAddr, registry: Registry ) -> io::Result<Endpoint> {
+	let listener = TcpListener::bind( addr )?;
+	let local_addr = listener.local_addr()?;
+	let stop = CancellationToken::new();
+	let token = stop.clone();
+
+	let thread = thread::Builder::new().name( "metrics".to_string() ).spawn( move || {
+		for stream in listener.incoming() {
+			if token.is_cancelled() {
+				break;
+			}
+
+			if let Ok( stream ) = stream {
+				let _ = answer( stream, &registry );
+			}
+		}
+	})?;
+
+	return Ok( Endpoint { local_addr, stop, thread: Some( thread ) } );
+}
+
+impl Endpoint {
+	pub fn local_addr( &self ) -> SocketAddr {
+		return self.local_addr;
+	}
+
+	pub fn stop( &mut self ) {
+		let Some( thread ) = self.thread.take() else { return };
+
+		// Same trick as Server::stop(): connecting wakes up accept(), which then sees the token
+		self.stop.cancel();
+		let _ = TcpStream::connect_timeout( &self.local_addr, Duration::from_secs( 1 ) );
+		let _ = thread.join();
+	}
+}
+
+impl Drop for Endpoint {
+	fn drop( &mut self ) {
+		self.stop();
+	}
+}
+
+fn answer( stream: TcpStream, registry: &Registry ) -> io::Result<()> {
+	stream.set_read_timeout( Some( Duration::from_secs( 2 ) ) )?;
+	stream.set_write_timeout( Some( Duration::from_secs( 2 ) ) )?;
+
+	let mut reader = BufReader::new( stream.try_clone()? );
+	let mut request = String::new();
+	reader.read_line( &mut request )?;
+
+	// Headers end with an empty line. None of them matter here
+	let mut header = String::new();
+
+	while reader.read_line( &mut header )? > 0 && !header.trim_end().is_empty() {
+		header.clear();
+	}
+
+	let mut parts = request.split_whitespace();
+
+	let ( status, content_type, body ) = match ( parts.next(), parts.next().map( |p| p.split( '?' ).next().unwrap_or( p ) ) ) {
+		( Some( "GET" ), Some( "/metrics" ) ) => ( "200 OK", "text/plain; version=0.0.4; charset=utf-8", registry.render() ),
+		( Some( "GET" ), _ ) => ( "404 Not Found", "text/plain; charset=utf-8", "Try /metrics\n".to_string() ),
+		_ => ( "405 Method Not Allowed", "text/plain; charset=utf-8", "Only GET\n".to_string() ),
+	};
+
+	let mut writer = stream;
+	let head = format! ( "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n", body.len() );
+
+	writer.write_all( head.as_bytes() )?;
+	writer.write_all( body.as_bytes() )?;
+
+	return writer.flush();
+}
@@ -0,0 +1,160 @@
+// Closure pipelines and middleware chains
+
+/*
+Pipelines.
+
+- Functions that take closures and return new closures ("combinators") let small steps be glued into bigger ones:
+	* pipe( f, g )      x -> g( f( x ) )   (left to right, like a shell pipe)
+	* compose( f, g )   x -> f( g( x ) )   (right to left, like maths)
+	* tap( f )          x -> x, calling f( &x ) on the way (logging, debugging)
+	* map_err( f, m )   Like f, but errors are converted with m
+	* and_then( f, g )  Like f, then g on the Ok value. The first Err stops the pipeline
+- They return 'impl Fn', so there is no boxing and the compiler can inline the whole chain.
+
+Middleware.
+
+- A middleware is a closure 'Fn( Req, Next ) -> Resp' wrapped around a handler. It can look at (or change) the request, decide whether to call 'next.run( req )' at all, and look at (or change) the response.
+- This keeps cross-cutting code (logging, authorization, timing) out of the handlers themselves:
+
+	logging -> auth -> timing -> handler
+	logging <- auth <- timing <- handler
+
+- A 'Chain' runs its middleware in the order they were added, the first one being the outermost.
+*/
+
+use std::fmt::Debug;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+// Combinators
+
+pub fn pipe<A, B, C>( f: impl Fn( A ) -> B, g: impl Fn( B ) -> C ) -> impl Fn( A ) -> C {
+	return move |x| g( f( x ) );
+}
+
+pub fn compose<A, B, C>( f: impl Fn( B ) -> C, g: impl Fn( A ) -> B ) -> impl Fn( A ) -> C {
+	return move |x| f( g( x ) );
+}
+
+pub fn tap<A>( f: impl Fn( &A ) ) -> impl Fn( A ) -> A {
+	return move |x| {
+		f( &x );
+		return x;
+	};
+}
+
+pub fn map_err<A, B, E, F>( f: impl Fn( A ) -> Result<B, E>, m: impl Fn( E ) -> F ) -> impl Fn( A ) -> Result<B, F> {
+	return move |x| f( x ).map_err( &m );
+}
+
+pub fn and_then<A, B, C, E>( f: impl Fn( A ) -> Result<B, E>, g: impl Fn( B ) -> Result<C, E> ) -> impl Fn( A ) -> Result<C, E> {
+	return move |x| f( x ).and_then( &g );
+}
+
+// Middleware
+
+pub type Middleware<Req, Resp> = Arc<dyn for<'a> Fn( Req, Next<'a, Req, Resp> ) -> Resp + Send + Sync>;
+
+// What's left of the chain after the current middleware, ending in the handler
+pub struct Next<'a, Req, Resp> {
+	rest: &'a [Middleware<Req, Resp>],
+	handler: &'a ( dyn Fn( Req ) -> Resp + Send + Sync ),
+}
+
+impl<Req, Resp> Next<'_, Req, Resp> {
+	pub fn run( self, req: Req ) -> Resp {
+		match self.rest.split_first() {
+			Some( ( first, rest ) ) => first( req, Next { rest, handler: self.handler } ),
+			None => ( self.handler )( req ),
+		}
+	}
+}
+
+pub struct Chain<Req, Resp> {
+	middleware: Vec<Middleware<Req, Resp>>,
+}
+
+impl<Req, Resp> Clone for Chain<Req, Resp> {
+	fn clone( &self ) -> Self {
+		Self { middleware: self.middleware.clone() }
+	}
+}
+
+impl<Req, Resp> Default for Chain<Req, Resp> {
+	fn default() -> Self {
+		Self { middleware: Vec::new() }
+	}
+}
+
+impl<Req, Resp> Chain<Req, Resp> {
+	pub fn new() -> Self {
+		return Self::default();
+	}
+
+	pub fn with( mut self, middleware: impl Fn( Req, Next<'_, Req, Resp> ) -> Resp + Send + Sync + 'static ) -> Self {
+		self.middleware.push( Arc::new( middleware ) );
+		self
+	}
+
+	// Appends another chain's middleware, which then runs inside this chain's
+	pub fn then( mut self, inner: Chain<Req, Resp> ) -> Self {
+		self.middleware.extend( inner.middleware );
+		self
+	}
+
+	pub fn len( &self ) -> usize {
+		return self.middleware.len();
+	}
+
+	pub fn is_empty( &self ) -> bool {
+		return self.middleware.is_empty();
+	}
+
+	pub fn run( &self, req: Req, handler: &( dyn Fn( Req ) -> Resp + Send + Sync ) ) -> Resp {
+		return Next { rest: &self.middleware, handler }.run( req );
+	}
+
+	// Bakes the chain and the handler into a single closure (cheap to clone into threads)
+	pub fn handler( &self, handler: impl Fn( Req ) -> Resp + Send + Sync + 'static ) -> impl Fn( Req ) -> Resp + Send + Sync + 'static
+	where
+		Req: 'static,
+		Resp: 'static,
+	{
+		let chain = self.clone();
+
+		return move |req| chain.run( req, &handler );
+	}
+}
+
+// Built-in middleware. The sinks receive the output, e.g. '|line| println! ( "{}", line )'
+
+pub fn logging<Req: Debug, Resp: Debug>( sink: impl Fn( String ) + Send + Sync + 'static ) -> impl Fn( Req, Next<'_, Req, Resp> ) -> Resp + Send + Sync + 'static {
+	return move |req, next| {
+		sink( format! ( "--> {:?}", req ) );
+		let resp = next.run( req );
+		sink( format! ( "<-- {:?}", resp ) );
+
+		return resp;
+	};
+}
+
+// Requests failing the check never reach the rest of the chain, 'deny' builds their response instead
+pub fn auth<Req, Resp>( check: impl Fn( &Req ) -> bool + Send + Sync + 'static, deny: impl Fn( &Req ) -> Resp + Send + Sync + 'static ) -> impl Fn( Req, Next<'_, Req, Resp> ) -> Resp + Send + Sync + 'static {
+	return move |req, next| {
+		if check( &req ) {
+			return next.run( req );
+		}
+
+		return deny( &req );
+	};
+}
+
+pub fn timing<Req, Resp>( sink: impl Fn( Duration ) + Send + Sync + 'static ) -> impl Fn( Req, Next<'_, Req, Resp> ) -> Resp + Send + Sync + 'static {
+	return move |req, next| {
+		let start = Instant::now();
+		let resp = next.run( req );
+		sink( start.elapsed() );
+
+		return resp;
+	};
+}
@@ -0,0 +1,410 @@
+// Host and platform introspection
+
+/*
+Platform.
+
+- 'helpers::fn_for_Linux' shows how '#[cfg( target_os = "linux" )]' works, this module puts it to use.
+- On Linux, everything is read from the pseudo-filesystems the kernel exposes:
+	* /etc/os-release         -> OsRelease
+	* /proc/sys/kernel/...    -> KernelInfo
+	* /proc/cpuinfo and /sys/devices/system/cpu/online -> CpuInfo
+	* /proc/meminfo           -> MemoryInfo
+	* /proc/uptime            -> Duration
+	* /proc/loadavg           -> LoadAverage
+	* /proc/self/stat, status -> ProcessUsage
+- The parsing functions ('parse_*') only take strings, so they work on every OS. Only the functions reading the files are cfg-gated.
+- On other targets, the readers are stubs that return an Err( String ).
+*/
+
+use std::fmt;
+use std::time::Duration;
+
+// Clock ticks per second used in /proc/<pid>/stat. USER_HZ is 100 on every mainstream Linux architecture, and reading the real value needs sysconf() (i.e. libc)
+const CLOCK_TICKS_PER_SEC: u64 = 100;
+
+#[derive( Debug, Clone, Default, PartialEq )]
+pub struct OsRelease {
+	pub id: String,
+	pub name: String,
+	pub version: String,
+	pub version_id: String,
+	pub pretty_name: String,
+}
+
+#[derive( Debug, Clone, Default, PartialEq )]
+pub struct KernelInfo {
+	pub os_type: String, // "Linux"
+	pub release: String, // e.g. "6.1.0-18-amd64"
+	pub version: String, // Build string, e.g. "#1 SMP PREEMPT_DYNAMIC Debian 6.1.76-1"
+	pub hostname: String,
+}
+
+#[derive( Debug, Clone, Default, PartialEq )]
+pub struct CpuInfo {
+	pub model: String,
+	pub logical_cpus: usize, // "processor" entries in /proc/cpuinfo
+	pub online_cpus: usize, // CPUs listed in /sys/devices/system/cpu/online
+}
+
+// All sizes are in bytes (/proc/meminfo reports kB)
+#[derive( Debug, Clone, Copy, Default, PartialEq )]
+pub struct MemoryInfo {
+	pub total: u64,
+	pub free: u64,
+	pub available: u64,
+	pub swap_total: u64,
+	pub swap_free: u64,
+}
+
+impl MemoryInfo {
+	pub fn used( &self ) -> u64 {
+		return self.total.saturating_sub( self.available );
+	}
+}
+
+#[derive( Debug, Clone, Copy, Default, PartialEq )]
+pub struct LoadAverage {
+	pub one: f64,
+	pub five: f64,
+	pub fifteen: f64,
+	pub running: u32, // Currently runnable scheduling entities
+	pub total: u32, // Existing scheduling entities
+}
+
+#[derive( Debug, Clone, Copy, Default, PartialEq )]
+pub struct ProcessUsage {
+	pub pid: u32,
+	pub user_time: Duration,
+	pub system_time: Duration,
+	pub threads: u32,
+	pub virtual_memory: u64, // Bytes
+	pub resident_memory: u64, // Bytes (VmRSS)
+	pub peak_resident_memory: u64, // Bytes (VmHWM)
+	pub voluntary_switches: u64,
+	pub involuntary_switches: u64,
+}
+
+// Parsers (OS independent)
+
+fn unquote( value: &str ) -> String {
+	let v = value.trim();
+
+	if v.len() >= 2 && ( ( v.starts_with( '"' ) && v.ends_with( '"' ) ) || ( v.starts_with( '\'' ) && v.ends_with( '\'' ) ) ) {
+		return v[1..v.len() - 1].replace( "\\\"", "\"" );
+	}
+
+	return v.to_string();
+}
+
+pub fn parse_os_release( text: &str ) -> OsRelease {
+	let mut release = OsRelease::default();
+
+	for line in text.lines() {
+		let Some( ( key, value ) ) = line.split_once( '=' ) else { continue };
+		let value = unquote( value );
+
+		match key.trim() {
+			"ID" => release.id = value,
+			"NAME" => release.name = value,
+			"VERSION" => release.version = value,
+			"VERSION_ID" => release.version_id = value,
+			"PRETTY_NAME" => release.pretty_name = value,
+			_ => {}
+		}
+	}
+
+	return release;
+}
+
+pub fn parse_cpuinfo( text: &str ) -> ( String, usize ) {
+	let mut model = String::new();
+	let mut count: usize = 0;
+
+	for line in text.lines() {
+		let Some( ( key, value ) ) = line.split_once( ':' ) else { continue };
+
+		match key.trim() {
+			"processor" => count += 1,
+			// x86 uses "model name", some ARM kernels only have "Model" or "Hardware"
+			"model name" | "Model" | "Hardware" | "cpu model" if model.is_empty() => model = value.trim().to_string(),
+			_ => {}
+		}
+	}
+
+	return ( model, count );
+}
+
+// Parses a kernel CPU list like "0-3,5,7-8" into the number of CPUs it contains
+pub fn parse_cpu_list( text: &str ) -> Result<usize, String> {
+	let mut count: usize = 0;
+
+	for part in text.trim().split( ',' ).filter( |p| !p.is_empty() ) {
+		let bad = || format! ( "Invalid CPU list: '{}'", text.trim() );
+
+		match part.split_once( '-' ) {
+			Some( ( a, b ) ) => {
+				let a: usize = a.parse().map_err( |_| bad() )?;
+				let b: usize = b.parse().map_err( |_| bad() )?;
+
+				if b < a {
+					return Err( bad() );
+				}
+
+				count += b - a + 1;
+			},
+			None => {
+				part.parse::<usize>().map_err( |_| bad() )?;
+				count += 1;
+			}
+		}
+	}
+
+	return Ok( count );
+}
+
+pub fn parse_meminfo( text: &str ) -> Result<MemoryInfo, String> {
+	let mut info = MemoryInfo::default();
+	let mut found_total = false;
+
+	for line in text.lines() {
+		let Some( ( key, value ) ) = line.split_once( ':' ) else { continue };
+		let kb: u64 = match value.split_whitespace().next().and_then( |v| v.parse().ok() ) {
+			Some( v ) => v,
+			None => continue
+		};
+		let bytes = kb * 1024;
+
+		match key {
+			"MemTotal" => { info.total = bytes; found_total = true; },
+			"MemFree" => info.free = bytes,
+			"MemAvailable" => info.available = bytes,
+			"SwapTotal" => info.swap_total = bytes,
+			"SwapFree" => info.swap_free = bytes,
+			_ => {}
+		}
+	}
+
+	if !found_total {
+		return Err( "MemTotal missing from meminfo".to_string() );
+	}
+
+	// Kernels older than 3.14 have no MemAvailable
+	if info.available == 0 {
+		info.available = info.free;
+	}
+
+	return Ok( info );
+}
+
+pub fn parse_uptime( text: &str ) -> Result<Duration, String> {
+	let secs: f64 = text
+		.split_whitespace()
+		.next()
+		.and_then( |v| v.parse().ok() )
+		.ok_or( format! ( "Invalid uptime: '{}'", text.trim() ) )?;
+
+	return Ok( Duration::from_secs_f64( secs ) );
+}
+
+pub fn parse_loadavg( text: &str ) -> Result<LoadAverage, String> {
+	let bad = || format! ( "Invalid loadavg: '{}'", text.trim() );
+	let parts: Vec<&str> = text.split_whitespace().collect();
+
+	if parts.len() < 4 {
+		return Err( bad() );
+	}
+
+	let ( running, total ) = parts[3].split_once( '/' ).ok_or( bad() )?;
+
+	return Ok( LoadAverage {
+		one: parts[0].parse().map_err( |_| bad() )?,
+		five: parts[1].parse().map_err( |_| bad() )?,
+		fifteen: parts[2].parse().map_err( |_| bad() )?,
+		running: running.parse().map_err( |_| bad() )?,
+		total: total.parse().map_err( |_| bad() )?,
+	});
+}
+
+/*
+- /proc/<pid>/stat is a single line, but field 2 is the command name in parentheses, which can contain spaces (and parentheses), so we split after the LAST ')'.
+- /proc/<pid>/status adds the RSS, peak RSS and context switches. Stat has an RSS too, but in pages, and the page size (4K, 16K or 64K depending on the kernel) needs sysconf() as well.
+*/
+pub fn parse_process_usage( stat: &str, status: &str ) -> Result<ProcessUsage, String> {
+	let bad = || "Invalid /proc/<pid>/stat contents".to_string();
+	let ( head, rest ) = stat.rsplit_once( ')' ).ok_or( bad() )?;
+	let pid: u32 = head.split( '(' ).next().unwrap_or( "" ).trim().parse().map_err( |_| bad() )?;
+
+	// 'fields[0]' is field 3 (state) of the man page, so field N is at index N - 3
+	let fields: Vec<&str> = rest.split_whitespace().collect();
+	let field = |n: usize| -> Result<u64, String> {
+		return fields.get( n - 3 ).and_then( |v| v.parse().ok() ).ok_or( bad() );
+	};
+
+	let mut usage = ProcessUsage {
+		pid,
+		user_time: Duration::from_millis( field( 14 )? * 1000 / CLOCK_TICKS_PER_SEC ),
+		system_time: Duration::from_millis( field( 15 )? * 1000 / CLOCK_TICKS_PER_SEC ),
+		threads: field( 20 )? as u32,
+		virtual_memory: field( 23 )?,
+		..ProcessUsage::default()
+	};
+
+	for line in status.lines() {
+		let Some( ( key, value ) ) = line.split_once( ':' ) else { continue };
+		let number: u64 = match value.split_whitespace().next().and_then( |v| v.parse().ok() ) {
+			Some( v ) => v,
+			None => continue
+		};
+
+		match key {
+			"VmHWM" => usage.peak_resident_memory = number * 1024,
+			"VmRSS" => usage.resident_memory = number * 1024, // Missing for kernel threads, which have no memory of their own
+			"voluntary_ctxt_switches" => usage.voluntary_switches = number,
+			"nonvoluntary_ctxt_switches" => usage.involuntary_switches = number,
+			_ => {}
+		}
+	}
+
+	return Ok( usage );
+}
+
+// Readers (Linux)
+
+#[cfg( target_os = "linux" )]
+mod imp {
+	use super::*;
+
+	fn read( path: &str ) -> Result<String, String> {
+		return std::fs::read_to_string( path ).map_err( |e| format! ( "Cannot read {}: {}", path, e ) );
+	}
+
+	fn read_trimmed( path: &str ) -> Result<String, String> {
+		return read( path ).map( |s| s.trim().to_string() );
+	}
+
+	pub fn os_release() -> Result<OsRelease, String> {
+		// /etc/os-release is the standard location, /usr/lib/os-release is the fallback
+		let text = read( "/etc/os-release" ).or_else( |_| read( "/usr/lib/os-release" ) )?;
+
+		return Ok( parse_os_release( &text ) );
+	}
+
+	pub fn kernel() -> Result<KernelInfo, String> {
+		return Ok( KernelInfo {
+			os_type: read_trimmed( "/proc/sys/kernel/ostype" )?,
+			release: read_trimmed( "/proc/sys/kernel/osrelease" )?,
+			version: read_trimmed( "/proc/sys/kernel/version" )?,
+			hostname: read_trimmed( "/proc/sys/kernel/hostname" )?,
+		});
+	}
+
+	pub fn cpu() -> Result<CpuInfo, String> {
+		let ( model, logical_cpus ) = parse_cpuinfo( &read( "/proc/cpuinfo" )? );
+		let online_cpus = match read( "/sys/devices/system/cpu/online" ) {
+			Ok( list ) => parse_cpu_list( &list )?,
+			Err( _ ) => logical_cpus // /sys may not be mounted (e.g. in some containers)
+		};
+
+		return Ok( CpuInfo { model, logical_cpus, online_cpus } );
+	}
+
+	pub fn memory() -> Result<MemoryInfo, String> {
+		return parse_meminfo( &read( "/proc/meminfo" )? );
+	}
+
+	pub fn uptime() -> Result<Duration, String> {
+		return parse_uptime( &read( "/proc/uptime" )? );
+	}
+
+	pub fn load_average() -> Result<LoadAverage, String> {
+		return parse_loadavg( &read( "/proc/loadavg" )? );
+	}
+
+	pub fn process_usage() -> Result<ProcessUsage, String> {
+		return parse_process_usage( &read( "/proc/self/stat" )?, &read( "/proc/self/status" )? );
+	}
+}
+
+// Stubs (other targets)
+
+#[cfg( not( target_os = "linux" ) )]
+mod imp {
+	use super::*;
+
+	fn unsupported<T>() -> Result<T, String> {
+		return Err( format! ( "Platform introspection is not supported on {}", std::env::consts::OS ) );
+	}
+
+	pub fn os_release() -> Result<OsRelease, String> { unsupported() }
+	pub fn kernel() -> Result<KernelInfo, String> { unsupported() }
+	pub fn cpu() -> Result<CpuInfo, String> { unsupported() }
+	pub fn memory() -> Result<MemoryInfo, String> { unsupported() }
+	pub fn uptime() -> Result<Duration, String> { unsupported() }
+	pub fn load_average() -> Result<LoadAverage, String> { unsupported() }
+	pub fn process_usage() -> Result<ProcessUsage, String> { unsupported() }
+}
+
+pub use imp::{cpu, kernel, load_average, memory, os_release, process_usage, uptime};
+
+// A snapshot of everything above (each part can fail independently)
+#[derive( Debug, Clone )]
+pub struct HostInfo {
+	pub os: Result<OsRelease, String>,
+	pub kernel: Result<KernelInfo, String>,
+	pub cpu: Result<CpuInfo, String>,
+	pub memory: Result<MemoryInfo, String>,
+	pub uptime: Result<Duration, String>,
+	pub load: Result<LoadAverage, String>,
+	pub process: Result<ProcessUsage, String>,
+}
+
+impl HostInfo {
+	pub fn collect() -> HostInfo {
+		return HostInfo {
+			os: os_release(),
+			kernel: kernel(),
+			cpu: cpu(),
+			memory: memory(),
+			uptime: uptime(),
+			load: load_average(),
+			process: process_usage(),
+		};
+	}
+}
+
+fn mib( bytes: u64 ) -> f64 {
+	return bytes as f64 / ( 1024.0 * 1024.0 );
+}
+
+impl fmt::Display for HostInfo {
+	fn fmt( &self, f: &mut fmt::Formatter<'_> ) -> fmt::Result {
+		match &self.os {
+			Ok( os ) => writeln! ( f, "OS:      {}", os.pretty_name )?,
+			Err( e ) => writeln! ( f, "OS:      {}", e )?,
+		}
+		match &self.kernel {
+			Ok( k ) => writeln! ( f, "Kernel:  {} {} ({})", k.os_type, k.release, k.hostname )?,
+			Err( e ) => writeln! ( f, "Kernel:  {}", e )?,
+		}
+		match &self.cpu {
+			Ok( c ) => writeln! ( f, "CPU:     {} x {} ({} online)", c.logical_cpus, c.model, c.online_cpus )?,
+			Err( e ) => writeln! ( f, "CPU:     {}", e )?,
+		}
+		match &self.memory {
+			Ok( m ) => writeln! ( f, "Memory:  {:.0} / {:.0} MiB used", mib( m.used() ), mib( m.total ) )?,
+			Err( e ) => writeln! ( f, "Memory:  {}", e )?,
+		}
+		match &self.uptime {
+			Ok( u ) => writeln! ( f, "Uptime:  {}s", u.as_secs() )?,
+			Err( e ) => writeln! ( f, "Uptime:  {}", e )?,
+		}
+		match &self.load {
+			Ok( l ) => writeln! ( f, "Load:    {:.2} {:.2} {:.2}", l.one, l.five, l.fifteen )?,
+			Err( e ) => writeln! ( f, "Load:    {}", e )?,
+		}
+		match &self.process {
+			Ok( p ) => write! ( f, "Process: pid {}, {} threads, {:.1} MiB RSS, {:?} user / {:?} sys", p.pid, p.threads, mib( p.resident_memory ), p.user_time, p.system_time ),
+			Err( e ) => write! ( f, "Process: {}", e ),
+		}
+	}
+}
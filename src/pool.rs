@@ -0,0 +1,279 @@
+// Fixed-size thread pool
+
+/*
+Thread pool.
+
+- 'thread::spawn' starts a brand new OS thread for every task (~10-20 µs and its own stack each), and dropping its JoinHandle means nobody ever hears how the task went.
+- A pool starts a fixed number of worker threads once. Jobs go into a queue, and each worker takes the next job whenever it's free:
+
+	execute( job ) --> [ job | job | job ] --> worker 1
+	                                      \--> worker 2
+
+- 'execute( f )' queues a job and forgets about it. 'spawn( f )' also returns a 'JobHandle', whose 'join()' waits for the job's return value.
+- A job that panics doesn't take its worker down: the panic is caught, counted in the metrics, and handed to the job's JoinHandle as an Err (with the panic message).
+- 'join()' waits until every queued job has run, and the pool can be used again after. 'shutdown()' (also run on drop) stops taking jobs, lets the queued ones finish, then joins the workers.
+- 'with_shutdown( &signal )' hooks the pool up to a ShutdownSignal (src/shutdown.rs): running jobs are tracked in it, and when it fires the queued jobs are dropped (counted as 'cancelled') and the workers stop after their current job. Jobs that should stop early can check a clone of 'signal.token()' themselves.
+*/
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::{Arc, Condvar, Mutex, OnceLock};
+use std::thread::{self, JoinHandle};
+
+use crate::shutdown::ShutdownSignal;
+
+type Job = Box<dyn FnOnce() + Send>;
+
+#[derive( Debug, Clone, Copy, Default, PartialEq, Eq )]
+pub struct Metrics {
+	pub workers: usize,
+	pub queued: usize,
+	pub active: usize,
+	pub completed: usize, // Includes the ones that panicked
+	pub panicked: usize,
+	pub cancelled: usize, // Dropped without running, because the shutdown signal fired
+}
+
+impl fmt::Display for Metrics {
+	fn fmt( &self, f: &mut fmt::Formatter ) -> fmt::Result {
+		write! ( f, "{} workers: {} queued, {} active, {} completed ({} panicked), {} cancelled", self.workers, self.queued, self.active, self.completed, self.panicked, self.cancelled )
+	}
+}
+
+struct State {
+	queue: VecDeque<Job>,
+	active: usize,
+	completed: usize,
+	panicked: usize,
+	cancelled: usize,
+	shutdown: bool,
+}
+
+struct Shared {
+	state: Mutex<State>,
+	work: Condvar, // A job was queued, or we're shutting down
+	idle: Condvar, // The queue emptied and the last active job finished
+	signal: OnceLock<ShutdownSignal>,
+}
+
+impl Shared {
+	// Drops the queued jobs and lets the workers finish
+	fn cancel( &self ) {
+		let mut state = self.state.lock().unwrap();
+		let dropped: Vec<Job> = state.queue.drain( .. ).collect();
+
+		state.cancelled += dropped.len();
+		state.shutdown = true;
+
+		if state.active == 0 {
+			self.idle.notify_all();
+		}
+
+		drop( state );
+		drop( dropped ); // Outside the lock: a spawn()ed job's JobHandle wakes up when its job is dropped
+		self.work.notify_all();
+	}
+}
+
+pub struct ThreadPool {
+	shared: Arc<Shared>,
+	workers: Vec<JoinHandle<()>>,
+}
+
+impl ThreadPool {
+	// Panics if size is 0
+	pub fn new( size: usize ) -> Self {
+		assert! ( size > 0, "ThreadPool needs at least one worker" );
+
+		let state = State { queue: VecDeque::new(), active: 0, completed: 0, panicked: 0, cancelled: 0, shutdown: false };
+		let shared = Arc::new( Shared { state: Mutex::new( state ), work: Condvar::new(), idle: Condvar::new(), signal: OnceLock::new() } );
+
+		let workers = ( 0..size )
+			.map( |i| {
+				let shared = Arc::clone( &shared );
+
+				thread::Builder::new()
+					.name( format! ( "pool-worker-{i}" ) )
+					.spawn( move || worker( shared ) )
+					.expect( "Failed to spawn a pool worker" )
+			})
+			.collect();
+
+		Self { shared, workers }
+	}
+
+	// One worker per CPU core
+	pub fn with_available_parallelism() -> Self {
+		return Self::new( thread::available_parallelism().map( |n| n.get() ).unwrap_or( 4 ) );
+	}
+
+	// Only the first signal counts if called more than once
+	pub fn with_shutdown( self, signal: &ShutdownSignal ) -> Self {
+		if self.shared.signal.set( signal.clone() ).is_ok() {
+			let shared = Arc::downgrade( &self.shared );
+
+			signal.token().on_cancel( move || {
+				if let Some( shared ) = shared.upgrade() {
+					shared.cancel();
+				}
+			});
+		}
+
+		self
+	}
+
+	pub fn size( &self ) -> usize {
+		return self.workers.len();
+	}
+
+	// Once the shutdown signal has fired, jobs are dropped instead of queued
+	pub fn execute( &self, job: impl FnOnce() + Send + 'static ) {
+		let mut state = self.shared.state.lock().unwrap();
+
+		if state.shutdown {
+			state.cancelled += 1;
+			return;
+		}
+
+		state.queue.push_back( Box::new( job ) );
+		drop( state );
+
+		self.shared.work.notify_one();
+	}
+
+	pub fn spawn<T: Send + 'static>( &self, job: impl FnOnce() -> T + Send + 'static ) -> JobHandle<T> {
+		let ( tx, rx ) = mpsc::sync_channel( 1 );
+
+		// The panic is re-raised after sending, so the worker still counts it
+		self.execute( move || {
+			match panic::catch_unwind( AssertUnwindSafe( job ) ) {
+				Ok( value ) => {
+					let _ = tx.send( Ok( value ) ); // Nobody listening if the handle was dropped
+				},
+				Err( payload ) => {
+					let _ = tx.send( Err( panic_message( payload.as_ref() ) ) );
+					panic::resume_unwind( payload );
+				}
+			}
+		});
+
+		return JobHandle { rx, result: None };
+	}
+
+	pub fn metrics( &self ) -> Metrics {
+		let state = self.shared.state.lock().unwrap();
+
+		return Metrics { workers: self.workers.len(), queued: state.queue.len(), active: state.active, completed: state.completed, panicked: state.panicked, cancelled: state.cancelled };
+	}
+
+	// Blocks until the queue is empty and no job is running
+	pub fn join( &self ) {
+		let mut state = self.shared.state.lock().unwrap();
+
+		while !state.queue.is_empty() || state.active > 0 {
+			state = self.shared.idle.wait( state ).unwrap();
+		}
+	}
+
+	// Runs what's already queued, then stops the workers. Returns the final metrics
+	pub fn shutdown( mut self ) -> Metrics {
+		self.stop();
+
+		return self.metrics();
+	}
+
+	fn stop( &mut self ) {
+		self.shared.state.lock().unwrap().shutdown = true;
+		self.shared.work.notify_all();
+
+		for worker in self.workers.drain( .. ) {
+			let _ = worker.join();
+		}
+	}
+}
+
+impl Drop for ThreadPool {
+	fn drop( &mut self ) {
+		self.stop();
+	}
+}
+
+fn worker( shared: Arc<Shared> ) {
+	loop {
+		let job = {
+			let mut state = shared.state.lock().unwrap();
+
+			loop {
+				if let Some( job ) = state.queue.pop_front() {
+					state.active += 1;
+					break job;
+				}
+
+				if state.shutdown {
+					return;
+				}
+
+				state = shared.work.wait( state ).unwrap();
+			}
+		};
+
+		let work = shared.signal.get().map( |s| s.track( format! ( "{} job", thread::current().name().unwrap_or( "pool-worker" ) ) ) );
+		let ok = panic::catch_unwind( AssertUnwindSafe( job ) ).is_ok();
+		drop( work );
+
+		let mut state = shared.state.lock().unwrap();
+		state.active -= 1;
+		state.completed += 1;
+
+		if !ok {
+			state.panicked += 1;
+		}
+
+		if state.queue.is_empty() && state.active == 0 {
+			shared.idle.notify_all();
+		}
+	}
+}
+
+fn panic_message( payload: &( dyn std::any::Any + Send ) ) -> String {
+	if let Some( s ) = payload.downcast_ref::<&str>() {
+		return s.to_string();
+	}
+
+	if let Some( s ) = payload.downcast_ref::<String>() {
+		return s.clone();
+	}
+
+	return "Job panicked".to_string();
+}
+
+// The result of a job started with 'spawn'. Err holds the panic message if the job panicked
+pub struct JobHandle<T> {
+	rx: Receiver<Result<T, String>>,
+	result: Option<Result<T, String>>, // Kept by is_finished() until join()
+}
+
+impl<T> JobHandle<T> {
+	pub fn is_finished( &mut self ) -> bool {
+		if self.result.is_none() {
+			match self.rx.try_recv() {
+				Ok( result ) => self.result = Some( result ),
+				Err( TryRecvError::Empty ) => return false,
+				Err( TryRecvError::Disconnected ) => self.result = Some( Err( "Job was cancelled".to_string() ) ),
+			}
+		}
+
+		return true;
+	}
+
+	pub fn join( self ) -> Result<T, String> {
+		if let Some( result ) = self.result {
+			return result;
+		}
+
+		// Only disconnected if the job was dropped without running, i.e. cancelled by the shutdown signal
+		return self.rx.recv().unwrap_or_else( |_| Err( "Job was cancelled".to_string() ) );
+	}
+}
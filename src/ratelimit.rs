@@ -0,0 +1,204 @@
+// Per-key rate limiting: token bucket, leaky bucket and sliding window
+
+/*
+Rate limiting.
+
+- Caps how often each key (e.g. a client id) may do something. 'check( &key )' says Allowed, or Limited with how long to wait before trying again.
+- Three algorithms:
+	* Token bucket: a bucket of 'capacity' tokens, refilled with one token every 'refill_every'. Each request takes a token. Allows bursts of up to 'capacity', then one request per 'refill_every'.
+	* Leaky bucket: requests pour into a bucket that drains one request every 'leak_every', and overflow past 'capacity' is refused. As a meter it allows the same traffic as a token bucket, but it's implemented as GCRA ("generic cell rate algorithm"): one timestamp per key, the "theoretical arrival time" of the next request, updated with a compare-and-swap instead of a lock.
+	* Sliding window: at most 'limit' requests in any 'window'. Kept as two counters (this fixed window and the one before), the older one weighted by how much of it still overlaps the sliding window. Approximate, but O(1) memory instead of one timestamp per request.
+- Thread safety: keys are spread over shards ('SHARDS' RwLocks), so clients in different shards never contend, and looking up a key that exists only takes a read lock. Each key's state then has its own small Mutex, or an atomic for the leaky bucket.
+- 'check_at( &key, now )' takes the time as a parameter, so the algorithms can be tested without sleeping.
+*/
+
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+const SHARDS: usize = 16;
+
+#[derive( Debug, Clone, Copy, PartialEq, Eq )]
+pub enum Algorithm {
+	TokenBucket { capacity: u32, refill_every: Duration },
+	LeakyBucket { capacity: u32, leak_every: Duration },
+	SlidingWindow { limit: u32, window: Duration },
+}
+
+#[derive( Debug, Clone, Copy, PartialEq, Eq )]
+pub enum Decision {
+	Allowed,
+	Limited { retry_after: Duration },
+}
+
+impl Decision {
+	pub fn is_allowed( &self ) -> bool {
+		return *self == Decision::Allowed;
+	}
+}
+
+// Times are kept as durations since the limiter was created, so they fit in a u64 of nanoseconds
+struct Bucket {
+	tokens: f64,
+	last: Duration,
+}
+
+struct Window {
+	start: Duration,
+	previous: u32,
+	current: u32,
+}
+
+enum State {
+	Bucket( Mutex<Bucket> ),
+	Gcra( AtomicU64 ), // Theoretical arrival time, in nanoseconds
+	Window( Mutex<Window> ),
+}
+
+type Shard<K> = RwLock<HashMap<K, Arc<State>>>;
+
+pub struct RateLimiter<K> {
+	algorithm: Algorithm,
+	shards: Vec<Shard<K>>,
+	epoch: Instant,
+}
+
+impl<K: Hash + Eq + Clone> RateLimiter<K> {
+	// Panics if the capacity/limit is 0 or the duration is zero
+	pub fn new( algorithm: Algorithm ) -> Self {
+		let ( amount, period ) = match algorithm {
+			Algorithm::TokenBucket { capacity, refill_every } => ( capacity, refill_every ),
+			Algorithm::LeakyBucket { capacity, leak_every } => ( capacity, leak_every ),
+			Algorithm::SlidingWindow { limit, window } => ( limit, window ),
+		};
+
+		assert! ( amount > 0 && !period.is_zero(), "Rate limits need a capacity and a duration above zero" );
+
+		Self { algorithm, shards: ( 0..SHARDS ).map( |_| RwLock::new( HashMap::new() ) ).collect(), epoch: Instant::now() }
+	}
+
+	pub fn algorithm( &self ) -> Algorithm {
+		return self.algorithm;
+	}
+
+	pub fn check( &self, key: &K ) -> Decision {
+		return self.check_at( key, Instant::now() );
+	}
+
+	// 'now' shouldn't go backwards for a key (earlier times count as the latest one seen)
+	pub fn check_at( &self, key: &K, now: Instant ) -> Decision {
+		let now = now.saturating_duration_since( self.epoch );
+		let state = self.state( key, now );
+
+		match ( &*state, self.algorithm ) {
+			( State::Bucket( bucket ), Algorithm::TokenBucket { capacity, refill_every } ) => {
+				let mut bucket = bucket.lock().unwrap();
+				let refilled = now.saturating_sub( bucket.last ).as_secs_f64() / refill_every.as_secs_f64();
+
+				bucket.tokens = ( bucket.tokens + refilled ).min( capacity as f64 );
+				bucket.last = bucket.last.max( now );
+
+				if bucket.tokens >= 1.0 {
+					bucket.tokens -= 1.0;
+					return Decision::Allowed;
+				}
+
+				Decision::Limited { retry_after: refill_every.mul_f64( 1.0 - bucket.tokens ) }
+			},
+			( State::Gcra( tat ), Algorithm::LeakyBucket { capacity, leak_every } ) => {
+				let now = now.as_nanos() as u64;
+				let interval = leak_every.as_nanos() as u64;
+				let tolerance = interval * ( capacity as u64 - 1 ); // How far ahead of 'now' the bucket may be, i.e. the burst
+
+				let mut current = tat.load( Ordering::Acquire );
+
+				loop {
+					let arrival = current.max( now );
+
+					if arrival - now > tolerance {
+						return Decision::Limited { retry_after: Duration::from_nanos( arrival - now - tolerance ) };
+					}
+
+					// Someone else may have updated it since we loaded it. Then try again with their value
+					match tat.compare_exchange_weak( current, arrival + interval, Ordering::AcqRel, Ordering::Acquire ) {
+						Ok( _ ) => return Decision::Allowed,
+						Err( actual ) => current = actual,
+					}
+				}
+			},
+			( State::Window( window ), Algorithm::SlidingWindow { limit, window: length } ) => {
+				let mut w = window.lock().unwrap();
+				let passed = now.saturating_sub( w.start ).as_nanos() / length.as_nanos();
+
+				if passed >= 2 {
+					w.previous = 0;
+					w.current = 0;
+				} else if passed == 1 {
+					w.previous = w.current;
+					w.current = 0;
+				}
+
+				w.start += length * passed as u32;
+
+				let into = now.saturating_sub( w.start ).as_secs_f64() / length.as_secs_f64(); // 0..1 through the current window
+				let estimate = w.previous as f64 * ( 1.0 - into ) + w.current as f64;
+
+				if estimate + 1.0 <= limit as f64 {
+					w.current += 1;
+					return Decision::Allowed;
+				}
+
+				// When the previous window's share has shrunk enough. If this window alone is full, not before it ends
+				let room = limit as f64 - 1.0 - w.current as f64;
+				let wait = match room >= 0.0 && w.previous > 0 {
+					true => ( 1.0 - room / w.previous as f64 ) - into,
+					false => 1.0 - into,
+				};
+
+				Decision::Limited { retry_after: length.mul_f64( wait.max( 0.0 ) ) }
+			},
+			_ => unreachable! ( "State always matches the limiter's algorithm" ),
+		}
+	}
+
+	// Forgets a key, e.g. when a client disconnects
+	pub fn remove( &self, key: &K ) {
+		self.shard( key ).write().unwrap().remove( key );
+	}
+
+	pub fn len( &self ) -> usize {
+		return self.shards.iter().map( |s| s.read().unwrap().len() ).sum();
+	}
+
+	pub fn is_empty( &self ) -> bool {
+		return self.len() == 0;
+	}
+
+	fn shard( &self, key: &K ) -> &Shard<K> {
+		let mut hasher = DefaultHasher::new();
+		key.hash( &mut hasher );
+
+		return &self.shards[hasher.finish() as usize % SHARDS];
+	}
+
+	// Read lock for keys we've seen, write lock only to add a new one
+	fn state( &self, key: &K, now: Duration ) -> Arc<State> {
+		let shard = self.shard( key );
+
+		if let Some( state ) = shard.read().unwrap().get( key ) {
+			return Arc::clone( state );
+		}
+
+		let fresh = match self.algorithm {
+			Algorithm::TokenBucket { capacity, .. } => State::Bucket( Mutex::new( Bucket { tokens: capacity as f64, last: now } ) ),
+			Algorithm::LeakyBucket { .. } => State::Gcra( AtomicU64::new( 0 ) ),
+			Algorithm::SlidingWindow { .. } => State::Window( Mutex::new( Window { start: now, previous: 0, current: 0 } ) ),
+		};
+
+		// Another thread may have added it between the two locks, then theirs is used
+		return Arc::clone( shard.write().unwrap().entry( key.clone() ).or_insert_with( || Arc::new( fresh ) ) );
+	}
+}
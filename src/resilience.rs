@@ -0,0 +1,323 @@
+// Retries, timeouts and circuit breakers for fallible closures
+
+/*
+Resilience.
+
+- I/O fails for reasons that often go away by themselves (a busy peer, a dropped packet). These wrap a fallible closure so callers don't have to loop by hand:
+	* retry( &policy, f )                   Calls 'FnMut() -> Result<T, E>' until it succeeds or runs out of attempts, sleeping between attempts.
+	* timeout( limit, f )                   Runs 'f' on a worker thread and gives up waiting after 'limit'.
+	* retry_with_timeout( &policy, limit, f ) Both: every attempt gets its own worker thread and time limit.
+	* CircuitBreaker                        Stops calling something that keeps failing, and lets it recover.
+- Backoff between attempts:
+	* Fixed( d )        Same delay every time.
+	* Exponential       initial, initial * factor, initial * factor^2... capped at max.
+	* Jitter (0.0-1.0) randomly shortens each delay by up to that fraction, so many clients retrying at once don't all hit the server at the same moment. The randomness is seeded, so a policy always produces the same delays (handy when testing).
+- Threads can't be killed from the outside: a timed-out attempt keeps running in the background, its result is just ignored.
+- Only retry what is safe to repeat. A socket 'write_all' that failed may have sent part of the data already, so retrying it duplicates or interleaves bytes: better to drop the connection. Binding is fine to repeat, which is what 'Server::with_bind_retry' does.
+- A panic in 'timeout''s closure is caught on the worker thread and resumed on the caller's, with its original payload.
+
+Circuit breaker states:
+
+	Closed ---- failure_threshold failures in a row ----> Open
+	Open ------ reset_timeout elapsed ------------------> HalfOpen (one probe call at a time is let through)
+	HalfOpen -- success_threshold probe successes ------> Closed
+	HalfOpen -- a probe fails (or panics) --------------> Open (and the timeout starts again)
+*/
+
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::{Duration, Instant};
+
+#[derive( Debug, Clone, PartialEq )]
+pub enum CallError<E> {
+	Inner( E ),           // The closure's own error (after the last attempt)
+	TimedOut( Duration ), // No answer within the limit
+	Open,                 // Rejected by a circuit breaker without calling the closure
+}
+
+impl<E: fmt::Display> fmt::Display for CallError<E> {
+	fn fmt( &self, f: &mut fmt::Formatter ) -> fmt::Result {
+		match self {
+			CallError::Inner( e ) => write! ( f, "{}", e ),
+			CallError::TimedOut( limit ) => write! ( f, "Timed out after {:?}", limit ),
+			CallError::Open => write! ( f, "Circuit breaker is open" ),
+		}
+	}
+}
+
+#[derive( Debug, Clone, Copy, PartialEq )]
+pub enum Backoff {
+	Fixed( Duration ),
+	Exponential { initial: Duration, factor: f64, max: Duration },
+}
+
+#[derive( Debug, Clone, Copy )]
+pub struct RetryPolicy {
+	max_attempts: u32, // Including the first one
+	backoff: Backoff,
+	jitter: f64,
+	seed: u64,
+	sleep: fn( Duration ), // Swappable so tests don't have to actually wait
+}
+
+impl RetryPolicy {
+	pub fn fixed( max_attempts: u32, delay: Duration ) -> Self {
+		Self { max_attempts: max_attempts.max( 1 ), backoff: Backoff::Fixed( delay ), jitter: 0.0, seed: 0, sleep: std::thread::sleep }
+	}
+
+	pub fn exponential( max_attempts: u32, initial: Duration, max: Duration ) -> Self {
+		let backoff = Backoff::Exponential { initial, factor: 2.0, max };
+
+		Self { max_attempts: max_attempts.max( 1 ), backoff, jitter: 0.0, seed: 0, sleep: std::thread::sleep }
+	}
+
+	pub fn factor( mut self, factor: f64 ) -> Self {
+		if let Backoff::Exponential { initial, max, .. } = self.backoff {
+			self.backoff = Backoff::Exponential { initial, factor: factor.max( 1.0 ), max };
+		}
+		self
+	}
+
+	pub fn jitter( mut self, jitter: f64 ) -> Self {
+		self.jitter = jitter.clamp( 0.0, 1.0 );
+		self
+	}
+
+	pub fn seed( mut self, seed: u64 ) -> Self {
+		self.seed = seed;
+		self
+	}
+
+	pub fn sleeper( mut self, sleep: fn( Duration ) ) -> Self {
+		self.sleep = sleep;
+		self
+	}
+
+	pub fn max_attempts( &self ) -> u32 {
+		return self.max_attempts;
+	}
+
+	// The waits between attempts (one less than the number of attempts)
+	pub fn delays( &self ) -> Vec<Duration> {
+		let mut rng = SplitMix64( self.seed );
+
+		return ( 1..self.max_attempts ).map( |retry| self.delay( retry, &mut rng ) ).collect();
+	}
+
+	fn delay( &self, retry: u32, rng: &mut SplitMix64 ) -> Duration {
+		let base = match self.backoff {
+			Backoff::Fixed( d ) => d,
+			Backoff::Exponential { initial, factor, max } => {
+				let secs = initial.as_secs_f64() * factor.powi( retry as i32 - 1 );
+
+				if secs >= max.as_secs_f64() { max } else { Duration::from_secs_f64( secs ) }
+			}
+		};
+
+		if self.jitter == 0.0 {
+			return base;
+		}
+
+		return base.mul_f64( 1.0 - self.jitter * rng.next_f64() );
+	}
+}
+
+// Tiny seeded PRNG (SplitMix64), good enough for jitter
+struct SplitMix64( u64 );
+
+impl SplitMix64 {
+	fn next_u64( &mut self ) -> u64 {
+		self.0 = self.0.wrapping_add( 0x9E37_79B9_7F4A_7C15 );
+
+		let mut z = self.0;
+		z = ( z ^ ( z >> 30 ) ).wrapping_mul( 0xBF58_476D_1CE4_E5B9 );
+		z = ( z ^ ( z >> 27 ) ).wrapping_mul( 0x94D0_49BB_1331_11EB );
+
+		return z ^ ( z >> 31 );
+	}
+
+	// In [0, 1)
+	fn next_f64( &mut self ) -> f64 {
+		return ( self.next_u64() >> 11 ) as f64 / ( 1u64 << 53 ) as f64;
+	}
+}
+
+pub fn retry<T, E>( policy: &RetryPolicy, f: impl FnMut() -> Result<T, E> ) -> Result<T, E> {
+	return retry_if( policy, f, |_| true );
+}
+
+// Only errors passing 'should_retry' are retried, the others are returned right away (e.g. "not found" won't fix itself)
+pub fn retry_if<T, E>( policy: &RetryPolicy, mut f: impl FnMut() -> Result<T, E>, should_retry: impl Fn( &E ) -> bool ) -> Result<T, E> {
+	let mut rng = SplitMix64( policy.seed );
+	let mut attempt = 1;
+
+	loop {
+		match f() {
+			Ok( value ) => return Ok( value ),
+			Err( e ) if attempt >= policy.max_attempts || !should_retry( &e ) => return Err( e ),
+			Err( _ ) => {
+				( policy.sleep )( policy.delay( attempt, &mut rng ) );
+				attempt += 1;
+			}
+		}
+	}
+}
+
+pub fn timeout<T, E>( limit: Duration, f: impl FnOnce() -> Result<T, E> + Send + 'static ) -> Result<T, CallError<E>>
+where
+	T: Send + 'static,
+	E: Send + 'static,
+{
+	let ( tx, rx ) = mpsc::channel();
+
+	std::thread::spawn( move || {
+		let _ = tx.send( panic::catch_unwind( AssertUnwindSafe( f ) ) ); // The receiver is gone if we already timed out
+	});
+
+	match rx.recv_timeout( limit ) {
+		Ok( Ok( result ) ) => result.map_err( CallError::Inner ),
+		Ok( Err( payload ) ) => panic::resume_unwind( payload ), // The closure panicked, pass it on
+		Err( mpsc::RecvTimeoutError::Timeout ) => Err( CallError::TimedOut( limit ) ),
+		Err( mpsc::RecvTimeoutError::Disconnected ) => unreachable! ( "The worker always sends, even when the closure panics" ),
+	}
+}
+
+// 'Fn + Sync' rather than 'FnMut', since a timed-out attempt may still be running when the next one starts
+pub fn retry_with_timeout<T, E>( policy: &RetryPolicy, limit: Duration, f: impl Fn() -> Result<T, E> + Send + Sync + 'static ) -> Result<T, CallError<E>>
+where
+	T: Send + 'static,
+	E: Send + 'static,
+{
+	let f = Arc::new( f );
+
+	return retry( policy, || {
+		let f = Arc::clone( &f );
+
+		timeout( limit, move || f() )
+	});
+}
+
+#[derive( Debug, Clone, Copy, PartialEq, Eq )]
+pub enum State {
+	Closed,
+	Open,
+	HalfOpen,
+}
+
+#[derive( Debug )]
+struct Breaker {
+	state: State,
+	failures: u32,
+	successes: u32,
+	opened_at: Option<Instant>,
+	probing: bool,
+}
+
+#[derive( Debug )]
+pub struct CircuitBreaker {
+	failure_threshold: u32,
+	success_threshold: u32,
+	reset_timeout: Duration,
+	inner: Mutex<Breaker>,
+}
+
+// Trips the breaker if the probe panics, otherwise 'probing' would stay set and every later call be rejected
+struct ProbeGuard<'a>( Option<&'a Mutex<Breaker>> );
+
+impl Drop for ProbeGuard<'_> {
+	fn drop( &mut self ) {
+		if let Some( inner ) = self.0
+			&& let Ok( mut inner ) = inner.lock() {
+			CircuitBreaker::trip( &mut inner );
+		}
+	}
+}
+
+impl CircuitBreaker {
+	pub fn new( failure_threshold: u32, reset_timeout: Duration ) -> Self {
+		Self {
+			failure_threshold: failure_threshold.max( 1 ),
+			success_threshold: 1,
+			reset_timeout,
+			inner: Mutex::new( Breaker { state: State::Closed, failures: 0, successes: 0, opened_at: None, probing: false } ),
+		}
+	}
+
+	pub fn success_threshold( mut self, successes: u32 ) -> Self {
+		self.success_threshold = successes.max( 1 );
+		self
+	}
+
+	pub fn state( &self ) -> State {
+		let mut inner = self.inner.lock().unwrap();
+		self.refresh( &mut inner );
+
+		return inner.state;
+	}
+
+	// Open turns into HalfOpen once the reset timeout has passed
+	fn refresh( &self, inner: &mut Breaker ) {
+		if inner.state == State::Open && inner.opened_at.is_some_and( |t| t.elapsed() >= self.reset_timeout ) {
+			inner.state = State::HalfOpen;
+			inner.successes = 0;
+			inner.probing = false;
+		}
+	}
+
+	fn trip( inner: &mut Breaker ) {
+		inner.state = State::Open;
+		inner.opened_at = Some( Instant::now() );
+		inner.probing = false;
+	}
+
+	// The lock is only held to check and update the state, never while 'f' runs
+	pub fn call<T, E>( &self, f: impl FnOnce() -> Result<T, E> ) -> Result<T, CallError<E>> {
+		let mut probe = {
+			let mut inner = self.inner.lock().unwrap();
+			self.refresh( &mut inner );
+
+			match inner.state {
+				State::Closed => ProbeGuard( None ),
+				State::Open => return Err( CallError::Open ),
+				State::HalfOpen if inner.probing => return Err( CallError::Open ), // Someone else is probing already
+				State::HalfOpen => {
+					inner.probing = true;
+					ProbeGuard( Some( &self.inner ) )
+				},
+			}
+		};
+
+		let result = f();
+		probe.0 = None; // Returned normally, the result is handled below
+		let mut inner = self.inner.lock().unwrap();
+
+		match ( inner.state, result.is_ok() ) {
+			( State::HalfOpen, true ) => {
+				inner.probing = false;
+				inner.successes += 1;
+
+				if inner.successes >= self.success_threshold {
+					inner.state = State::Closed;
+					inner.failures = 0;
+					inner.opened_at = None;
+				}
+			},
+			( State::HalfOpen, false ) => Self::trip( &mut inner ),
+			( _, true ) => inner.failures = 0,
+			( _, false ) => {
+				inner.failures += 1;
+
+				if inner.failures >= self.failure_threshold && inner.state == State::Closed {
+					Self::trip( &mut inner );
+				}
+			}
+		}
+
+		return result.map_err( CallError::Inner );
+	}
+
+	pub fn reset( &self ) {
+		*self.inner.lock().unwrap() = Breaker { state: State::Closed, failures: 0, successes: 0, opened_at: None, probing: false };
+	}
+}
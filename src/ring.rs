@@ -0,0 +1,335 @@
+// Lock-free bounded queues: a single-producer/single-consumer ring buffer, and a multi-producer one
+
+/*
+Lock-free ring buffers.
+
+- A ring buffer is a fixed array used as a queue: the producer writes at 'tail', the consumer reads at 'head', and both wrap around. Here head and tail only ever count up (wrapping at usize::MAX, which never happens in practice), and the slot is 'index & mask'. The capacity is rounded up to a power of two so that's one AND instead of a division.
+- "Lock-free": no Mutex, so a thread that gets descheduled in the middle of a push can't make everyone else wait for the OS to run it again. Threads coordinate only through atomics, and the memory orderings are what make that correct.
+- Both queues are try-only: 'push' hands the value back when full, 'pop' returns None when empty. Waiting is up to the caller (spin, yield, sleep, or use channel::bounded, which blocks).
+
+SPSC ('spsc( capacity )'): one Producer, one Consumer.
+- Only the producer writes 'tail', only the consumer writes 'head'. So each side can read its own index with Relaxed, and no compare-and-swap is needed anywhere.
+- The producer writes the value into the slot, *then* stores tail with Release. The consumer loads tail with Acquire, and if it sees the new tail it's guaranteed to see the value too. That pair is the whole trick. With Relaxed instead, the consumer could see the new tail and read a slot that isn't written yet (on ARM that really happens; on x86 the compiler may still reorder).
+- The same goes the other way for head: the consumer reads the value, then releases head, so the producer doesn't overwrite a slot that's still being read.
+- Each side also keeps a cached copy of the other's index and only reloads it when the cache says full (or empty). Loading an index the other core keeps writing is the expensive part, so this saves most of them.
+- 'push'/'pop' take &mut self: that's what makes "single producer" a compile-time guarantee rather than a promise (a Producer can be moved to another thread, but not shared).
+
+MPSC ('mpsc( capacity )'): Producers are Clone, one Consumer. Dmitry Vyukov's bounded queue.
+- Producers race for slots, so tail is claimed with a compare-and-swap. But then "tail moved" no longer means "the value is there": another producer may have claimed a later slot and finished first.
+- So every slot has its own sequence number, which says what the slot is waiting for: 'seq == index' means empty and ready for the producer of 'index', 'seq == index + 1' means written and ready for the consumer. The producer publishes with a Release store of seq, the consumer checks it with an Acquire load.
+- At least 2 slots ('mpsc( 1 )' gets 2): with a single slot, "written for index i" (i + 1) and "empty, waiting for index i + 1" (also i + 1) would look the same, and a producer would overwrite a value the consumer hasn't read.
+- Not quite lock-free for the consumer: if a producer claims a slot and gets descheduled before writing it, the consumer sees nothing after it until that producer runs again. Producers never wait on each other for longer than a failed compare-and-swap.
+
+False sharing.
+- CPUs move memory between cores in cache lines of 64 bytes. If head and tail sat next to each other, every write to tail would also invalidate the consumer's copy of head, and the line would bounce between the two cores on every operation. 'CachePadded' aligns each index to its own line.
+*/
+
+use std::cell::UnsafeCell;
+use std::mem::MaybeUninit;
+use std::ops::Deref;
+use std::sync::Arc;
+use std::sync::atomic::{ AtomicBool, AtomicUsize, Ordering };
+
+#[derive( Default )]
+#[repr( align( 64 ) )]
+struct CachePadded<T>( T );
+
+impl<T> Deref for CachePadded<T> {
+	type Target = T;
+
+	fn deref( &self ) -> &T {
+		return &self.0;
+	}
+}
+
+type Slot<T> = UnsafeCell<MaybeUninit<T>>;
+
+fn slots<T>( capacity: usize ) -> Box<[Slot<T>]> {
+	return ( 0..capacity ).map( |_| UnsafeCell::new( MaybeUninit::uninit() ) ).collect();
+}
+
+// Panics on 0, like channel::bounded
+fn round_capacity( capacity: usize ) -> usize {
+	assert! ( capacity > 0, "Ring buffer capacity must be at least 1" );
+
+	return capacity.next_power_of_two();
+}
+
+// SPSC
+
+struct Spsc<T> {
+	head: CachePadded<AtomicUsize>, // Next slot to read. Written by the consumer only
+	tail: CachePadded<AtomicUsize>, // Next slot to write. Written by the producer only
+	producer_alive: AtomicBool,
+	consumer_alive: AtomicBool,
+	mask: usize,
+	buffer: Box<[Slot<T>]>,
+}
+
+// The slots are only touched by whoever owns them at the time (see the indices), so sharing the buffer is fine as long as T can move between threads
+unsafe impl<T: Send> Send for Spsc<T> {}
+unsafe impl<T: Send> Sync for Spsc<T> {}
+
+impl<T> Drop for Spsc<T> {
+	// Both sides are gone, so the values between head and tail are ours to drop
+	fn drop( &mut self ) {
+		let ( head, tail ) = ( *self.head.0.get_mut(), *self.tail.0.get_mut() );
+
+		for i in 0..tail.wrapping_sub( head ) {
+			unsafe { self.buffer[head.wrapping_add( i ) & self.mask].get_mut().assume_init_drop() };
+		}
+	}
+}
+
+pub struct Producer<T> {
+	ring: Arc<Spsc<T>>,
+	cached_head: usize,
+}
+
+pub struct Consumer<T> {
+	ring: Arc<Spsc<T>>,
+	cached_tail: usize,
+}
+
+pub fn spsc<T>( capacity: usize ) -> ( Producer<T>, Consumer<T> ) {
+	let capacity = round_capacity( capacity );
+
+	let ring = Arc::new( Spsc {
+		head: CachePadded::default(),
+		tail: CachePadded::default(),
+		producer_alive: AtomicBool::new( true ),
+		consumer_alive: AtomicBool::new( true ),
+		mask: capacity - 1,
+		buffer: slots( capacity ),
+	});
+
+	return ( Producer { ring: Arc::clone( &ring ), cached_head: 0 }, Consumer { ring, cached_tail: 0 } );
+}
+
+impl<T> Producer<T> {
+	// Gives the value back if the buffer is full
+	pub fn push( &mut self, value: T ) -> Result<(), T> {
+		let ring = &*self.ring;
+		let tail = ring.tail.load( Ordering::Relaxed ); // Our own index
+
+		if tail.wrapping_sub( self.cached_head ) > ring.mask {
+			self.cached_head = ring.head.load( Ordering::Acquire ); // The consumer is done with everything before it
+
+			if tail.wrapping_sub( self.cached_head ) > ring.mask {
+				return Err( value );
+			}
+		}
+
+		unsafe { ( *ring.buffer[tail & ring.mask].get() ).write( value ) };
+		ring.tail.store( tail.wrapping_add( 1 ), Ordering::Release ); // Publishes the write above
+
+		return Ok( () );
+	}
+
+	pub fn capacity( &self ) -> usize {
+		return self.ring.mask + 1;
+	}
+
+	pub fn is_disconnected( &self ) -> bool {
+		return !self.ring.consumer_alive.load( Ordering::Acquire );
+	}
+}
+
+impl<T> Consumer<T> {
+	pub fn pop( &mut self ) -> Option<T> {
+		let ring = &*self.ring;
+		let head = ring.head.load( Ordering::Relaxed );
+
+		if head == self.cached_tail {
+			self.cached_tail = ring.tail.load( Ordering::Acquire ); // Everything before it has been written
+
+			if head == self.cached_tail {
+				return None;
+			}
+		}
+
+		let value = unsafe { ( *ring.buffer[head & ring.mask].get() ).assume_init_read() };
+		ring.head.store( head.wrapping_add( 1 ), Ordering::Release ); // The slot may be reused now
+
+		return Some( value );
+	}
+
+	pub fn len( &self ) -> usize {
+		return self.ring.tail.load( Ordering::Acquire ).wrapping_sub( self.ring.head.load( Ordering::Relaxed ) );
+	}
+
+	pub fn is_empty( &self ) -> bool {
+		return self.len() == 0;
+	}
+
+	pub fn capacity( &self ) -> usize {
+		return self.ring.mask + 1;
+	}
+
+	// The producer is gone. Whatever it pushed can still be popped: once this is true, an empty pop means there's nothing more to come
+	pub fn is_disconnected( &self ) -> bool {
+		return !self.ring.producer_alive.load( Ordering::Acquire );
+	}
+}
+
+// Release, so that whoever sees the flag also sees everything pushed (or popped) before
+impl<T> Drop for Producer<T> {
+	fn drop( &mut self ) {
+		self.ring.producer_alive.store( false, Ordering::Release );
+	}
+}
+
+impl<T> Drop for Consumer<T> {
+	fn drop( &mut self ) {
+		self.ring.consumer_alive.store( false, Ordering::Release );
+	}
+}
+
+// MPSC
+
+struct Cell<T> {
+	seq: AtomicUsize, // index: empty, waiting for the producer of 'index'. index + 1: full, waiting for the consumer
+	value: Slot<T>,
+}
+
+struct Mpsc<T> {
+	head: CachePadded<AtomicUsize>, // Written by the consumer only
+	tail: CachePadded<AtomicUsize>, // Claimed by producers with compare-and-swap
+	producers: AtomicUsize,
+	consumer_alive: AtomicBool,
+	mask: usize,
+	cells: Box<[Cell<T>]>,
+}
+
+unsafe impl<T: Send> Send for Mpsc<T> {}
+unsafe impl<T: Send> Sync for Mpsc<T> {}
+
+impl<T> Drop for Mpsc<T> {
+	fn drop( &mut self ) {
+		let mut index = *self.head.0.get_mut();
+
+		// Every push finished before its producer was dropped, so the full cells are exactly the ones after head
+		loop {
+			let cell = &mut self.cells[index & self.mask];
+
+			if *cell.seq.get_mut() != index.wrapping_add( 1 ) {
+				break;
+			}
+
+			unsafe { cell.value.get_mut().assume_init_drop() };
+			index = index.wrapping_add( 1 );
+		}
+	}
+}
+
+pub struct MpscProducer<T> {
+	ring: Arc<Mpsc<T>>,
+}
+
+pub struct MpscConsumer<T> {
+	ring: Arc<Mpsc<T>>,
+}
+
+pub fn mpsc<T>( capacity: usize ) -> ( MpscProducer<T>, MpscConsumer<T> ) {
+	let capacity = round_capacity( capacity ).max( 2 ); // Sequence numbers need two slots to tell full from empty
+
+	let ring = Arc::new( Mpsc {
+		head: CachePadded::default(),
+		tail: CachePadded::default(),
+		producers: AtomicUsize::new( 1 ),
+		consumer_alive: AtomicBool::new( true ),
+		mask: capacity - 1,
+		cells: ( 0..capacity ).map( |i| Cell { seq: AtomicUsize::new( i ), value: UnsafeCell::new( MaybeUninit::uninit() ) } ).collect(),
+	});
+
+	return ( MpscProducer { ring: Arc::clone( &ring ) }, MpscConsumer { ring } );
+}
+
+impl<T> MpscProducer<T> {
+	// Gives the value back if the buffer is full
+	pub fn push( &self, value: T ) -> Result<(), T> {
+		let ring = &*self.ring;
+		let mut tail = ring.tail.load( Ordering::Relaxed );
+
+		loop {
+			let cell = &ring.cells[tail & ring.mask];
+			let seq = cell.seq.load( Ordering::Acquire ); // Pairs with the consumer's Release: the old value has been read out
+			let lag = seq.wrapping_sub( tail ) as isize;
+
+			if lag == 0 {
+				// Free for this index: claim it. Relaxed is enough, the cell's seq does the publishing
+				match ring.tail.compare_exchange_weak( tail, tail.wrapping_add( 1 ), Ordering::Relaxed, Ordering::Relaxed ) {
+					Ok( _ ) => {
+						unsafe { ( *cell.value.get() ).write( value ) };
+						cell.seq.store( tail.wrapping_add( 1 ), Ordering::Release );
+
+						return Ok( () );
+					},
+					Err( actual ) => tail = actual, // Another producer got it, try the next one
+				}
+			} else if lag < 0 {
+				return Err( value ); // Still holds the value from a lap ago: full
+			} else {
+				tail = ring.tail.load( Ordering::Relaxed ); // Another producer claimed it since we loaded tail
+			}
+		}
+	}
+
+	pub fn capacity( &self ) -> usize {
+		return self.ring.mask + 1;
+	}
+
+	pub fn is_disconnected( &self ) -> bool {
+		return !self.ring.consumer_alive.load( Ordering::Acquire );
+	}
+}
+
+impl<T> Clone for MpscProducer<T> {
+	fn clone( &self ) -> Self {
+		self.ring.producers.fetch_add( 1, Ordering::Relaxed );
+
+		return MpscProducer { ring: Arc::clone( &self.ring ) };
+	}
+}
+
+impl<T> Drop for MpscProducer<T> {
+	fn drop( &mut self ) {
+		self.ring.producers.fetch_sub( 1, Ordering::Release );
+	}
+}
+
+impl<T> MpscConsumer<T> {
+	pub fn pop( &mut self ) -> Option<T> {
+		let ring = &*self.ring;
+		let head = ring.head.load( Ordering::Relaxed );
+		let cell = &ring.cells[head & ring.mask];
+
+		// Acquire: pairs with the producer's Release, so the value is there
+		if cell.seq.load( Ordering::Acquire ) != head.wrapping_add( 1 ) {
+			return None;
+		}
+
+		let value = unsafe { ( *cell.value.get() ).assume_init_read() };
+
+		// Ready for the producer of the same slot one lap later
+		cell.seq.store( head.wrapping_add( ring.mask + 1 ), Ordering::Release );
+		ring.head.store( head.wrapping_add( 1 ), Ordering::Relaxed );
+
+		return Some( value );
+	}
+
+	pub fn capacity( &self ) -> usize {
+		return self.ring.mask + 1;
+	}
+
+	// Every producer is gone. As with spsc, what they pushed can still be popped
+	pub fn is_disconnected( &self ) -> bool {
+		return self.ring.producers.load( Ordering::Acquire ) == 0;
+	}
+}
+
+impl<T> Drop for MpscConsumer<T> {
+	fn drop( &mut self ) {
+		self.ring.consumer_alive.store( false, Ordering::Release );
+	}
+}
@@ -0,0 +1,484 @@
+// Delayed and periodic jobs on a background thread, using a hierarchical timer wheel
+
+/*
+Scheduler.
+
+- 'scheduler.after( delay, job )' runs a 'FnOnce() + Send' job once, 'scheduler.every( interval, job )' runs a 'FnMut() + Send' job repeatedly. Both return a 'JobHandle' whose 'cancel()' stops the job (a job that is already running finishes first).
+- Jobs run one after another on the scheduler's own thread, so a slow job delays the others. Hand long work to another thread (or a pool) from inside the job.
+- A job that panics is dropped, the scheduler thread keeps going.
+- Periodic jobs that fall behind (e.g. the machine slept) skip the missed runs instead of running them all in a burst.
+
+Timer wheel.
+
+- Like a clock face: 64 slots, one per millisecond "tick". A timer due in 5 ticks goes 5 slots ahead of the current one, and each tick only looks at one slot, so adding, firing and cancelling are all O(1) (a sorted list or heap would be O(log n)).
+- One wheel only covers 64 ticks, so there are 4 levels, each slot of a level covering a whole turn of the level below (1 ms, 64 ms, ~4 s, ~4.5 min per slot, ~4.7 hours in total). Timers further away wait in an overflow list.
+- When a level's slot comes up, its timers "cascade" down into the finer level below, until they end up on level 0 and fire:
+
+	level 3  [ | | |x| ... ]  ~4.5 min slots
+	level 2  [ | | | | ... ]  ~4 s slots
+	level 1  [ |x| | | ... ]  64 ms slots
+	level 0  [x| | | | ... ]  1 ms slots   <- current tick
+
+Clocks.
+
+- Time comes from a 'Clock', so tests can use 'ManualClock' and move time forward by hand: nothing sleeps and every run is the same. 'scheduler.wait_idle()' blocks until the scheduler thread has run everything due at the current time.
+*/
+
+use std::collections::HashMap;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex, Weak};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+const SLOT_BITS: u32 = 6;
+const SLOTS: usize = 1 << SLOT_BITS; // 64
+const LEVELS: usize = 4;
+
+pub const TICK: Duration = Duration::from_millis( 1 );
+
+fn to_ticks( d: Duration ) -> u64 {
+	return ( d.as_nanos() / TICK.as_nanos() ) as u64;
+}
+
+// Rounded up, so a job never runs early
+fn to_ticks_ceil( d: Duration ) -> u64 {
+	return d.as_nanos().div_ceil( TICK.as_nanos() ) as u64;
+}
+
+fn from_ticks( t: u64 ) -> Duration {
+	return Duration::from_nanos( ( TICK.as_nanos() as u64 ).saturating_mul( t ) );
+}
+
+// Clocks
+
+type Waker = Box<dyn Fn() + Send + Sync>;
+
+pub trait Clock: Send + Sync {
+	// Time since the clock's own starting point
+	fn now( &self ) -> Duration;
+
+	// How long the scheduler thread should really wait for 'd' of this clock's time to pass. None means "until woken up"
+	fn real_wait( &self, d: Duration ) -> Option<Duration> {
+		return Some( d );
+	}
+
+	// Called once by the scheduler, for clocks that move by themselves in jumps (see ManualClock)
+	fn on_advance( &self, _wake: Waker ) {}
+}
+
+pub struct SystemClock {
+	start: Instant,
+}
+
+impl Default for SystemClock {
+	fn default() -> Self {
+		Self { start: Instant::now() }
+	}
+}
+
+impl SystemClock {
+	pub fn new() -> Self {
+		Self::default()
+	}
+}
+
+impl Clock for SystemClock {
+	fn now( &self ) -> Duration {
+		return self.start.elapsed();
+	}
+}
+
+#[derive( Default )]
+pub struct ManualClock {
+	now: Mutex<Duration>,
+	wakers: Mutex<Vec<Waker>>,
+}
+
+impl ManualClock {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn advance( &self, d: Duration ) {
+		*self.now.lock().unwrap() += d; // Released before waking, the scheduler reads the time when it wakes up
+
+		for wake in self.wakers.lock().unwrap().iter() {
+			wake();
+		}
+	}
+}
+
+impl Clock for ManualClock {
+	fn now( &self ) -> Duration {
+		return *self.now.lock().unwrap();
+	}
+
+	fn real_wait( &self, _d: Duration ) -> Option<Duration> {
+		return None; // Time only moves when 'advance' is called, which wakes us up
+	}
+
+	fn on_advance( &self, wake: Waker ) {
+		self.wakers.lock().unwrap().push( wake );
+	}
+}
+
+// Timer wheel
+
+pub struct TimerWheel {
+	current: u64,
+	levels: Vec<Vec<Vec<( u64, u64 )>>>, // [level][slot] -> (deadline tick, id)
+	overflow: Vec<( u64, u64 )>,
+	due: Vec<( u64, u64 )>, // Inserted with a deadline that already passed
+	len: usize,
+}
+
+impl Default for TimerWheel {
+	fn default() -> Self {
+		Self { current: 0, levels: vec![vec![Vec::new(); SLOTS]; LEVELS], overflow: Vec::new(), due: Vec::new(), len: 0 }
+	}
+}
+
+impl TimerWheel {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn current( &self ) -> u64 {
+		return self.current;
+	}
+
+	pub fn len( &self ) -> usize {
+		return self.len;
+	}
+
+	pub fn is_empty( &self ) -> bool {
+		return self.len == 0;
+	}
+
+	pub fn insert( &mut self, deadline: u64, id: u64 ) {
+		self.len += 1;
+		self.place( deadline, id );
+	}
+
+	// Also O(1): a timer can only be in the slot its deadline maps to on each level, the overflow list or the due list. Returns false if it isn't in the wheel
+	pub fn remove( &mut self, deadline: u64, id: u64 ) -> bool {
+		let mut lists: Vec<&mut Vec<( u64, u64 )>> = vec![&mut self.overflow, &mut self.due];
+
+		for ( level, slots ) in self.levels.iter_mut().enumerate() {
+			lists.push( &mut slots[( ( deadline >> ( SLOT_BITS * level as u32 ) ) as usize ) & ( SLOTS - 1 )] );
+		}
+
+		for list in lists {
+			if let Some( pos ) = list.iter().position( |&t| t == ( deadline, id ) ) {
+				list.swap_remove( pos );
+				self.len -= 1;
+				return true;
+			}
+		}
+
+		return false;
+	}
+
+	fn place( &mut self, deadline: u64, id: u64 ) {
+		if deadline <= self.current {
+			self.due.push( ( deadline, id ) );
+			return;
+		}
+
+		let delta = deadline - self.current;
+
+		// The first level whose whole turn covers the delay
+		for level in 0..LEVELS {
+			if delta < 1 << ( SLOT_BITS * ( level as u32 + 1 ) ) {
+				let slot = ( ( deadline >> ( SLOT_BITS * level as u32 ) ) as usize ) & ( SLOTS - 1 );
+				self.levels[level][slot].push( ( deadline, id ) );
+				return;
+			}
+		}
+
+		self.overflow.push( ( deadline, id ) );
+	}
+
+	// Moves time forward to 'to', returning the ids of every timer that came due, in deadline order
+	pub fn advance( &mut self, to: u64 ) -> Vec<u64> {
+		let mut fired = std::mem::take( &mut self.due );
+
+		while self.current < to {
+			if self.len == fired.len() {
+				self.current = to; // Nothing left in the wheel, jump straight there
+				break;
+			}
+
+			self.current += 1;
+			let t = self.current;
+
+			// Top level first, so timers can cascade through several levels in the same tick
+			if t.is_multiple_of( 1 << ( SLOT_BITS * ( LEVELS as u32 - 1 ) ) ) {
+				for ( deadline, id ) in std::mem::take( &mut self.overflow ) {
+					self.place( deadline, id );
+				}
+			}
+
+			for level in ( 1..LEVELS ).rev() {
+				let shift = SLOT_BITS * level as u32;
+
+				if t.is_multiple_of( 1 << shift ) {
+					let slot = ( ( t >> shift ) as usize ) & ( SLOTS - 1 );
+
+					for ( deadline, id ) in std::mem::take( &mut self.levels[level][slot] ) {
+						self.place( deadline, id );
+					}
+				}
+			}
+
+			fired.append( &mut self.levels[0][( t as usize ) & ( SLOTS - 1 )] );
+			fired.append( &mut self.due );
+		}
+
+		self.len -= fired.len();
+		fired.sort_by_key( |&( deadline, id )| ( deadline, id ) );
+
+		return fired.into_iter().map( |( _, id )| id ).collect();
+	}
+}
+
+// Scheduler
+
+enum Task {
+	Once( Box<dyn FnOnce() + Send> ),
+	Every( Duration, Box<dyn FnMut() + Send> ),
+}
+
+struct Job {
+	deadline: u64,
+	task: Option<Task>, // Taken out while the job runs
+}
+
+struct State {
+	wheel: TimerWheel,
+	jobs: HashMap<u64, Job>,
+	dirty: bool, // Something changed since the thread last looked
+	running: bool,
+	shutdown: bool,
+}
+
+struct Shared {
+	state: Mutex<State>,
+	wake: Condvar,
+	idle: Condvar,
+	clock: Arc<dyn Clock>,
+	next_id: AtomicU64,
+}
+
+pub struct JobHandle {
+	id: u64,
+	shared: Weak<Shared>,
+}
+
+impl JobHandle {
+	// Returns false if the job already ran (for one-off jobs), was cancelled, or the scheduler is gone
+	pub fn cancel( &self ) -> bool {
+		let Some( shared ) = self.shared.upgrade() else { return false };
+		let mut state = shared.state.lock().unwrap();
+		let Some( job ) = state.jobs.remove( &self.id ) else { return false };
+
+		// Out of the wheel too, so cancelled jobs don't pile up until their deadline (a running job has no entry left)
+		state.wheel.remove( job.deadline, self.id );
+
+		return true;
+	}
+
+	pub fn is_pending( &self ) -> bool {
+		let Some( shared ) = self.shared.upgrade() else { return false };
+
+		return shared.state.lock().unwrap().jobs.contains_key( &self.id );
+	}
+}
+
+pub struct Scheduler {
+	shared: Arc<Shared>,
+	thread: Option<JoinHandle<()>>,
+}
+
+impl Default for Scheduler {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl Scheduler {
+	pub fn new() -> Self {
+		return Self::with_clock( Arc::new( SystemClock::new() ) );
+	}
+
+	pub fn with_clock( clock: Arc<dyn Clock> ) -> Self {
+		let mut wheel = TimerWheel::new();
+		wheel.advance( to_ticks( clock.now() ) ); // Start the wheel at the clock's current time
+
+		let state = State { wheel, jobs: HashMap::new(), dirty: false, running: false, shutdown: false };
+		let shared = Arc::new( Shared { state: Mutex::new( state ), wake: Condvar::new(), idle: Condvar::new(), clock, next_id: AtomicU64::new( 0 ) } );
+
+		// Weak, so the clock (which may outlive us) doesn't keep the scheduler alive
+		let weak = Arc::downgrade( &shared );
+		shared.clock.on_advance( Box::new( move || {
+			if let Some( shared ) = weak.upgrade() {
+				shared.state.lock().unwrap().dirty = true;
+				shared.wake.notify_all();
+			}
+		}));
+
+		let worker = Arc::clone( &shared );
+		let thread = std::thread::Builder::new()
+			.name( "scheduler".to_string() )
+			.spawn( move || run( worker ) )
+			.expect( "Failed to spawn the scheduler thread" );
+
+		Self { shared, thread: Some( thread ) }
+	}
+
+	fn schedule( &self, delay: Duration, task: Task ) -> JobHandle {
+		let id = self.shared.next_id.fetch_add( 1, Ordering::Relaxed );
+		let deadline = to_ticks( self.shared.clock.now() ) + to_ticks_ceil( delay );
+
+		let mut state = self.shared.state.lock().unwrap();
+		state.wheel.insert( deadline, id );
+		state.jobs.insert( id, Job { deadline, task: Some( task ) } );
+		state.dirty = true;
+		drop( state );
+
+		self.shared.wake.notify_all();
+
+		return JobHandle { id, shared: Arc::downgrade( &self.shared ) };
+	}
+
+	pub fn after( &self, delay: Duration, job: impl FnOnce() + Send + 'static ) -> JobHandle {
+		return self.schedule( delay, Task::Once( Box::new( job ) ) );
+	}
+
+	// First run after one interval. Intervals shorter than a tick are rounded up to one tick
+	pub fn every( &self, interval: Duration, job: impl FnMut() + Send + 'static ) -> JobHandle {
+		let interval = interval.max( TICK );
+
+		return self.schedule( interval, Task::Every( interval, Box::new( job ) ) );
+	}
+
+	pub fn pending( &self ) -> usize {
+		return self.shared.state.lock().unwrap().jobs.len();
+	}
+
+	// Blocks until everything due at the clock's current time has run
+	pub fn wait_idle( &self ) {
+		let mut state = self.shared.state.lock().unwrap();
+
+		while ( state.dirty || state.running ) && !state.shutdown {
+			state = self.shared.idle.wait( state ).unwrap();
+		}
+	}
+
+	// Stops the thread (after the job currently running, if any). Jobs that haven't run are dropped
+	pub fn shutdown( mut self ) {
+		self.stop();
+	}
+
+	fn stop( &mut self ) {
+		self.shared.state.lock().unwrap().shutdown = true;
+		self.shared.wake.notify_all();
+		self.shared.idle.notify_all();
+
+		// Dropped by one of its own jobs: joining would wait for ourselves. The thread ends once the job returns
+		if let Some( thread ) = self.thread.take() && thread.thread().id() != std::thread::current().id() {
+			let _ = thread.join();
+		}
+	}
+}
+
+impl Drop for Scheduler {
+	fn drop( &mut self ) {
+		self.stop();
+	}
+}
+
+fn run( shared: Arc<Shared> ) {
+	let mut state = shared.state.lock().unwrap();
+
+	loop {
+		if state.shutdown {
+			return;
+		}
+
+		state.dirty = false;
+
+		let now = to_ticks( shared.clock.now() );
+		let mut ready: Vec<( u64, Task )> = Vec::new();
+
+		for id in state.wheel.advance( now ) {
+			// Skip timers whose job was cancelled (or rescheduled since)
+			if let Some( job ) = state.jobs.get_mut( &id ) && job.deadline <= now && let Some( task ) = job.task.take() {
+				ready.push( ( id, task ) );
+			}
+		}
+
+		if !ready.is_empty() {
+			state.running = true;
+			drop( state );
+
+			let mut done: Vec<( u64, Option<Task> )> = Vec::new();
+
+			for ( id, task ) in ready {
+				match task {
+					Task::Once( f ) => {
+						let _ = panic::catch_unwind( AssertUnwindSafe( f ) );
+						done.push( ( id, None ) );
+					},
+					Task::Every( interval, mut f ) => {
+						let ok = panic::catch_unwind( AssertUnwindSafe( &mut f ) ).is_ok();
+						done.push( ( id, ok.then_some( Task::Every( interval, f ) ) ) );
+					}
+				}
+			}
+
+			state = shared.state.lock().unwrap();
+			state.running = false;
+
+			let now = to_ticks( shared.clock.now() );
+
+			for ( id, task ) in done {
+				match task {
+					// Still scheduled (not cancelled while running): put it back for its next run
+					Some( Task::Every( interval, f ) ) if state.jobs.contains_key( &id ) => {
+						let step = to_ticks( interval ).max( 1 );
+						let deadline = state.jobs[&id].deadline;
+						let missed = now.saturating_sub( deadline ) / step; // Runs we're too late for
+						let next = deadline + ( missed + 1 ) * step;
+
+						let job = state.jobs.get_mut( &id ).unwrap();
+						job.deadline = next;
+						job.task = Some( Task::Every( interval, f ) );
+						state.wheel.insert( next, id );
+					},
+					_ => {
+						state.jobs.remove( &id );
+					}
+				}
+			}
+
+			continue; // Time may have moved while the jobs ran
+		}
+
+		if state.dirty {
+			continue;
+		}
+
+		shared.idle.notify_all();
+
+		// Sleep until the next deadline, or until something changes
+		let next = state.jobs.values().filter( |j| j.task.is_some() ).map( |j| j.deadline ).min();
+		let wait = next.and_then( |deadline| shared.clock.real_wait( from_ticks( deadline.saturating_sub( now ) ) ) );
+
+		state = match wait {
+			Some( d ) => shared.wake.wait_timeout( state, d ).unwrap().0,
+			None => shared.wake.wait( state ).unwrap(),
+		};
+	}
+}
@@ -0,0 +1,396 @@
+// Cancellation tokens and graceful shutdown
+
+/*
+Graceful shutdown.
+
+- Stopping a program "nicely" means: stop taking new work, let the work in progress finish (up to a limit), then exit and say what didn't make it. Killing threads from the outside isn't possible in Rust (or safe anywhere), so every long-running loop has to check whether it should stop. That's what a cancellation token is for.
+- 'CancellationToken': a flag shared by clones. 'cancel()' sets it once and for all; loops check 'is_cancelled()', blocked threads can 'wait()' for it, and 'on_cancel( f )' runs f when it happens (e.g. to wake up a thread stuck in accept()). 'child()' makes a token that is also cancelled with its parent, but can be cancelled on its own.
+- 'ShutdownSignal': a token, plus why it fired, plus a list of the work in progress:
+	* 'trigger()' fires it from code. 'listen_for_os_signals()' also fires it on SIGINT (Ctrl+C) or SIGTERM (e.g. 'kill', systemd, docker stop). Linux only.
+	* 'track( name )' registers some work and returns a guard; the work counts as running until the guard is dropped.
+	* 'drain( timeout )' fires the signal, waits until no tracked work is left (or the timeout passes), and returns a 'ShutdownReport' listing whatever was still running.
+- Server and ThreadPool take a ShutdownSignal ('with_shutdown'): the Server stops accepting and lets each client finish its current request, the pool drops queued jobs and lets running ones finish. Both track their work in the signal.
+
+OS signals.
+
+- A signal handler interrupts the program at any point (even in the middle of malloc), so it may only do "async-signal-safe" things. Here, it writes one byte into a pipe, and a normal thread reading the other end does the real work (the "self-pipe trick").
+- After the first signal the default handlers are put back, so a second Ctrl+C kills the program right away if it hangs while shutting down. A signal that starts listening after that puts the handlers back in place, so it still gets notified.
+- The handler is installed through libc's 'signal()' and 'write()', declared by hand (std links libc already), which needs 'unsafe'.
+*/
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex, Weak};
+use std::time::{Duration, Instant};
+
+type Callback = Box<dyn FnOnce() + Send>;
+
+// Cancellation tokens
+
+#[derive( Default )]
+struct TokenInner {
+	cancelled: AtomicBool,
+	callbacks: Mutex<Vec<Callback>>,
+	cond: Condvar,
+}
+
+#[derive( Clone, Default )]
+pub struct CancellationToken {
+	inner: Arc<TokenInner>,
+}
+
+impl CancellationToken {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	// Only the first call does anything. Callbacks run on the calling thread
+	pub fn cancel( &self ) {
+		let callbacks = {
+			let mut callbacks = self.inner.callbacks.lock().unwrap();
+
+			if self.inner.cancelled.swap( true, Ordering::SeqCst ) {
+				return;
+			}
+
+			std::mem::take( &mut *callbacks )
+		};
+
+		self.inner.cond.notify_all();
+
+		for callback in callbacks {
+			callback();
+		}
+	}
+
+	pub fn is_cancelled( &self ) -> bool {
+		return self.inner.cancelled.load( Ordering::SeqCst );
+	}
+
+	pub fn wait( &self ) {
+		let mut callbacks = self.inner.callbacks.lock().unwrap();
+
+		while !self.is_cancelled() {
+			callbacks = self.inner.cond.wait( callbacks ).unwrap();
+		}
+	}
+
+	// True if cancelled, false if the timeout passed first
+	pub fn wait_timeout( &self, timeout: Duration ) -> bool {
+		let callbacks = self.inner.callbacks.lock().unwrap();
+		let ( _callbacks, _ ) = self.inner.cond.wait_timeout_while( callbacks, timeout, |_| !self.is_cancelled() ).unwrap();
+
+		return self.is_cancelled();
+	}
+
+	// Runs right away if already cancelled
+	pub fn on_cancel( &self, f: impl FnOnce() + Send + 'static ) {
+		let mut callbacks = self.inner.callbacks.lock().unwrap();
+
+		if self.is_cancelled() {
+			drop( callbacks );
+			return f();
+		}
+
+		callbacks.push( Box::new( f ) );
+	}
+
+	// Cancelled along with this token, but cancelling it doesn't affect this one
+	pub fn child( &self ) -> CancellationToken {
+		let child = CancellationToken::new();
+		let weak: Weak<TokenInner> = Arc::downgrade( &child.inner );
+
+		// Weak, so a parent that lives for the whole program doesn't keep every child alive
+		self.on_cancel( move || {
+			if let Some( inner ) = weak.upgrade() {
+				CancellationToken { inner }.cancel();
+			}
+		});
+
+		return child;
+	}
+}
+
+impl fmt::Debug for CancellationToken {
+	fn fmt( &self, f: &mut fmt::Formatter ) -> fmt::Result {
+		write! ( f, "CancellationToken {{ cancelled: {} }}", self.is_cancelled() )
+	}
+}
+
+// Shutdown signal
+
+#[derive( Debug, Clone, Copy, PartialEq, Eq )]
+pub enum Reason {
+	Requested, // trigger() or drain()
+	Interrupt, // SIGINT
+	Terminate, // SIGTERM
+}
+
+impl fmt::Display for Reason {
+	fn fmt( &self, f: &mut fmt::Formatter ) -> fmt::Result {
+		match self {
+			Reason::Requested => write! ( f, "requested" ),
+			Reason::Interrupt => write! ( f, "SIGINT" ),
+			Reason::Terminate => write! ( f, "SIGTERM" ),
+		}
+	}
+}
+
+#[derive( Debug, Clone, PartialEq, Eq )]
+pub struct RunningWork {
+	pub name: String,
+	pub running_for: Duration,
+}
+
+#[derive( Default )]
+struct Work {
+	next_id: u64,
+	running: BTreeMap<u64, ( String, Instant )>, // By id, i.e. oldest first
+}
+
+#[derive( Default )]
+struct SignalInner {
+	token: CancellationToken,
+	reason: Mutex<Option<Reason>>,
+	work: Mutex<Work>,
+	idle: Condvar, // The last tracked work finished
+}
+
+#[derive( Clone, Default )]
+pub struct ShutdownSignal {
+	inner: Arc<SignalInner>,
+}
+
+impl ShutdownSignal {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	// Cancelled when the signal fires. Cheap to clone into every thread that has to stop
+	pub fn token( &self ) -> CancellationToken {
+		return self.inner.token.clone();
+	}
+
+	pub fn trigger( &self ) {
+		self.fire( Reason::Requested );
+	}
+
+	// The first reason wins
+	fn fire( &self, reason: Reason ) {
+		self.inner.reason.lock().unwrap().get_or_insert( reason );
+		self.inner.token.cancel();
+	}
+
+	pub fn is_triggered( &self ) -> bool {
+		return self.inner.token.is_cancelled();
+	}
+
+	pub fn reason( &self ) -> Option<Reason> {
+		return *self.inner.reason.lock().unwrap();
+	}
+
+	// Blocks until the signal fires, and says why
+	pub fn wait( &self ) -> Reason {
+		self.inner.token.wait();
+
+		return self.reason().unwrap_or( Reason::Requested );
+	}
+
+	// Fires on SIGINT and SIGTERM from now on. Only on Linux, elsewhere this is an Err( Unsupported )
+	pub fn listen_for_os_signals( &self ) -> io::Result<()> {
+		return os::listen( Arc::downgrade( &self.inner ) );
+	}
+
+	// The work counts as running until the guard is dropped
+	pub fn track( &self, name: impl Into<String> ) -> WorkGuard {
+		let mut work = self.inner.work.lock().unwrap();
+		work.next_id += 1;
+
+		let id = work.next_id;
+		work.running.insert( id, ( name.into(), Instant::now() ) );
+
+		return WorkGuard { signal: Arc::clone( &self.inner ), id };
+	}
+
+	// Oldest first
+	pub fn running( &self ) -> Vec<RunningWork> {
+		let work = self.inner.work.lock().unwrap();
+
+		return work.running.values().map( |( name, started )| RunningWork { name: name.clone(), running_for: started.elapsed() } ).collect();
+	}
+
+	// Fires the signal (if it hasn't fired yet), then waits up to 'timeout' for the tracked work to finish
+	pub fn drain( &self, timeout: Duration ) -> ShutdownReport {
+		self.fire( Reason::Requested );
+
+		let start = Instant::now();
+		let work = self.inner.work.lock().unwrap();
+		let ( work, _ ) = self.inner.idle.wait_timeout_while( work, timeout, |w| !w.running.is_empty() ).unwrap();
+		drop( work );
+
+		return ShutdownReport {
+			reason: self.reason().unwrap_or( Reason::Requested ),
+			waited: start.elapsed(),
+			still_running: self.running(),
+		};
+	}
+}
+
+impl fmt::Debug for ShutdownSignal {
+	fn fmt( &self, f: &mut fmt::Formatter ) -> fmt::Result {
+		write! ( f, "ShutdownSignal {{ reason: {:?}, running: {} }}", self.reason(), self.inner.work.lock().unwrap().running.len() )
+	}
+}
+
+// Returned by ShutdownSignal::track
+pub struct WorkGuard {
+	signal: Arc<SignalInner>,
+	id: u64,
+}
+
+impl Drop for WorkGuard {
+	fn drop( &mut self ) {
+		let mut work = self.signal.work.lock().unwrap();
+		work.running.remove( &self.id );
+
+		if work.running.is_empty() {
+			self.signal.idle.notify_all();
+		}
+	}
+}
+
+#[derive( Debug, Clone, PartialEq, Eq )]
+pub struct ShutdownReport {
+	pub reason: Reason,
+	pub waited: Duration,
+	pub still_running: Vec<RunningWork>, // Empty if everything finished in time
+}
+
+impl ShutdownReport {
+	pub fn is_clean( &self ) -> bool {
+		return self.still_running.is_empty();
+	}
+}
+
+impl fmt::Display for ShutdownReport {
+	fn fmt( &self, f: &mut fmt::Formatter ) -> fmt::Result {
+		if self.is_clean() {
+			return write! ( f, "Shutdown ({}): everything finished in {:.2?}", self.reason, self.waited );
+		}
+
+		write! ( f, "Shutdown ({}): {} still running after {:.2?}", self.reason, self.still_running.len(), self.waited )?;
+
+		for work in &self.still_running {
+			write! ( f, "\n  {} (running for {:.2?})", work.name, work.running_for )?;
+		}
+
+		Ok( () )
+	}
+}
+
+// SIGINT/SIGTERM through the self-pipe trick
+
+#[cfg( target_os = "linux" )]
+mod os {
+	use super::{Reason, ShutdownSignal, SignalInner};
+	use std::io::{self, Read};
+	use std::os::fd::IntoRawFd;
+	use std::os::raw::c_int;
+	use std::os::unix::net::UnixStream;
+	use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+	use std::sync::{Mutex, OnceLock, Weak};
+	use std::thread;
+
+	const SIGINT: c_int = 2;
+	const SIGTERM: c_int = 15;
+	const SIG_DFL: usize = 0;
+	const SIG_ERR: usize = usize::MAX;
+
+	unsafe extern "C" {
+		fn signal( signum: c_int, handler: usize ) -> usize;
+		fn write( fd: c_int, buf: *const u8, count: usize ) -> isize;
+	}
+
+	// Write end of the pipe, for the handler (which can't take a lock)
+	static PIPE: AtomicI32 = AtomicI32::new( -1 );
+	static LISTENERS: Mutex<Vec<Weak<SignalInner>>> = Mutex::new( Vec::new() );
+	static INSTALLED: OnceLock<io::Result<()>> = OnceLock::new();
+	static ARMED: AtomicBool = AtomicBool::new( false ); // Cleared when the handler puts the defaults back
+
+	// Only async-signal-safe calls in here (atomics are)
+	extern "C" fn on_signal( signum: c_int ) {
+		let byte = signum as u8;
+
+		unsafe {
+			signal( SIGINT, SIG_DFL );
+			signal( SIGTERM, SIG_DFL );
+			ARMED.store( false, Ordering::SeqCst );
+			write( PIPE.load( Ordering::SeqCst ), &byte, 1 );
+		}
+	}
+
+	fn arm() -> io::Result<()> {
+		for signum in [SIGINT, SIGTERM] {
+			if unsafe { signal( signum, on_signal as extern "C" fn( c_int ) as usize ) } == SIG_ERR {
+				return Err( io::Error::last_os_error() );
+			}
+		}
+
+		ARMED.store( true, Ordering::SeqCst );
+
+		return Ok( () );
+	}
+
+	fn install() -> io::Result<()> {
+		let ( mut reader, writer ) = UnixStream::pair()?;
+		PIPE.store( writer.into_raw_fd(), Ordering::SeqCst ); // Kept open for the rest of the program
+
+		arm()?;
+
+		thread::Builder::new().name( "signals".to_string() ).spawn( move || {
+			let mut byte = [0u8];
+
+			while reader.read_exact( &mut byte ).is_ok() {
+				let reason = if byte[0] as c_int == SIGINT { Reason::Interrupt } else { Reason::Terminate };
+				let listeners: Vec<_> = LISTENERS.lock().unwrap().iter().filter_map( Weak::upgrade ).collect();
+
+				for inner in listeners {
+					ShutdownSignal { inner }.fire( reason );
+				}
+			}
+		})?;
+
+		return Ok( () );
+	}
+
+	pub fn listen( inner: Weak<SignalInner> ) -> io::Result<()> {
+		if let Err( e ) = INSTALLED.get_or_init( install ) {
+			return Err( io::Error::new( e.kind(), e.to_string() ) );
+		}
+
+		let mut listeners = LISTENERS.lock().unwrap();
+
+		// A signal already came in and the defaults are back: without re-arming, this listener would never hear anything. Under the lock, so two late listeners don't both arm
+		if !ARMED.load( Ordering::SeqCst ) {
+			arm()?;
+		}
+
+		listeners.retain( |l| l.strong_count() > 0 ); // Signals that were dropped
+		listeners.push( inner );
+
+		return Ok( () );
+	}
+}
+
+#[cfg( not( target_os = "linux" ) )]
+mod os {
+	use super::SignalInner;
+	use std::io;
+	use std::sync::Weak;
+
+	pub fn listen( _inner: Weak<SignalInner> ) -> io::Result<()> {
+		return Err( io::Error::new( io::ErrorKind::Unsupported, "OS signals are only handled on Linux" ) );
+	}
+}
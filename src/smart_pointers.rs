@@ -8,6 +8,9 @@
 use std::cell::{Cell, RefCell}; // Must be imported for interior mutability
 use std::rc::Rc;
 
+use rs_basics::calendar::Calendar;
+use rs_basics::lazy::{ Lazy, SyncLazy, Thunk };
+
 /*
 Smart Pointers.
 - A Pointer ...
@@ -169,3 +172,26 @@ fn check_rc() {
 	println! ( "Node A: {}!", node_a.value );
 	println! ( "Node B: {}!", node_b.value );
 }
+
+// Lazy<T>: Holds a closure instead of a value, and only calls it the first time the value is used (see src/lazy.rs).
+// Useful for expensive setup that a demo might not even need. Under the hood it's a Cell (for the closure) and a OnceCell (for the value).
+
+// A lazy static: a plain static can't hold a parsed file, this one parses it on first use (from any thread)
+static HOLIDAYS: SyncLazy<Option<Calendar>> = SyncLazy::new( || Calendar::from_rules_file( "holidays.rules" ).ok() );
+
+pub fn test_lazy() {
+	let primes = Lazy::new( || {
+		println! ( "Sieving..." ); // Printed once, on first use
+		( 2..10_000u32 ).filter( |n| ( 2..*n ).take_while( |d| d * d <= *n ).all( |d| n % d != 0 ) ).collect::<Vec<u32>>()
+	});
+
+	println! ( "Nothing computed yet: {:?}", Lazy::get( &primes ).map( |p| p.len() ) );
+	println! ( "{} primes, the last is {:?}", primes.len(), primes.last() ); // Deref: computes here, reuses it for '.last()'
+
+	// Thunks chain steps without running any of them
+	let report = Thunk::new( || HOLIDAYS.as_ref().map( |c| c.holidays_in( 2025 ).len() ).unwrap_or( 0 ) )
+		.map( |n| format! ( "{} holidays in 2025", n ) )
+		.memo();
+
+	println! ( "{}", *report ); // The rules file is read here, not before
+}
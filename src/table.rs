@@ -0,0 +1,343 @@
+// Aligned table rendering for collections
+
+/*
+Tables.
+
+- Printing a Vec<Car> with '{:?}' gives one long line. A table puts each item on its own row, with a column per field:
+
+	┌─────────────┬───────────┐
+	│ Name        │ Model     │
+	├─────────────┼───────────┤
+	│ Lamborghini │ Aventador │
+	│ Porsche     │ Panamera  │
+	└─────────────┴───────────┘
+
+- Any type implementing the 'Row' trait can be rendered, so 'Table::from_rows( &car_lot )' works for any 'Vec<T>' (or slice) of rows.
+- Columns can be capped with a max width, and long cells are then either truncated with '…' or wrapped over several lines.
+- Output formats: plain text (Unicode or ASCII borders), Markdown and CSV (RFC 4180 quoting). CSV always contains the full cell text.
+- Widths are counted in chars, which is right for most text (CJK and emoji take two terminal columns, and would need a width table).
+*/
+
+#[derive( Debug, Clone, Copy, PartialEq, Eq )]
+pub enum Format {
+	Plain,
+	Markdown,
+	Csv,
+}
+
+#[derive( Debug, Clone, Copy, PartialEq, Eq )]
+pub enum Border {
+	Unicode,
+	Ascii,
+	None, // Columns separated by two spaces, header underlined
+}
+
+#[derive( Debug, Clone, Copy, PartialEq, Eq )]
+pub enum Overflow {
+	Truncate,
+	Wrap,
+}
+
+#[derive( Debug, Clone, Copy, PartialEq, Eq )]
+pub enum Align {
+	Left,
+	Right,
+	Center,
+}
+
+pub trait Row {
+	fn headers() -> Vec<String>;
+	fn cells( &self ) -> Vec<String>;
+}
+
+#[derive( Debug, Clone )]
+pub struct Table {
+	headers: Vec<String>,
+	rows: Vec<Vec<String>>,
+	aligns: Vec<Align>,
+	max_width: Option<usize>,
+	overflow: Overflow,
+	border: Border,
+}
+
+// Box drawing pieces: [left, middle, right, horizontal] for the top, separator and bottom lines, plus the vertical bar
+struct Pieces {
+	top: [&'static str; 4],
+	mid: [&'static str; 4],
+	bottom: [&'static str; 4],
+	vertical: &'static str,
+}
+
+const UNICODE: Pieces = Pieces {
+	top: ["┌", "┬", "┐", "─"],
+	mid: ["├", "┼", "┤", "─"],
+	bottom: ["└", "┴", "┘", "─"],
+	vertical: "│",
+};
+
+const ASCII: Pieces = Pieces {
+	top: ["+", "+", "+", "-"],
+	mid: ["+", "+", "+", "-"],
+	bottom: ["+", "+", "+", "-"],
+	vertical: "|",
+};
+
+fn width( s: &str ) -> usize {
+	return s.chars().count();
+}
+
+fn truncate( s: &str, max: usize ) -> String {
+	if width( s ) <= max {
+		return s.to_string();
+	}
+
+	if max == 0 {
+		return String::new();
+	}
+
+	return s.chars().take( max - 1 ).chain( std::iter::once( '…' ) ).collect();
+}
+
+// Greedy word wrap. Words longer than the width are split
+fn wrap( s: &str, max: usize ) -> Vec<String> {
+	let max = max.max( 1 );
+	let mut lines: Vec<String> = Vec::new();
+
+	for paragraph in s.split( '\n' ) {
+		let mut line = String::new();
+
+		for word in paragraph.split_whitespace() {
+			let mut word: Vec<char> = word.chars().collect();
+
+			// Start a new line if the word doesn't fit after the current one
+			if !line.is_empty() && width( &line ) + 1 + word.len() > max {
+				lines.push( std::mem::take( &mut line ) );
+			}
+
+			while word.len() > max {
+				if !line.is_empty() {
+					lines.push( std::mem::take( &mut line ) );
+				}
+
+				lines.push( word.drain( ..max ).collect() );
+			}
+
+			if !word.is_empty() {
+				if !line.is_empty() {
+					line.push( ' ' );
+				}
+
+				line.extend( word );
+			}
+		}
+
+		lines.push( line );
+	}
+
+	return lines;
+}
+
+fn pad( s: &str, w: usize, align: Align ) -> String {
+	let gap = w.saturating_sub( width( s ) );
+
+	match align {
+		Align::Left => format! ( "{}{}", s, " ".repeat( gap ) ),
+		Align::Right => format! ( "{}{}", " ".repeat( gap ), s ),
+		Align::Center => format! ( "{}{}{}", " ".repeat( gap / 2 ), s, " ".repeat( gap - gap / 2 ) ),
+	}
+}
+
+fn csv_field( s: &str ) -> String {
+	if s.contains( [',', '"', '\n', '\r'] ) {
+		return format! ( "\"{}\"", s.replace( '"', "\"\"" ) );
+	}
+
+	return s.to_string();
+}
+
+fn markdown_cell( s: &str ) -> String {
+	return s.replace( '\\', "\\\\" ).replace( '|', "\\|" ).replace( '\n', "<br>" );
+}
+
+impl Table {
+	pub fn new( headers: Vec<String> ) -> Self {
+		let aligns = vec![Align::Left; headers.len()];
+
+		Self { headers, rows: Vec::new(), aligns, max_width: None, overflow: Overflow::Truncate, border: Border::Unicode }
+	}
+
+	pub fn from_rows<T: Row>( rows: &[T] ) -> Self {
+		let mut table = Table::new( T::headers() );
+
+		for row in rows {
+			table.push( row.cells() );
+		}
+
+		return table;
+	}
+
+	// Rows shorter than the header are padded with empty cells, extra cells are dropped
+	pub fn push( &mut self, mut cells: Vec<String> ) {
+		cells.resize( self.headers.len(), String::new() );
+		self.rows.push( cells );
+	}
+
+	pub fn align( mut self, column: usize, align: Align ) -> Self {
+		if let Some( a ) = self.aligns.get_mut( column ) {
+			*a = align;
+		}
+		self
+	}
+
+	pub fn max_width( mut self, max: usize ) -> Self {
+		self.max_width = Some( max );
+		self
+	}
+
+	pub fn overflow( mut self, overflow: Overflow ) -> Self {
+		self.overflow = overflow;
+		self
+	}
+
+	pub fn border( mut self, border: Border ) -> Self {
+		self.border = border;
+		self
+	}
+
+	pub fn render( &self, format: Format ) -> String {
+		match format {
+			Format::Plain => self.render_plain(),
+			Format::Markdown => self.render_markdown(),
+			Format::Csv => self.render_csv(),
+		}
+	}
+
+	// Splits a cell into the lines it takes up, after applying the max width
+	fn cell_lines( &self, cell: &str ) -> Vec<String> {
+		match ( self.max_width, self.overflow ) {
+			( Some( max ), Overflow::Wrap ) => wrap( cell, max ),
+			( Some( max ), Overflow::Truncate ) => vec![truncate( &cell.replace( '\n', " " ), max )],
+			( None, _ ) => cell.split( '\n' ).map( |l| l.to_string() ).collect(),
+		}
+	}
+
+	fn render_plain( &self ) -> String {
+		let header_lines: Vec<Vec<String>> = self.headers.iter().map( |h| self.cell_lines( h ) ).collect();
+		let body: Vec<Vec<Vec<String>>> = self.rows
+			.iter()
+			.map( |row| row.iter().map( |c| self.cell_lines( c ) ).collect() )
+			.collect();
+
+		let mut widths: Vec<usize> = header_lines.iter().map( |lines| lines.iter().map( |l| width( l ) ).max().unwrap_or( 0 ) ).collect();
+
+		for row in &body {
+			for ( i, lines ) in row.iter().enumerate() {
+				widths[i] = widths[i].max( lines.iter().map( |l| width( l ) ).max().unwrap_or( 0 ) );
+			}
+		}
+
+		let mut out = String::new();
+
+		let rule = |pieces: &[&str; 4]| -> String {
+			let segments: Vec<String> = widths.iter().map( |w| pieces[3].repeat( w + 2 ) ).collect();
+
+			return format! ( "{}{}{}\n", pieces[0], segments.join( pieces[1] ), pieces[2] );
+		};
+
+		// A logical row can span several lines when cells wrap
+		let lines_of = |row: &[Vec<String>], aligns: &[Align], vertical: Option<&str>| -> String {
+			let height = row.iter().map( |c| c.len() ).max().unwrap_or( 1 );
+			let mut text = String::new();
+
+			for n in 0..height {
+				let cells: Vec<String> = row
+					.iter()
+					.enumerate()
+					.map( |( i, lines )| pad( lines.get( n ).map( |s| s.as_str() ).unwrap_or( "" ), widths[i], aligns[i] ) )
+					.collect();
+
+				let line = match vertical {
+					Some( v ) => format! ( "{} {} {}", v, cells.join( &format! ( " {} ", v ) ), v ),
+					None => cells.join( "  " ).trim_end().to_string()
+				};
+
+				text.push_str( &line );
+				text.push( '\n' );
+			}
+
+			return text;
+		};
+
+		let pieces = match self.border {
+			Border::Unicode => Some( &UNICODE ),
+			Border::Ascii => Some( &ASCII ),
+			Border::None => None
+		};
+
+		match pieces {
+			Some( p ) => {
+				out.push_str( &rule( &p.top ) );
+				out.push_str( &lines_of( &header_lines, &vec![Align::Left; widths.len()], Some( p.vertical ) ) );
+				out.push_str( &rule( &p.mid ) );
+
+				for row in &body {
+					out.push_str( &lines_of( row, &self.aligns, Some( p.vertical ) ) );
+				}
+
+				out.push_str( &rule( &p.bottom ) );
+			},
+			None => {
+				out.push_str( &lines_of( &header_lines, &vec![Align::Left; widths.len()], None ) );
+				out.push_str( &widths.iter().map( |w| "-".repeat( *w ) ).collect::<Vec<_>>().join( "  " ) );
+				out.push( '\n' );
+
+				for row in &body {
+					out.push_str( &lines_of( row, &self.aligns, None ) );
+				}
+			}
+		}
+
+		return out;
+	}
+
+	fn render_markdown( &self ) -> String {
+		let cell = |c: &str| -> String {
+			let c = match self.max_width {
+				Some( max ) if self.overflow == Overflow::Truncate => truncate( c, max ),
+				_ => c.to_string() // Markdown renderers wrap by themselves
+			};
+
+			return markdown_cell( &c );
+		};
+
+		let mut out = format! ( "| {} |\n", self.headers.iter().map( |h| cell( h ) ).collect::<Vec<_>>().join( " | " ) );
+		let separators: Vec<&str> = self.aligns
+			.iter()
+			.map( |a| match a { Align::Left => "---", Align::Right => "---:", Align::Center => ":---:" } )
+			.collect();
+
+		out.push_str( &format! ( "| {} |\n", separators.join( " | " ) ) );
+
+		for row in &self.rows {
+			out.push_str( &format! ( "| {} |\n", row.iter().map( |c| cell( c ) ).collect::<Vec<_>>().join( " | " ) ) );
+		}
+
+		return out;
+	}
+
+	fn render_csv( &self ) -> String {
+		let mut out = String::new();
+
+		for row in std::iter::once( &self.headers ).chain( self.rows.iter() ) {
+			out.push_str( &row.iter().map( |c| csv_field( c ) ).collect::<Vec<_>>().join( "," ) );
+			out.push_str( "\r\n" ); // RFC 4180 line endings
+		}
+
+		return out;
+	}
+}
+
+// Shorthand for the common case: a Unicode table with default settings
+pub fn render<T: Row>( rows: &[T] ) -> String {
+	return Table::from_rows( rows ).render( Format::Plain );
+}
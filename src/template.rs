@@ -0,0 +1,479 @@
+// A small template engine for display strings
+
+/*
+Templates.
+
+- Instead of hardcoding formats in 'format!' and 'println!', a template keeps the text and its placeholders in one string:
+
+	"Person {{name}} is {{age}} years old{{#if pet}} and owns {{pet}}{{/if}}!"
+
+- Syntax:
+	* {{name}}                       Inserts a value (nothing if missing). Dotted paths work too: {{owner.name}}
+	* {{name | upper | truncate:10}} Pipes the value through filters, left to right
+	* {{#if pet}}..{{else}}..{{/if}} Conditional. Null, false, 0, "" and empty lists are false
+	* {{#unless pet}}..{{/unless}}   The opposite of #if
+	* {{#each cars}}..{{/each}}      Loops over a list. Inside, {{this}} is the item, {{@index}} its position, and the item's fields are in scope
+	* {{! a comment }}               Ignored
+- Filters: upper, lower, trim, title, snake, len, default:"text", truncate:N, join:", ", pad:N.
+- Values come from a 'Context': any struct can implement it, to expose its fields by name.
+- Syntax errors (unclosed tags, unbalanced blocks, unknown filters) are reported with a line and column when parsing, so a bad template fails before it's ever rendered.
+*/
+
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::text;
+
+#[derive( Debug, Clone, PartialEq )]
+pub enum Value {
+	Null,
+	Bool( bool ),
+	Int( i64 ),
+	Float( f64 ),
+	Str( String ),
+	List( Vec<Value> ),
+	Map( HashMap<String, Value> ),
+}
+
+impl Value {
+	pub fn is_truthy( &self ) -> bool {
+		match self {
+			Value::Null => false,
+			Value::Bool( b ) => *b,
+			Value::Int( n ) => *n != 0,
+			Value::Float( f ) => *f != 0.0,
+			Value::Str( s ) => !s.is_empty(),
+			Value::List( l ) => !l.is_empty(),
+			Value::Map( m ) => !m.is_empty(),
+		}
+	}
+
+	fn field( &self, key: &str ) -> Option<&Value> {
+		match self {
+			Value::Map( m ) => m.get( key ),
+			Value::List( l ) => key.parse::<usize>().ok().and_then( |i| l.get( i ) ),
+			_ => None
+		}
+	}
+}
+
+impl fmt::Display for Value {
+	fn fmt( &self, f: &mut fmt::Formatter<'_> ) -> fmt::Result {
+		match self {
+			Value::Null => Ok( () ),
+			Value::Bool( b ) => write! ( f, "{}", b ),
+			Value::Int( n ) => write! ( f, "{}", n ),
+			Value::Float( x ) => write! ( f, "{}", x ),
+			Value::Str( s ) => f.write_str( s ),
+			Value::List( l ) => {
+				let items: Vec<String> = l.iter().map( |v| v.to_string() ).collect();
+				f.write_str( &items.join( ", " ) )
+			},
+			Value::Map( _ ) => f.write_str( "[object]" ),
+		}
+	}
+}
+
+// Conversions, so contexts can write 'self.Age.into()'
+impl From<&str> for Value { fn from( v: &str ) -> Self { Value::Str( v.to_string() ) } }
+impl From<String> for Value { fn from( v: String ) -> Self { Value::Str( v ) } }
+impl From<&String> for Value { fn from( v: &String ) -> Self { Value::Str( v.clone() ) } }
+impl From<bool> for Value { fn from( v: bool ) -> Self { Value::Bool( v ) } }
+impl From<i64> for Value { fn from( v: i64 ) -> Self { Value::Int( v ) } }
+impl From<i32> for Value { fn from( v: i32 ) -> Self { Value::Int( v as i64 ) } }
+impl From<u8> for Value { fn from( v: u8 ) -> Self { Value::Int( v as i64 ) } }
+impl From<u16> for Value { fn from( v: u16 ) -> Self { Value::Int( v as i64 ) } }
+impl From<u32> for Value { fn from( v: u32 ) -> Self { Value::Int( v as i64 ) } }
+impl From<usize> for Value { fn from( v: usize ) -> Self { Value::Int( v as i64 ) } }
+impl From<f64> for Value { fn from( v: f64 ) -> Self { Value::Float( v ) } }
+
+impl<T: Into<Value>> From<Option<T>> for Value {
+	fn from( v: Option<T> ) -> Self {
+		match v {
+			Some( v ) => v.into(),
+			None => Value::Null
+		}
+	}
+}
+
+impl<T: Into<Value>> From<Vec<T>> for Value {
+	fn from( v: Vec<T> ) -> Self {
+		Value::List( v.into_iter().map( |x| x.into() ).collect() )
+	}
+}
+
+// Anything templates can read values from
+pub trait Context {
+	fn get( &self, key: &str ) -> Option<Value>;
+
+	// Turns the whole context into a Value::Map (used to put structs inside lists), given the keys to copy
+	fn to_value( &self, keys: &[&str] ) -> Value {
+		let map = keys.iter().filter_map( |k| self.get( k ).map( |v| ( k.to_string(), v ) ) ).collect();
+
+		return Value::Map( map );
+	}
+}
+
+impl Context for HashMap<String, Value> {
+	fn get( &self, key: &str ) -> Option<Value> {
+		return HashMap::get( self, key ).cloned();
+	}
+}
+
+impl Context for Value {
+	fn get( &self, key: &str ) -> Option<Value> {
+		return self.field( key ).cloned();
+	}
+}
+
+#[derive( Debug, Clone, PartialEq, Eq )]
+pub struct TemplateError {
+	pub message: String,
+	pub line: usize,
+	pub col: usize,
+}
+
+impl fmt::Display for TemplateError {
+	fn fmt( &self, f: &mut fmt::Formatter<'_> ) -> fmt::Result {
+		write! ( f, "line {}, column {}: {}", self.line, self.col, self.message )
+	}
+}
+
+impl std::error::Error for TemplateError {}
+
+#[derive( Debug, Clone, PartialEq )]
+enum Filter {
+	Upper,
+	Lower,
+	Trim,
+	Title,
+	Snake,
+	Len,
+	Default( String ),
+	Truncate( usize ),
+	Join( String ),
+	Pad( usize ),
+}
+
+#[derive( Debug, Clone, PartialEq )]
+enum Node {
+	Text( String ),
+	Var { path: Vec<String>, filters: Vec<Filter> },
+	If { path: Vec<String>, negate: bool, then: Vec<Node>, otherwise: Vec<Node> },
+	Each { path: Vec<String>, body: Vec<Node>, otherwise: Vec<Node> },
+}
+
+#[derive( Debug, Clone, PartialEq )]
+pub struct Template {
+	nodes: Vec<Node>,
+}
+
+// The block currently being parsed, and where it was opened (for "unclosed {{#if}}" errors)
+struct OpenBlock {
+	kind: String, // "if", "unless" or "each"
+	path: Vec<String>,
+	then: Vec<Node>,
+	otherwise: Option<Vec<Node>>, // Some once {{else}} was seen
+	line: usize,
+	col: usize,
+}
+
+fn position( src: &str, offset: usize ) -> ( usize, usize ) {
+	let before = &src[..offset];
+	let line = before.matches( '\n' ).count() + 1;
+	let col = before.rfind( '\n' ).map( |i| before[i + 1..].chars().count() + 1 ).unwrap_or( before.chars().count() + 1 );
+
+	return ( line, col );
+}
+
+fn parse_path( expr: &str, err: &dyn Fn( String ) -> TemplateError ) -> Result<Vec<String>, TemplateError> {
+	let expr = expr.trim();
+
+	if expr.is_empty() {
+		return Err( err( "Missing variable name".to_string() ) );
+	}
+
+	let path: Vec<String> = expr.split( '.' ).map( |s| s.trim().to_string() ).collect();
+
+	for part in &path {
+		let valid = !part.is_empty() && part.chars().enumerate().all( |( i, c )| c.is_alphanumeric() || c == '_' || ( i == 0 && c == '@' ) );
+
+		if !valid {
+			return Err( err( format! ( "Invalid variable name '{}'", expr ) ) );
+		}
+	}
+
+	return Ok( path );
+}
+
+fn parse_filter( spec: &str, err: &dyn Fn( String ) -> TemplateError ) -> Result<Filter, TemplateError> {
+	let ( name, arg ) = match spec.split_once( ':' ) {
+		Some( ( n, a ) ) => ( n.trim(), Some( a.trim() ) ),
+		None => ( spec.trim(), None )
+	};
+
+	// Arguments are either "quoted strings" or bare numbers/words
+	let string_arg = || -> Result<String, TemplateError> {
+		let a = arg.ok_or( err( format! ( "Filter '{}' needs an argument", name ) ) )?;
+
+		if a.len() >= 2 && a.starts_with( '"' ) && a.ends_with( '"' ) {
+			return Ok( a[1..a.len() - 1].to_string() );
+		}
+
+		return Ok( a.to_string() );
+	};
+	let number_arg = || -> Result<usize, TemplateError> {
+		return string_arg()?.parse::<usize>().map_err( |_| err( format! ( "Filter '{}' needs a number", name ) ) );
+	};
+
+	let filter = match name {
+		"upper" => Filter::Upper,
+		"lower" => Filter::Lower,
+		"trim" => Filter::Trim,
+		"title" => Filter::Title,
+		"snake" => Filter::Snake,
+		"len" => Filter::Len,
+		"default" => Filter::Default( string_arg()? ),
+		"truncate" => Filter::Truncate( number_arg()? ),
+		"join" => Filter::Join( string_arg()? ),
+		"pad" => Filter::Pad( number_arg()? ),
+		_ => return Err( err( format! ( "Unknown filter '{}'", name ) ) )
+	};
+
+	return Ok( filter );
+}
+
+// Splits "name | default:\"a | b\"" on the pipes that aren't inside quotes
+fn split_pipes( expr: &str ) -> Vec<&str> {
+	let mut parts: Vec<&str> = Vec::new();
+	let mut in_quotes = false;
+	let mut start = 0;
+
+	for ( i, c ) in expr.char_indices() {
+		match c {
+			'"' => in_quotes = !in_quotes,
+			'|' if !in_quotes => {
+				parts.push( &expr[start..i] );
+				start = i + 1;
+			},
+			_ => {}
+		}
+	}
+
+	parts.push( &expr[start..] );
+
+	return parts;
+}
+
+impl Template {
+	pub fn parse( src: &str ) -> Result<Template, TemplateError> {
+		let mut stack: Vec<OpenBlock> = Vec::new();
+		let mut nodes: Vec<Node> = Vec::new(); // Top level
+		let mut rest = 0; // Byte offset of the text not parsed yet
+
+		// Nodes go to the innermost open block (its 'else' branch if we've seen one), or the top level
+		fn push( stack: &mut [OpenBlock], nodes: &mut Vec<Node>, node: Node ) {
+			match stack.last_mut() {
+				Some( block ) => match &mut block.otherwise {
+					Some( otherwise ) => otherwise.push( node ),
+					None => block.then.push( node )
+				},
+				None => nodes.push( node )
+			}
+		}
+
+		while let Some( found ) = src[rest..].find( "{{" ) {
+			let start = rest + found;
+			let ( line, col ) = position( src, start );
+			let err = |message: String| TemplateError { message, line, col };
+
+			if start > rest {
+				push( &mut stack, &mut nodes, Node::Text( src[rest..start].to_string() ) );
+			}
+
+			let end = src[start + 2..].find( "}}" ).map( |i| start + 2 + i ).ok_or( err( "Unclosed tag, expected '}}'".to_string() ) )?;
+			let tag = src[start + 2..end].trim();
+			rest = end + 2;
+
+			if tag.starts_with( '!' ) {
+				continue; // Comments render nothing
+			}
+
+			if let Some( open ) = tag.strip_prefix( '#' ) {
+				let ( kind, expr ) = open.split_once( char::is_whitespace ).unwrap_or( ( open, "" ) );
+
+				if !matches! ( kind, "if" | "unless" | "each" ) {
+					return Err( err( format! ( "Unknown block '#{}'", kind ) ) );
+				}
+
+				stack.push( OpenBlock {
+					kind: kind.to_string(),
+					path: parse_path( expr, &err )?,
+					then: Vec::new(),
+					otherwise: None,
+					line,
+					col
+				});
+			} else if tag == "else" {
+				match stack.last_mut() {
+					Some( block ) if block.otherwise.is_none() => block.otherwise = Some( Vec::new() ),
+					Some( _ ) => return Err( err( "Duplicate {{else}}".to_string() ) ),
+					None => return Err( err( "{{else}} outside of a block".to_string() ) )
+				}
+			} else if let Some( close ) = tag.strip_prefix( '/' ) {
+				let block = stack.pop().ok_or( err( format! ( "Unexpected {{{{/{}}}}}", close.trim() ) ) )?;
+
+				if close.trim() != block.kind {
+					return Err( err( format! ( "Expected {{{{/{}}}}} (opened at line {}, column {}), found {{{{/{}}}}}", block.kind, block.line, block.col, close.trim() ) ) );
+				}
+
+				let otherwise = block.otherwise.unwrap_or_default();
+				let node = match block.kind.as_str() {
+					"each" => Node::Each { path: block.path, body: block.then, otherwise },
+					kind => Node::If { path: block.path, negate: kind == "unless", then: block.then, otherwise }
+				};
+
+				push( &mut stack, &mut nodes, node );
+			} else {
+				let mut parts = split_pipes( tag ).into_iter();
+				let path = parse_path( parts.next().unwrap_or( "" ), &err )?;
+				let filters = parts.map( |p| parse_filter( p, &err ) ).collect::<Result<Vec<_>, _>>()?;
+
+				push( &mut stack, &mut nodes, Node::Var { path, filters } );
+			}
+		}
+
+		if let Some( block ) = stack.last() {
+			return Err( TemplateError { message: format! ( "Unclosed {{{{#{}}}}}", block.kind ), line: block.line, col: block.col } );
+		}
+
+		if rest < src.len() {
+			nodes.push( Node::Text( src[rest..].to_string() ) );
+		}
+
+		return Ok( Template { nodes } );
+	}
+
+	pub fn render( &self, ctx: &dyn Context ) -> String {
+		let mut out = String::new();
+		let mut scopes: Vec<Value> = Vec::new();
+
+		render_nodes( &self.nodes, ctx, &mut scopes, &mut out );
+
+		return out;
+	}
+}
+
+// Parses and renders in one go
+pub fn render( src: &str, ctx: &dyn Context ) -> Result<String, TemplateError> {
+	return Ok( Template::parse( src )?.render( ctx ) );
+}
+
+// Looks a path up in the loop scopes (innermost first), then in the root context
+fn lookup( path: &[String], ctx: &dyn Context, scopes: &[Value] ) -> Value {
+	let first = path[0].as_str();
+	let mut value: Option<Value> = None;
+
+	if first == "this" || first.starts_with( '@' ) {
+		if let Some( scope ) = scopes.last() {
+			value = if first == "this" { scope.field( "this" ).cloned() } else { scope.field( first ).cloned() };
+		}
+	} else {
+		for scope in scopes.iter().rev() {
+			if let Some( v ) = scope.field( "this" ).and_then( |item| item.field( first ) ) {
+				value = Some( v.clone() );
+				break;
+			}
+		}
+
+		if value.is_none() {
+			value = ctx.get( first );
+		}
+	}
+
+	let mut current = match value {
+		Some( v ) => v,
+		None => return Value::Null
+	};
+
+	for part in &path[1..] {
+		current = match current.field( part ) {
+			Some( v ) => v.clone(),
+			None => return Value::Null
+		};
+	}
+
+	return current;
+}
+
+fn apply_filter( value: Value, filter: &Filter ) -> Value {
+	match filter {
+		Filter::Upper => Value::Str( value.to_string().to_uppercase() ),
+		Filter::Lower => Value::Str( value.to_string().to_lowercase() ),
+		Filter::Trim => Value::Str( value.to_string().trim().to_string() ),
+		Filter::Title => Value::Str( text::to_title_case( &value.to_string() ) ),
+		Filter::Snake => Value::Str( text::to_snake_case( &value.to_string() ) ),
+		Filter::Len => match &value {
+			Value::List( l ) => Value::Int( l.len() as i64 ),
+			Value::Map( m ) => Value::Int( m.len() as i64 ),
+			v => Value::Int( v.to_string().chars().count() as i64 )
+		},
+		Filter::Default( d ) => if value.is_truthy() { value } else { Value::Str( d.clone() ) },
+		Filter::Truncate( n ) => {
+			let s = value.to_string();
+
+			if s.chars().count() <= *n {
+				Value::Str( s )
+			} else {
+				Value::Str( s.chars().take( n.saturating_sub( 1 ) ).chain( std::iter::once( '…' ) ).collect() )
+			}
+		},
+		Filter::Join( sep ) => match &value {
+			Value::List( l ) => Value::Str( l.iter().map( |v| v.to_string() ).collect::<Vec<_>>().join( sep ) ),
+			_ => value
+		},
+		Filter::Pad( n ) => Value::Str( format! ( "{:<width$}", value.to_string(), width = *n ) ),
+	}
+}
+
+fn render_nodes( nodes: &[Node], ctx: &dyn Context, scopes: &mut Vec<Value>, out: &mut String ) {
+	for node in nodes {
+		match node {
+			Node::Text( t ) => out.push_str( t ),
+			Node::Var { path, filters } => {
+				let value = filters.iter().fold( lookup( path, ctx, scopes ), apply_filter );
+				out.push_str( &value.to_string() );
+			},
+			Node::If { path, negate, then, otherwise } => {
+				let truthy = lookup( path, ctx, scopes ).is_truthy() != *negate;
+
+				render_nodes( if truthy { then } else { otherwise }, ctx, scopes, out );
+			},
+			Node::Each { path, body, otherwise } => {
+				let items = match lookup( path, ctx, scopes ) {
+					Value::List( l ) => l,
+					Value::Null => Vec::new(),
+					other => vec![other] // A single value loops once
+				};
+
+				if items.is_empty() {
+					render_nodes( otherwise, ctx, scopes, out );
+				}
+
+				let count = items.len();
+
+				for ( i, item ) in items.into_iter().enumerate() {
+					let mut scope: HashMap<String, Value> = HashMap::new();
+					scope.insert( "this".to_string(), item );
+					scope.insert( "@index".to_string(), Value::Int( i as i64 ) );
+					scope.insert( "@first".to_string(), Value::Bool( i == 0 ) );
+					scope.insert( "@last".to_string(), Value::Bool( i + 1 == count ) );
+
+					scopes.push( Value::Map( scope ) );
+					render_nodes( body, ctx, scopes, out );
+					scopes.pop();
+				}
+			},
+		}
+	}
+}
@@ -0,0 +1,173 @@
+// Text utilities: case conversion between naming conventions
+
+/*
+Case conversion.
+
+- Any identifier or phrase is first split into words, then re-joined in the target convention:
+	* "HTTPServer"     -> ["HTTP", "Server"]
+	* "getHTTPResponse" -> ["get", "HTTP", "Response"]
+	* "user_id", "user-id", "User ID" -> ["user", "id"] / ["User", "ID"]
+	* "utf8Value"      -> ["utf8", "Value"] (digits stick to the word before them)
+	* "parseURLs2"     -> ["parse", "URLs2"] (a lone 's' after an acronym is its plural, not the start of a word)
+	* "DoB"            -> ["Do", "B"] (a single capital is a word of its own, like in "isA", so 'DoB' suggests 'do_b')
+- Word boundaries are: any non-alphanumeric character, a lowercase (or digit) followed by an uppercase, and the last capital of an acronym followed by a lowercase ("HTTPServer" splits before 'S').
+- Acronyms follow the Rust API guidelines in camelCase and PascalCase, i.e. they count as one word: "HTTP server" -> "HttpServer", not "HTTPServer".
+- Title Case keeps acronyms upper-cased: "http_server" -> "Http Server", but "HTTP_SERVER" -> "HTTP SERVER".
+*/
+
+use std::fmt;
+
+#[derive( Debug, Clone, Copy, PartialEq, Eq, Hash )]
+pub enum Case {
+	Snake, // snake_case
+	Camel, // camelCase
+	Pascal, // PascalCase
+	Kebab, // kebab-case
+	ScreamingSnake, // SCREAMING_SNAKE_CASE
+	Title, // Title Case
+}
+
+impl Case {
+	pub const ALL: [Case; 6] = [Case::Snake, Case::Camel, Case::Pascal, Case::Kebab, Case::ScreamingSnake, Case::Title];
+
+	pub fn name( &self ) -> &'static str {
+		match self {
+			Case::Snake => "snake_case",
+			Case::Camel => "camelCase",
+			Case::Pascal => "PascalCase",
+			Case::Kebab => "kebab-case",
+			Case::ScreamingSnake => "SCREAMING_SNAKE_CASE",
+			Case::Title => "Title Case",
+		}
+	}
+}
+
+impl fmt::Display for Case {
+	fn fmt( &self, f: &mut fmt::Formatter<'_> ) -> fmt::Result {
+		f.write_str( self.name() )
+	}
+}
+
+pub fn split_words( input: &str ) -> Vec<String> {
+	let chars: Vec<char> = input.chars().collect();
+	let mut words: Vec<String> = Vec::new();
+	let mut current = String::new();
+
+	for ( i, &c ) in chars.iter().enumerate() {
+		if !c.is_alphanumeric() {
+			if !current.is_empty() {
+				words.push( std::mem::take( &mut current ) );
+			}
+			continue;
+		}
+
+		if !current.is_empty() && c.is_uppercase() {
+			let prev = chars[i - 1];
+			let next_is_lower = chars.get( i + 1 ).is_some_and( |n| n.is_lowercase() );
+			let plural = chars.get( i + 1 ) == Some( &'s' ) && !chars.get( i + 2 ).is_some_and( |n| n.is_lowercase() );
+
+			// "fooBar" and "utf8Value", or the end of an acronym in "HTTPServer" (but not in "URLs")
+			if prev.is_lowercase() || prev.is_numeric() || ( prev.is_uppercase() && next_is_lower && !plural ) {
+				words.push( std::mem::take( &mut current ) );
+			}
+		}
+
+		current.push( c );
+	}
+
+	if !current.is_empty() {
+		words.push( current );
+	}
+
+	return words;
+}
+
+// "HTTP", "UTF8" and plurals like "URLs"
+fn is_acronym( word: &str ) -> bool {
+	let chars: Vec<char> = word.chars().collect();
+	let plural_s = |i: usize| chars[i] == 's' && i >= 2 && chars[i - 1].is_uppercase() && chars[i - 2].is_uppercase();
+	let lowercase: Vec<usize> = ( 0..chars.len() ).filter( |&i| chars[i].is_lowercase() ).collect();
+
+	return chars.iter().filter( |c| c.is_uppercase() ).count() > 1 && ( lowercase.is_empty() || ( lowercase.len() == 1 && plural_s( lowercase[0] ) ) );
+}
+
+fn capitalize( word: &str ) -> String {
+	let mut chars = word.chars();
+
+	match chars.next() {
+		Some( first ) => first.to_uppercase().chain( chars.flat_map( |c| c.to_lowercase() ) ).collect(),
+		None => String::new()
+	}
+}
+
+pub fn to_snake_case( input: &str ) -> String {
+	return split_words( input ).iter().map( |w| w.to_lowercase() ).collect::<Vec<_>>().join( "_" );
+}
+
+pub fn to_kebab_case( input: &str ) -> String {
+	return split_words( input ).iter().map( |w| w.to_lowercase() ).collect::<Vec<_>>().join( "-" );
+}
+
+pub fn to_screaming_snake_case( input: &str ) -> String {
+	return split_words( input ).iter().map( |w| w.to_uppercase() ).collect::<Vec<_>>().join( "_" );
+}
+
+pub fn to_pascal_case( input: &str ) -> String {
+	return split_words( input ).iter().map( |w| capitalize( w ) ).collect();
+}
+
+pub fn to_camel_case( input: &str ) -> String {
+	let mut out = String::new();
+
+	for ( i, word ) in split_words( input ).iter().enumerate() {
+		if i == 0 {
+			out.push_str( &word.to_lowercase() );
+		} else {
+			out.push_str( &capitalize( word ) );
+		}
+	}
+
+	return out;
+}
+
+pub fn to_title_case( input: &str ) -> String {
+	return split_words( input )
+		.iter()
+		.map( |w| if is_acronym( w ) { w.clone() } else { capitalize( w ) } )
+		.collect::<Vec<_>>()
+		.join( " " );
+}
+
+pub fn convert( input: &str, case: Case ) -> String {
+	match case {
+		Case::Snake => to_snake_case( input ),
+		Case::Camel => to_camel_case( input ),
+		Case::Pascal => to_pascal_case( input ),
+		Case::Kebab => to_kebab_case( input ),
+		Case::ScreamingSnake => to_screaming_snake_case( input ),
+		Case::Title => to_title_case( input ),
+	}
+}
+
+/*
+- An identifier is "in" a case if converting it to that case doesn't change it.
+- Leading and trailing underscores are ignored, since Rust uses them for unused bindings ('_name') and to dodge keywords ('type_').
+*/
+pub fn is_case( input: &str, case: Case ) -> bool {
+	let core = match case {
+		Case::Snake | Case::ScreamingSnake | Case::Camel | Case::Pascal => input.trim_matches( '_' ),
+		_ => input
+	};
+
+	// Only underscores ('_', '__'): a placeholder, fine in every case that allows them
+	if core.is_empty() {
+		return !input.is_empty() && core.len() != input.len();
+	}
+
+	return convert( core, case ) == core;
+}
+
+// The first case (in 'Case::ALL' order) the input is already written in, if any. A single lowercase word like "name" counts as snake_case
+pub fn detect_case( input: &str ) -> Option<Case> {
+	return Case::ALL.iter().copied().find( |c| is_case( input, *c ) );
+}
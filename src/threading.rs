@@ -11,9 +11,24 @@
 // Arc is also immutable by default, but can be wrapped inside a Mutex<T> or a RwLock<T> for interior mutability.
 
 use std::{sync::{
-	Arc, Mutex, RwLock
+	Arc, Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard
 }, thread};
 
+use std::collections::{ BTreeMap, HashMap };
+use std::fmt;
+use std::io::{ self, BufRead, BufReader, Write };
+use std::net::{ Shutdown, SocketAddr, TcpListener, TcpStream };
+use std::sync::atomic::{ AtomicBool, Ordering };
+use std::thread::JoinHandle;
+use std::time::{ Duration, Instant, SystemTime };
+
+use crate::events::{ Event, SyncEventBus };
+use crate::pipeline::{ self, Chain, Next };
+use crate::shutdown::{ CancellationToken, ShutdownSignal, WorkGuard };
+use crate::ratelimit::{ Algorithm, Decision, RateLimiter };
+use crate::metrics::{ self, Counter, Endpoint, Gauge, Histogram, Registry };
+use crate::resilience::{ self, RetryPolicy };
+
 fn check_arc() {
 	let data = Arc::new( 5 ); // Arc<T> allows using the underlying type natively (i.e., we can use the i32 inside this Arc<T> natively as an i32)
 	// Atomic borrowing. Both data or data_clone can be used inside another thread safely.
@@ -98,46 +113,866 @@ fn check_rw_th() {
 // Using thread-safety traits: Send + Sync allow us to track "Can this value move to another thread?" and "Can this be shared accross threads?". T is Sync if &T is Send.
 
 // Examples (mostly for Arc and RwLock, the ones I'll use frequently)
-struct Server {
-	addr: String, // std::net::SocketAddr ideally
-	// Servers manage different clients in a different thread, hence the Arc<RwLock<T>>
-	clients: Arc<RwLock<Vec<String>>>,
+
+/*
+Client registry.
+- The first version of push_client spawned a detached thread to add the client and printed the list right away, so the new client was usually missing (the thread hadn't run yet). Popping removed whichever client happened to be last.
+- 'ClientRegistry' does every operation right away under its lock, so they happen in the order they're called, and the caller gets an acknowledgement back: the new client's id, or the removed client.
+- Ids are never reused, and iteration is in id order, i.e. the order clients were added in.
+- 'snapshot()' copies the list under a single read lock, so it never shows half of a concurrent change, and nothing stays locked while the caller loops over it.
+- Every client also has a 'Session': where it connected from, when, and when it was last heard from. The Server's reaper thread uses it to drop clients that went quiet (see "Liveness" below).
+*/
+
+pub type ClientId = u64;
+
+#[derive( Debug, Clone, PartialEq, Eq )]
+pub struct Client {
+	pub id: ClientId,
+	pub name: String, // "ip:port" for socket clients
+}
+
+// Liveness information, kept next to each Client
+#[derive( Debug, Clone, PartialEq )]
+pub struct Session {
+	pub peer: Option<SocketAddr>, // None for clients without a socket (push_client)
+	pub connected_at: SystemTime, // Wall clock, for showing
+	pub last_activity: Instant,   // Last request. Answers to pings don't count
+	pub last_seen: Instant,       // Last line of any kind
+	pub ping_sent: Option<Instant>, // A PING that hasn't been answered yet
+	pub requests: u64,
+	pub throttled: u32, // Requests refused by the rate limit
+}
+
+impl Session {
+	fn new( peer: Option<SocketAddr> ) -> Self {
+		let now = Instant::now();
+
+		Self { peer, connected_at: SystemTime::now(), last_activity: now, last_seen: now, ping_sent: None, requests: 0, throttled: 0 }
+	}
+
+	pub fn idle_for( &self ) -> Duration {
+		return self.last_activity.elapsed();
+	}
+}
+
+#[derive( Default )]
+struct Clients {
+	next_id: ClientId,
+	by_id: BTreeMap<ClientId, Client>,
+	sessions: HashMap<ClientId, Session>,
+}
+
+#[derive( Default )]
+pub struct ClientRegistry {
+	inner: RwLock<Clients>,
+	lock_wait: Histogram, // How long each read()/write() waited for the lock
+}
+
+impl ClientRegistry {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	// Records lock waits into a registered histogram (the Server's 'server_lock_wait_seconds{lock="clients"}')
+	pub fn timed( lock_wait: Histogram ) -> Self {
+		Self { inner: RwLock::default(), lock_wait }
+	}
+
+	fn read( &self ) -> RwLockReadGuard<'_, Clients> {
+		let start = Instant::now();
+		let guard = self.inner.read().unwrap();
+		self.lock_wait.observe_duration( start.elapsed() );
+
+		return guard;
+	}
+
+	fn write( &self ) -> RwLockWriteGuard<'_, Clients> {
+		let start = Instant::now();
+		let guard = self.inner.write().unwrap();
+		self.lock_wait.observe_duration( start.elapsed() );
+
+		return guard;
+	}
+
+	pub fn add( &self, name: &str ) -> ClientId {
+		return self.add_peer( name, None );
+	}
+
+	pub fn add_peer( &self, name: &str, peer: Option<SocketAddr> ) -> ClientId {
+		let mut inner = self.write();
+		inner.next_id += 1;
+
+		let id = inner.next_id;
+		inner.by_id.insert( id, Client { id, name: name.to_string() } );
+		inner.sessions.insert( id, Session::new( peer ) );
+
+		return id;
+	}
+
+	pub fn remove( &self, id: ClientId ) -> Option<Client> {
+		let mut inner = self.write();
+		inner.sessions.remove( &id );
+
+		return inner.by_id.remove( &id );
+	}
+
+	pub fn session( &self, id: ClientId ) -> Option<Session> {
+		return self.read().sessions.get( &id ).cloned();
+	}
+
+	// In id order, like snapshot()
+	pub fn sessions( &self ) -> Vec<( Client, Session )> {
+		let inner = self.read();
+
+		return inner.by_id.values().filter_map( |c| Some( ( c.clone(), inner.sessions.get( &c.id )?.clone() ) ) ).collect();
+	}
+
+	// A line came in from the client. Any line answers an outstanding ping, only requests count as activity
+	pub fn touch( &self, id: ClientId, request: bool ) {
+		let mut inner = self.write();
+		let Some( session ) = inner.sessions.get_mut( &id ) else { return };
+		let now = Instant::now();
+
+		session.last_seen = now;
+		session.ping_sent = None;
+
+		if request {
+			session.last_activity = now;
+			session.requests += 1;
+		}
+	}
+
+	// Counts a request refused by the rate limit
+	fn throttle( &self, id: ClientId ) {
+		if let Some( session ) = self.write().sessions.get_mut( &id ) {
+			session.throttled += 1;
+		}
+	}
+
+	fn ping_sent( &self, id: ClientId ) {
+		if let Some( session ) = self.write().sessions.get_mut( &id ) {
+			session.ping_sent = Some( Instant::now() );
+		}
+	}
+
+	pub fn get( &self, id: ClientId ) -> Option<Client> {
+		return self.read().by_id.get( &id ).cloned();
+	}
+
+	pub fn contains( &self, id: ClientId ) -> bool {
+		return self.read().by_id.contains_key( &id );
+	}
+
+	// The first (oldest) client with that name
+	pub fn find( &self, name: &str ) -> Option<Client> {
+		return self.read().by_id.values().find( |c| c.name == name ).cloned();
+	}
+
+	pub fn snapshot( &self ) -> Vec<Client> {
+		return self.read().by_id.values().cloned().collect();
+	}
+
+	pub fn len( &self ) -> usize {
+		return self.read().by_id.len();
+	}
+
+	pub fn is_empty( &self ) -> bool {
+		return self.len() == 0;
+	}
+}
+
+#[derive( Debug, Clone, Copy, PartialEq, Eq )]
+pub enum DisconnectReason {
+	Closed,        // The client closed the connection (or it broke)
+	Removed,       // remove_client()
+	Idle,          // No request for longer than the idle timeout
+	Unresponsive,  // Didn't answer a PING in time
+	RateLimited,   // Kept going over the rate limit
+	ServerStopped, // stop(), or the shutdown signal
+}
+
+impl fmt::Display for DisconnectReason {
+	fn fmt( &self, f: &mut fmt::Formatter ) -> fmt::Result {
+		match self {
+			DisconnectReason::Closed => write! ( f, "Connection closed" ),
+			DisconnectReason::Removed => write! ( f, "Removed by the server" ),
+			DisconnectReason::Idle => write! ( f, "Idle timeout" ),
+			DisconnectReason::Unresponsive => write! ( f, "No answer to ping" ),
+			DisconnectReason::RateLimited => write! ( f, "Too many requests" ),
+			DisconnectReason::ServerStopped => write! ( f, "Server stopped" ),
+		}
+	}
+}
+
+impl DisconnectReason {
+	// For the 'reason' label of 'server_disconnections_total'
+	fn label( &self ) -> &'static str {
+		match self {
+			DisconnectReason::Closed => "closed",
+			DisconnectReason::Removed => "removed",
+			DisconnectReason::Idle => "idle",
+			DisconnectReason::Unresponsive => "unresponsive",
+			DisconnectReason::RateLimited => "rate_limited",
+			DisconnectReason::ServerStopped => "server_stopped",
+		}
+	}
+}
+
+// Published on the server's event bus, so other parts of the program can react to clients coming and going
+#[derive( Debug, Clone, PartialEq )]
+pub enum ServerEvent {
+	Connected( Client ),
+	Disconnected( Client, DisconnectReason ),
+}
+
+impl Event for ServerEvent {
+	fn topic( &self ) -> &str {
+		match self {
+			ServerEvent::Connected( _ ) => "connect",
+			ServerEvent::Disconnected( .. ) => "disconnect",
+		}
+	}
+}
+
+/*
+Liveness.
+
+- A client that vanishes without closing its connection (unplugged cable, crashed laptop, NAT timeout) looks exactly like a quiet one: the read just blocks. Only sending something finds out.
+- With 'with_heartbeat( interval, timeout )', a client that has been silent for 'interval' gets a "PING" line, and must answer "PONG" (any line will do) within 'timeout', or it's disconnected as Unresponsive. PONGs aren't passed on to the handler.
+- With 'with_idle_timeout( limit )', a client that sent no request for 'limit' is disconnected as Idle, even if it answers pings.
+- Both are off by default. When either is on, the server runs a reaper thread that checks every session a few times per timeout. It's told to stop through a CancellationToken, so stop() doesn't wait for its sleep to end.
+- Every disconnect, for whatever reason, is a 'ServerEvent::Disconnected( client, reason )'.
+*/
+
+#[derive( Debug, Clone, Copy, Default, PartialEq )]
+struct Liveness {
+	heartbeat: Option<( Duration, Duration )>, // Ping after this much silence, then wait this long for the answer
+	idle_timeout: Option<Duration>,
+}
+
+impl Liveness {
+	fn is_enabled( &self ) -> bool {
+		return self.heartbeat.is_some() || self.idle_timeout.is_some();
+	}
+
+	// A quarter of the shortest timeout, so clients are dropped at most 25% late
+	fn check_every( &self ) -> Duration {
+		let shortest = [self.heartbeat.map( |( i, t )| i.min( t ) ), self.idle_timeout].into_iter().flatten().min().unwrap_or( Duration::from_secs( 1 ) );
+
+		return ( shortest / 4 ).max( Duration::from_millis( 5 ) );
+	}
+}
+
+// What a client sends, and what the server answers
+#[derive( Debug, Clone, PartialEq )]
+pub struct Request {
+	pub id: ClientId,
+	pub client: String,
+	pub body: String,
+}
+
+#[derive( Debug, Clone, PartialEq )]
+pub struct Response {
+	pub ok: bool,
+	pub body: String,
+}
+
+// The open sockets, by client. A clone can be handed to a handler that needs to reach other clients than the one it's answering (e.g. chat rooms)
+#[derive( Clone, Default )]
+pub struct Outbox {
+	connections: Arc<Mutex<HashMap<ClientId, Connection>>>,
+	sent: Counter,        // Lines written, to any client
+	lock_wait: Histogram, // Waits for a client's writer, e.g. a broadcast and an answer to the same client
+}
+
+struct Connection {
+	socket: TcpStream,              // For shutdown(), which must not wait for a write in progress
+	writer: Arc<Mutex<TcpStream>>, // Shared with the client's own thread, so lines never interleave
+}
+
+impl Outbox {
+	fn add( &self, id: ClientId, stream: &TcpStream ) -> io::Result<()> {
+		let connection = Connection { socket: stream.try_clone()?, writer: Arc::new( Mutex::new( stream.try_clone()? ) ) };
+		self.connections.lock().unwrap().insert( id, connection );
+
+		return Ok( () );
+	}
+
+	fn writer( &self, id: ClientId ) -> Option<Arc<Mutex<TcpStream>>> {
+		return self.connections.lock().unwrap().get( &id ).map( |c| Arc::clone( &c.writer ) );
+	}
+
+	// Closes the socket both ways and forgets it
+	fn close( &self, id: ClientId ) {
+		if let Some( connection ) = self.connections.lock().unwrap().remove( &id ) {
+			let _ = connection.socket.shutdown( Shutdown::Both );
+		}
+	}
+
+	fn close_all( &self ) {
+		for connection in self.connections.lock().unwrap().values() {
+			let _ = connection.socket.shutdown( Shutdown::Both );
+		}
+	}
+
+	// Writes one line ('\n' is added). False if the client isn't connected or the write failed
+	pub fn send( &self, id: ClientId, line: &str ) -> bool {
+		let Some( writer ) = self.writer( id ) else { return false };
+
+		return self.write( &writer, &format! ( "{line}\n" ) ).is_ok();
+	}
+
+	// 'out' already ends with '\n'
+	fn write( &self, writer: &Mutex<TcpStream>, out: &str ) -> io::Result<()> {
+		metrics::timed_lock( writer, &self.lock_wait ).write_all( out.as_bytes() )?;
+		self.sent.inc();
+
+		return Ok( () );
+	}
+
+	// Returns how many clients got it
+	pub fn send_all( &self, ids: impl IntoIterator<Item = ClientId>, line: &str ) -> usize {
+		return ids.into_iter().filter( |&id| self.send( id, line ) ).count();
+	}
+
+	fn disconnect_all( &self ) {
+		for connection in self.connections.lock().unwrap().values() {
+			let _ = connection.socket.shutdown( Shutdown::Read );
+		}
+	}
+
+	// Stops reading from the client: the request being handled still gets its answer, then the connection closes
+	pub fn disconnect( &self, id: ClientId ) -> bool {
+		match self.connections.lock().unwrap().get( &id ) {
+			Some( connection ) => connection.socket.shutdown( Shutdown::Read ).is_ok(),
+			None => false,
+		}
+	}
+
+	pub fn is_connected( &self, id: ClientId ) -> bool {
+		return self.connections.lock().unwrap().contains_key( &id );
+	}
+}
+
+// A line-based TCP server: every line a client sends is a Request, and every Request gets one line back
+pub struct Server {
+	addr: SocketAddr,
+	local_addr: Option<SocketAddr>, // The real address once started (e.g. when binding port 0)
+	shared: Arc<Shared>,
+	accept_thread: Option<JoinHandle<()>>,
+	reaper: Option<( CancellationToken, JoinHandle<()> )>,
+	metrics_addr: Option<SocketAddr>, // Where to serve GET /metrics, if anywhere
+	metrics_endpoint: Option<Endpoint>,
+	bind_retry: RetryPolicy, // A single attempt unless with_bind_retry() is used
+}
+
+// Everything the accept loop and the client threads need, behind one Arc
+struct Shared {
+	// Servers manage different clients in a different thread, hence the Arc (the registry has its own RwLock inside)
+	clients: Arc<ClientRegistry>,
+	// SyncEventBus is cheap to clone (handlers are shared), so each client thread gets its own handle
+	events: SyncEventBus<ServerEvent>,
+	// Logging, auth and timing live here once, instead of in every handler. Built from the two fields below by rebuild_middleware()
+	middleware: Chain<Request, Response>,
+	custom_middleware: Chain<Request, Response>, // Runs outside 'authorize', in the order it was added
+	request_log: Option<Arc<dyn Fn( String ) + Send + Sync>>,
+	handler: Box<dyn Fn( Request ) -> Response + Send + Sync>,
+	// Socket write timeout. A write that fails or times out disconnects the client: it may have sent part of a line, so it's never retried
+	io_timeout: Duration,
+	running: AtomicBool,
+	// Every open socket, so stop() can shut them down (which unblocks their threads' reads), and handlers can push lines to clients
+	outbox: Outbox,
+	// When it fires, the server stops accepting and every client thread ends after its current request
+	shutdown: Option<ShutdownSignal>,
+	liveness: Liveness,
+	rate_limit: Option<RateLimit>,
+	metrics: ServerMetrics,
+}
+
+impl Shared {
+	fn register( &self, name: &str, stream: Option<&TcpStream> ) -> ClientId {
+		let id = self.clients.add_peer( name, stream.and_then( |s| s.peer_addr().ok() ) );
+
+		self.metrics.connections.inc();
+		self.metrics.connections_total.inc();
+
+		if let Some( stream ) = stream && self.outbox.add( id, stream ).is_err() {
+			let _ = stream.shutdown( Shutdown::Both ); // Its thread sees the EOF right away and unregisters it
+		}
+
+		self.events.publish( &ServerEvent::Connected( Client { id, name: name.to_string() } ) );
+
+		return id;
+	}
+
+	// Also shuts its socket down, so its thread stops reading (a no-op if the client is the one that left)
+	fn unregister( &self, id: ClientId, reason: DisconnectReason ) -> Option<Client> {
+		let client = self.clients.remove( id )?;
+
+		self.outbox.close( id );
+		self.metrics.disconnected( reason );
+
+		if let Some( limit ) = &self.rate_limit {
+			limit.limiter.remove( &id );
+		}
+
+		self.events.publish( &ServerEvent::Disconnected( client.clone(), reason ) );
+
+		return Some( client );
+	}
+
+	fn is_shutting_down( &self ) -> bool {
+		return self.shutdown.as_ref().is_some_and( |s| s.is_triggered() );
+	}
+
+	fn track( &self, name: String ) -> Option<WorkGuard> {
+		return self.shutdown.as_ref().map( |s| s.track( name ) );
+	}
+
+	// logging -> custom middleware -> authorize -> timing -> handler. Logging is outermost, so rejected requests get logged too
+	fn rebuild_middleware( &mut self ) {
+		let mut chain = Chain::new();
+
+		if let Some( sink ) = &self.request_log {
+			let log = Arc::clone( sink );
+			chain = chain.with( pipeline::logging( move |line| log( line ) ) );
+		}
+
+		chain = chain.then( self.custom_middleware.clone() ).with( authorize( &self.clients ) );
+
+		if let Some( sink ) = &self.request_log {
+			let time = Arc::clone( sink );
+			chain = chain.with( pipeline::timing( move |took| time( format! ( "Handled in {:?}", took ) ) ) );
+		}
+
+		self.middleware = chain;
+	}
+}
+
+// Only connected clients may send requests
+fn authorize( clients: &Arc<ClientRegistry> ) -> impl Fn( Request, Next<'_, Request, Response> ) -> Response + Send + Sync + 'static {
+	let connected = Arc::clone( clients );
+
+	return pipeline::auth(
+		move |r: &Request| connected.contains( r.id ),
+		|r: &Request| Response { ok: false, body: format! ( "{} is not connected", r.client ) }
+	);
 }
 
 // Let use impl for internal mutation
 impl Server {
-	fn new() -> Self {
-		Self { addr: String::new(), clients: Arc::new( RwLock::new( Vec::new() ) ) }
+	// Nothing is bound until start(). Port 0 picks a free port
+	pub fn new( addr: SocketAddr ) -> Self {
+		let metrics = ServerMetrics::new( Registry::new() );
+		let clients = Arc::new( ClientRegistry::timed( metrics.lock_wait( "clients" ) ) );
+		let middleware = Chain::new().with( authorize( &clients ) ); // Silent by default, see with_request_log()
+
+		let shared = Shared {
+			clients,
+			events: SyncEventBus::new(),
+			middleware,
+			custom_middleware: Chain::new(),
+			request_log: None,
+			handler: Box::new( |r: Request| Response { ok: true, body: r.body } ), // Echo
+			io_timeout: Duration::from_secs( 2 ),
+			running: AtomicBool::new( false ),
+			outbox: Outbox { connections: Arc::default(), sent: metrics.messages( "out" ), lock_wait: metrics.lock_wait( "writer" ) },
+			shutdown: None,
+			liveness: Liveness::default(),
+			rate_limit: None,
+			metrics,
+		};
+
+		Self { addr, local_addr: None, shared: Arc::new( shared ), accept_thread: None, reaper: None, metrics_addr: None, metrics_endpoint: None, bind_retry: RetryPolicy::fixed( 1, Duration::ZERO ) }
 	}
 
-	// We don't need a mut ref to self, we just get a read/write key for the RwLock (since the Arc let's us pass through)
-	fn push_client( &self, data: String ) -> () {
-		// Don't forget to clone the main arc object
-		let cli_arc = Arc::clone( &self.clients );
-		// Make the String thread-accessible (this won't work for String literals)
-		let data_arc = Arc::new( data ); // It must persist throughout the method
-		let data_arc_clone = Arc::clone( &data_arc ); // And throughout the thread (not required, just being extra safe, LoL)
+	// Configuration has to happen before start(), while nothing else holds the shared state
+	fn configure( &mut self ) -> &mut Shared {
+		Arc::get_mut( &mut self.shared ).expect( "Server must be configured before it is started" )
+	}
 
-		std::thread::spawn( move || { // This will be a Tokio::thread::spawn( async move {} );
-			let mut rw_w_guard = cli_arc.write().unwrap(); // Don't forget the difference between r lock and w lock (and mut)!
+	// Handlers only deal with the request itself, e.g. '.with_handler( |r| Response { ok: true, body: r.body.to_uppercase() } )'
+	pub fn with_handler( mut self, handler: impl Fn( Request ) -> Response + Send + Sync + 'static ) -> Self {
+		self.configure().handler = Box::new( handler );
+		self
+	}
 
-			rw_w_guard.push( data_arc_clone.to_string() );
-		});
+	// Added around the built-in authorization (which always runs), after any middleware added before. Can be called several times
+	pub fn with_middleware( mut self, middleware: Chain<Request, Response> ) -> Self {
+		let shared = self.configure();
+		shared.custom_middleware = std::mem::take( &mut shared.custom_middleware ).then( middleware );
+		shared.rebuild_middleware();
+		self
+	}
+
+	// Logs every request, its response and how long it took, e.g. '.with_request_log( |line| println! ( "{line}" ) )'. Works with custom middleware, in any order
+	pub fn with_request_log( mut self, sink: impl Fn( String ) + Send + Sync + 'static ) -> Self {
+		let shared = self.configure();
+		shared.request_log = Some( Arc::new( sink ) );
+		shared.rebuild_middleware();
+		self
+	}
+
+	pub fn with_io_timeout( mut self, timeout: Duration ) -> Self {
+		self.configure().io_timeout = timeout;
+		self
+	}
+
+	// Pings clients that have been silent for 'interval', and disconnects them if they don't answer within 'timeout'
+	pub fn with_heartbeat( mut self, interval: Duration, timeout: Duration ) -> Self {
+		self.configure().liveness.heartbeat = Some( ( interval, timeout ) );
+		self
+	}
+
+	// Disconnects clients that sent no request for this long
+	pub fn with_idle_timeout( mut self, limit: Duration ) -> Self {
+		self.configure().liveness.idle_timeout = Some( limit );
+		self
+	}
+
+	// Each client's requests go through its own limiter. Over the limit, a request is answered "ERR Rate limited, retry in ..." instead of being handled, and the 'max_violations'th request refused in a row disconnects the client
+	pub fn with_rate_limit( mut self, algorithm: Algorithm, max_violations: u32 ) -> Self {
+		self.configure().rate_limit = Some( RateLimit { limiter: RateLimiter::new( algorithm ), max_violations } );
+		self
+	}
+
+	// Retries start()'s bind while the address is in use, e.g. while the previous instance of a restarted server is still shutting down. Binding is safe to repeat, unlike writes
+	pub fn with_bind_retry( mut self, policy: RetryPolicy ) -> Self {
+		self.bind_retry = policy;
+		self
+	}
 
-		// Debug
-		for cli in self.clients.read().unwrap().iter() {
-			println! ( "{cli}" );
+	// Serves the metrics in the Prometheus text format at http://addr/metrics while the server runs
+	pub fn with_metrics_endpoint( mut self, addr: SocketAddr ) -> Self {
+		self.metrics_addr = Some( addr );
+		self
+	}
+
+	// Shuts down gracefully when the signal fires: no new clients, and the connected ones get the answer to the request they're in before being disconnected
+	pub fn with_shutdown( mut self, signal: &ShutdownSignal ) -> Self {
+		self.configure().shutdown = Some( signal.clone() );
+		self
+	}
+
+	// e.g. server.events().on( "connect", |e| println! ( "{:?}", e ) );
+	pub fn events( &self ) -> &SyncEventBus<ServerEvent> {
+		&self.shared.events
+	}
+
+	pub fn addr( &self ) -> SocketAddr {
+		return self.addr;
+	}
+
+	pub fn local_addr( &self ) -> Option<SocketAddr> {
+		return self.local_addr;
+	}
+
+	// The server's own metrics, to read or to add more to (e.g. from a handler)
+	pub fn metrics( &self ) -> &Registry {
+		return &self.shared.metrics.registry;
+	}
+
+	// The real address of the metrics endpoint, while it runs
+	pub fn metrics_addr( &self ) -> Option<SocketAddr> {
+		return self.metrics_endpoint.as_ref().map( |e| e.local_addr() );
+	}
+
+	pub fn is_running( &self ) -> bool {
+		return self.shared.running.load( Ordering::SeqCst );
+	}
+
+	pub fn clients( &self ) -> &ClientRegistry {
+		return &self.shared.clients;
+	}
+
+	// Can be taken before start(), e.g. for a handler that sends to other clients
+	pub fn outbox( &self ) -> Outbox {
+		return self.shared.outbox.clone();
+	}
+
+	pub fn handle( &self, req: Request ) -> Response {
+		let shared = &self.shared;
+
+		shared.middleware.run( req, &|r| ( shared.handler )( r ) )
+	}
+
+	// Binds the address and accepts connections on a background thread. Returns the address actually bound
+	pub fn start( &mut self ) -> io::Result<SocketAddr> {
+		if self.is_running() {
+			return Err( io::Error::new( io::ErrorKind::AlreadyExists, "Server is already running" ) );
 		}
+
+		if self.shared.is_shutting_down() {
+			return Err( io::Error::new( io::ErrorKind::Interrupted, "Server's shutdown signal has already fired" ) );
+		}
+
+		self.stop(); // Joins the accept thread of an earlier run that a shutdown signal ended
+
+		let listener = resilience::retry_if( &self.bind_retry, || TcpListener::bind( self.addr ), |e| e.kind() == io::ErrorKind::AddrInUse )?;
+		let local_addr = listener.local_addr()?;
+
+		if let Some( addr ) = self.metrics_addr {
+			self.metrics_endpoint = Some( metrics::serve( addr, self.shared.metrics.registry.clone() )? );
+		}
+		let shared = Arc::clone( &self.shared );
+
+		shared.running.store( true, Ordering::SeqCst );
+		self.local_addr = Some( local_addr );
+		self.accept_thread = Some( thread::Builder::new().name( "accept".to_string() ).spawn( move || accept_loop( listener, shared ) )? );
+
+		if self.shared.liveness.is_enabled() {
+			let stop = self.shared.shutdown.as_ref().map( |s| s.token().child() ).unwrap_or_default();
+			let ( shared, token ) = ( Arc::clone( &self.shared ), stop.clone() );
+
+			self.reaper = Some( ( stop, thread::Builder::new().name( "reaper".to_string() ).spawn( move || reap( shared, token ) )? ) );
+		}
+
+		// Same trick as stop() to wake up accept(). Weak, so the signal doesn't keep a dropped server alive
+		if let Some( signal ) = &self.shared.shutdown {
+			let shared = Arc::downgrade( &self.shared );
+
+			signal.token().on_cancel( move || {
+				if shared.upgrade().is_some_and( |s| s.running.load( Ordering::SeqCst ) ) {
+					let _ = TcpStream::connect_timeout( &local_addr, Duration::from_secs( 1 ) );
+				}
+			});
+		}
+
+		return Ok( local_addr );
 	}
 
-	fn pop_client( &self ) -> () {
-		let cli_arc = Arc::clone( &self.clients );
+	// Adds a client that isn't behind a socket (e.g. for the lessons), returning its id once it's registered
+	pub fn push_client( &self, name: &str ) -> ClientId {
+		return self.shared.register( name, None );
+	}
 
-		std::thread::spawn( move || {
-			let mut rw_w_guard = cli_arc.write().unwrap();
+	// Socket clients are disconnected too. Returns None if there was no such client
+	// Unregistered before the socket is shut down, otherwise the client's thread could see the EOF and unregister it first
+	pub fn remove_client( &self, id: ClientId ) -> Option<Client> {
+		return self.shared.unregister( id, DisconnectReason::Removed );
+	}
 
-			rw_w_guard.pop();
-		});
+	// Stops accepting, disconnects every client and waits for all the server's threads to finish
+	pub fn stop( &mut self ) {
+		// accept() blocks, so connect to ourselves to wake it up (it then sees 'running' is false)
+		if self.shared.running.swap( false, Ordering::SeqCst ) && let Some( addr ) = self.local_addr {
+			let _ = TcpStream::connect_timeout( &addr, Duration::from_secs( 1 ) );
+		}
+
+		if let Some( thread ) = self.accept_thread.take() {
+			let _ = thread.join();
+		}
+
+		if let Some( ( stop, thread ) ) = self.reaper.take() {
+			stop.cancel();
+			let _ = thread.join();
+		}
+
+		if let Some( mut endpoint ) = self.metrics_endpoint.take() {
+			endpoint.stop();
+		}
+	}
+}
+
+impl Drop for Server {
+	fn drop( &mut self ) {
+		self.stop();
+	}
+}
+
+// Echoes every line back until Enter is pressed
+pub fn serve_echo( addr: SocketAddr ) {
+	let mut server = Server::new( addr ).with_request_log( |line| println! ( "{line}" ) );
+	server.events().on( "*", |e| println! ( "{:?}", e ) );
+
+	match server.start() {
+		Ok( bound ) => println! ( "Listening on {bound}, press Enter to stop" ),
+		Err( e ) => return println! ( "Can't listen on {addr}: {e}" )
+	}
+
+	let _ = io::stdin().read_line( &mut String::new() );
+	server.stop(); // Also happens when 'server' is dropped
+}
+
+fn accept_loop( listener: TcpListener, shared: Arc<Shared> ) {
+	let mut threads: Vec<JoinHandle<()>> = Vec::new();
+
+	for stream in listener.incoming() {
+		if !shared.running.load( Ordering::SeqCst ) || shared.is_shutting_down() {
+			break;
+		}
+
+		let Ok( stream ) = stream else { continue }; // e.g. the client gave up before we accepted
+		let Ok( peer ) = stream.peer_addr() else { continue };
+		let name = peer.to_string();
+
+		// A client that stops reading would otherwise block whoever writes to it (e.g. a broadcast) forever
+		let _ = stream.set_write_timeout( Some( shared.io_timeout ) );
+
+		// Registered here rather than in the client's thread, so ids are handed out in the order clients connected in
+		let id = shared.register( &name, Some( &stream ) );
+
+		let shared = Arc::clone( &shared );
+		threads.push( thread::spawn( move || serve_client( stream, Client { id, name }, shared ) ) );
+		threads.retain( |t| !t.is_finished() );
+	}
+
+	// Shutting the sockets down makes the clients' blocking reads return, so their threads can end. On a graceful shutdown only the reading half, so answers still go out
+	match shared.is_shutting_down() {
+		true => shared.outbox.disconnect_all(),
+		false => shared.outbox.close_all(),
+	}
+
+	for thread in threads {
+		let _ = thread.join();
+	}
+
+	shared.running.store( false, Ordering::SeqCst );
+}
+
+fn serve_client( stream: TcpStream, client: Client, shared: Arc<Shared> ) {
+	let Some( writer ) = shared.outbox.writer( client.id ) else {
+		shared.unregister( client.id, DisconnectReason::Closed );
+		return;
+	};
+
+	let _work = shared.track( format! ( "client {} ({})", client.id, client.name ) );
+	let mut reader = BufReader::new( stream );
+	let mut line = String::new();
+	let mut violations = 0; // Refused requests in a row: a client that backs off when told to starts over
+
+	while !shared.is_shutting_down() {
+		line.clear();
+
+		match reader.read_line( &mut line ) {
+			Ok( 0 ) | Err( _ ) => break, // Disconnected
+			Ok( _ ) => ()
+		}
+
+		shared.metrics.messages_in.inc();
+
+		let body = line.trim_end_matches( ['\r', '\n'] );
+		let pong = body == "PONG" || body.starts_with( "PONG " );
+		shared.clients.touch( client.id, !pong );
+
+		if pong {
+			continue;
+		}
+
+		// Refused before the middleware, so a flood costs as little as possible
+		if let Some( limit ) = &shared.rate_limit && let Decision::Limited { retry_after } = limit.limiter.check( &client.id ) {
+			shared.metrics.rate_limited.inc();
+			shared.clients.throttle( client.id );
+			violations += 1;
+
+			if violations >= limit.max_violations {
+				shared.outbox.send( client.id, &format! ( "ERR {}", DisconnectReason::RateLimited ) );
+				shared.unregister( client.id, DisconnectReason::RateLimited );
+				break;
+			}
+
+			let out = format! ( "ERR Rate limited, retry in {}ms\n", retry_after.as_millis().max( 1 ) );
+
+			match shared.outbox.write( &writer, &out ) {
+				Ok( _ ) => continue,
+				Err( _ ) => break,
+			}
+		}
+
+		violations = 0;
+
+		let req = Request { id: client.id, client: client.name.clone(), body: body.to_string() };
+		let start = Instant::now();
+		let resp = shared.middleware.run( req, &|r| ( shared.handler )( r ) );
+		shared.metrics.request_duration.observe_duration( start.elapsed() );
+
+		let out = match resp.ok {
+			true => format! ( "{}\n", resp.body ),
+			false => format! ( "ERR {}\n", resp.body )
+		};
+
+		if shared.outbox.write( &writer, &out ).is_err() {
+			break;
+		}
+	}
+
+	// Already done if remove_client() or the reaper disconnected us
+	let reason = match shared.running.load( Ordering::SeqCst ) && !shared.is_shutting_down() {
+		true => DisconnectReason::Closed,
+		false => DisconnectReason::ServerStopped,
+	};
+
+	shared.unregister( client.id, reason );
+}
+
+// The handles the server updates itself. Everything is registered in 'registry', which is what gets served
+struct ServerMetrics {
+	registry: Registry,
+	connections: Gauge,
+	connections_total: Counter,
+	messages_in: Counter,
+	rate_limited: Counter,
+	request_duration: Histogram,
+}
+
+impl ServerMetrics {
+	fn new( registry: Registry ) -> Self {
+		Self {
+			connections: registry.gauge( "server_connections", "Clients connected right now" ),
+			connections_total: registry.counter( "server_connections_total", "Clients that connected since the server was created" ),
+			messages_in: registry.counter_with( "server_messages_total", "Lines received from or sent to clients", &[( "direction", "in" )] ),
+			rate_limited: registry.counter( "server_rate_limited_total", "Requests refused by the rate limit" ),
+			request_duration: registry.histogram( "server_request_duration_seconds", "Time to handle a request, middleware included", &metrics::DEFAULT_BUCKETS ),
+			registry,
+		}
+	}
+
+	fn messages( &self, direction: &str ) -> Counter {
+		return self.registry.counter_with( "server_messages_total", "Lines received from or sent to clients", &[( "direction", direction )] );
+	}
+
+	// Waits are mostly well under a millisecond, so the buckets start at 1µs
+	fn lock_wait( &self, lock: &str ) -> Histogram {
+		return self.registry.histogram_with( "server_lock_wait_seconds", "Time spent waiting for a lock", &metrics::exponential_buckets( 0.000001, 4.0, 10 ), &[( "lock", lock )] );
+	}
+
+	// Disconnects are rare enough to look the counter up each time
+	fn disconnected( &self, reason: DisconnectReason ) {
+		self.connections.dec();
+		self.registry.counter_with( "server_disconnections_total", "Clients that left, by reason", &[( "reason", reason.label() )] ).inc();
+	}
+}
+
+struct RateLimit {
+	limiter: RateLimiter<ClientId>,
+	max_violations: u32,
+}
+
+// Checks every session until 'stop' is cancelled (by stop(), or along with the shutdown signal)
+fn reap( shared: Arc<Shared>, stop: CancellationToken ) {
+	let liveness = shared.liveness;
+	let mut pings: u64 = 0;
+
+	while !stop.wait_timeout( liveness.check_every() ) {
+		for ( client, session ) in shared.clients.sessions() {
+			if session.peer.is_none() {
+				continue; // Nobody to ping, e.g. push_client()
+			}
+
+			let idle = liveness.idle_timeout.is_some_and( |limit| session.last_activity.elapsed() >= limit );
+			let unresponsive = matches! ( ( liveness.heartbeat, session.ping_sent ), ( Some( ( _, timeout ) ), Some( sent ) ) if sent.elapsed() >= timeout );
+
+			if idle || unresponsive {
+				let reason = if idle { DisconnectReason::Idle } else { DisconnectReason::Unresponsive };
+
+				shared.outbox.send( client.id, &format! ( "ERR {reason}" ) ); // Best effort, it may well not be listening
+				shared.unregister( client.id, reason );
+			} else if let Some( ( interval, _ ) ) = liveness.heartbeat && session.ping_sent.is_none() && session.last_seen.elapsed() >= interval {
+				pings += 1;
+				shared.clients.ping_sent( client.id );
+				shared.outbox.send( client.id, &format! ( "PING {pings}" ) );
+			}
+		}
 	}
 }
 
@@ -145,6 +980,9 @@ impl Server {
 Atomic Types:
 - Atomic types in Rust are types that provide safe concurrent access without the need for a Mutex<T>.
 - They operate using atomic operations to ensure that increments, decrements, and reads/writes are performed as a single atomic action, which means they cannot be interrupted.
+- The Ordering says what *other* memory the operation makes visible to other threads. Relaxed: nothing, only the number itself is atomic (fine for counters). Release on a store + Acquire on the load that sees it: everything written before the store is visible after the load (how a flag can "publish" data). SeqCst: like Acquire/Release, plus all threads agree on one order of all SeqCst operations.
+- A counter that many threads bump is the textbook use, and what metrics are made of (see src/metrics.rs): an Arc<AtomicU64> per number, no lock on the hot path.
+- Whole data structures can be built on them too, e.g. queues without a Mutex (see src/ring.rs). There the orderings carry the data: a producer writes a slot and then stores the index with Release, the consumer loads the index with Acquire and can then read the slot.
 */
 
 // Not fully sure on these!
@@ -154,6 +992,124 @@ fn check_atomic() {
 	let counter = AtomicUsize::new( 0 );
 
 	counter.fetch_add( 1 , Ordering::Relaxed );
+
+	// Shared between threads with an Arc, no Mutex needed. Always 4000: no increment is lost
+	let shared = Arc::new( AtomicUsize::new( 0 ) );
+	let threads: Vec<_> = ( 0..4 ).map( |_| {
+		let shared = Arc::clone( &shared );
+		thread::spawn( move || for _ in 0..1000 { shared.fetch_add( 1, Ordering::Relaxed ); } )
+	}).collect();
+
+	for t in threads {
+		t.join().unwrap();
+	}
+
+	println! ( "{}", shared.load( Ordering::Relaxed ) ); // 4000
+
+	// The same, with names, labels and a text format Prometheus can scrape
+	let registry = crate::metrics::Registry::new();
+	let handled = registry.counter_with( "requests_total", "Requests handled", &[( "status", "ok" )] );
+	let took = registry.histogram( "request_duration_seconds", "Time to handle a request", &crate::metrics::DEFAULT_BUCKETS );
+
+	handled.inc();
+	took.observe_duration( Duration::from_millis( 7 ) );
+
+	print! ( "{}", registry.render() ); // requests_total{status="ok"} 1, request_duration_seconds_bucket{le="0.01"} 1, ...
+}
+
+// A bounded queue made of two atomic indices and an array (see src/ring.rs). Try-only: push gives the value back when full
+fn check_ring() {
+	use crate::ring;
+
+	let ( mut tx, mut rx ) = ring::spsc::<u32>( 4 );
+
+	let producer = thread::spawn( move || for i in 0..100 {
+		let mut value = i;
+
+		while let Err( back ) = tx.push( value ) {
+			value = back;
+			thread::yield_now(); // Full, let the consumer catch up
+		}
+	});
+
+	let mut sum = 0;
+	let mut taken = 0;
+
+	while taken < 100 {
+		match rx.pop() {
+			Some( value ) => { sum += value; taken += 1; },
+			None => thread::yield_now(),
+		}
+	}
+
+	producer.join().unwrap();
+	println! ( "{sum}" ); // 4950, in order and nothing lost, without a single lock
+}
+
+// Running closures later, or every so often, on a background thread (see src/scheduler.rs)
+fn check_scheduler() {
+	use crate::scheduler::Scheduler;
+
+	let scheduler = Scheduler::new();
+	let ticks = Arc::new( std::sync::atomic::AtomicUsize::new( 0 ) );
+	let ticks_clone = Arc::clone( &ticks );
+
+	scheduler.after( Duration::from_millis( 50 ), || println! ( "50ms later" ) );
+	let heartbeat = scheduler.every( Duration::from_millis( 10 ), move || { ticks_clone.fetch_add( 1, std::sync::atomic::Ordering::Relaxed ); } );
+
+	thread::sleep( Duration::from_millis( 100 ) );
+	heartbeat.cancel();
+
+	println! ( "Heartbeats: {}", ticks.load( std::sync::atomic::Ordering::Relaxed ) ); // ~10
+}
+
+// The thread::spawn examples above start a new thread per task and drop the handle. A pool reuses a few threads, and spawn() hands back the result (see src/pool.rs)
+fn check_pool() {
+	use crate::pool::ThreadPool;
+
+	let pool = ThreadPool::new( 4 );
+	let counter: Arc<Mutex<i32>> = Arc::new( Mutex::new( 0 ) );
+
+	for _ in 0..10 {
+		let counter_arc = Arc::clone( &counter );
+		pool.execute( move || *counter_arc.lock().unwrap() += 1 );
+	}
+
+	let doubled = pool.spawn( || 21 * 2 );
+	let failed = pool.spawn( || -> i32 { panic! ( "Oops" ) } ); // Caught, the worker keeps going
+
+	println! ( "{:?} {:?}", doubled.join(), failed.join() ); // Ok(42) Err("Oops")
+
+	pool.join(); // Waits for the 10 increments
+	println! ( "Counter: {} [{}]", counter.lock().unwrap(), pool.metrics() );
+}
+
+// Stopping threads from the outside isn't possible, they have to be told (see src/shutdown.rs)
+fn check_shutdown() {
+	use crate::pool::ThreadPool;
+	use crate::shutdown::ShutdownSignal;
+
+	let signal = ShutdownSignal::new();
+	let pool = ThreadPool::new( 2 ).with_shutdown( &signal );
+
+	for i in 0..6 {
+		let token = signal.token();
+
+		pool.execute( move || {
+			// A long job that checks in regularly, and wraps up early when asked to
+			for _ in 0..( 10 * ( i + 1 ) ) {
+				if token.wait_timeout( Duration::from_millis( 10 ) ) {
+					return println! ( "Job {} stopping early", i );
+				}
+			}
+		});
+	}
+
+	thread::sleep( Duration::from_millis( 50 ) );
+
+	let report = signal.drain( Duration::from_secs( 1 ) ); // The 4 queued jobs are dropped, the 2 running ones stop early
+	println! ( "{}", report ); // Shutdown (requested): everything finished in ...
+	println! ( "{}", pool.metrics() ); // ... 4 cancelled
 }
 
 /*
@@ -166,4 +1122,74 @@ When to use which:
 /*
 Channels (Message Passing):
 - “Do not communicate by sharing memory; share memory by communicating.""
+- std::sync::mpsc: many Senders, one Receiver. 'channel()' is unbounded, 'sync_channel( n )' blocks senders once n messages are waiting.
+- src/channel.rs: bounded, and both ends can be cloned (MPMC), so several workers can take from one queue. Also explicit close(), timeouts, and select() over several receivers.
+- benches/channel.rs compares it with a Mutex<Vec> like the old Server.clients ('cargo bench --bench channel').
 */
+
+fn check_mpsc() {
+	use std::sync::mpsc;
+
+	let ( tx, rx ) = mpsc::channel();
+
+	for id in 0..3 {
+		let tx = tx.clone();
+		thread::spawn( move || tx.send( id * 10 ).unwrap() );
+	}
+
+	drop( tx ); // Otherwise the loop below never ends: a Sender is still alive
+
+	for value in rx {
+		println! ( "Got {}", value ); // 0, 10, 20 in any order
+	}
+}
+
+fn check_channels() {
+	use crate::channel::{ self, RecvError, SendError };
+
+	let ( jobs_tx, jobs_rx ) = channel::bounded::<u32>( 2 );
+	let ( done_tx, done_rx ) = channel::bounded::<String>( 8 );
+
+	// Two workers share one queue
+	let workers: Vec<_> = ( 0..2 ).map( |w| {
+		let jobs = jobs_rx.clone();
+		let done = done_tx.clone();
+
+		thread::spawn( move || {
+			for job in jobs.iter() {
+				done.send( format! ( "worker {} did job {}", w, job ) ).unwrap();
+			}
+		})
+	}).collect();
+
+	drop( done_tx );
+
+	for job in 0..6 {
+		jobs_tx.send( job ).unwrap(); // Blocks while 2 jobs are already waiting (backpressure)
+	}
+
+	jobs_tx.close(); // Workers finish what's queued, then their loop ends
+	assert! ( matches! ( jobs_tx.try_send( 99 ), Err( SendError::Closed( 99 ) ) ) );
+
+	for line in done_rx.iter() {
+		println! ( "{}", line );
+	}
+
+	for worker in workers {
+		worker.join().unwrap();
+	}
+
+	// Waiting on two channels at once
+	let ( fast_tx, fast_rx ) = channel::bounded( 1 );
+	let ( slow_tx, slow_rx ) = channel::bounded( 1 );
+
+	thread::spawn( move || {
+		thread::sleep( Duration::from_millis( 50 ) );
+		let _ = slow_tx.send( "slow" );
+	});
+	fast_tx.send( "fast" ).unwrap();
+
+	println! ( "{:?}", channel::select( &[&slow_rx, &fast_rx] ) ); // Ok((1, "fast"))
+	println! ( "{:?}", slow_rx.recv_timeout( Duration::from_secs( 1 ) ) ); // Ok("slow")
+	println! ( "{:?}", fast_rx.recv_timeout( Duration::from_millis( 10 ) ) == Err( RecvError::Timeout ) ); // true, fast_tx is still alive
+}
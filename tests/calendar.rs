@@ -0,0 +1,75 @@
+// Integration tests for calendar: Easter, ISO weeks, nth weekdays and holiday rules
+
+use rs_basics::calendar::{ self, Calendar, HolidayRule, Month, Weekday };
+use rs_basics::date::Date;
+
+fn d( year: i32, month: u8, day: u8 ) -> Date {
+	return Date::new( year, month, day ).unwrap();
+}
+
+#[test]
+fn easter_sunday_matches_known_dates() {
+	let known = [
+		( 1961, 4, 2 ),
+		( 2000, 4, 23 ),
+		( 2008, 3, 23 ), // Early
+		( 2011, 4, 24 ),
+		( 2019, 4, 21 ),
+		( 2024, 3, 31 ),
+		( 2038, 4, 25 ), // As late as it gets
+		( 2285, 3, 22 ), // As early as it gets
+	];
+
+	for ( year, month, day ) in known {
+		assert_eq! ( calendar::easter_sunday( year ), Some( d( year, month, day ) ), "{year}" );
+	}
+
+	for year in 1..3000 {
+		let easter = calendar::easter_sunday( year ).unwrap();
+		assert_eq! ( easter.weekday(), Weekday::Sunday, "{year}" );
+	}
+
+	assert_eq! ( calendar::easter_sunday( 0 ), None );
+	assert_eq! ( calendar::easter_sunday( -44 ), None );
+}
+
+#[test]
+fn iso_weeks_cross_year_boundaries() {
+	assert_eq! ( calendar::iso_week( &d( 2024, 1, 1 ) ), ( 2024, 1 ) ); // A Monday
+	assert_eq! ( calendar::iso_week( &d( 2021, 1, 3 ) ), ( 2020, 53 ) ); // Belongs to the previous year's last week
+	assert_eq! ( calendar::iso_week( &d( 2019, 12, 30 ) ), ( 2020, 1 ) ); // Belongs to the next year's first week
+	assert_eq! ( calendar::iso_week( &d( 2015, 12, 31 ) ), ( 2015, 53 ) );
+	assert_eq! ( calendar::iso_week( &d( 2023, 6, 15 ) ), ( 2023, 24 ) );
+}
+
+#[test]
+fn nth_and_last_weekdays() {
+	assert_eq! ( calendar::nth_weekday( 2024, Month::November, Weekday::Thursday, 4 ), Some( d( 2024, 11, 28 ) ) );
+	assert_eq! ( calendar::nth_weekday( 2024, Month::May, Weekday::Monday, -1 ), Some( d( 2024, 5, 27 ) ) );
+	assert_eq! ( calendar::nth_weekday( 2024, Month::February, Weekday::Monday, 5 ), None );
+}
+
+#[test]
+fn holiday_rules_and_business_days() {
+	let calendar = Calendar::parse_rules( "
+		# Comments and blank lines are skipped
+		01-01        | New Year's Day
+		easter-2     | Good Friday
+		easter+1     | Easter Monday
+		last Mon May | Memorial Day
+	" ).unwrap();
+
+	assert_eq! ( calendar.holiday_on( &d( 2024, 3, 29 ) ), Some( "Good Friday" ) );
+	assert_eq! ( calendar.holiday_on( &d( 2024, 4, 1 ) ), Some( "Easter Monday" ) );
+	assert_eq! ( calendar.holiday_on( &d( 2024, 5, 27 ) ), Some( "Memorial Day" ) );
+	assert_eq! ( calendar.holidays_in( 2024 ).len(), 4 );
+
+	// Thursday before Easter, + 1 business day skips Good Friday, the weekend and Easter Monday
+	assert_eq! ( calendar.add_business_days( &d( 2024, 3, 28 ), 1 ), d( 2024, 4, 2 ) );
+	assert! ( !calendar.is_business_day( &d( 2024, 1, 1 ) ) );
+
+	// Easter-relative rules simply have no date in years Easter isn't computed for
+	assert_eq! ( HolidayRule::parse( "easter" ).unwrap().date_in( 0 ), None );
+	assert! ( HolidayRule::parse( "13-01" ).is_err() );
+	assert! ( Calendar::parse_rules( "whenever | Nope" ).is_err() );
+}
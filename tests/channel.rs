@@ -0,0 +1,135 @@
+// Integration tests for channel::bounded and select
+
+use std::collections::HashSet;
+use std::sync::{Arc, Barrier};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use rs_basics::channel::{ self, RecvError, SendError };
+
+const WAIT: Duration = Duration::from_secs( 5 );
+
+#[test]
+fn full_channel_pushes_back_on_senders() {
+	let ( tx, rx ) = channel::bounded( 2 );
+
+	tx.send( 1 ).unwrap();
+	tx.send( 2 ).unwrap();
+
+	assert! ( tx.is_full() );
+	assert_eq! ( tx.try_send( 3 ), Err( SendError::Full( 3 ) ) );
+	assert_eq! ( tx.send_timeout( 3, Duration::from_millis( 20 ) ), Err( SendError::Timeout( 3 ) ) );
+
+	// A blocked send goes through as soon as a receiver makes room
+	let sender = thread::spawn( move || tx.send( 3 ) );
+	thread::sleep( Duration::from_millis( 20 ) );
+
+	assert_eq! ( rx.recv(), Ok( 1 ) );
+	assert_eq! ( sender.join().unwrap(), Ok( () ) );
+	assert_eq! ( rx.iter().collect::<Vec<_>>(), vec![2, 3] );
+}
+
+#[test]
+fn close_lets_receivers_drain_what_is_left() {
+	let ( tx, rx ) = channel::bounded( 4 );
+
+	tx.send( "a" ).unwrap();
+	tx.send( "b" ).unwrap();
+	rx.close();
+
+	assert! ( tx.is_closed() );
+	assert_eq! ( tx.send( "c" ).map_err( SendError::into_inner ), Err( "c" ) );
+	assert_eq! ( rx.recv(), Ok( "a" ) );
+	assert_eq! ( rx.try_recv(), Ok( "b" ) );
+	assert_eq! ( rx.try_recv(), Err( RecvError::Closed ) );
+	assert_eq! ( rx.recv_timeout( WAIT ), Err( RecvError::Closed ) );
+}
+
+#[test]
+fn dropping_every_end_closes_the_other_side() {
+	let ( tx, rx ) = channel::bounded::<i32>( 1 );
+	let rx2 = rx.clone();
+
+	assert_eq! ( rx.try_recv(), Err( RecvError::Empty ) );
+
+	// A receiver blocked on an empty channel wakes up when the last sender goes
+	let waiting = thread::spawn( move || rx2.recv() );
+	thread::sleep( Duration::from_millis( 20 ) );
+	drop( tx );
+
+	assert_eq! ( waiting.join().unwrap(), Err( RecvError::Closed ) );
+
+	let ( tx, rx ) = channel::bounded( 1 );
+	drop( rx );
+
+	assert_eq! ( tx.send( 1 ), Err( SendError::Closed( 1 ) ) );
+}
+
+#[test]
+fn recv_timeout_waits_at_least_the_timeout() {
+	let ( _tx, rx ) = channel::bounded::<i32>( 1 );
+	let start = Instant::now();
+
+	assert_eq! ( rx.recv_timeout( Duration::from_millis( 30 ) ), Err( RecvError::Timeout ) );
+	assert! ( start.elapsed() >= Duration::from_millis( 30 ) );
+}
+
+#[test]
+fn every_value_goes_to_exactly_one_receiver() {
+	let ( tx, rx ) = channel::bounded( 8 );
+	let producers = 4;
+	let per_producer = 1000;
+
+	let senders: Vec<_> = ( 0..producers ).map( |p| {
+		let tx = tx.clone();
+		thread::spawn( move || for i in 0..per_producer { tx.send( p * per_producer + i ).unwrap(); } )
+	}).collect();
+
+	let receivers: Vec<_> = ( 0..3 ).map( |_| {
+		let rx = rx.clone();
+		thread::spawn( move || rx.iter().collect::<Vec<_>>() )
+	}).collect();
+
+	drop( ( tx, rx ) );
+
+	for sender in senders {
+		sender.join().unwrap();
+	}
+
+	let received: Vec<usize> = receivers.into_iter().flat_map( |r| r.join().unwrap() ).collect();
+	let unique: HashSet<usize> = received.iter().copied().collect();
+
+	assert_eq! ( received.len(), producers * per_producer );
+	assert_eq! ( unique.len(), producers * per_producer );
+}
+
+#[test]
+fn select_returns_whichever_receiver_is_ready() {
+	let ( a_tx, a_rx ) = channel::bounded( 1 );
+	let ( b_tx, b_rx ) = channel::bounded( 1 );
+
+	b_tx.send( "b" ).unwrap();
+	assert_eq! ( channel::select( &[&a_rx, &b_rx] ), Ok( ( 1, "b" ) ) );
+
+	// Nothing ready yet: select blocks until a value arrives on either
+	let ready = Arc::new( Barrier::new( 2 ) );
+	let sender = {
+		let ready = Arc::clone( &ready );
+
+		thread::spawn( move || {
+			ready.wait();
+			thread::sleep( Duration::from_millis( 20 ) );
+			a_tx.send( "a" ).unwrap();
+		})
+	};
+
+	ready.wait();
+	assert_eq! ( channel::select_timeout( &[&a_rx, &b_rx], WAIT ), Ok( ( 0, "a" ) ) );
+	sender.join().unwrap();
+
+	// a's sender is gone, b's is still there
+	assert_eq! ( channel::select_timeout( &[&a_rx, &b_rx], Duration::from_millis( 20 ) ), Err( RecvError::Timeout ) );
+
+	drop( b_tx );
+	assert_eq! ( channel::select( &[&a_rx, &b_rx] ), Err( RecvError::Closed ) );
+}
@@ -0,0 +1,229 @@
+// Integration tests for the chat protocol, with several clients over real sockets on 127.0.0.1
+
+use std::io::{ BufRead, BufReader, Write };
+use std::net::{ SocketAddr, TcpStream };
+use std::sync::Arc;
+use std::time::Duration;
+
+use rs_basics::chat::{ self, Chat };
+use rs_basics::threading::Server;
+
+const WAIT: Duration = Duration::from_secs( 5 );
+
+struct User {
+	stream: TcpStream,
+	reader: BufReader<TcpStream>,
+}
+
+impl User {
+	fn connect( addr: SocketAddr ) -> Self {
+		let stream = TcpStream::connect( addr ).unwrap();
+		stream.set_read_timeout( Some( WAIT ) ).unwrap();
+		let reader = BufReader::new( stream.try_clone().unwrap() );
+
+		Self { stream, reader }
+	}
+
+	fn named( addr: SocketAddr, nick: &str ) -> Self {
+		let mut user = Self::connect( addr );
+		assert_eq! ( user.send( &format! ( "NICK {nick}" ) ), format! ( "OK NICK {nick}" ) );
+
+		user
+	}
+
+	// Sends a command and returns its answer
+	fn send( &mut self, line: &str ) -> String {
+		self.stream.write_all( format! ( "{line}\n" ).as_bytes() ).unwrap();
+
+		return self.line();
+	}
+
+	fn line( &mut self ) -> String {
+		let mut line = String::new();
+		self.reader.read_line( &mut line ).unwrap();
+
+		return line.trim_end().to_string();
+	}
+
+	fn is_closed( &mut self ) -> bool {
+		let mut rest = String::new();
+
+		return self.reader.read_line( &mut rest ).unwrap() == 0;
+	}
+}
+
+fn start() -> ( Server, Arc<Chat>, SocketAddr ) {
+	let ( mut server, chat ) = chat::server( "127.0.0.1:0".parse().unwrap() );
+	let addr = server.start().unwrap();
+
+	return ( server, chat, addr );
+}
+
+#[test]
+fn nicknames_are_unique_and_checked() {
+	let ( _server, chat, addr ) = start();
+	let mut alice = User::named( addr, "alice" );
+	let mut other = User::connect( addr );
+
+	assert_eq! ( other.send( "NICK alice" ), "ERR 433 Nickname is already in use: alice" );
+	assert_eq! ( other.send( "NICK 9lives" ), "ERR 432 Erroneous nickname: 9lives" );
+	assert_eq! ( other.send( "NICK" ), "ERR 461 Not enough parameters: NICK" );
+	assert_eq! ( other.send( "nick bob" ), "OK NICK bob" ); // Commands are case-insensitive
+
+	// alice's old name is free again after a rename
+	assert_eq! ( alice.send( "NICK alicia" ), "OK NICK alicia" );
+	assert_eq! ( other.send( "NICK alice" ), "OK NICK alice" );
+	assert_eq! ( chat.nick( 2 ), Some( "alice".to_string() ) );
+}
+
+#[test]
+fn commands_need_a_nickname_first() {
+	let ( _server, _chat, addr ) = start();
+	let mut anon = User::connect( addr );
+
+	assert_eq! ( anon.send( "JOIN #rust" ), "ERR 451 Pick a nickname first (NICK <name>)" );
+	assert_eq! ( anon.send( "MSG #rust hi" ), "ERR 451 Pick a nickname first (NICK <name>)" );
+	assert_eq! ( anon.send( "LIST" ), "OK LIST" );
+	assert_eq! ( anon.send( "DANCE" ), "ERR 421 Unknown command: DANCE" );
+}
+
+#[test]
+fn rooms_broadcast_to_their_members_only() {
+	let ( _server, chat, addr ) = start();
+	let mut alice = User::named( addr, "alice" );
+	let mut bob = User::named( addr, "bob" );
+	let mut carol = User::named( addr, "carol" );
+
+	assert_eq! ( alice.send( "JOIN #rust" ), "OK JOIN #rust" );
+	assert_eq! ( bob.send( "JOIN #rust" ), "OK JOIN #rust" );
+	assert_eq! ( alice.line(), ":bob JOIN #rust" );
+	assert_eq! ( carol.send( "JOIN #go" ), "OK JOIN #go" );
+
+	assert_eq! ( alice.send( "MSG #rust hello everyone" ), "OK MSG #rust 1" );
+	assert_eq! ( bob.line(), ":alice MSG #rust hello everyone" );
+
+	assert_eq! ( carol.send( "MSG #rust let me in" ), "ERR 404 Cannot send to a room you're not in: #rust" );
+	assert_eq! ( carol.send( "LIST" ), "OK LIST #go:1 #rust:2" );
+	assert_eq! ( carol.send( "LIST #rust" ), "OK LIST #rust alice bob" );
+	assert_eq! ( carol.send( "JOIN rust" ), "ERR 403 No such room: rust" );
+
+	assert_eq! ( bob.send( "PART #rust" ), "OK PART #rust" );
+	assert_eq! ( alice.line(), ":bob PART #rust" );
+	assert_eq! ( bob.send( "PART #rust" ), "ERR 442 You're not in that room: #rust" );
+
+	// The last one out removes the room
+	assert_eq! ( carol.send( "PART #go" ), "OK PART #go" );
+	assert_eq! ( chat.rooms(), vec![( "#rust".to_string(), 1 )] );
+
+	// carol got nothing from #rust
+	assert_eq! ( carol.send( "LIST" ), "OK LIST #rust:1" );
+}
+
+#[test]
+fn private_messages_reach_one_client() {
+	let ( _server, _chat, addr ) = start();
+	let mut alice = User::named( addr, "alice" );
+	let mut bob = User::named( addr, "bob" );
+	let mut carol = User::named( addr, "carol" );
+
+	assert_eq! ( alice.send( "MSG bob psst, over here" ), "OK MSG bob 1" );
+	assert_eq! ( bob.line(), ":alice MSG bob psst, over here" );
+
+	assert_eq! ( alice.send( "MSG dave hi" ), "ERR 401 No such nickname: dave" );
+	assert_eq! ( alice.send( "MSG bob" ), "ERR 412 No text to send" );
+	assert_eq! ( alice.send( "MSG" ), "ERR 411 No recipient given" );
+
+	// Had carol received the private message, this wouldn't be the next line she reads
+	assert_eq! ( carol.send( "LIST" ), "OK LIST" );
+}
+
+#[test]
+fn renames_are_announced_in_shared_rooms() {
+	let ( _server, _chat, addr ) = start();
+	let mut alice = User::named( addr, "alice" );
+	let mut bob = User::named( addr, "bob" );
+
+	alice.send( "JOIN #a" );
+	alice.send( "JOIN #b" );
+	bob.send( "JOIN #a" );
+	bob.send( "JOIN #b" );
+	assert_eq! ( alice.line(), ":bob JOIN #a" );
+	assert_eq! ( alice.line(), ":bob JOIN #b" );
+
+	assert_eq! ( bob.send( "NICK robert" ), "OK NICK robert" );
+	assert_eq! ( alice.line(), ":bob NICK robert" ); // Once, even though they share two rooms
+	assert_eq! ( alice.send( "MSG #a still here?" ), "OK MSG #a 1" );
+	assert_eq! ( bob.line(), ":alice MSG #a still here?" );
+}
+
+#[test]
+fn quit_and_dropped_connections_leave_every_room() {
+	let ( server, chat, addr ) = start();
+	let mut alice = User::named( addr, "alice" );
+	let mut bob = User::named( addr, "bob" );
+	let mut carol = User::named( addr, "carol" );
+
+	for user in [&mut alice, &mut bob, &mut carol] {
+		user.send( "JOIN #rust" );
+	}
+	assert_eq! ( alice.line(), ":bob JOIN #rust" );
+	assert_eq! ( alice.line(), ":carol JOIN #rust" );
+	assert_eq! ( bob.line(), ":carol JOIN #rust" );
+
+	assert_eq! ( bob.send( "QUIT see you" ), "OK QUIT Bye" );
+	assert! ( bob.is_closed() );
+	assert_eq! ( alice.line(), ":bob QUIT see you" );
+	assert_eq! ( carol.line(), ":bob QUIT see you" );
+
+	drop( carol );
+	assert_eq! ( alice.line(), ":carol QUIT Connection closed" );
+
+	assert_eq! ( chat.rooms(), vec![( "#rust".to_string(), 1 )] );
+	assert_eq! ( server.clients().len(), 1 );
+
+	// Their nicknames are free again
+	let mut again = User::named( addr, "bob" );
+	assert_eq! ( again.send( "LIST #rust" ), "OK LIST #rust alice" );
+}
+
+#[test]
+fn clients_that_stop_reading_are_dropped_without_stalling_the_room() {
+	let ( server, chat ) = chat::server( "127.0.0.1:0".parse().unwrap() );
+	let mut server = server.with_io_timeout( Duration::from_millis( 100 ) );
+	let addr = server.start().unwrap();
+
+	let mut alice = User::named( addr, "alice" );
+	let mut bob = User::named( addr, "bob" );
+	let mut stuck = User::named( addr, "stuck" ); // Never reads what it's sent
+
+	for user in [&mut alice, &mut bob, &mut stuck] {
+		user.send( "JOIN #flood" );
+	}
+	assert_eq! ( alice.line(), ":bob JOIN #flood" );
+	assert_eq! ( alice.line(), ":stuck JOIN #flood" );
+	assert_eq! ( bob.line(), ":stuck JOIN #flood" );
+
+	// Once its socket buffers are full, a write to 'stuck' times out and it gets disconnected
+	let text = "x".repeat( 64 * 1024 );
+	let mut sent = 0;
+
+	loop {
+		let answer = alice.send( &format! ( "MSG #flood {text}" ) );
+		sent += 1;
+
+		if answer == "OK MSG #flood 1" {
+			break;
+		}
+
+		assert_eq! ( answer, "OK MSG #flood 2" );
+		assert! ( sent < 2000, "'stuck' was never disconnected" );
+	}
+
+	// Bob got every message, and the QUIT somewhere after the write that failed
+	let lines: Vec<String> = ( 0..=sent ).map( |_| bob.line() ).collect();
+	assert_eq! ( lines.iter().filter( |l| l.starts_with( ":alice MSG #flood x" ) ).count(), sent );
+	assert! ( lines.contains( &":stuck QUIT Connection closed".to_string() ) );
+
+	assert_eq! ( chat.rooms(), vec![( "#flood".to_string(), 2 )] );
+	assert_eq! ( chat.nick( 3 ), None );
+}
@@ -0,0 +1,83 @@
+// Integration tests for contact::Email and contact::Phone validation, including IDN domains
+
+use rs_basics::contact::{ self, CountryRule, Email, Phone, PhoneConfig };
+
+#[test]
+fn emails_accept_dot_atoms_quoted_locals_and_literals() {
+	for valid in ["john.doe+tag@example.com", "\"john doe\"@example.com", "user@[192.168.0.1]", "user@[IPv6:::1]", "a@b-c.io"] {
+		assert! ( Email::new( valid ).is_ok(), "{valid:?}" );
+	}
+
+	let email = Email::new( "  John.Doe@Example.com " ).unwrap();
+	assert_eq! ( ( email.local_part(), email.domain() ), ( "John.Doe", "example.com" ) ); // Domains are case-insensitive
+}
+
+#[test]
+fn emails_reject_malformed_addresses() {
+	let invalid = [
+		"",
+		"no-at-sign",
+		"two@@example.com",
+		".leading@example.com",
+		"double..dot@example.com",
+		"user@localhost", // No top-level domain
+		"user@-hyphen.com",
+		"user@exa_mple.com",
+		"user@[999.1.1.1]",
+	];
+
+	for input in invalid {
+		assert! ( Email::new( input ).is_err(), "{input:?}" );
+	}
+
+	let long_local = format! ( "{}@example.com", "a".repeat( 65 ) );
+	let long_label = format! ( "user@{}.com", "a".repeat( 64 ) );
+	assert! ( Email::new( &long_local ).is_err() );
+	assert! ( Email::new( &long_label ).unwrap_err().contains( "longer than 63" ) );
+}
+
+#[test]
+fn idn_labels_are_punycoded_and_length_checked() {
+	let email = Email::new( "info@bücher.de" ).unwrap();
+
+	assert! ( email.is_internationalized() );
+	assert_eq! ( email.ascii_domain(), "xn--bcher-kva.de" );
+	assert_eq! ( contact::punycode_encode( "münchen" ), Ok( "mnchen-3ya".to_string() ) );
+	assert_eq! ( contact::punycode_encode( "日本語" ), Ok( "wgv71a119e".to_string() ) );
+	assert! ( Email::new( "info@xn--bcher-kva.de" ).is_ok() ); // Already encoded
+
+	// Short in chars, but too long once encoded
+	let label = "aü".repeat( 30 );
+	assert! ( contact::ascii_domain( &label ).unwrap().len() > 63 );
+	assert! ( Email::new( &format! ( "a@{label}.de" ) ).is_err() );
+
+	// Used to overflow while encoding
+	let huge = format! ( "a@{}\u{20000}.com", "a".repeat( 40_000 ) );
+	assert! ( Email::new( &huge ).unwrap_err().contains( "longer than 63" ) );
+	assert! ( contact::punycode_encode( &format! ( "{}\u{20000}", "a".repeat( 40_000 ) ) ).unwrap_err().contains( "too long" ) );
+}
+
+#[test]
+fn phones_normalize_to_e164() {
+	let config = PhoneConfig::default();
+
+	assert_eq! ( Phone::new( "+44 20 7946 0958", &config ).unwrap().as_str(), "+442079460958" );
+	assert_eq! ( Phone::new( "0044 (0)20 7946-0958", &config ).unwrap().as_str(), "+442079460958" );
+	assert_eq! ( Phone::new( "+1 (555) 010-9999", &config ).unwrap().country_code( &config ), Some( "1" ) );
+
+	// National numbers need a default country
+	assert! ( Phone::new( "020 7946 0958", &config ).unwrap_err().contains( "no country code" ) );
+
+	let uk = config.clone().with_default_country( "44" );
+	assert_eq! ( Phone::new( "020 7946 0958", &uk ).unwrap().as_str(), "+442079460958" );
+}
+
+#[test]
+fn phones_reject_bad_input() {
+	let config = PhoneConfig::default().with_country( CountryRule::new( "353", Some( "0" ), 7, 9 ) );
+
+	assert! ( Phone::new( "+44 20 7946", &config ).is_err() ); // Too short for the UK
+	assert! ( Phone::new( "+999 1234567", &config ).unwrap_err().contains( "Unsupported country code" ) );
+	assert! ( Phone::new( "+44 20 7946 095x", &config ).unwrap_err().contains( "Invalid character" ) );
+	assert! ( Phone::new( "+353 1 234 5678", &config ).is_ok() ); // Added to the defaults
+}
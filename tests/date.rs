@@ -0,0 +1,70 @@
+// Integration tests for date::Date: parsing, weekdays, arithmetic and ages
+
+use rs_basics::date::{ self, Date, Weekday };
+
+fn d( year: i32, month: u8, day: u8 ) -> Date {
+	return Date::new( year, month, day ).unwrap();
+}
+
+#[test]
+fn parses_every_supported_format() {
+	let expected = d( 2003, 8, 3 );
+
+	for input in ["2003-08-03", "2003/08/03", "03.08.2003", "August 03 2003", "Aug 3, 2003", "3 August 2003", "03-Aug-2003", "2003 Aug 03", " aug. 3 2003 "] {
+		assert_eq! ( input.parse::<Date>(), Ok( expected ), "{input:?}" );
+	}
+
+	assert! ( Date::parse( "Augu 3 2003" ).is_err() ); // Full names or 3-letter abbreviations only
+
+	assert_eq! ( expected.to_string(), "2003-08-03" );
+}
+
+#[test]
+fn rejects_ambiguous_and_impossible_dates() {
+	assert! ( Date::parse( "" ).is_err() );
+	assert! ( Date::parse( "03/08/2003" ).is_err() ); // US or European? We don't guess
+	assert! ( Date::parse( "2003-13-01" ).is_err() );
+	assert! ( Date::parse( "2003-02-29" ).is_err() );
+	assert! ( Date::parse( "Smarch 3 2003" ).unwrap_err().contains( "Unknown month" ) );
+	assert! ( Date::parse( "2004-02-29" ).is_ok() );
+	assert! ( Date::new( 2000, 2, 29 ).is_ok() && Date::new( 1900, 2, 29 ).is_err() ); // Centuries are leap years only every 400
+}
+
+#[test]
+fn weekdays_and_day_arithmetic() {
+	assert_eq! ( d( 1970, 1, 1 ).weekday(), Weekday::Thursday );
+	assert_eq! ( d( 2003, 8, 3 ).weekday(), Weekday::Sunday );
+	assert_eq! ( d( 1969, 12, 31 ).weekday(), Weekday::Wednesday ); // Before the epoch
+
+	assert_eq! ( d( 2003, 12, 31 ).add_days( 1 ), d( 2004, 1, 1 ) );
+	assert_eq! ( d( 2004, 1, 31 ).add_months( 1 ), d( 2004, 2, 29 ) ); // Clamped to the end of February
+	assert_eq! ( d( 2004, 2, 29 ).add_years( 1 ), d( 2005, 2, 28 ) );
+	assert_eq! ( d( 2004, 3, 1 ).add_months( -15 ), d( 2002, 12, 1 ) );
+	assert_eq! ( d( i32::MAX, 12, 1 ).checked_add_months( 1 ), None ); // Would overflow the year
+	assert_eq! ( d( i32::MIN, 1, 31 ).checked_add_months( -1 ), None );
+	assert_eq! ( d( i32::MAX - 1, 1, 31 ).checked_add_months( 13 ), Some( d( i32::MAX, 2, 28 ) ) );
+	assert_eq! ( d( 2000, 1, 1 ).checked_add_years( i32::MAX ), None );
+	assert_eq! ( d( 2003, 1, 1 ).days_until( &d( 2004, 1, 1 ) ), 365 );
+	assert_eq! ( d( 2004, 12, 31 ).ordinal(), 366 );
+
+	for days in [-800_000, -1, 0, 1, 12_000, 800_000] {
+		assert_eq! ( Date::from_days_since_epoch( days ).days_since_epoch(), days );
+	}
+
+	assert_eq! ( date::days_in_month( 2100, 2 ), 28 );
+}
+
+#[test]
+fn leap_day_birthdays_age_on_march_first() {
+	let born = d( 2004, 2, 29 );
+
+	assert_eq! ( born.age_on( &d( 2005, 2, 28 ) ), Some( 0 ) );
+	assert_eq! ( born.age_on( &d( 2005, 3, 1 ) ), Some( 1 ) );
+	assert_eq! ( born.age_on( &d( 2008, 2, 29 ) ), Some( 4 ) );
+	assert_eq! ( born.age_on( &born ), Some( 0 ) );
+	assert_eq! ( born.age_on( &d( 2004, 2, 28 ) ), None ); // Not born yet
+
+	let born = d( 1990, 8, 3 );
+	assert_eq! ( born.age_on( &d( 2020, 8, 2 ) ), Some( 29 ) );
+	assert_eq! ( born.age_on( &d( 2020, 8, 3 ) ), Some( 30 ) );
+}
@@ -0,0 +1,121 @@
+// Integration tests for events: handler order, once handlers, unsubscribing and the thread-safe bus
+
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::atomic::{ AtomicUsize, Ordering };
+use std::sync::{ Arc, Barrier, Mutex };
+use std::thread;
+
+use rs_basics::events::{ Event, EventBus, Handler, SyncEventBus, ALL_TOPICS };
+
+#[derive( Debug, Clone )]
+struct Message( &'static str, u32 );
+
+impl Event for Message {
+	fn topic( &self ) -> &str {
+		return self.0;
+	}
+}
+
+type Log = Rc<RefCell<Vec<String>>>;
+
+fn logger( log: &Log, name: &'static str ) -> impl Fn( &Message ) + 'static {
+	let log = Rc::clone( log );
+
+	return move |m: &Message| log.borrow_mut().push( format! ( "{name} {}", m.1 ) );
+}
+
+fn taken( log: &Log ) -> Vec<String> {
+	return std::mem::take( &mut *log.borrow_mut() );
+}
+
+#[test]
+fn handlers_run_by_priority_then_registration_order() {
+	let log: Log = Rc::default();
+	let mut bus = EventBus::new();
+
+	bus.on( "login", logger( &log, "first" ) );
+	bus.subscribe( "login", 10, Handler::Fn( Box::new( logger( &log, "urgent" ) ) ) );
+	bus.subscribe( "login", -5, Handler::Fn( Box::new( logger( &log, "last" ) ) ) );
+	bus.on( "login", logger( &log, "second" ) );
+	bus.on( ALL_TOPICS, logger( &log, "wildcard" ) );
+	bus.on( "logout", logger( &log, "other topic" ) );
+
+	assert_eq! ( bus.publish( &Message( "login", 1 ) ), 5 );
+	assert_eq! ( taken( &log ), ["urgent 1", "first 1", "second 1", "last 1", "wildcard 1"] );
+
+	// The wildcard hears every topic, even ones nobody subscribed to
+	assert_eq! ( bus.publish( &Message( "unknown", 2 ) ), 1 );
+	assert_eq! ( taken( &log ), ["wildcard 2"] );
+}
+
+#[test]
+fn once_handlers_fire_once_and_unsubscribe_removes() {
+	let log: Log = Rc::default();
+	let mut bus = EventBus::new();
+	let count = Rc::new( RefCell::new( 0 ) );
+	let counter = Rc::clone( &count );
+
+	let name = String::from( "welcome" ); // Moved into the FnOnce
+	let inner = Rc::clone( &log );
+	let once = bus.once( "login", move |m: &Message| inner.borrow_mut().push( format! ( "{name} {}", m.1 ) ) );
+	let every = bus.on_mut( "login", move |_| *counter.borrow_mut() += 1 );
+
+	bus.publish( &Message( "login", 1 ) );
+	bus.publish( &Message( "login", 2 ) );
+	assert_eq! ( taken( &log ), ["welcome 1"] );
+	assert_eq! ( *count.borrow(), 2 );
+	assert_eq! ( bus.handler_count( "login" ), 1 );
+	assert! ( !bus.unsubscribe( &once ) ); // Already gone
+
+	assert! ( bus.unsubscribe( &every ) );
+	assert! ( !bus.unsubscribe( &every ) );
+	assert_eq! ( bus.publish( &Message( "login", 3 ) ), 0 );
+	assert_eq! ( *count.borrow(), 2 );
+	assert_eq! ( every.topic(), "login" );
+}
+
+#[test]
+fn sync_bus_shares_handlers_between_clones() {
+	let bus: SyncEventBus<Message> = SyncEventBus::new();
+	let log = Arc::new( Mutex::new( Vec::new() ) );
+
+	let ( a, b, c ) = ( Arc::clone( &log ), Arc::clone( &log ), Arc::clone( &log ) );
+	bus.on( "tick", move |m| a.lock().unwrap().push( format! ( "normal {}", m.1 ) ) );
+	bus.subscribe( "tick", 1, move |m| b.lock().unwrap().push( format! ( "high {}", m.1 ) ) );
+	let all = bus.on( ALL_TOPICS, move |m| c.lock().unwrap().push( format! ( "all {}", m.1 ) ) );
+
+	let clone = bus.clone();
+	thread::spawn( move || clone.publish( &Message( "tick", 1 ) ) ).join().unwrap();
+	assert_eq! ( *log.lock().unwrap(), ["high 1", "normal 1", "all 1"] );
+
+	assert! ( bus.unsubscribe( &all ) );
+	assert_eq! ( bus.handler_count( ALL_TOPICS ), 0 );
+}
+
+#[test]
+fn sync_once_runs_exactly_once_under_concurrent_publishes() {
+	const THREADS: usize = 8;
+
+	for _ in 0..20 {
+		let bus: SyncEventBus<Message> = SyncEventBus::new();
+		let runs = Arc::new( AtomicUsize::new( 0 ) );
+		let barrier = Arc::new( Barrier::new( THREADS ) );
+
+		let owned = vec![1, 2, 3]; // Moved in and consumed: only an FnOnce can do that
+		let counter = Arc::clone( &runs );
+		bus.once( "start", move |_| { drop( owned ); counter.fetch_add( 1, Ordering::SeqCst ); } );
+
+		let threads: Vec<_> = ( 0..THREADS ).map( |_| {
+			let ( bus, barrier ) = ( bus.clone(), Arc::clone( &barrier ) );
+
+			thread::spawn( move || { barrier.wait(); bus.publish( &Message( "start", 0 ) ) } )
+		}).collect();
+
+		let called: usize = threads.into_iter().map( |t| t.join().unwrap() ).sum();
+
+		assert_eq! ( runs.load( Ordering::SeqCst ), 1 );
+		assert_eq! ( called, 1 );
+		assert_eq! ( bus.handler_count( "start" ), 0 );
+	}
+}
@@ -0,0 +1,131 @@
+// Integration tests for history: undo, redo, transactions and the memory cap
+
+use std::mem::size_of;
+
+use rs_basics::history::{ edits, History };
+
+#[derive( Debug, Clone, PartialEq )]
+struct Person {
+	name: String,
+	age: u8,
+}
+
+#[test]
+fn undo_and_redo_walk_back_and_forth() {
+	let mut list = History::new( vec![1, 2, 3] );
+
+	list.execute( edits::push( 4 ) );
+	list.execute( edits::insert( 0, 0 ) );
+	list.execute( edits::remove( 2 ) );
+	list.execute( edits::retain( |n: &i32| n % 2 == 0 ) );
+	assert_eq! ( list.target(), &[0, 4] );
+	assert_eq! ( list.undo_labels(), ["push", "insert at 0", "remove at 2", "retain (removed 2)"] );
+
+	let states = [vec![0, 1, 3, 4], vec![0, 1, 2, 3, 4], vec![1, 2, 3, 4], vec![1, 2, 3]];
+
+	for state in &states {
+		assert! ( list.undo() );
+		assert_eq! ( list.target(), state );
+	}
+
+	assert! ( !list.undo() && !list.can_undo() );
+
+	for state in states.iter().rev().skip( 1 ) {
+		assert! ( list.redo() );
+		assert_eq! ( list.target(), state );
+	}
+
+	// A new edit forgets what could have been redone
+	assert! ( list.can_redo() );
+	list.execute( edits::append( vec![7, 8] ) );
+	assert! ( !list.can_redo() && !list.redo() );
+	assert_eq! ( list.into_inner(), [0, 1, 3, 4, 7, 8] );
+}
+
+#[test]
+fn assign_swaps_a_field_in_and_out() {
+	let mut person = History::new( Person { name: "John".to_string(), age: 30 } );
+
+	person.execute( edits::assign( "name", |p: &mut Person| &mut p.name, "Jane".to_string() ) );
+	person.execute( edits::assign( "age", |p: &mut Person| &mut p.age, 31 ) );
+	assert_eq! ( person.target(), &Person { name: "Jane".to_string(), age: 31 } );
+
+	person.undo();
+	assert_eq! ( person.target().age, 30 );
+	assert_eq! ( person.redo_labels(), ["set age"] );
+
+	person.undo();
+	person.redo();
+	assert_eq! ( person.target(), &Person { name: "Jane".to_string(), age: 30 } );
+}
+
+#[test]
+fn transactions_are_one_undo_step() {
+	let mut list: History<Vec<i32>> = History::new( Vec::new() );
+
+	assert! ( list.begin( "fill" ) );
+	assert! ( !list.begin( "nested" ) ); // They don't nest
+	list.execute( edits::push( 1 ) );
+	list.execute( edits::push( 2 ) );
+	assert! ( !list.undo() ); // Not while one is open
+	assert! ( list.commit() );
+
+	list.execute( edits::push( 3 ) );
+	assert_eq! ( list.undo_labels(), ["fill", "push"] );
+
+	list.undo();
+	list.undo();
+	assert! ( list.target().is_empty() );
+	list.redo();
+	assert_eq! ( list.target(), &[1, 2] );
+
+	// Rollback undoes right away, and leaves no step behind
+	list.begin( "oops" );
+	list.execute( edits::push( 9 ) );
+	assert! ( list.rollback() );
+	assert_eq! ( list.target(), &[1, 2] );
+	assert_eq! ( list.undo_labels(), ["fill"] );
+
+	let failed: Result<(), String> = list.transaction( "checked", |h| {
+		h.execute( edits::remove( 0 ) );
+		h.transaction( "inner", |h| { h.execute( edits::push( 5 ) ); Ok::<_, String>( () ) } )?; // Joins the outer one
+		Err( "validation failed".to_string() )
+	});
+
+	assert! ( failed.is_err() );
+	assert_eq! ( list.target(), &[1, 2] );
+
+	let ok: Result<(), String> = list.transaction( "checked", |h| { h.execute( edits::push( 3 ) ); Ok( () ) } );
+	assert! ( ok.is_ok() );
+	assert_eq! ( list.undo_labels(), ["fill", "checked"] );
+
+	// Empty transactions aren't worth an undo step
+	list.begin( "nothing" );
+	list.commit();
+	assert_eq! ( list.undo_labels().len(), 2 );
+}
+
+#[test]
+fn the_memory_cap_forgets_the_oldest_steps() {
+	let step = size_of::<u64>() * 1000 + size_of::<Vec<u64>>() + size_of::<usize>(); // One 'append' of 1000 u64
+	let mut list = History::new( Vec::new() ).with_memory_limit( step * 3 );
+
+	for i in 0..5 {
+		list.execute( edits::append( vec![i as u64; 1000] ) );
+	}
+
+	assert_eq! ( list.undo_labels().len(), 3 );
+	assert_eq! ( list.memory_used(), step * 3 );
+
+	while list.undo() {}
+	assert_eq! ( list.target().len(), 2000 ); // The first two appends can't be undone anymore
+	assert_eq! ( list.memory_used(), step * 3 ); // Undone steps are still held, for redoing
+
+	// A new edit drops the redo stack, and its memory
+	list.execute( edits::push( 7 ) );
+	assert! ( list.memory_used() < step );
+
+	list.clear();
+	assert_eq! ( list.memory_used(), 0 );
+	assert! ( !list.can_undo() );
+}
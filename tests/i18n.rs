@@ -0,0 +1,90 @@
+// Integration tests for i18n: catalogs, plural selection and the locale fallback chain
+
+use rs_basics::i18n::{ self, Catalog, Localizer, PluralCategory };
+
+fn localizer( locale: &str ) -> Localizer {
+	let mut localizer = Localizer::new( locale );
+	localizer.add_catalog( Catalog::parse( "de", "
+		lesson.greeting = Hallo, {name}!
+		files[one] = {count} Datei
+		files[other] = {count} Dateien
+	" ).unwrap() );
+	localizer.add_catalog( Catalog::parse( "de-AT", "lesson.greeting = Servus, {name}!" ).unwrap() );
+	localizer.add_catalog( Catalog::parse( "ru", "
+		files[one] = {count} файл
+		files[few] = {count} файла
+		files[many] = {count} файлов
+	" ).unwrap() );
+
+	return localizer;
+}
+
+#[test]
+fn plural_categories_follow_cldr() {
+	use PluralCategory::*;
+
+	let cases = [
+		( "en", [( 0, Other ), ( 1, One ), ( 2, Other ), ( 11, Other )] ),
+		( "fr-CA", [( 0, One ), ( 1, One ), ( 2, Other ), ( 11, Other )] ),
+		( "ru", [( 1, One ), ( 3, Few ), ( 11, Many ), ( 21, One )] ),
+		( "pl", [( 1, One ), ( 22, Few ), ( 12, Many ), ( 21, Many )] ),
+		( "ar", [( 0, Zero ), ( 2, Two ), ( 105, Few ), ( 111, Many )] ),
+		( "ja", [( 0, Other ), ( 1, Other ), ( 2, Other ), ( 100, Other )] ),
+		( "xx", [( 0, Other ), ( 1, One ), ( -1, One ), ( 5, Other )] ), // Unknown languages use the English rule
+	];
+
+	for ( locale, expected ) in cases {
+		for ( n, category ) in expected {
+			assert_eq! ( i18n::plural_category( locale, n ), category, "{locale} {n}" );
+		}
+	}
+}
+
+#[test]
+fn plural_messages_pick_the_form_for_the_count() {
+	let en = Localizer::new( "en" );
+	assert_eq! ( en.format( "lesson.years_left", &[( "count", &1 )] ), "1 year to go" );
+	assert_eq! ( en.format( "lesson.years_left", &[( "count", &5 )] ), "5 years to go" );
+	assert_eq! ( en.format( "lesson.years_left", &[( "count", &1.5 )] ), "1.5 years to go" ); // Not an integer: 'other'
+	assert_eq! ( en.format( "lesson.waiting", &[] ), "Waiting..." );
+
+	let ru = localizer( "ru" );
+	assert_eq! ( ru.format( "files", &[( "count", &1 )] ), "1 файл" );
+	assert_eq! ( ru.format( "files", &[( "count", &4 )] ), "4 файла" );
+	assert_eq! ( ru.format( "files", &[( "count", &12 )] ), "12 файлов" );
+	assert_eq! ( ru.format( "files", &[] ), "files" ); // No count and no 'other' form
+
+	// The plural rule is the one of the catalog the message came from
+	assert_eq! ( localizer( "de" ).format( "files", &[( "count", &0 )] ), "0 Dateien" );
+}
+
+#[test]
+fn lookups_fall_back_through_the_chain() {
+	assert_eq! ( i18n::fallback_chain( "de_AT.UTF-8" ), ["de-AT", "de", "en"] );
+	assert_eq! ( i18n::fallback_chain( "en-GB" ), ["en-GB", "en"] );
+
+	let at = localizer( "de-AT" );
+	let de = localizer( "de-DE" );
+	let name: &dyn std::fmt::Display = &"Ann";
+
+	assert_eq! ( at.format( "lesson.greeting", &[( "name", name )] ), "Servus, Ann!" );
+	assert_eq! ( de.format( "lesson.greeting", &[( "name", name )] ), "Hallo, Ann!" ); // No de-DE catalog
+	assert_eq! ( de.format( "lesson.waiting", &[] ), "Waiting..." ); // Only in English
+	assert_eq! ( de.format( "no.such.key", &[] ), "no.such.key" );
+	assert! ( at.has( "files" ) && !at.has( "no.such.key" ) );
+}
+
+#[test]
+fn catalogs_reject_bad_lines() {
+	assert! ( Catalog::parse( "en", "no equals sign" ).unwrap_err().contains( "line 1" ) );
+	assert! ( Catalog::parse( "en", "a = 1\na = 2" ).unwrap_err().contains( "duplicate key" ) );
+	assert_eq! ( Catalog::parse( "en", "a[one] = x\na[other] = y\n\na[one] = z" ).unwrap_err(), "en.msg line 4: duplicate key 'a[one]'" );
+	assert! ( Catalog::parse( "en", "a[lots] = x" ).unwrap_err().contains( "invalid plural category" ) );
+	assert! ( Catalog::parse( "en", "a = x\na[one] = y" ).unwrap_err().contains( "both a plain and a plural" ) );
+
+	let catalog = Catalog::parse( "en", "# Comment\n\nbraces = {{literal}} {missing}\\n" ).unwrap();
+	let mut localizer = Localizer::new( "en" );
+	localizer.add_catalog( catalog );
+
+	assert_eq! ( localizer.format( "braces", &[] ), "{literal} {missing}\n" );
+}
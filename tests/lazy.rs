@@ -0,0 +1,131 @@
+// Integration tests for lazy: Lazy and SyncLazy initialization, poisoning and re-entrancy, and thunks
+
+use std::cell::{ Cell, OnceCell };
+use std::panic::{ self, AssertUnwindSafe };
+use std::rc::{ Rc, Weak };
+use std::sync::atomic::{ AtomicUsize, Ordering };
+use std::sync::{ Arc, Barrier };
+use std::thread;
+use std::time::Duration;
+
+use rs_basics::lazy::{ Lazy, Poisoned, SyncLazy, Thunk };
+use rs_basics::lazy_static;
+
+static CALLS: AtomicUsize = AtomicUsize::new( 0 );
+static SHARED: SyncLazy<Vec<u32>> = SyncLazy::new( || {
+	CALLS.fetch_add( 1, Ordering::SeqCst );
+	thread::sleep( Duration::from_millis( 20 ) ); // Long enough for the other threads to pile up
+	( 1..=10 ).collect()
+});
+
+static SELF_REFERENCING: SyncLazy<u32> = SyncLazy::new( || *SELF_REFERENCING + 1 );
+
+lazy_static! {
+	static ref GREETING: String = format! ( "Hello, {}!", "world" );
+}
+
+fn panics<T>( f: impl FnOnce() -> T ) -> String {
+	let payload = panic::catch_unwind( AssertUnwindSafe( f ) ).err().expect( "Expected a panic" );
+
+	return payload.downcast_ref::<String>().cloned().or( payload.downcast_ref::<&str>().map( |s| s.to_string() ) ).unwrap_or_default();
+}
+
+#[test]
+fn lazy_runs_its_initializer_once_on_first_use() {
+	let calls = Cell::new( 0 );
+	let value = Lazy::new( || { calls.set( calls.get() + 1 ); 42 } );
+
+	assert_eq! ( Lazy::get( &value ), None );
+	assert_eq! ( calls.get(), 0 );
+	assert_eq! ( *value + *value, 84 );
+	assert_eq! ( Lazy::get( &value ), Some( &42 ) );
+	assert_eq! ( calls.get(), 1 );
+	assert_eq! ( format! ( "{:?}", value ), "Lazy(42)" );
+}
+
+#[test]
+fn a_panicking_initializer_poisons_the_lazy() {
+	let value: Lazy<u32> = Lazy::new( || panic! ( "Config file missing" ) );
+
+	assert_eq! ( panics( || *value ), "Config file missing" ); // The first caller gets the original panic
+	assert! ( Lazy::is_poisoned( &value ) );
+	assert_eq! ( Lazy::try_force( &value ), Err( Poisoned ) );
+	assert_eq! ( panics( || *value ), "Lazy instance has been poisoned" );
+	assert_eq! ( format! ( "{:?}", value ), "Lazy(<uninit>)" );
+}
+
+#[test]
+fn lazy_used_by_its_own_initializer_panics_instead_of_recursing() {
+	type Boxed = Lazy<u32, Box<dyn FnOnce() -> u32>>;
+
+	let slot: Rc<OnceCell<Weak<Boxed>>> = Rc::default();
+	let inner = Rc::clone( &slot );
+	let value: Rc<Boxed> = Rc::new( Lazy::new( Box::new( move || **inner.get().unwrap().upgrade().unwrap() + 1 ) ) );
+	slot.set( Rc::downgrade( &value ) ).unwrap();
+
+	assert_eq! ( panics( || **value ), "Lazy value used during its own initialization" );
+	assert! ( Lazy::is_poisoned( &value ) );
+}
+
+#[test]
+fn sync_lazy_initializes_once_across_threads() {
+	let barrier = Arc::new( Barrier::new( 8 ) );
+
+	let threads: Vec<_> = ( 0..8 ).map( |_| {
+		let barrier = Arc::clone( &barrier );
+
+		thread::spawn( move || { barrier.wait(); SHARED.iter().sum::<u32>() } )
+	}).collect();
+
+	for t in threads {
+		assert_eq! ( t.join().unwrap(), 55 );
+	}
+
+	assert_eq! ( CALLS.load( Ordering::SeqCst ), 1 );
+	assert_eq! ( GREETING.as_str(), "Hello, world!" );
+}
+
+#[test]
+fn sync_lazy_poison_reaches_waiting_threads() {
+	let value: Arc<SyncLazy<u32>> = Arc::new( SyncLazy::new( || {
+		thread::sleep( Duration::from_millis( 20 ) );
+		panic! ( "Initializer failed" )
+	}));
+
+	let threads: Vec<_> = ( 0..2 ).map( |_| {
+		let value = Arc::clone( &value );
+
+		thread::spawn( move || SyncLazy::try_force( &value ).copied() )
+	}).collect();
+
+	let results: Vec<_> = threads.into_iter().map( |t| t.join() ).collect();
+
+	// Whichever thread ran the initializer panicked, the other one waited for it and got the poison
+	assert_eq! ( results.iter().filter( |r| r.is_err() ).count(), 1 );
+	assert! ( results.iter().any( |r| matches! ( r, Ok( Err( Poisoned ) ) ) ) );
+	assert! ( SyncLazy::is_poisoned( &value ) );
+}
+
+#[test]
+fn sync_lazy_re_entrancy_panics_instead_of_deadlocking() {
+	assert_eq! ( panics( || *SELF_REFERENCING ), "Lazy value used during its own initialization" );
+	assert! ( SyncLazy::is_poisoned( &SELF_REFERENCING ) );
+}
+
+#[test]
+fn thunks_only_run_when_forced() {
+	let runs = Rc::new( Cell::new( 0 ) );
+	let counter = Rc::clone( &runs );
+
+	let chained = Thunk::new( move || { counter.set( counter.get() + 1 ); 20 } )
+		.map( |n| n + 1 )
+		.and_then( |n| Thunk::value( n * 2 ) )
+		.zip( Thunk::value( "done" ) );
+
+	assert_eq! ( runs.get(), 0 );
+	assert_eq! ( chained.force(), ( 42, "done" ) );
+	assert_eq! ( runs.get(), 1 );
+
+	let memo = Thunk::new( || 7 ).memo();
+	assert_eq! ( *memo * *memo, 49 );
+}
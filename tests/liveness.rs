@@ -0,0 +1,141 @@
+// Integration tests for client sessions, heartbeats and idle timeouts on threading::Server
+
+use std::io::{ BufRead, BufReader, Write };
+use std::net::{ SocketAddr, TcpStream };
+use std::sync::mpsc::{ self, Receiver };
+use std::time::{ Duration, Instant };
+
+use rs_basics::threading::{ DisconnectReason, Server, ServerEvent };
+
+const WAIT: Duration = Duration::from_secs( 5 );
+
+fn event_log( server: &Server ) -> Receiver<ServerEvent> {
+	let ( tx, rx ) = mpsc::channel();
+	let tx = std::sync::Mutex::new( tx );
+
+	server.events().on( "*", move |e: &ServerEvent| { let _ = tx.lock().unwrap().send( e.clone() ); } );
+
+	return rx;
+}
+
+fn connect( addr: SocketAddr ) -> ( TcpStream, BufReader<TcpStream> ) {
+	let stream = TcpStream::connect( addr ).unwrap();
+	stream.set_read_timeout( Some( WAIT ) ).unwrap();
+	let reader = BufReader::new( stream.try_clone().unwrap() );
+
+	return ( stream, reader );
+}
+
+fn read_line( reader: &mut BufReader<TcpStream> ) -> String {
+	let mut line = String::new();
+	reader.read_line( &mut line ).unwrap();
+
+	return line.trim_end().to_string();
+}
+
+#[test]
+fn sessions_record_peer_and_activity() {
+	let mut server = Server::new( "127.0.0.1:0".parse().unwrap() );
+	let events = event_log( &server );
+	let addr = server.start().unwrap();
+	let ( mut stream, mut reader ) = connect( addr );
+
+	let ServerEvent::Connected( client ) = events.recv_timeout( WAIT ).unwrap() else { panic! ( "Expected a connect event" ) };
+	let before = server.clients().session( client.id ).unwrap();
+
+	assert_eq! ( before.peer, Some( stream.local_addr().unwrap() ) );
+	assert_eq! ( before.requests, 0 );
+
+	stream.write_all( b"hello\n" ).unwrap();
+	assert_eq! ( read_line( &mut reader ), "hello" );
+
+	let after = server.clients().session( client.id ).unwrap();
+	assert_eq! ( after.requests, 1 );
+	assert! ( after.last_activity > before.last_activity );
+	assert_eq! ( after.connected_at, before.connected_at );
+
+	// Clients without a socket have no peer
+	let local = server.push_client( "lesson" );
+	assert_eq! ( server.clients().session( local ).unwrap().peer, None );
+}
+
+#[test]
+fn silent_clients_get_pinged_and_answering_keeps_them() {
+	let mut server = Server::new( "127.0.0.1:0".parse().unwrap() ).with_heartbeat( Duration::from_millis( 50 ), Duration::from_millis( 200 ) );
+	let events = event_log( &server );
+	let addr = server.start().unwrap();
+	let ( mut stream, mut reader ) = connect( addr );
+
+	let ServerEvent::Connected( client ) = events.recv_timeout( WAIT ).unwrap() else { panic! ( "Expected a connect event" ) };
+
+	for _ in 0..3 {
+		let ping = read_line( &mut reader );
+		assert! ( ping.starts_with( "PING " ), "Got {ping:?}" );
+
+		stream.write_all( format! ( "PONG {}\n", &ping[5..] ).as_bytes() ).unwrap();
+	}
+
+	// PONGs go to nobody: the next answer is the echo, not "PONG ..."
+	stream.write_all( b"still here\n" ).unwrap();
+	assert_eq! ( read_line( &mut reader ), "still here" );
+
+	let session = server.clients().session( client.id ).unwrap();
+	assert_eq! ( session.requests, 1 );
+	assert! ( events.try_recv().is_err() ); // No disconnect
+}
+
+#[test]
+fn clients_that_ignore_pings_are_disconnected_as_unresponsive() {
+	let mut server = Server::new( "127.0.0.1:0".parse().unwrap() ).with_heartbeat( Duration::from_millis( 30 ), Duration::from_millis( 60 ) );
+	let events = event_log( &server );
+	let addr = server.start().unwrap();
+	let ( _stream, mut reader ) = connect( addr );
+
+	let ServerEvent::Connected( client ) = events.recv_timeout( WAIT ).unwrap() else { panic! ( "Expected a connect event" ) };
+
+	assert! ( read_line( &mut reader ).starts_with( "PING " ) );
+	assert_eq! ( events.recv_timeout( WAIT ).unwrap(), ServerEvent::Disconnected( client, DisconnectReason::Unresponsive ) );
+	assert_eq! ( read_line( &mut reader ), "ERR No answer to ping" );
+	assert! ( server.clients().is_empty() );
+}
+
+#[test]
+fn idle_clients_are_disconnected_even_if_they_answer_pings() {
+	let limit = Duration::from_millis( 150 );
+	let mut server = Server::new( "127.0.0.1:0".parse().unwrap() )
+		.with_heartbeat( Duration::from_millis( 20 ), Duration::from_secs( 1 ) )
+		.with_idle_timeout( limit );
+	let events = event_log( &server );
+	let addr = server.start().unwrap();
+	let ( mut stream, mut reader ) = connect( addr );
+	let start = Instant::now();
+
+	let ServerEvent::Connected( client ) = events.recv_timeout( WAIT ).unwrap() else { panic! ( "Expected a connect event" ) };
+
+	// Answer every ping until the server gives up on us
+	loop {
+		let line = read_line( &mut reader );
+
+		match line.strip_prefix( "PING " ) {
+			Some( seq ) => stream.write_all( format! ( "PONG {seq}\n" ).as_bytes() ).unwrap(),
+			None => {
+				assert_eq! ( line, "ERR Idle timeout" );
+				break;
+			}
+		}
+	}
+
+	assert! ( start.elapsed() >= limit );
+	assert_eq! ( events.recv_timeout( WAIT ).unwrap(), ServerEvent::Disconnected( client, DisconnectReason::Idle ) );
+}
+
+#[test]
+fn stop_ends_the_reaper_without_waiting_for_it() {
+	let mut server = Server::new( "127.0.0.1:0".parse().unwrap() ).with_idle_timeout( Duration::from_secs( 60 ) );
+	server.start().unwrap();
+
+	let start = Instant::now();
+	server.stop();
+
+	assert! ( start.elapsed() < Duration::from_secs( 5 ) ); // The reaper checks every 15s here
+}
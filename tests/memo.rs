@@ -0,0 +1,120 @@
+// Integration tests for memo: LRU and TTL eviction, recursion and the thread-safe variant
+
+use std::cell::Cell;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::sync::atomic::{ AtomicUsize, Ordering };
+use std::thread;
+use std::time::Duration;
+
+use rs_basics::memo::{ self, Policy, Stats };
+
+// A memoized identity function that counts how often it really ran
+fn counted( policy: Policy ) -> ( memo::Memo<u32, u32, u32>, Rc<Cell<u32>> ) {
+	let calls = Rc::new( Cell::new( 0 ) );
+	let counter = Rc::clone( &calls );
+
+	return ( memo::memoize( policy, move |n: u32| { counter.set( counter.get() + 1 ); n } ), calls );
+}
+
+#[test]
+fn lru_drops_the_least_recently_used() {
+	let ( cached, calls ) = counted( Policy::Lru( 2 ) );
+
+	cached.call( 1 );
+	cached.call( 2 );
+	cached.call( 1 ); // 1 is now more recent than 2
+	cached.call( 3 ); // Evicts 2
+
+	assert_eq! ( calls.get(), 3 );
+	assert_eq! ( cached.len(), 2 );
+
+	cached.call( 1 );
+	assert_eq! ( calls.get(), 3 ); // Still cached
+	cached.call( 2 );
+	assert_eq! ( calls.get(), 4 ); // Was evicted
+
+	assert_eq! ( cached.stats(), Stats { hits: 2, misses: 4, evictions: 2 } );
+
+	let ( nothing, calls ) = counted( Policy::Lru( 0 ) );
+	nothing.call( 1 );
+	nothing.call( 1 );
+	assert_eq! ( ( calls.get(), nothing.len() ), ( 2, 0 ) ); // A zero-sized cache caches nothing
+}
+
+#[test]
+fn ttl_entries_expire_when_read() {
+	let ( lookup, calls ) = counted( Policy::Ttl( Duration::from_millis( 50 ) ) );
+
+	lookup.call( 7 );
+	lookup.call( 7 );
+	assert_eq! ( calls.get(), 1 );
+
+	thread::sleep( Duration::from_millis( 60 ) );
+
+	lookup.call( 7 );
+	assert_eq! ( calls.get(), 2 );
+	assert_eq! ( lookup.stats().evictions, 1 );
+}
+
+#[test]
+fn ttl_entries_that_are_never_read_again_get_swept() {
+	let ( lookup, _ ) = counted( Policy::Ttl( Duration::from_millis( 50 ) ) );
+
+	for n in 0..100 {
+		lookup.call( n );
+	}
+
+	assert_eq! ( lookup.len(), 100 ); // Nothing expired yet, so nothing was dropped
+	thread::sleep( Duration::from_millis( 60 ) );
+
+	// Only new keys from now on: the stale ones go in the next sweep
+	for n in 1000..1100 {
+		lookup.call( n );
+	}
+
+	assert! ( lookup.len() <= 100, "{}", lookup.len() );
+	assert_eq! ( lookup.stats().evictions, 100 );
+}
+
+#[test]
+fn recursion_goes_through_the_cache() {
+	let calls = Rc::new( Cell::new( 0 ) );
+	let counter = Rc::clone( &calls );
+	let fib = memo::memoize_rec( Policy::Unbounded, move |fib, n: u64| {
+		counter.set( counter.get() + 1 );
+
+		if n < 2 { n } else { fib( n - 1 ) + fib( n - 2 ) }
+	});
+
+	assert_eq! ( fib.call( 90 ), 2880067194370816120 );
+	assert_eq! ( calls.get(), 91 ); // Each n once
+
+	fib.clear();
+	assert! ( fib.is_empty() );
+
+	let upper = memo::memoize_by( Policy::Unbounded, |s: &String| s.to_lowercase(), |s: String| s.to_uppercase() );
+	assert_eq! ( upper.call( "Hi".to_string() ), "HI" );
+	assert_eq! ( upper.call( "hI".to_string() ), "HI" );
+	assert_eq! ( upper.stats().hits, 1 ); // Same key
+}
+
+#[test]
+fn sync_memo_is_shared_between_threads() {
+	let calls = Arc::new( AtomicUsize::new( 0 ) );
+	let counter = Arc::clone( &calls );
+	let slow = Arc::new( memo::sync_memoize( Policy::Lru( 8 ), move |n: u64| { counter.fetch_add( 1, Ordering::SeqCst ); n * 2 } ) );
+
+	let threads: Vec<_> = ( 0..4 ).map( |_| {
+		let slow = Arc::clone( &slow );
+
+		thread::spawn( move || ( 0..8 ).map( |n| slow.call( n ) ).sum::<u64>() )
+	}).collect();
+
+	for t in threads {
+		assert_eq! ( t.join().unwrap(), 56 );
+	}
+
+	assert! ( calls.load( Ordering::SeqCst ) >= 8 ); // Racing misses may compute a key twice
+	assert_eq! ( slow.len(), 8 );
+}
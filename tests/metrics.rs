@@ -0,0 +1,183 @@
+// Integration tests for metrics::Registry, its HTTP endpoint, and the metrics threading::Server keeps
+
+use std::io::{ BufRead, BufReader, Read, Write };
+use std::net::{ SocketAddr, TcpStream };
+use std::thread;
+use std::time::Duration;
+
+use rs_basics::metrics::{ self, Registry };
+use rs_basics::threading::Server;
+
+const WAIT: Duration = Duration::from_secs( 5 );
+
+// The whole HTTP response: status line, and the body after the headers
+fn http_get( addr: SocketAddr, request: &str ) -> ( String, String ) {
+	let mut stream = TcpStream::connect( addr ).unwrap();
+	stream.set_read_timeout( Some( WAIT ) ).unwrap();
+	stream.write_all( request.as_bytes() ).unwrap();
+
+	let mut response = String::new();
+	stream.read_to_string( &mut response ).unwrap();
+
+	let ( head, body ) = response.split_once( "\r\n\r\n" ).unwrap();
+
+	return ( head.lines().next().unwrap().to_string(), body.to_string() );
+}
+
+fn scrape( addr: SocketAddr ) -> String {
+	let ( status, body ) = http_get( addr, "GET /metrics HTTP/1.1\r\nHost: localhost\r\n\r\n" );
+	assert_eq! ( status, "HTTP/1.1 200 OK" );
+
+	return body;
+}
+
+// The value of one exact series, e.g. 'server_messages_total{direction="in"}'
+fn value( text: &str, series: &str ) -> Option<f64> {
+	return text.lines().find_map( |l| l.strip_prefix( series )?.strip_prefix( ' ' )?.parse().ok() );
+}
+
+#[test]
+fn renders_the_prometheus_text_format() {
+	let registry = Registry::new();
+
+	let requests = registry.counter_with( "app_requests_total", "Requests handled", &[( "method", "get" )] );
+	requests.add( 2 );
+	registry.counter_with( "app_requests_total", "Requests handled", &[( "method", "get" )] ).inc(); // Same series
+	registry.counter_with( "app_requests_total", "Requests handled", &[( "method", "a \"quoted\"\nvalue\\" )] );
+
+	let queue = registry.gauge( "app_queue_length", "Jobs waiting" );
+	queue.set( 5 );
+	queue.dec();
+
+	let latency = registry.histogram( "app_latency_seconds", "How long it took", &[0.1, 0.5] );
+	latency.observe( 0.05 );
+	latency.observe( 0.3 );
+	latency.observe_duration( Duration::from_secs( 2 ) );
+
+	assert_eq! ( requests.get(), 3 );
+	assert_eq! ( latency.count(), 3 );
+
+	let expected = "\
+# HELP app_latency_seconds How long it took
+# TYPE app_latency_seconds histogram
+app_latency_seconds_bucket{le=\"0.1\"} 1
+app_latency_seconds_bucket{le=\"0.5\"} 2
+app_latency_seconds_bucket{le=\"+Inf\"} 3
+app_latency_seconds_sum 2.35
+app_latency_seconds_count 3
+# HELP app_queue_length Jobs waiting
+# TYPE app_queue_length gauge
+app_queue_length 4
+# HELP app_requests_total Requests handled
+# TYPE app_requests_total counter
+app_requests_total{method=\"a \\\"quoted\\\"\\nvalue\\\\\"} 0
+app_requests_total{method=\"get\"} 3
+";
+
+	assert_eq! ( registry.render(), expected );
+}
+
+#[test]
+#[should_panic( expected = "already registered as a counter" )]
+fn one_name_is_one_kind() {
+	let registry = Registry::new();
+
+	registry.counter( "things", "Things" );
+	registry.gauge( "things", "Things" );
+}
+
+#[test]
+fn counters_and_histograms_lose_nothing_across_threads() {
+	let registry = Registry::new();
+	let hits = registry.counter( "hits_total", "Hits" );
+	let sizes = registry.histogram( "sizes", "Sizes", &metrics::exponential_buckets( 1.0, 2.0, 4 ) );
+
+	let threads: Vec<_> = ( 0..8 ).map( |_| {
+		let ( hits, sizes ) = ( hits.clone(), sizes.clone() );
+
+		thread::spawn( move || {
+			for i in 0..1000 {
+				hits.inc();
+				sizes.observe( ( i % 10 ) as f64 );
+			}
+		})
+	}).collect();
+
+	for t in threads {
+		t.join().unwrap();
+	}
+
+	assert_eq! ( hits.get(), 8000 );
+	assert_eq! ( sizes.count(), 8000 );
+	assert_eq! ( sizes.sum(), 8.0 * 100.0 * 45.0 ); // 0..9 a hundred times per thread
+
+	let text = registry.render();
+	assert_eq! ( value( &text, "sizes_bucket{le=\"8\"}" ), Some( 7200.0 ) ); // 0..=8
+	assert_eq! ( value( &text, "sizes_bucket{le=\"+Inf\"}" ), Some( 8000.0 ) );
+}
+
+#[test]
+fn endpoint_answers_get_metrics_only() {
+	let registry = Registry::new();
+	registry.gauge( "up", "Whether it's up" ).set( 1 );
+
+	let mut endpoint = metrics::serve( "127.0.0.1:0".parse().unwrap(), registry.clone() ).unwrap();
+	let addr = endpoint.local_addr();
+
+	assert_eq! ( value( &scrape( addr ), "up" ), Some( 1.0 ) );
+
+	// Later changes show up in the next scrape
+	registry.counter( "late_total", "Registered after the endpoint started" ).inc();
+	assert_eq! ( value( &scrape( addr ), "late_total" ), Some( 1.0 ) );
+
+	assert_eq! ( http_get( addr, "GET / HTTP/1.1\r\n\r\n" ).0, "HTTP/1.1 404 Not Found" );
+	assert_eq! ( http_get( addr, "POST /metrics HTTP/1.1\r\n\r\n" ).0, "HTTP/1.1 405 Method Not Allowed" );
+
+	endpoint.stop();
+	assert! ( TcpStream::connect_timeout( &addr, Duration::from_secs( 1 ) ).is_err() );
+}
+
+#[test]
+fn server_counts_connections_messages_and_lock_waits() {
+	let mut server = Server::new( "127.0.0.1:0".parse().unwrap() ).with_metrics_endpoint( "127.0.0.1:0".parse().unwrap() );
+	let addr = server.start().unwrap();
+	let metrics_addr = server.metrics_addr().unwrap();
+
+	let mut stream = TcpStream::connect( addr ).unwrap();
+	stream.set_read_timeout( Some( WAIT ) ).unwrap();
+	let mut reader = BufReader::new( stream.try_clone().unwrap() );
+
+	for line in ["one\n", "two\n", "three\n"] {
+		stream.write_all( line.as_bytes() ).unwrap();
+		reader.read_line( &mut String::new() ).unwrap();
+	}
+
+	let text = scrape( metrics_addr );
+
+	assert_eq! ( value( &text, "server_connections" ), Some( 1.0 ) );
+	assert_eq! ( value( &text, "server_connections_total" ), Some( 1.0 ) );
+	assert_eq! ( value( &text, "server_messages_total{direction=\"in\"}" ), Some( 3.0 ) );
+	assert_eq! ( value( &text, "server_messages_total{direction=\"out\"}" ), Some( 3.0 ) );
+	assert_eq! ( value( &text, "server_request_duration_seconds_count" ), Some( 3.0 ) );
+	assert! ( value( &text, "server_lock_wait_seconds_count{lock=\"clients\"}" ).unwrap() > 0.0 );
+	assert_eq! ( value( &text, "server_lock_wait_seconds_count{lock=\"writer\"}" ), Some( 3.0 ) );
+
+	// Leaving is counted by reason
+	drop( ( stream, reader ) );
+
+	let start = std::time::Instant::now();
+	let mut text = server.metrics().render();
+
+	while value( &text, "server_disconnections_total{reason=\"closed\"}" ).is_none() && start.elapsed() < WAIT {
+		thread::sleep( Duration::from_millis( 5 ) );
+		text = server.metrics().render();
+	}
+
+	assert_eq! ( value( &text, "server_connections" ), Some( 0.0 ) );
+	assert_eq! ( value( &text, "server_disconnections_total{reason=\"closed\"}" ), Some( 1.0 ) );
+
+	// The endpoint goes away with the server
+	server.stop();
+	assert! ( server.metrics_addr().is_none() );
+	assert! ( TcpStream::connect_timeout( &metrics_addr, Duration::from_secs( 1 ) ).is_err() );
+}
@@ -0,0 +1,66 @@
+// Integration tests for the naming_lint binary and the text::is_case check behind it
+
+use std::path::PathBuf;
+use std::process::Command;
+
+use rs_basics::text::{ self, Case };
+
+// Lints one source file, returning the reported lines (without the summary)
+fn lint( name: &str, source: &str ) -> Vec<String> {
+	let dir: PathBuf = std::env::temp_dir().join( format! ( "naming_lint_{}_{name}", std::process::id() ) );
+	std::fs::create_dir_all( &dir ).unwrap();
+
+	let file = dir.join( "sample.rs" );
+	std::fs::write( &file, source ).unwrap();
+
+	let output = Command::new( env! ( "CARGO_BIN_EXE_naming_lint" ) ).arg( &file ).output().unwrap();
+	let _ = std::fs::remove_dir_all( &dir );
+
+	return String::from_utf8( output.stdout ).unwrap().lines().filter( |l| !l.contains( "naming issue(s)" ) ).map( |l| l.to_string() ).collect();
+}
+
+#[test]
+fn underscore_bindings_are_not_reported() {
+	let found = lint( "underscores", "
+		fn main() {
+			let _ = compute();
+			let _unused = 1;
+			let __ = 2;
+			const _: () = ();
+		}
+	" );
+
+	assert! ( found.is_empty(), "{found:?}" );
+}
+
+#[test]
+fn real_violations_still_are() {
+	let found = lint( "violations", "
+		struct my_struct { Name: String }
+		fn DoThing() { let camelCase = 1; }
+		const lower: u8 = 1;
+	" );
+
+	assert_eq! ( found.len(), 5, "{found:?}" );
+	assert! ( found.iter().any( |l| l.contains( "variable `camelCase` should be snake_case, e.g. `camel_case`" ) ) );
+	assert! ( found.iter().any( |l| l.contains( "constant `lower` should be SCREAMING_SNAKE_CASE, e.g. `LOWER`" ) ) );
+}
+
+#[test]
+fn only_underscores_fit_the_underscore_cases() {
+	for case in [Case::Snake, Case::ScreamingSnake, Case::Camel, Case::Pascal] {
+		assert! ( text::is_case( "_", case ) && text::is_case( "__", case ), "{case:?}" );
+	}
+
+	assert! ( !text::is_case( "", Case::Snake ) );
+	assert! ( !text::is_case( "_", Case::Kebab ) );
+	assert! ( text::is_case( "_name", Case::Snake ) && !text::is_case( "_Name", Case::Snake ) );
+}
+
+#[test]
+fn single_capitals_are_suggested_as_their_own_word() {
+	let found = lint( "single_capital", "fn DoB() {}" );
+
+	assert_eq! ( found.len(), 1, "{found:?}" );
+	assert! ( found[0].contains( "`DoB` should be snake_case, e.g. `do_b`" ), "{found:?}" );
+}
@@ -0,0 +1,97 @@
+// Integration tests for pipeline: the closure combinators and middleware chains
+
+use std::sync::{ Arc, Mutex };
+use std::time::Duration;
+
+use rs_basics::pipeline::{ self, Chain, Next };
+
+type Log = Arc<Mutex<Vec<String>>>;
+
+// A middleware writing "name>" on the way in and "<name" on the way out
+fn layer( log: &Log, name: &'static str ) -> impl Fn( u32, Next<'_, u32, String> ) -> String + Send + Sync + 'static {
+	let log = Arc::clone( log );
+
+	return move |req, next| {
+		log.lock().unwrap().push( format! ( "{name}>" ) );
+		let resp = next.run( req + 1 );
+		log.lock().unwrap().push( format! ( "<{name}" ) );
+
+		return resp;
+	};
+}
+
+fn handler( req: u32 ) -> String {
+	return format! ( "handled {req}" );
+}
+
+#[test]
+fn combinators_glue_functions_together() {
+	let double = |x: i32| x * 2;
+	let add_one = |x: i32| x + 1;
+
+	assert_eq! ( pipeline::pipe( double, add_one )( 5 ), 11 ); // add_one( double( 5 ) )
+	assert_eq! ( pipeline::compose( double, add_one )( 5 ), 12 ); // double( add_one( 5 ) )
+
+	let seen = Mutex::new( Vec::new() );
+	let tapped = pipeline::pipe( pipeline::tap( |x: &i32| seen.lock().unwrap().push( *x ) ), double );
+	assert_eq! ( tapped( 4 ), 8 );
+	assert_eq! ( *seen.lock().unwrap(), [4] );
+}
+
+#[test]
+fn result_combinators_stop_at_the_first_error() {
+	let parse = |s: &str| s.trim().parse::<i32>();
+	let positive = |n: i32| if n > 0 { Ok( n ) } else { Err( format! ( "{n} is not positive" ) ) };
+
+	let parse_message = pipeline::map_err( parse, |e| e.to_string() );
+	let checked = pipeline::and_then( parse_message, positive );
+
+	assert_eq! ( checked( " 42 " ), Ok( 42 ) );
+	assert_eq! ( checked( "-3" ), Err( "-3 is not positive".to_string() ) );
+	assert_eq! ( checked( "abc" ), Err( "invalid digit found in string".to_string() ) ); // 'positive' never ran
+}
+
+#[test]
+fn chains_run_their_middleware_first_to_last() {
+	let log: Log = Arc::default();
+	let chain = Chain::new().with( layer( &log, "a" ) ).with( layer( &log, "b" ) ).then( Chain::new().with( layer( &log, "c" ) ) );
+
+	assert_eq! ( chain.len(), 3 );
+	assert_eq! ( chain.run( 0, &handler ), "handled 3" );
+	assert_eq! ( *log.lock().unwrap(), ["a>", "b>", "c>", "<c", "<b", "<a"] );
+
+	// An empty chain goes straight to the handler, and a baked handler is the chain plus the handler
+	assert! ( Chain::<u32, String>::new().is_empty() );
+	assert_eq! ( Chain::new().run( 7, &handler ), "handled 7" );
+	assert_eq! ( chain.handler( handler )( 10 ), "handled 13" );
+}
+
+#[test]
+fn auth_short_circuits_denied_requests() {
+	let log: Log = Arc::default();
+	let chain = Chain::new()
+		.with( pipeline::auth( |req: &u32| req.is_multiple_of( 2 ), |req: &u32| format! ( "{req} denied" ) ) )
+		.with( layer( &log, "inner" ) );
+
+	assert_eq! ( chain.run( 4, &handler ), "handled 5" );
+	assert_eq! ( chain.run( 3, &handler ), "3 denied" );
+	assert_eq! ( *log.lock().unwrap(), ["inner>", "<inner"] ); // Only the allowed request got past auth
+}
+
+#[test]
+fn logging_and_timing_report_to_their_sinks() {
+	let lines: Log = Arc::default();
+	let took: Arc<Mutex<Vec<Duration>>> = Arc::default();
+	let ( sink, durations ) = ( Arc::clone( &lines ), Arc::clone( &took ) );
+
+	let chain = Chain::new()
+		.with( pipeline::logging( move |line| sink.lock().unwrap().push( line ) ) )
+		.with( pipeline::timing( move |d| durations.lock().unwrap().push( d ) ) );
+
+	let slow = |req: u32| { std::thread::sleep( Duration::from_millis( 10 ) ); return handler( req ); };
+	assert_eq! ( chain.run( 1, &slow ), "handled 1" );
+
+	assert_eq! ( *lines.lock().unwrap(), ["--> 1", "<-- \"handled 1\""] );
+	assert_eq! ( took.lock().unwrap().len(), 1 );
+	assert! ( took.lock().unwrap()[0] >= Duration::from_millis( 10 ) );
+}
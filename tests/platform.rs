@@ -0,0 +1,89 @@
+// Integration tests for platform: the /proc and /etc parsers, fed with fixture strings
+
+use std::time::Duration;
+
+use rs_basics::platform::{ self, LoadAverage };
+
+#[test]
+fn os_release_values_may_be_quoted_or_not() {
+	let release = platform::parse_os_release( "
+NAME=\"Debian GNU/Linux\"
+VERSION_ID='12'
+ID=debian
+PRETTY_NAME=\"Debian \\\"bookworm\\\"\"
+# VERSION=ignored
+not a key value line
+" );
+
+	assert_eq! ( release.name, "Debian GNU/Linux" );
+	assert_eq! ( release.version_id, "12" );
+	assert_eq! ( release.id, "debian" );
+	assert_eq! ( release.pretty_name, "Debian \"bookworm\"" );
+	assert_eq! ( release.version, "" );
+}
+
+#[test]
+fn cpu_lists_count_ranges_and_single_cpus() {
+	assert_eq! ( platform::parse_cpu_list( "0-3,5\n" ), Ok( 5 ) );
+	assert_eq! ( platform::parse_cpu_list( "0" ), Ok( 1 ) );
+	assert_eq! ( platform::parse_cpu_list( "0-1,4-7,9" ), Ok( 7 ) );
+
+	for bad in ["0-x", "3-1", "a,b", "1-"] {
+		assert! ( platform::parse_cpu_list( bad ).unwrap_err().contains( "Invalid CPU list" ), "{bad:?}" );
+	}
+}
+
+#[test]
+fn meminfo_is_converted_to_bytes() {
+	let info = platform::parse_meminfo( "\
+MemTotal:       16000000 kB
+MemFree:         2000000 kB
+MemAvailable:    8000000 kB
+Buffers:          300000 kB
+SwapTotal:       1000000 kB
+SwapFree:         999000 kB
+HugePages_Total:       0
+" ).unwrap();
+
+	assert_eq! ( info.total, 16_000_000 * 1024 );
+	assert_eq! ( info.available, 8_000_000 * 1024 );
+	assert_eq! ( info.swap_free, 999_000 * 1024 );
+	assert_eq! ( info.used(), 8_000_000 * 1024 );
+
+	// Old kernels have no MemAvailable: MemFree stands in
+	let old = platform::parse_meminfo( "MemTotal: 100 kB\nMemFree: 40 kB\n" ).unwrap();
+	assert_eq! ( old.available, 40 * 1024 );
+
+	assert! ( platform::parse_meminfo( "MemFree: 40 kB\n" ).is_err() );
+}
+
+#[test]
+fn uptime_and_loadavg_parse_their_single_line() {
+	assert_eq! ( platform::parse_uptime( "12345.67 54321.00\n" ), Ok( Duration::from_secs_f64( 12345.67 ) ) );
+	assert! ( platform::parse_uptime( "" ).is_err() );
+
+	assert_eq! ( platform::parse_loadavg( "0.52 0.58 0.59 2/1043 123456\n" ), Ok( LoadAverage { one: 0.52, five: 0.58, fifteen: 0.59, running: 2, total: 1043 } ) );
+	assert! ( platform::parse_loadavg( "0.52 0.58 0.59" ).is_err() );
+	assert! ( platform::parse_loadavg( "0.52 0.58 0.59 2-1043" ).is_err() );
+}
+
+#[test]
+fn process_usage_splits_stat_after_the_last_parenthesis() {
+	// The command name "my ) (proc" would throw off a split on the first ')'
+	let stat = "4242 (my ) (proc) S 1 4242 4242 0 -1 4194304 100 0 0 0 250 50 0 0 20 0 4 0 12345 1048576 300 18446744073709551615";
+	let status = "Name:\tmy ) (proc\nVmHWM:\t    2048 kB\nVmRSS:\t    1200 kB\nvoluntary_ctxt_switches:\t17\nnonvoluntary_ctxt_switches:\t3\n";
+
+	let usage = platform::parse_process_usage( stat, status ).unwrap();
+
+	assert_eq! ( usage.pid, 4242 );
+	assert_eq! ( usage.user_time, Duration::from_millis( 2500 ) ); // 250 ticks at 100 Hz
+	assert_eq! ( usage.system_time, Duration::from_millis( 500 ) );
+	assert_eq! ( usage.threads, 4 );
+	assert_eq! ( usage.virtual_memory, 1_048_576 );
+	assert_eq! ( usage.resident_memory, 1200 * 1024 ); // From VmRSS, not stat's page count
+	assert_eq! ( usage.peak_resident_memory, 2048 * 1024 );
+	assert_eq! ( ( usage.voluntary_switches, usage.involuntary_switches ), ( 17, 3 ) );
+
+	assert! ( platform::parse_process_usage( "4242 (truncated) S 1 2", "" ).is_err() );
+	assert! ( platform::parse_process_usage( "no parenthesis at all", "" ).is_err() );
+}
@@ -0,0 +1,75 @@
+// Integration tests for pool::ThreadPool
+
+use std::sync::atomic::{ AtomicUsize, Ordering };
+use std::sync::{ Arc, Barrier };
+
+use rs_basics::pool::ThreadPool;
+
+#[test]
+fn spawn_returns_each_jobs_result() {
+	let pool = ThreadPool::new( 4 );
+	let handles: Vec<_> = ( 0..20u64 ).map( |n| pool.spawn( move || n * n ) ).collect();
+	let squares: Vec<u64> = handles.into_iter().map( |h| h.join().unwrap() ).collect();
+
+	assert_eq! ( squares, ( 0..20 ).map( |n| n * n ).collect::<Vec<_>>() );
+}
+
+#[test]
+fn a_panicking_job_does_not_take_down_its_worker() {
+	let pool = ThreadPool::new( 1 );
+
+	let bad = pool.spawn( || -> u32 { panic! ( "bad job" ) } );
+	let good = pool.spawn( || 7 );
+
+	assert_eq! ( bad.join(), Err( "bad job".to_string() ) );
+	assert_eq! ( good.join(), Ok( 7 ) );
+
+	pool.join();
+	let metrics = pool.metrics();
+	assert_eq! ( ( metrics.completed, metrics.panicked ), ( 2, 1 ) );
+}
+
+#[test]
+fn metrics_show_queued_and_active_jobs() {
+	let pool = ThreadPool::new( 2 );
+	let gate = Arc::new( Barrier::new( 3 ) ); // Both workers and this thread
+
+	for _ in 0..2 {
+		let gate = Arc::clone( &gate );
+		pool.execute( move || { gate.wait(); } );
+	}
+
+	for _ in 0..3 {
+		pool.execute( || () );
+	}
+
+	// Spin until both workers picked up a blocking job (the queue is then exactly the other three)
+	while pool.metrics().active < 2 {
+		std::thread::yield_now();
+	}
+
+	let metrics = pool.metrics();
+	assert_eq! ( ( metrics.workers, metrics.active, metrics.queued, metrics.completed ), ( 2, 2, 3, 0 ) );
+
+	gate.wait();
+	pool.join();
+
+	let metrics = pool.metrics();
+	assert_eq! ( ( metrics.active, metrics.queued, metrics.completed ), ( 0, 0, 5 ) );
+}
+
+#[test]
+fn shutdown_runs_everything_already_queued() {
+	let pool = ThreadPool::new( 3 );
+	let counter = Arc::new( AtomicUsize::new( 0 ) );
+
+	for _ in 0..100 {
+		let counter = Arc::clone( &counter );
+		pool.execute( move || { counter.fetch_add( 1, Ordering::SeqCst ); } );
+	}
+
+	let metrics = pool.shutdown();
+
+	assert_eq! ( counter.load( Ordering::SeqCst ), 100 );
+	assert_eq! ( metrics.completed, 100 );
+}
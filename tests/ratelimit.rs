@@ -0,0 +1,192 @@
+// Integration tests for ratelimit::RateLimiter, and rate limiting on threading::Server
+
+use std::io::{ BufRead, BufReader, Write };
+use std::net::TcpStream;
+use std::sync::atomic::{ AtomicUsize, Ordering };
+use std::sync::{ mpsc, Arc };
+use std::thread;
+use std::time::{ Duration, Instant };
+
+use rs_basics::ratelimit::{ Algorithm, Decision, RateLimiter };
+use rs_basics::threading::{ DisconnectReason, Server, ServerEvent };
+
+const WAIT: Duration = Duration::from_secs( 5 );
+
+fn ms( n: u64 ) -> Duration {
+	return Duration::from_millis( n );
+}
+
+#[test]
+fn token_bucket_allows_a_burst_then_refills() {
+	let limiter = RateLimiter::new( Algorithm::TokenBucket { capacity: 3, refill_every: ms( 100 ) } );
+	let start = Instant::now();
+
+	for _ in 0..3 {
+		assert! ( limiter.check_at( &"a", start ).is_allowed() );
+	}
+
+	assert_eq! ( limiter.check_at( &"a", start ), Decision::Limited { retry_after: ms( 100 ) } );
+	assert! ( limiter.check_at( &"b", start ).is_allowed() ); // Every key has its own bucket
+
+	// Half a token later: half the wait
+	assert_eq! ( limiter.check_at( &"a", start + ms( 50 ) ), Decision::Limited { retry_after: ms( 50 ) } );
+	assert! ( limiter.check_at( &"a", start + ms( 100 ) ).is_allowed() );
+
+	// Never more than the capacity, however long it was quiet
+	let later = start + Duration::from_secs( 60 );
+	assert_eq! ( ( 0..5 ).filter( |_| limiter.check_at( &"a", later ).is_allowed() ).count(), 3 );
+}
+
+#[test]
+fn leaky_bucket_spaces_requests_out_after_the_burst() {
+	let limiter = RateLimiter::new( Algorithm::LeakyBucket { capacity: 2, leak_every: ms( 100 ) } );
+	let start = Instant::now() + ms( 10 );
+
+	assert! ( limiter.check_at( &1, start ).is_allowed() );
+	assert! ( limiter.check_at( &1, start ).is_allowed() );
+	assert_eq! ( limiter.check_at( &1, start ), Decision::Limited { retry_after: ms( 100 ) } );
+	assert_eq! ( limiter.check_at( &1, start + ms( 30 ) ), Decision::Limited { retry_after: ms( 70 ) } );
+
+	// One drains every 100ms, so one more fits each time
+	assert! ( limiter.check_at( &1, start + ms( 100 ) ).is_allowed() );
+	assert! ( !limiter.check_at( &1, start + ms( 100 ) ).is_allowed() );
+	assert! ( limiter.check_at( &1, start + ms( 200 ) ).is_allowed() );
+}
+
+#[test]
+fn sliding_window_counts_part_of_the_previous_window() {
+	let limiter = RateLimiter::new( Algorithm::SlidingWindow { limit: 4, window: ms( 100 ) } );
+	let start = Instant::now() + ms( 10 );
+
+	for _ in 0..4 {
+		assert! ( limiter.check_at( &"k", start ).is_allowed() );
+	}
+
+	assert! ( !limiter.check_at( &"k", start + ms( 50 ) ).is_allowed() );
+
+	// A quarter into the next window, 3 of the previous 4 still count, so only 1 more fits
+	let next = start + ms( 125 );
+	assert! ( limiter.check_at( &"k", next ).is_allowed() );
+	assert! ( !limiter.check_at( &"k", next ).is_allowed() );
+
+	// Two windows on, it's all forgotten
+	let later = start + ms( 300 );
+	assert_eq! ( ( 0..6 ).filter( |_| limiter.check_at( &"k", later ).is_allowed() ).count(), 4 );
+}
+
+#[test]
+fn limits_hold_across_threads() {
+	for algorithm in [
+		Algorithm::TokenBucket { capacity: 100, refill_every: Duration::from_secs( 3600 ) },
+		Algorithm::LeakyBucket { capacity: 100, leak_every: Duration::from_secs( 3600 ) },
+		Algorithm::SlidingWindow { limit: 100, window: Duration::from_secs( 3600 ) },
+	] {
+		let limiter = Arc::new( RateLimiter::new( algorithm ) );
+		let allowed = Arc::new( AtomicUsize::new( 0 ) );
+
+		let threads: Vec<_> = ( 0..8 ).map( |_| {
+			let ( limiter, allowed ) = ( Arc::clone( &limiter ), Arc::clone( &allowed ) );
+
+			thread::spawn( move || {
+				for key in 0..4 {
+					for _ in 0..50 {
+						if limiter.check( &key ).is_allowed() {
+							allowed.fetch_add( 1, Ordering::SeqCst );
+						}
+					}
+				}
+			})
+		}).collect();
+
+		for t in threads {
+			t.join().unwrap();
+		}
+
+		// 400 tries per key, exactly 100 get through for each of the 4
+		assert_eq! ( allowed.load( Ordering::SeqCst ), 400, "{algorithm:?}" );
+		assert_eq! ( limiter.len(), 4 );
+
+		limiter.remove( &0 );
+		assert_eq! ( limiter.len(), 3 );
+	}
+}
+
+#[test]
+fn server_throttles_then_disconnects_abusive_clients() {
+	let mut server = Server::new( "127.0.0.1:0".parse().unwrap() )
+		.with_rate_limit( Algorithm::TokenBucket { capacity: 2, refill_every: Duration::from_secs( 60 ) }, 3 );
+
+	let ( tx, events ) = mpsc::channel();
+	let tx = std::sync::Mutex::new( tx );
+	server.events().on( "disconnect", move |e: &ServerEvent| { let _ = tx.lock().unwrap().send( e.clone() ); } );
+
+	let addr = server.start().unwrap();
+	let mut stream = TcpStream::connect( addr ).unwrap();
+	stream.set_read_timeout( Some( WAIT ) ).unwrap();
+	let mut reader = BufReader::new( stream.try_clone().unwrap() );
+
+	let mut answers = Vec::new();
+
+	for i in 0..5 {
+		stream.write_all( format! ( "request {i}\n" ).as_bytes() ).unwrap();
+
+		let mut line = String::new();
+		reader.read_line( &mut line ).unwrap();
+		answers.push( line.trim_end().to_string() );
+	}
+
+	assert_eq! ( &answers[..2], ["request 0", "request 1"] );
+	assert! ( answers[2].starts_with( "ERR Rate limited, retry in " ), "Got {:?}", answers[2] );
+	assert! ( answers[3].starts_with( "ERR Rate limited, retry in " ) );
+	assert_eq! ( answers[4], "ERR Too many requests" );
+
+	let ServerEvent::Disconnected( _, reason ) = events.recv_timeout( WAIT ).unwrap() else { panic! ( "Expected a disconnect event" ) };
+	assert_eq! ( reason, DisconnectReason::RateLimited );
+
+	let mut line = String::new();
+	assert_eq! ( reader.read_line( &mut line ).unwrap(), 0 );
+	assert! ( server.clients().is_empty() );
+
+	// Other clients have their own allowance
+	let mut other = TcpStream::connect( addr ).unwrap();
+	other.set_read_timeout( Some( WAIT ) ).unwrap();
+	other.write_all( b"hi\n" ).unwrap();
+
+	let mut line = String::new();
+	BufReader::new( other ).read_line( &mut line ).unwrap();
+	assert_eq! ( line, "hi\n" );
+}
+
+#[test]
+fn clients_that_back_off_are_forgiven() {
+	let mut server = Server::new( "127.0.0.1:0".parse().unwrap() )
+		.with_rate_limit( Algorithm::TokenBucket { capacity: 1, refill_every: ms( 50 ) }, 2 );
+
+	let addr = server.start().unwrap();
+	let mut stream = TcpStream::connect( addr ).unwrap();
+	stream.set_read_timeout( Some( WAIT ) ).unwrap();
+	let mut reader = BufReader::new( stream.try_clone().unwrap() );
+
+	let mut request = |body: &str| {
+		stream.write_all( format! ( "{body}\n" ).as_bytes() ).unwrap();
+
+		let mut line = String::new();
+		reader.read_line( &mut line ).unwrap();
+		line.trim_end().to_string()
+	};
+
+	// One refusal at a time, each followed by an allowed request: never 2 in a row
+	for i in 0..3 {
+		assert_eq! ( request( &format! ( "request {i}" ) ), format! ( "request {i}" ) );
+		assert! ( request( "too soon" ).starts_with( "ERR Rate limited" ) );
+		thread::sleep( ms( 60 ) );
+	}
+
+	let sessions = server.clients().sessions();
+	assert_eq! ( sessions.len(), 1 );
+	assert_eq! ( sessions[0].1.throttled, 3 ); // Still counted in total
+
+	assert_eq! ( request( "last" ), "last" );
+	assert! ( request( "too soon" ).starts_with( "ERR Rate limited" ) );
+	assert_eq! ( request( "too soon" ), "ERR Too many requests" );
+}
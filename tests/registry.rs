@@ -0,0 +1,67 @@
+// Integration tests for threading::ClientRegistry
+
+use std::sync::Arc;
+use std::thread;
+
+use rs_basics::threading::{ Client, ClientRegistry };
+
+#[test]
+fn ids_are_returned_in_call_order_and_never_reused() {
+	let registry = ClientRegistry::new();
+
+	let alice = registry.add( "alice" );
+	let bob = registry.add( "bob" );
+	assert_eq! ( ( alice, bob ), ( 1, 2 ) );
+
+	assert_eq! ( registry.remove( alice ), Some( Client { id: 1, name: "alice".to_string() } ) );
+	assert_eq! ( registry.add( "alice" ), 3 );
+}
+
+#[test]
+fn removes_by_id_not_position() {
+	let registry = ClientRegistry::new();
+	let ids: Vec<u64> = [ "a", "b", "c" ].iter().map( |n| registry.add( n ) ).collect();
+
+	registry.remove( ids[1] );
+
+	let names: Vec<String> = registry.snapshot().into_iter().map( |c| c.name ).collect();
+	assert_eq! ( names, vec![ "a", "c" ] );
+	assert_eq! ( registry.remove( ids[1] ), None );
+	assert! ( registry.get( ids[1] ).is_none() );
+	assert_eq! ( registry.find( "c" ).map( |c| c.id ), Some( ids[2] ) );
+}
+
+#[test]
+fn added_clients_are_visible_as_soon_as_add_returns() {
+	let registry = Arc::new( ClientRegistry::new() );
+
+	let handles: Vec<_> = ( 0..8 )
+		.map( |t| {
+			let registry = Arc::clone( &registry );
+			thread::spawn( move || ( 0..100 ).map( |i| registry.add( &format! ( "{t}-{i}" ) ) ).collect::<Vec<_>>() )
+		})
+		.collect();
+
+	let mut ids: Vec<u64> = handles.into_iter().flat_map( |h| h.join().unwrap() ).collect();
+	ids.sort();
+	ids.dedup();
+
+	assert_eq! ( ids.len(), 800 );
+	assert_eq! ( registry.len(), 800 );
+
+	// Snapshots come back in id order
+	let snapshot: Vec<u64> = registry.snapshot().iter().map( |c| c.id ).collect();
+	assert_eq! ( snapshot, ids );
+}
+
+#[test]
+fn snapshots_are_unaffected_by_later_changes() {
+	let registry = ClientRegistry::new();
+	let id = registry.add( "first" );
+
+	let snapshot = registry.snapshot();
+	registry.remove( id );
+	registry.add( "second" );
+
+	assert_eq! ( snapshot, vec![ Client { id, name: "first".to_string() } ] );
+}
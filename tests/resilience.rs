@@ -0,0 +1,126 @@
+// Integration tests for resilience: backoff delays, retries, timeouts and the circuit breaker's state machine
+
+use std::panic::{ self, AssertUnwindSafe };
+use std::sync::Arc;
+use std::sync::atomic::{ AtomicU32, Ordering };
+use std::thread;
+use std::time::Duration;
+
+use rs_basics::resilience::{ self, CallError, CircuitBreaker, RetryPolicy, State };
+
+const RESET: Duration = Duration::from_millis( 30 );
+
+fn failing() -> Result<(), &'static str> {
+	return Err( "down" );
+}
+
+// Opens the breaker, then waits until it lets a probe through
+fn half_open() -> CircuitBreaker {
+	let breaker = CircuitBreaker::new( 2, RESET );
+	let _ = breaker.call( failing );
+	let _ = breaker.call( failing );
+	assert_eq! ( breaker.state(), State::Open );
+
+	thread::sleep( RESET + Duration::from_millis( 10 ) );
+	assert_eq! ( breaker.state(), State::HalfOpen );
+
+	return breaker;
+}
+
+#[test]
+fn retries_until_success_or_out_of_attempts() {
+	let policy = RetryPolicy::fixed( 3, Duration::ZERO );
+	let mut attempts = 0;
+
+	assert_eq! ( resilience::retry( &policy, || { attempts += 1; if attempts < 3 { Err( attempts ) } else { Ok( attempts ) } } ), Ok( 3 ) );
+
+	attempts = 0;
+	assert_eq! ( resilience::retry( &policy, || { attempts += 1; Err::<(), _>( attempts ) } ), Err( 3 ) );
+}
+
+#[test]
+fn exponential_delays_grow_until_the_cap() {
+	let ms = Duration::from_millis;
+
+	assert_eq! ( RetryPolicy::exponential( 5, ms( 10 ), ms( 50 ) ).delays(), [ms( 10 ), ms( 20 ), ms( 40 ), ms( 50 )] );
+	assert_eq! ( RetryPolicy::exponential( 4, ms( 10 ), ms( 1000 ) ).factor( 3.0 ).delays(), [ms( 10 ), ms( 30 ), ms( 90 )] );
+	assert_eq! ( RetryPolicy::fixed( 3, ms( 7 ) ).delays(), [ms( 7 ), ms( 7 )] );
+	assert! ( RetryPolicy::fixed( 0, ms( 7 ) ).delays().is_empty() ); // At least one attempt, and no waiting
+}
+
+#[test]
+fn jitter_is_deterministic_for_a_seed() {
+	let policy = RetryPolicy::exponential( 8, Duration::from_millis( 100 ), Duration::from_secs( 10 ) ).jitter( 0.5 );
+	let plain = RetryPolicy::exponential( 8, Duration::from_millis( 100 ), Duration::from_secs( 10 ) ).delays();
+
+	assert_eq! ( policy.seed( 42 ).delays(), policy.seed( 42 ).delays() );
+	assert_ne! ( policy.seed( 42 ).delays(), policy.seed( 43 ).delays() );
+
+	// Jitter only ever shortens a delay, by up to half here
+	for ( jittered, base ) in policy.seed( 42 ).delays().into_iter().zip( plain ) {
+		assert! ( jittered <= base && jittered >= base / 2, "{jittered:?} for {base:?}" );
+	}
+}
+
+#[test]
+fn timeouts_give_up_waiting_and_pass_panics_on() {
+	let limit = Duration::from_millis( 20 );
+
+	assert_eq! ( resilience::timeout( limit, || { thread::sleep( Duration::from_millis( 300 ) ); Ok::<_, ()>( 1 ) } ), Err( CallError::TimedOut( limit ) ) );
+	assert_eq! ( resilience::timeout( Duration::from_secs( 5 ), || Err::<(), _>( "refused" ) ), Err( CallError::Inner( "refused" ) ) );
+
+	let worker = String::from( "worker 7" );
+	let panicked = panic::catch_unwind( || resilience::timeout( Duration::from_secs( 5 ), move || -> Result<(), ()> { panic! ( "{worker} failed" ) } ) );
+	let payload = panicked.expect_err( "Expected the worker's panic" );
+	assert_eq! ( payload.downcast_ref::<String>().map( |s| s.as_str() ), Some( "worker 7 failed" ) ); // The original payload, not a generic message
+}
+
+#[test]
+fn retry_with_timeout_gives_every_attempt_its_own_limit() {
+	let policy = RetryPolicy::fixed( 3, Duration::ZERO );
+	let limit = Duration::from_millis( 50 );
+	let attempts = Arc::new( AtomicU32::new( 0 ) );
+	let counter = Arc::clone( &attempts );
+
+	// The first attempt hangs, the second one answers
+	let result = resilience::retry_with_timeout( &policy, limit, move || {
+		if counter.fetch_add( 1, Ordering::SeqCst ) == 0 {
+			thread::sleep( Duration::from_millis( 500 ) );
+		}
+
+		return Ok::<_, ()>( "answer" );
+	});
+
+	assert_eq! ( result, Ok( "answer" ) );
+	assert_eq! ( attempts.load( Ordering::SeqCst ), 2 );
+
+	assert_eq! ( resilience::retry_with_timeout( &policy, limit, || { thread::sleep( Duration::from_millis( 300 ) ); Ok::<_, ()>( () ) } ), Err( CallError::TimedOut( limit ) ) );
+	assert_eq! ( resilience::retry_with_timeout( &policy, limit, failing ), Err( CallError::Inner( "down" ) ) );
+}
+
+#[test]
+fn breaker_opens_and_recovers_through_a_probe() {
+	let breaker = half_open();
+
+	assert_eq! ( breaker.call( || Ok::<_, ()>( 1 ) ), Ok( 1 ) );
+	assert_eq! ( breaker.state(), State::Closed );
+
+	// A failed probe opens it again
+	let breaker = half_open();
+	assert_eq! ( breaker.call( failing ), Err( CallError::Inner( "down" ) ) );
+	assert_eq! ( breaker.state(), State::Open );
+	assert_eq! ( breaker.call( || Ok::<_, ()>( () ) ), Err( CallError::Open ) );
+}
+
+#[test]
+fn a_panicking_probe_trips_the_breaker() {
+	let breaker = half_open();
+
+	let probe = panic::catch_unwind( AssertUnwindSafe( || breaker.call( || -> Result<(), ()> { panic! ( "probe blew up" ) } ) ) );
+	assert! ( probe.is_err() );
+	assert_eq! ( breaker.state(), State::Open ); // Not stuck in HalfOpen with a probe that will never finish
+
+	thread::sleep( RESET + Duration::from_millis( 10 ) );
+	assert_eq! ( breaker.call( || Ok::<_, ()>( 7 ) ), Ok( 7 ) ); // The next probe gets through
+	assert_eq! ( breaker.state(), State::Closed );
+}
@@ -0,0 +1,228 @@
+// Integration tests for ring::spsc and ring::mpsc, including a stress harness for their memory orderings
+
+use std::sync::Arc;
+use std::sync::atomic::{ AtomicUsize, Ordering };
+use std::thread;
+
+use rs_basics::ring::{ self, Consumer, MpscConsumer, MpscProducer, Producer };
+
+/*
+The stress harness.
+
+- Producers push numbered messages as fast as they can into a tiny buffer (so it wraps around and fills up constantly), while one consumer pops and checks each one.
+- A message carries its numbers in a Box, written just before the push. If an ordering were too weak, the consumer could pop a slot before the producer's writes to it (or to the Box) are visible, and see garbage or a stale message: the 'check' words must all match 'seq', and every producer's messages must arrive in order, each exactly once.
+- Every message counts its own drop, so values lost or dropped twice (e.g. a slot read twice) show up too.
+- Weak orderings mostly break on ARM; on x86 this still catches indices that are wrong, and the compiler reordering what it's allowed to.
+*/
+
+const ROUNDS: usize = 10;
+const PER_PRODUCER: usize = 20_000;
+
+struct Message {
+	producer: usize,
+	seq: usize,
+	check: Box<[usize; 8]>,
+	drops: Arc<AtomicUsize>,
+}
+
+impl Drop for Message {
+	fn drop( &mut self ) {
+		self.drops.fetch_add( 1, Ordering::Relaxed );
+	}
+}
+
+trait Push: Send + 'static {
+	fn try_push( &mut self, message: Message ) -> Result<(), Message>;
+	fn disconnected( &self ) -> bool;
+}
+
+trait Pop: Send + 'static {
+	fn try_pop( &mut self ) -> Option<Message>;
+	fn disconnected( &self ) -> bool;
+}
+
+impl Push for Producer<Message> {
+	fn try_push( &mut self, message: Message ) -> Result<(), Message> {
+		return self.push( message );
+	}
+
+	fn disconnected( &self ) -> bool {
+		return self.is_disconnected();
+	}
+}
+
+impl Push for MpscProducer<Message> {
+	fn try_push( &mut self, message: Message ) -> Result<(), Message> {
+		return self.push( message );
+	}
+
+	fn disconnected( &self ) -> bool {
+		return self.is_disconnected();
+	}
+}
+
+impl Pop for Consumer<Message> {
+	fn try_pop( &mut self ) -> Option<Message> {
+		return self.pop();
+	}
+
+	fn disconnected( &self ) -> bool {
+		return self.is_disconnected();
+	}
+}
+
+impl Pop for MpscConsumer<Message> {
+	fn try_pop( &mut self ) -> Option<Message> {
+		return self.pop();
+	}
+
+	fn disconnected( &self ) -> bool {
+		return self.is_disconnected();
+	}
+}
+
+fn stress( producers: Vec<impl Push>, mut consumer: impl Pop ) {
+	let count = producers.len();
+	let drops = Arc::new( AtomicUsize::new( 0 ) );
+
+	let threads: Vec<_> = producers.into_iter().enumerate().map( |( producer, mut queue )| {
+		let drops = Arc::clone( &drops );
+
+		thread::spawn( move || {
+			for seq in 0..PER_PRODUCER {
+				let mut message = Message { producer, seq, check: Box::new( [seq; 8] ), drops: Arc::clone( &drops ) };
+
+				// Full: hand the CPU to the consumer (there may be fewer cores than threads). Unless its checks failed and it's gone
+				while let Err( back ) = queue.try_push( message ) {
+					if queue.disconnected() {
+						return;
+					}
+
+					message = back;
+					thread::yield_now();
+				}
+			}
+		})
+	}).collect();
+
+	let checker = thread::spawn( move || {
+		let mut next = vec![0; count];
+
+		loop {
+			// Checked before popping: once it's true, anything still in the buffer is visible to this pop
+			let finished = consumer.disconnected();
+
+			match consumer.try_pop() {
+				Some( m ) => {
+					assert! ( m.check.iter().all( |&c| c == m.seq ), "Torn message {} from producer {}: {:?}", m.seq, m.producer, m.check );
+					assert_eq! ( m.seq, next[m.producer], "Producer {}'s messages out of order (or lost, or repeated)", m.producer );
+
+					next[m.producer] += 1;
+				},
+				None if finished => break,
+				None => thread::yield_now(),
+			}
+		}
+
+		return next;
+	});
+
+	let checked = checker.join();
+
+	for t in threads {
+		t.join().unwrap();
+	}
+
+	assert_eq! ( checked.unwrap(), vec![PER_PRODUCER; count] );
+	assert_eq! ( drops.load( Ordering::Relaxed ), count * PER_PRODUCER );
+}
+
+#[test]
+fn spsc_stress() {
+	for round in 0..ROUNDS {
+		let ( producer, consumer ) = ring::spsc::<Message>( 1 << ( round % 3 ) ); // 1, 2 and 4 slots
+		stress( vec![producer], consumer );
+	}
+}
+
+#[test]
+fn mpsc_stress() {
+	for round in 0..ROUNDS {
+		let ( producer, consumer ) = ring::mpsc::<Message>( 1 << ( round % 4 ) ); // 1 (really 2), 2, 4 and 8 slots
+		let producers: Vec<_> = ( 0..4 ).map( |_| producer.clone() ).collect();
+
+		drop( producer ); // Otherwise the consumer never sees every producer gone
+		stress( producers, consumer );
+	}
+}
+
+#[test]
+fn push_gives_the_value_back_when_full() {
+	let ( mut tx, mut rx ) = ring::spsc( 3 );
+	assert_eq! ( ( tx.capacity(), rx.capacity() ), ( 4, 4 ) ); // Rounded up to a power of two
+
+	for i in 0..4 {
+		tx.push( i ).unwrap();
+	}
+
+	assert_eq! ( tx.push( 4 ), Err( 4 ) );
+	assert_eq! ( rx.len(), 4 );
+	assert_eq! ( rx.pop(), Some( 0 ) );
+
+	tx.push( 4 ).unwrap();
+	assert_eq! ( std::iter::from_fn( || rx.pop() ).collect::<Vec<_>>(), vec![1, 2, 3, 4] );
+	assert! ( rx.is_empty() );
+
+	let ( tx, mut rx ) = ring::mpsc( 2 );
+	let other = tx.clone();
+
+	assert_eq! ( ( tx.push( 'a' ), other.push( 'b' ), tx.push( 'c' ) ), ( Ok( () ), Ok( () ), Err( 'c' ) ) );
+	assert_eq! ( ( rx.pop(), rx.pop(), rx.pop() ), ( Some( 'a' ), Some( 'b' ), None ) );
+}
+
+#[test]
+fn mpsc_with_capacity_one_still_holds_two() {
+	let ( tx, mut rx ) = ring::mpsc( 1 );
+	assert_eq! ( ( tx.capacity(), rx.capacity() ), ( 2, 2 ) );
+
+	// Round after round, so the indices wrap around the slots many times
+	for i in 0..100 {
+		assert_eq! ( ( tx.push( 2 * i ), tx.push( 2 * i + 1 ), tx.push( -1 ) ), ( Ok( () ), Ok( () ), Err( -1 ) ) );
+		assert_eq! ( ( rx.pop(), rx.pop(), rx.pop() ), ( Some( 2 * i ), Some( 2 * i + 1 ), None ) );
+	}
+
+	tx.push( 7 ).unwrap();
+	assert_eq! ( rx.pop(), Some( 7 ) ); // One at a time too
+	assert_eq! ( rx.pop(), None );
+}
+
+#[test]
+fn disconnects_are_seen_and_leftovers_dropped() {
+	let drops = Arc::new( AtomicUsize::new( 0 ) );
+	let message = |seq| Message { producer: 0, seq, check: Box::new( [seq; 8] ), drops: Arc::clone( &drops ) };
+
+	let ( mut tx, mut rx ) = ring::spsc( 4 );
+	tx.push( message( 0 ) ).map_err( |_| () ).unwrap();
+	tx.push( message( 1 ) ).map_err( |_| () ).unwrap();
+	drop( tx );
+
+	assert! ( rx.is_disconnected() );
+	assert_eq! ( rx.pop().map( |m| m.seq ), Some( 0 ) ); // Still there after the producer left
+	drop( rx );
+	assert_eq! ( drops.load( Ordering::Relaxed ), 2 ); // The one never popped went with the buffer
+
+	let ( tx, rx ) = ring::mpsc( 4 );
+	let other = tx.clone();
+
+	for seq in 0..3 {
+		other.push( message( seq ) ).map_err( |_| () ).unwrap();
+	}
+
+	drop( other );
+	assert! ( !rx.is_disconnected() ); // 'tx' is still around
+
+	drop( rx );
+	assert! ( tx.is_disconnected() );
+	drop( tx );
+	assert_eq! ( drops.load( Ordering::Relaxed ), 5 );
+}
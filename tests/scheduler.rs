@@ -0,0 +1,208 @@
+// Integration tests for scheduler: delayed, periodic and cancelled jobs on a ManualClock, and the timer wheel itself
+
+use std::sync::mpsc;
+use std::sync::{ Arc, Mutex };
+use std::time::Duration;
+
+use rs_basics::scheduler::{ ManualClock, Scheduler, TimerWheel };
+
+fn ms( n: u64 ) -> Duration {
+	return Duration::from_millis( n );
+}
+
+// A scheduler on a clock that only moves when told to, and a log the jobs write to
+fn manual() -> ( Scheduler, Arc<ManualClock>, Arc<Mutex<Vec<String>>> ) {
+	let clock = Arc::new( ManualClock::new() );
+	let scheduler = Scheduler::with_clock( clock.clone() );
+
+	return ( scheduler, clock, Arc::default() );
+}
+
+fn push( log: &Arc<Mutex<Vec<String>>>, entry: impl Into<String> ) -> impl FnMut() + Send + 'static {
+	let ( log, entry ) = ( Arc::clone( log ), entry.into() );
+
+	return move || log.lock().unwrap().push( entry.clone() );
+}
+
+// Moves the clock and waits for everything that came due to run
+fn advance( scheduler: &Scheduler, clock: &ManualClock, by: Duration ) {
+	clock.advance( by );
+	scheduler.wait_idle();
+}
+
+fn taken( log: &Mutex<Vec<String>> ) -> Vec<String> {
+	return std::mem::take( &mut *log.lock().unwrap() );
+}
+
+#[test]
+fn delayed_jobs_run_once_in_deadline_order() {
+	let ( scheduler, clock, log ) = manual();
+
+	scheduler.after( ms( 30 ), push( &log, "c" ) );
+	scheduler.after( ms( 10 ), push( &log, "a" ) );
+	scheduler.after( ms( 20 ), push( &log, "b" ) );
+	scheduler.after( Duration::from_micros( 10_500 ), push( &log, "a2" ) ); // Rounded up to 11ms, never early
+	scheduler.wait_idle();
+
+	advance( &scheduler, &clock, ms( 9 ) );
+	assert! ( taken( &log ).is_empty() );
+
+	advance( &scheduler, &clock, ms( 1 ) );
+	assert_eq! ( taken( &log ), ["a"] );
+
+	advance( &scheduler, &clock, ms( 100 ) ); // Several due at once still run in order
+	assert_eq! ( taken( &log ), ["a2", "b", "c"] );
+	assert_eq! ( scheduler.pending(), 0 );
+
+	advance( &scheduler, &clock, ms( 100 ) );
+	assert! ( taken( &log ).is_empty() );
+}
+
+#[test]
+fn periodic_jobs_repeat_and_skip_missed_runs() {
+	let ( scheduler, clock, log ) = manual();
+	let tick = scheduler.every( ms( 5 ), push( &log, "tick" ) );
+	let mut panics = 0;
+	scheduler.every( ms( 5 ), move || { panics += 1; if panics == 2 { panic! ( "Second run fails" ) } } );
+
+	advance( &scheduler, &clock, ms( 4 ) );
+	assert_eq! ( taken( &log ).len(), 0 );
+
+	for _ in 0..4 {
+		advance( &scheduler, &clock, ms( 5 ) ); // t = 9, 14, 19, 24
+	}
+
+	assert_eq! ( taken( &log ).len(), 4 );
+	assert_eq! ( scheduler.pending(), 1 ); // The panicking job was dropped
+
+	// Falling 3 runs behind only runs once, then it's back on the 5ms grid
+	advance( &scheduler, &clock, ms( 16 ) ); // t = 40
+	assert_eq! ( taken( &log ).len(), 1 );
+
+	advance( &scheduler, &clock, ms( 4 ) ); // t = 44
+	assert_eq! ( taken( &log ).len(), 0 );
+
+	advance( &scheduler, &clock, ms( 1 ) ); // t = 45
+	assert_eq! ( taken( &log ).len(), 1 );
+	assert! ( tick.is_pending() );
+}
+
+#[test]
+fn cancelled_jobs_never_run() {
+	let ( scheduler, clock, log ) = manual();
+
+	let once = scheduler.after( ms( 10 ), push( &log, "once" ) );
+	let every = scheduler.every( ms( 10 ), push( &log, "every" ) );
+	let kept = scheduler.after( ms( 10 ), push( &log, "kept" ) );
+
+	assert! ( once.cancel() );
+	assert! ( !once.cancel() ); // Only once
+	assert! ( !once.is_pending() );
+	assert_eq! ( scheduler.pending(), 2 );
+
+	advance( &scheduler, &clock, ms( 10 ) );
+	assert_eq! ( taken( &log ), ["every", "kept"] );
+	assert! ( !kept.cancel() ); // Already ran
+
+	assert! ( every.cancel() );
+	advance( &scheduler, &clock, ms( 50 ) );
+	assert! ( taken( &log ).is_empty() );
+	assert_eq! ( scheduler.pending(), 0 );
+
+	drop( scheduler );
+	assert! ( !every.cancel() && !every.is_pending() ); // The scheduler is gone
+}
+
+#[test]
+fn a_job_can_drop_its_own_scheduler() {
+	let ( scheduler, clock, _ ) = manual();
+	let slot: Arc<Mutex<Option<Scheduler>>> = Arc::default();
+	let ( tx, dropped ) = mpsc::channel();
+
+	let owner = Arc::clone( &slot );
+	scheduler.after( ms( 5 ), move || {
+		drop( owner.lock().unwrap().take() ); // The last handle, dropped on the scheduler's own thread
+		let _ = tx.send( () );
+	});
+	scheduler.wait_idle();
+	*slot.lock().unwrap() = Some( scheduler );
+
+	clock.advance( ms( 5 ) );
+	assert! ( dropped.recv_timeout( Duration::from_secs( 5 ) ).is_ok() ); // No deadlock, and no panic joining itself
+	assert! ( slot.lock().unwrap().is_none() );
+}
+
+#[test]
+fn jobs_wrap_around_the_wheel_several_times() {
+	let ( scheduler, clock, log ) = manual();
+
+	// Level 1 (64ms slots), level 2 (~4s slots) and level 3 (~4.5 min slots)
+	scheduler.after( ms( 64 * 3 + 5 ), push( &log, "level 1" ) );
+	scheduler.after( ms( 64 * 64 * 2 + 7 ), push( &log, "level 2" ) );
+	scheduler.after( ms( 64 * 64 * 64 + 3 ), push( &log, "level 3" ) );
+
+	// Every 7ms goes round level 0 (64 ticks) many times
+	let mut runs = 0;
+	let counted = Arc::new( Mutex::new( 0 ) );
+	let counter = Arc::clone( &counted );
+	scheduler.every( ms( 7 ), move || { runs += 1; *counter.lock().unwrap() = runs; } );
+
+	for _ in 0..700 {
+		advance( &scheduler, &clock, ms( 1 ) );
+	}
+
+	assert_eq! ( *counted.lock().unwrap(), 100 );
+	assert_eq! ( taken( &log ), ["level 1"] );
+
+	advance( &scheduler, &clock, ms( 64 * 64 * 2 + 7 - 701 ) ); // 1ms short
+	assert! ( taken( &log ).is_empty() );
+
+	advance( &scheduler, &clock, ms( 1 ) );
+	assert_eq! ( taken( &log ), ["level 2"] );
+
+	advance( &scheduler, &clock, ms( 64 * 64 * 64 + 3 ) );
+	assert_eq! ( taken( &log ), ["level 3"] );
+}
+
+#[test]
+fn timer_wheel_fires_far_timers_exactly_on_time() {
+	let mut wheel = TimerWheel::new();
+	let far = 1 << 24; // Past the last level, in the overflow list
+
+	for ( deadline, id ) in [( 5, 1 ), ( 64, 2 ), ( 4096 + 1, 3 ), ( far + 9, 4 ), ( 5, 0 )] {
+		wheel.insert( deadline, id );
+	}
+
+	assert_eq! ( wheel.advance( 5 ), [0, 1] ); // Same deadline: by id
+	assert! ( wheel.advance( 63 ).is_empty() );
+	assert_eq! ( wheel.advance( 64 ), [2] );
+	assert_eq! ( wheel.advance( far ), [3] );
+	assert! ( wheel.advance( far + 8 ).is_empty() );
+	assert_eq! ( wheel.advance( far + 100 ), [4] );
+	assert! ( wheel.is_empty() );
+
+	wheel.insert( 3, 5 ); // Already in the past: fires on the next advance
+	assert_eq! ( wheel.advance( wheel.current() ), [5] );
+}
+
+#[test]
+fn removed_timers_leave_the_wheel_right_away() {
+	let mut wheel = TimerWheel::new();
+	let far = 1 << 24;
+
+	for ( deadline, id ) in [( 10, 1 ), ( 10, 2 ), ( 5000, 3 ), ( far, 4 )] {
+		wheel.insert( deadline, id );
+	}
+
+	assert! ( wheel.remove( 10, 1 ) );
+	assert! ( !wheel.remove( 10, 1 ) );
+	assert! ( !wheel.remove( 11, 2 ) ); // Wrong deadline
+	assert! ( wheel.remove( far, 4 ) ); // From the overflow list
+	assert_eq! ( wheel.len(), 2 );
+
+	// After cascading down a level, it's still found
+	assert! ( wheel.advance( 4096 ).contains( &2 ) );
+	assert! ( wheel.remove( 5000, 3 ) );
+	assert! ( wheel.is_empty() );
+	assert! ( wheel.advance( far + 1 ).is_empty() );
+}
@@ -0,0 +1,203 @@
+// Integration tests for threading::Server, over real sockets on 127.0.0.1
+
+use std::io::{ BufRead, BufReader, Write };
+use std::net::{ SocketAddr, TcpListener, TcpStream };
+use std::sync::mpsc::{ self, Receiver };
+use std::time::Duration;
+
+use rs_basics::pipeline::{ Chain, Next };
+use rs_basics::resilience::RetryPolicy;
+use rs_basics::threading::{ Client, DisconnectReason, Request, Response, Server, ServerEvent };
+
+const WAIT: Duration = Duration::from_secs( 5 );
+
+fn any_port() -> SocketAddr {
+	return "127.0.0.1:0".parse().unwrap();
+}
+
+// Every event the server publishes, so tests can wait for them instead of sleeping
+fn event_log( server: &Server ) -> Receiver<ServerEvent> {
+	let ( tx, rx ) = mpsc::channel();
+	let tx = std::sync::Mutex::new( tx );
+
+	server.events().on( "*", move |e: &ServerEvent| { let _ = tx.lock().unwrap().send( e.clone() ); } );
+
+	return rx;
+}
+
+fn connect( addr: SocketAddr ) -> ( TcpStream, BufReader<TcpStream> ) {
+	let stream = TcpStream::connect( addr ).unwrap();
+	stream.set_read_timeout( Some( WAIT ) ).unwrap();
+	let reader = BufReader::new( stream.try_clone().unwrap() );
+
+	return ( stream, reader );
+}
+
+fn request( stream: &mut TcpStream, reader: &mut BufReader<TcpStream>, line: &str ) -> String {
+	stream.write_all( format! ( "{line}\n" ).as_bytes() ).unwrap();
+
+	let mut answer = String::new();
+	reader.read_line( &mut answer ).unwrap();
+
+	return answer.trim_end().to_string();
+}
+
+#[test]
+fn echoes_lines_back() {
+	let mut server = Server::new( any_port() );
+	let addr = server.start().unwrap();
+	let ( mut stream, mut reader ) = connect( addr );
+
+	assert_eq! ( request( &mut stream, &mut reader, "hello" ), "hello" );
+	assert_eq! ( request( &mut stream, &mut reader, "second line" ), "second line" );
+
+	server.stop();
+}
+
+#[test]
+fn custom_handler_answers_requests() {
+	let mut server = Server::new( any_port() ).with_handler( |r| match r.body.as_str() {
+		"" => Response { ok: false, body: "empty request".to_string() },
+		body => Response { ok: true, body: body.to_uppercase() },
+	});
+	let addr = server.start().unwrap();
+	let ( mut stream, mut reader ) = connect( addr );
+
+	assert_eq! ( request( &mut stream, &mut reader, "shout" ), "SHOUT" );
+	assert_eq! ( request( &mut stream, &mut reader, "" ), "ERR empty request" );
+}
+
+#[test]
+fn request_log_is_opt_in() {
+	let ( tx, lines ) = mpsc::channel();
+	let tx = std::sync::Mutex::new( tx );
+	let mut server = Server::new( any_port() ).with_request_log( move |line| { let _ = tx.lock().unwrap().send( line ); } );
+	let addr = server.start().unwrap();
+	let ( mut stream, mut reader ) = connect( addr );
+
+	assert_eq! ( request( &mut stream, &mut reader, "hello" ), "hello" );
+
+	let logged: Vec<String> = ( 0..3 ).map( |_| lines.recv_timeout( WAIT ).unwrap() ).collect();
+	assert! ( logged[0].starts_with( "--> Request" ) && logged[0].contains( "\"hello\"" ), "{logged:?}" );
+	assert! ( logged[1].starts_with( "Handled in " ), "{logged:?}" ); // Timing is inside logging
+	assert! ( logged[2].starts_with( "<-- Response" ), "{logged:?}" );
+}
+
+#[test]
+fn custom_middleware_keeps_authorization_and_the_request_log() {
+	for log_first in [true, false] {
+		let ( tx, lines ) = mpsc::channel();
+		let tx = std::sync::Mutex::new( tx );
+		let log = move |line: String| { let _ = tx.lock().unwrap().send( line ); };
+		let tag = Chain::new().with( |mut r: Request, next: Next<'_, Request, Response>| { r.body = format! ( "tagged {}", r.body ); next.run( r ) } );
+
+		// The result doesn't depend on the order the two were added in
+		let server = match log_first {
+			true => Server::new( any_port() ).with_request_log( log ).with_middleware( tag ),
+			false => Server::new( any_port() ).with_middleware( tag ).with_request_log( log ),
+		};
+		let id = server.push_client( "local" );
+
+		assert_eq! ( server.handle( Request { id, client: "local".to_string(), body: "hi".to_string() } ), Response { ok: true, body: "tagged hi".to_string() } );
+		assert_eq! ( server.handle( Request { id: 999, client: "ghost".to_string(), body: "hi".to_string() } ), Response { ok: false, body: "ghost is not connected".to_string() } );
+
+		// Logging sees the request before the custom middleware, timing only runs for authorized ones
+		let logged: Vec<String> = lines.try_iter().collect();
+		assert_eq! ( logged.len(), 5, "{logged:?}" );
+		assert! ( logged[0].contains( "body: \"hi\"" ) && logged[2].contains( "tagged hi" ), "{logged:?}" );
+		assert! ( logged[1].starts_with( "Handled in " ) && logged[4].contains( "is not connected" ), "{logged:?}" );
+	}
+}
+
+#[test]
+fn bind_is_retried_while_the_address_is_in_use() {
+	let previous = TcpListener::bind( any_port() ).unwrap(); // e.g. the old instance of a restarting server
+	let addr = previous.local_addr().unwrap();
+
+	assert_eq! ( Server::new( addr ).start().unwrap_err().kind(), std::io::ErrorKind::AddrInUse ); // A single attempt by default
+
+	let release = std::thread::spawn( move || { std::thread::sleep( Duration::from_millis( 100 ) ); drop( previous ); } );
+	let mut server = Server::new( addr ).with_bind_retry( RetryPolicy::fixed( 100, Duration::from_millis( 20 ) ) );
+
+	assert_eq! ( server.start().unwrap(), addr );
+	release.join().unwrap();
+
+	let ( mut stream, mut reader ) = connect( addr );
+	assert_eq! ( request( &mut stream, &mut reader, "hello" ), "hello" );
+}
+
+#[test]
+fn clients_are_registered_and_unregistered() {
+	let mut server = Server::new( any_port() );
+	let events = event_log( &server );
+	let addr = server.start().unwrap();
+
+	let first_socket = connect( addr );
+	let second_socket = connect( addr );
+	let first = Client { id: 1, name: first_socket.0.local_addr().unwrap().to_string() };
+	let second = Client { id: 2, name: second_socket.0.local_addr().unwrap().to_string() };
+
+	assert_eq! ( events.recv_timeout( WAIT ).unwrap(), ServerEvent::Connected( first.clone() ) );
+	assert_eq! ( events.recv_timeout( WAIT ).unwrap(), ServerEvent::Connected( second.clone() ) );
+	assert_eq! ( server.clients().snapshot(), vec![first.clone(), second.clone()] );
+
+	drop( first_socket ); // Both halves, or the socket stays open
+
+	assert_eq! ( events.recv_timeout( WAIT ).unwrap(), ServerEvent::Disconnected( first, DisconnectReason::Closed ) );
+	assert_eq! ( server.clients().snapshot(), vec![second] );
+}
+
+#[test]
+fn stop_disconnects_clients_and_closes_the_port() {
+	let mut server = Server::new( any_port() );
+	let events = event_log( &server );
+	let addr = server.start().unwrap();
+	let ( _stream, mut reader ) = connect( addr );
+
+	assert! ( matches! ( events.recv_timeout( WAIT ).unwrap(), ServerEvent::Connected( _ ) ) );
+
+	server.stop();
+
+	assert! ( !server.is_running() );
+	assert! ( server.clients().is_empty() );
+	assert! ( matches! ( events.recv_timeout( WAIT ).unwrap(), ServerEvent::Disconnected( _, DisconnectReason::ServerStopped ) ) );
+
+	// The client sees the connection closed
+	let mut rest = String::new();
+	assert_eq! ( reader.read_line( &mut rest ).unwrap(), 0 );
+
+	assert! ( TcpStream::connect_timeout( &addr, Duration::from_secs( 1 ) ).is_err() );
+}
+
+#[test]
+fn can_restart_but_not_start_twice() {
+	let mut server = Server::new( any_port() );
+
+	server.start().unwrap();
+	assert! ( server.start().is_err() );
+
+	server.stop();
+	server.stop(); // Stopping twice is fine
+
+	let addr = server.start().unwrap();
+	let ( mut stream, mut reader ) = connect( addr );
+
+	assert_eq! ( request( &mut stream, &mut reader, "back" ), "back" );
+}
+
+#[test]
+fn remove_client_disconnects_its_socket() {
+	let mut server = Server::new( any_port() );
+	let events = event_log( &server );
+	let addr = server.start().unwrap();
+	let ( _stream, mut reader ) = connect( addr );
+
+	let ServerEvent::Connected( client ) = events.recv_timeout( WAIT ).unwrap() else { panic! ( "Expected a connect event" ) };
+
+	assert_eq! ( server.remove_client( client.id ), Some( client.clone() ) );
+	assert_eq! ( server.remove_client( client.id ), None );
+	assert_eq! ( events.recv_timeout( WAIT ).unwrap(), ServerEvent::Disconnected( client, DisconnectReason::Removed ) );
+
+	let mut rest = String::new();
+	assert_eq! ( reader.read_line( &mut rest ).unwrap(), 0 );
+}
@@ -0,0 +1,166 @@
+// Integration tests for shutdown::CancellationToken/ShutdownSignal, and how Server and ThreadPool react to them
+
+use std::io::{ BufRead, BufReader, Write };
+use std::net::TcpStream;
+use std::sync::atomic::{ AtomicUsize, Ordering };
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use rs_basics::pool::ThreadPool;
+use rs_basics::shutdown::{ CancellationToken, Reason, ShutdownSignal };
+use rs_basics::threading::{ Response, Server };
+
+const WAIT: Duration = Duration::from_secs( 5 );
+
+#[test]
+fn cancelling_a_token_wakes_waiters_and_runs_callbacks_once() {
+	let token = CancellationToken::new();
+	let child = token.child();
+	let calls = Arc::new( AtomicUsize::new( 0 ) );
+
+	let counted = Arc::clone( &calls );
+	token.on_cancel( move || { counted.fetch_add( 1, Ordering::SeqCst ); } );
+
+	let waiter = {
+		let token = token.clone();
+		thread::spawn( move || token.wait() )
+	};
+
+	assert! ( !token.wait_timeout( Duration::from_millis( 10 ) ) );
+
+	token.cancel();
+	token.cancel();
+	waiter.join().unwrap();
+
+	assert! ( token.is_cancelled() && child.is_cancelled() );
+	assert_eq! ( calls.load( Ordering::SeqCst ), 1 );
+
+	// Too late to wait for it: runs right away
+	let counted = Arc::clone( &calls );
+	token.on_cancel( move || { counted.fetch_add( 1, Ordering::SeqCst ); } );
+	assert_eq! ( calls.load( Ordering::SeqCst ), 2 );
+
+	// Children don't cancel their parent
+	let parent = CancellationToken::new();
+	parent.child().cancel();
+	assert! ( !parent.is_cancelled() );
+}
+
+#[test]
+fn drain_reports_work_still_running_after_the_timeout() {
+	let signal = ShutdownSignal::new();
+	let quick = signal.track( "quick" );
+	let slow = signal.track( "slow" );
+
+	let finisher = thread::spawn( move || {
+		thread::sleep( Duration::from_millis( 10 ) );
+		drop( quick );
+	});
+
+	let report = signal.drain( Duration::from_millis( 100 ) );
+	finisher.join().unwrap();
+
+	assert_eq! ( report.reason, Reason::Requested );
+	assert! ( report.waited >= Duration::from_millis( 100 ) );
+	assert_eq! ( report.still_running.iter().map( |w| w.name.as_str() ).collect::<Vec<_>>(), vec!["slow"] );
+	assert! ( report.to_string().contains( "1 still running" ) );
+
+	drop( slow );
+
+	let report = signal.drain( Duration::from_millis( 100 ) );
+	assert! ( report.is_clean() );
+	assert! ( report.waited < Duration::from_millis( 100 ) );
+}
+
+#[test]
+fn server_finishes_requests_in_progress_then_stops() {
+	let signal = ShutdownSignal::new();
+	let ( started_tx, started_rx ) = mpsc::channel();
+	let started_tx = std::sync::Mutex::new( started_tx );
+
+	let mut server = Server::new( "127.0.0.1:0".parse().unwrap() )
+		.with_handler( move |r| {
+			let _ = started_tx.lock().unwrap().send( () );
+			thread::sleep( Duration::from_millis( 200 ) ); // Slow enough to still be running when the signal fires
+			Response { ok: true, body: r.body }
+		})
+		.with_shutdown( &signal );
+
+	let addr = server.start().unwrap();
+	let mut stream = TcpStream::connect( addr ).unwrap();
+	stream.set_read_timeout( Some( WAIT ) ).unwrap();
+	let mut reader = BufReader::new( stream.try_clone().unwrap() );
+
+	stream.write_all( b"almost done\n" ).unwrap();
+	started_rx.recv_timeout( WAIT ).unwrap();
+
+	// Too short for the request: it's in the report
+	let report = signal.drain( Duration::from_millis( 20 ) );
+	assert_eq! ( report.still_running.len(), 1 );
+	assert! ( report.still_running[0].name.starts_with( "client 1 " ) );
+
+	// But it still gets its answer, then the connection closes
+	let mut line = String::new();
+	reader.read_line( &mut line ).unwrap();
+	assert_eq! ( line, "almost done\n" );
+	line.clear();
+	assert_eq! ( reader.read_line( &mut line ).unwrap(), 0 );
+
+	assert! ( signal.drain( WAIT ).is_clean() );
+
+	// No more clients, and no restarting with a fired signal
+	server.stop();
+	assert! ( !server.is_running() );
+	assert! ( TcpStream::connect_timeout( &addr, Duration::from_secs( 1 ) ).is_err() );
+	assert! ( server.start().is_err() );
+}
+
+#[test]
+fn pool_drops_queued_jobs_and_lets_running_ones_finish() {
+	let signal = ShutdownSignal::new();
+	let pool = ThreadPool::new( 1 ).with_shutdown( &signal );
+	let ( started_tx, started_rx ) = mpsc::channel();
+
+	let running = pool.spawn( move || {
+		started_tx.send( () ).unwrap();
+		thread::sleep( Duration::from_millis( 50 ) );
+		"finished"
+	});
+	let queued = pool.spawn( || "never runs" );
+
+	started_rx.recv_timeout( WAIT ).unwrap();
+	let report = signal.drain( WAIT );
+
+	assert! ( report.is_clean() );
+	assert_eq! ( running.join(), Ok( "finished" ) );
+	assert_eq! ( queued.join(), Err( "Job was cancelled".to_string() ) );
+
+	pool.execute( || panic! ( "Not after the shutdown" ) );
+
+	let metrics = pool.shutdown();
+	assert_eq! ( ( metrics.completed, metrics.cancelled ), ( 1, 2 ) );
+}
+
+#[cfg( target_os = "linux" )]
+#[test]
+fn sigterm_fires_the_signal() {
+	let signal = ShutdownSignal::new();
+	signal.listen_for_os_signals().unwrap();
+
+	let status = std::process::Command::new( "kill" ).args( ["-TERM", &std::process::id().to_string()] ).status().unwrap();
+	assert! ( status.success() );
+
+	assert_eq! ( signal.wait(), Reason::Terminate );
+	drop( signal );
+
+	// The first signal put the default handlers back. A later listener re-arms them, instead of the next SIGTERM killing the tests
+	let late = ShutdownSignal::new();
+	late.listen_for_os_signals().unwrap();
+
+	let status = std::process::Command::new( "kill" ).args( ["-TERM", &std::process::id().to_string()] ).status().unwrap();
+	assert! ( status.success() );
+
+	assert_eq! ( late.wait(), Reason::Terminate );
+}
@@ -0,0 +1,114 @@
+// Integration tests for table: plain, Markdown and CSV output, max widths and alignment
+
+use rs_basics::table::{ self, Align, Border, Format, Overflow, Row, Table };
+
+// The same Row impl as the lesson's Car (in the binary, so not reachable from here)
+struct Car {
+	name: String,
+	model: String,
+}
+
+impl Row for Car {
+	fn headers() -> Vec<String> {
+		return vec!["Name".to_string(), "Model".to_string()];
+	}
+
+	fn cells( &self ) -> Vec<String> {
+		return vec![self.name.clone(), self.model.clone()];
+	}
+}
+
+fn car( name: &str, model: &str ) -> Car {
+	return Car { name: name.to_string(), model: model.to_string() };
+}
+
+fn strings( cells: &[&str] ) -> Vec<String> {
+	return cells.iter().map( |c| c.to_string() ).collect();
+}
+
+#[test]
+fn rows_render_as_a_unicode_table() {
+	let lot = [car( "Lamborghini", "Aventador" ), car( "Porsche", "Panamera" )];
+
+	assert_eq! ( table::render( &lot ), "\
+┌─────────────┬───────────┐
+│ Name        │ Model     │
+├─────────────┼───────────┤
+│ Lamborghini │ Aventador │
+│ Porsche     │ Panamera  │
+└─────────────┴───────────┘
+" );
+
+	assert_eq! ( table::render::<Car>( &[] ), "\
+┌──────┬───────┐
+│ Name │ Model │
+├──────┼───────┤
+└──────┴───────┘
+" );
+}
+
+#[test]
+fn long_cells_are_truncated_or_wrapped() {
+	let mut t = Table::new( strings( &["Id", "Description"] ) ).max_width( 10 ).border( Border::Ascii );
+	t.push( strings( &["1", "A rather long description"] ) );
+
+	assert_eq! ( t.clone().render( Format::Plain ), "\
++----+------------+
+| Id | Descripti… |
++----+------------+
+| 1  | A rather … |
++----+------------+
+" );
+
+	assert_eq! ( t.clone().overflow( Overflow::Wrap ).render( Format::Plain ), "\
++----+------------+
+| Id | Descriptio |
+|    | n          |
++----+------------+
+| 1  | A rather   |
+|    | long       |
+|    | descriptio |
+|    | n          |
++----+------------+
+" );
+
+	// CSV never loses text
+	assert! ( t.render( Format::Csv ).contains( "A rather long description" ) );
+}
+
+#[test]
+fn columns_align_left_right_or_center() {
+	let mut t = Table::new( strings( &["Item", "Qty", "Tag"] ) ).align( 1, Align::Right ).align( 2, Align::Center ).align( 9, Align::Right ).border( Border::None );
+	t.push( strings( &["Apples", "12", "a"] ) );
+	t.push( strings( &["Kiwi", "7"] ) ); // Missing cells are empty
+
+	assert_eq! ( t.render( Format::Plain ), "\
+Item    Qty  Tag
+------  ---  ---
+Apples   12   a
+Kiwi      7
+" );
+
+	assert_eq! ( t.render( Format::Markdown ).lines().nth( 1 ), Some( "| --- | ---: | :---: |" ) );
+}
+
+#[test]
+fn markdown_escapes_pipes_and_newlines() {
+	let mut t = Table::new( strings( &["Expr", "Note"] ) );
+	t.push( strings( &["a | b", "two\nlines \\ here"] ) );
+
+	assert_eq! ( t.render( Format::Markdown ), "\
+| Expr | Note |
+| --- | --- |
+| a \\| b | two<br>lines \\\\ here |
+" );
+}
+
+#[test]
+fn csv_quotes_commas_quotes_and_newlines() {
+	let mut t = Table::new( strings( &["Name", "Quote"] ) );
+	t.push( strings( &["Doe, John", "He said \"hi\""] ) );
+	t.push( strings( &["Plain", "line one\nline two"] ) );
+
+	assert_eq! ( t.render( Format::Csv ), "Name,Quote\r\n\"Doe, John\",\"He said \"\"hi\"\"\"\r\nPlain,\"line one\nline two\"\r\n" );
+}
@@ -0,0 +1,109 @@
+// Integration tests for template: variables, blocks, loops, filters and syntax errors
+
+use std::collections::HashMap;
+
+use rs_basics::template::{ self, Context, Template, Value };
+
+struct Owner {
+	name: String,
+	pets: Vec<&'static str>,
+}
+
+impl Context for Owner {
+	fn get( &self, key: &str ) -> Option<Value> {
+		match key {
+			"name" => Some( self.name.as_str().into() ),
+			"pets" => Some( self.pets.clone().into() ),
+			_ => None
+		}
+	}
+}
+
+fn ctx( pairs: &[( &str, Value )] ) -> HashMap<String, Value> {
+	return pairs.iter().map( |( k, v )| ( k.to_string(), v.clone() ) ).collect();
+}
+
+fn car( make: &str, year: i32 ) -> Value {
+	let mut map = HashMap::new();
+	map.insert( "make".to_string(), Value::from( make ) );
+	map.insert( "year".to_string(), Value::from( year ) );
+
+	return Value::Map( map );
+}
+
+#[test]
+fn variables_and_conditionals() {
+	let src = "Person {{name}} is {{age}} years old{{#if pet}} and owns {{pet}}{{/if}}{{! ignored }}!";
+	let with_pet = ctx( &[( "name", "Ann".into() ), ( "age", 30.into() ), ( "pet", "Rex".into() )] );
+	let without = ctx( &[( "name", "Bob".into() ), ( "age", 41.into() ), ( "pet", "".into() )] );
+
+	assert_eq! ( template::render( src, &with_pet ).unwrap(), "Person Ann is 30 years old and owns Rex!" );
+	assert_eq! ( template::render( src, &without ).unwrap(), "Person Bob is 41 years old!" );
+
+	let src = "{{#unless admin}}guest{{else}}admin{{/unless}} {{missing}}|{{owner.name}}";
+	let mut owner = HashMap::new();
+	owner.insert( "name".to_string(), Value::from( "Cy" ) );
+	let values = ctx( &[( "admin", false.into() ), ( "owner", Value::Map( owner ) )] );
+
+	assert_eq! ( template::render( src, &values ).unwrap(), "guest |Cy" ); // Missing values render as nothing
+}
+
+#[test]
+fn loops_expose_items_indices_and_fields() {
+	let owner = Owner { name: "Ann".to_string(), pets: vec!["Rex", "Tom", "Kit"] };
+	let src = "{{name}}: {{#each pets}}{{@index}}={{this}}{{#unless @last}}, {{/unless}}{{/each}}";
+
+	assert_eq! ( template::render( src, &owner ).unwrap(), "Ann: 0=Rex, 1=Tom, 2=Kit" );
+
+	let nobody = Owner { name: "Bob".to_string(), pets: Vec::new() };
+	assert_eq! ( template::render( "{{#each pets}}{{this}}{{else}}no pets{{/each}}", &nobody ).unwrap(), "no pets" );
+
+	// Items' fields are in scope, and the root context is still reachable from inside the loop
+	let cars = Value::List( vec![car( "Ford", 1999 ), car( "Saab", 2004 )] );
+	let values = ctx( &[( "cars", cars ), ( "owner", "Cy".into() )] );
+
+	assert_eq! ( template::render( "{{#each cars}}[{{owner}}: {{make}} {{year}}]{{/each}}", &values ).unwrap(), "[Cy: Ford 1999][Cy: Saab 2004]" );
+}
+
+#[test]
+fn filters_chain_left_to_right() {
+	let values = ctx( &[
+		( "name", "  ada lovelace ".into() ),
+		( "tags", vec!["a", "b", "c"].into() ),
+		( "empty", "".into() ),
+	] );
+
+	let cases = [
+		( "{{name | trim | upper}}", "ADA LOVELACE" ),
+		( "{{name | trim | title}}", "Ada Lovelace" ),
+		( "{{name | trim | snake}}", "ada_lovelace" ),
+		( "{{name | trim | truncate:5}}", "ada …" ),
+		( "{{tags | join:\" | \"}}", "a | b | c" ),
+		( "{{tags | len}}", "3" ),
+		( "{{empty | default:\"n/a\"}}", "n/a" ),
+		( "[{{tags | len | pad:3}}]", "[3  ]" ),
+	];
+
+	for ( src, expected ) in cases {
+		assert_eq! ( template::render( src, &values ).unwrap(), expected, "{src}" );
+	}
+}
+
+#[test]
+fn syntax_errors_report_line_and_column() {
+	let error = |src: &str| Template::parse( src ).unwrap_err();
+
+	let e = error( "Hello\n  {{#if pet}}owns {{pet}}" );
+	assert_eq! ( ( e.line, e.col ), ( 2, 3 ) );
+	assert! ( e.message.contains( "Unclosed {{#if}}" ) );
+
+	let e = error( "{{#each cars}}{{/if}}" );
+	assert! ( e.message.contains( "Expected {{/each}}" ) && e.col == 15 );
+
+	assert! ( error( "{{name | shout}}" ).message.contains( "Unknown filter 'shout'" ) );
+	assert! ( error( "{{name | truncate:x}}" ).message.contains( "needs a number" ) );
+	assert! ( error( "a {{name" ).message.contains( "Unclosed tag" ) );
+	assert! ( error( "{{else}}" ).message.contains( "outside of a block" ) );
+	assert! ( error( "{{#while x}}{{/while}}" ).message.contains( "Unknown block" ) );
+	assert_eq! ( error( "x\n\n {{/if}}" ).to_string(), "line 3, column 2: Unexpected {{/if}}" );
+}
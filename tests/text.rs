@@ -0,0 +1,58 @@
+// Integration tests for text: word splitting and conversion to every case
+
+use rs_basics::text::{ self, Case };
+
+#[test]
+fn words_split_on_separators_case_changes_and_acronyms() {
+	let cases: [( &str, &[&str] ); 11] = [
+		( "user_id", &["user", "id"] ),
+		( "user-id  User ID", &["user", "id", "User", "ID"] ),
+		( "fooBar", &["foo", "Bar"] ),
+		( "HTTPServer", &["HTTP", "Server"] ),
+		( "getHTTPResponse", &["get", "HTTP", "Response"] ),
+		( "parseURLs2", &["parse", "URLs2"] ),
+		( "APIsList", &["APIs", "List"] ),
+		( "utf8Value", &["utf8", "Value"] ),
+		( "HTTP2Server", &["HTTP2", "Server"] ),
+		( "DoB", &["Do", "B"] ),
+		( "__", &[] ),
+	];
+
+	for ( input, words ) in cases {
+		assert_eq! ( text::split_words( input ), words, "{input:?}" );
+	}
+}
+
+#[test]
+fn every_case_is_reachable_from_every_other() {
+	let expected = [
+		( Case::Snake, ["http_server", "parse_urls2", "base64_encode", "do_b"] ),
+		( Case::Kebab, ["http-server", "parse-urls2", "base64-encode", "do-b"] ),
+		( Case::ScreamingSnake, ["HTTP_SERVER", "PARSE_URLS2", "BASE64_ENCODE", "DO_B"] ),
+		( Case::Pascal, ["HttpServer", "ParseUrls2", "Base64Encode", "DoB"] ),
+		( Case::Camel, ["httpServer", "parseUrls2", "base64Encode", "doB"] ),
+		( Case::Title, ["HTTP Server", "Parse URLs2", "Base64 Encode", "Do B"] ),
+	];
+
+	for ( case, outputs ) in expected {
+		for ( input, output ) in ["HTTPServer", "parseURLs2", "base64_encode", "DoB"].iter().zip( outputs ) {
+			assert_eq! ( text::convert( input, case ), output, "{input:?} to {case}" );
+		}
+	}
+
+	assert_eq! ( text::to_snake_case( "getHTTPResponse" ), "get_http_response" );
+	assert_eq! ( text::to_pascal_case( "HTTP server" ), "HttpServer" ); // Acronyms are one word
+	assert_eq! ( text::to_title_case( "http_server" ), "Http Server" );
+	assert_eq! ( text::to_camel_case( "" ), "" );
+}
+
+#[test]
+fn single_capitals_are_words_of_their_own() {
+	// Intended: 'DoB' reads as "do B", so naming_lint suggests 'do_b' (like rustc's own lint), and the result round-trips
+	assert_eq! ( text::to_snake_case( "DoB" ), "do_b" );
+	assert_eq! ( text::to_pascal_case( "do_b" ), "DoB" );
+	assert_eq! ( text::detect_case( "isA" ), Some( Case::Camel ) );
+	assert_eq! ( text::detect_case( "parse_urls2" ), Some( Case::Snake ) );
+	assert_eq! ( text::detect_case( "HTTP Server" ), Some( Case::Title ) );
+	assert_eq! ( text::detect_case( "Mixed_Up-case" ), None );
+}